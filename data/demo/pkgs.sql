@@ -0,0 +1,36 @@
+-- Sample package set for --demo, in the layout of the nix-data package databases
+CREATE TABLE pkgs (attribute TEXT NOT NULL PRIMARY KEY, system TEXT, pname TEXT, version TEXT);
+CREATE TABLE meta (attribute TEXT NOT NULL PRIMARY KEY, broken INTEGER, insecure INTEGER, unsupported INTEGER, unfree INTEGER, description TEXT, longdescription TEXT, homepage TEXT, maintainers JSON, position TEXT, license JSON, platforms JSON);
+CREATE UNIQUE INDEX attributes ON pkgs (attribute);
+CREATE UNIQUE INDEX metaattributes ON meta (attribute);
+
+INSERT INTO pkgs VALUES ('firefox', 'x86_64-linux', 'firefox', '131.0.3');
+INSERT INTO pkgs VALUES ('gimp', 'x86_64-linux', 'gimp', '2.10.38');
+INSERT INTO pkgs VALUES ('inkscape', 'x86_64-linux', 'inkscape', '1.3.2');
+INSERT INTO pkgs VALUES ('vlc', 'x86_64-linux', 'vlc', '3.0.21');
+INSERT INTO pkgs VALUES ('audacity', 'x86_64-linux', 'audacity', '3.6.4');
+INSERT INTO pkgs VALUES ('blender', 'x86_64-linux', 'blender', '4.2.2');
+INSERT INTO pkgs VALUES ('libreoffice', 'x86_64-linux', 'libreoffice', '24.2.6.2');
+INSERT INTO pkgs VALUES ('supertuxkart', 'x86_64-linux', 'supertuxkart', '1.4');
+INSERT INTO pkgs VALUES ('vscodium', 'x86_64-linux', 'vscodium', '1.94.2.24286');
+INSERT INTO pkgs VALUES ('spotify', 'x86_64-linux', 'spotify', '1.2.45.454');
+INSERT INTO pkgs VALUES ('gnome-calculator', 'x86_64-linux', 'gnome-calculator', '46.1');
+INSERT INTO pkgs VALUES ('hello', 'x86_64-linux', 'hello', '2.12.1');
+INSERT INTO pkgs VALUES ('ripgrep', 'x86_64-linux', 'ripgrep', '14.1.1');
+INSERT INTO pkgs VALUES ('neovim', 'x86_64-linux', 'neovim', '0.10.2');
+INSERT INTO pkgs VALUES ('htop', 'x86_64-linux', 'htop', '3.3.0');
+INSERT INTO meta VALUES ('firefox', 0, 0, 0, 0, 'Web browser built from Firefox source tree', '', 'https://www.mozilla.org/firefox/', '[{"email": null, "github": "nixpkgs-demo", "matrix": null, "name": "Nixpkgs Demo"}]', 'pkgs/applications/networking/browsers/firefox/wrapper.nix:1', '{"free": true, "fullName": "Mozilla Public License 2.0", "spdxId": "MPL-2.0", "url": "https://spdx.org/licenses/MPL-2.0.html"}', '["x86_64-linux", "aarch64-linux"]');
+INSERT INTO meta VALUES ('gimp', 0, 0, 0, 0, 'GNU Image Manipulation Program', '', 'https://www.gimp.org/', '[{"email": null, "github": "nixpkgs-demo", "matrix": null, "name": "Nixpkgs Demo"}]', 'pkgs/applications/graphics/gimp/default.nix:62', '{"free": true, "fullName": "GNU General Public License v3.0 or later", "spdxId": "GPL-3.0-or-later", "url": "https://spdx.org/licenses/GPL-3.0-or-later.html"}', '["x86_64-linux", "aarch64-linux"]');
+INSERT INTO meta VALUES ('inkscape', 0, 0, 0, 0, 'Vector graphics editor', '', 'https://inkscape.org', '[{"email": null, "github": "nixpkgs-demo", "matrix": null, "name": "Nixpkgs Demo"}]', 'pkgs/applications/graphics/inkscape/default.nix:78', '{"free": true, "fullName": "GNU General Public License v3.0 or later", "spdxId": "GPL-3.0-or-later", "url": "https://spdx.org/licenses/GPL-3.0-or-later.html"}', '["x86_64-linux", "aarch64-linux"]');
+INSERT INTO meta VALUES ('vlc', 0, 0, 0, 0, 'Cross-platform media player and streaming server', '', 'https://www.videolan.org/vlc/', '[{"email": null, "github": "nixpkgs-demo", "matrix": null, "name": "Nixpkgs Demo"}]', 'pkgs/applications/video/vlc/default.nix:95', '{"free": true, "fullName": "GNU Lesser General Public License v2.1 or later", "spdxId": "LGPL-2.1-or-later", "url": "https://spdx.org/licenses/LGPL-2.1-or-later.html"}', '["x86_64-linux", "aarch64-linux"]');
+INSERT INTO meta VALUES ('audacity', 0, 0, 0, 0, 'Sound editor with graphical UI', '', 'https://www.audacityteam.org', '[{"email": null, "github": "nixpkgs-demo", "matrix": null, "name": "Nixpkgs Demo"}]', 'pkgs/applications/audio/audacity/default.nix:75', '{"free": true, "fullName": "GNU General Public License v2.0 or later", "spdxId": "GPL-2.0-or-later", "url": "https://spdx.org/licenses/GPL-2.0-or-later.html"}', '["x86_64-linux", "aarch64-linux"]');
+INSERT INTO meta VALUES ('blender', 0, 0, 0, 0, '3D Creation/Animation/Publishing System', '', 'https://www.blender.org', '[{"email": null, "github": "nixpkgs-demo", "matrix": null, "name": "Nixpkgs Demo"}]', 'pkgs/applications/misc/blender/default.nix:105', '{"free": true, "fullName": "GNU General Public License v2.0 or later", "spdxId": "GPL-2.0-or-later", "url": "https://spdx.org/licenses/GPL-2.0-or-later.html"}', '["x86_64-linux", "aarch64-linux"]');
+INSERT INTO meta VALUES ('libreoffice', 0, 0, 0, 0, 'Comprehensive, professional-quality productivity suite', '', 'https://libreoffice.org/', '[{"email": null, "github": "nixpkgs-demo", "matrix": null, "name": "Nixpkgs Demo"}]', 'pkgs/applications/office/libreoffice/wrapper.nix:1', '{"free": true, "fullName": "Mozilla Public License 2.0", "spdxId": "MPL-2.0", "url": "https://spdx.org/licenses/MPL-2.0.html"}', '["x86_64-linux", "aarch64-linux"]');
+INSERT INTO meta VALUES ('supertuxkart', 0, 0, 0, 0, 'Free 3D kart racing game', '', 'https://supertuxkart.net/', '[{"email": null, "github": "nixpkgs-demo", "matrix": null, "name": "Nixpkgs Demo"}]', 'pkgs/games/supertuxkart/default.nix:69', '{"free": true, "fullName": "GNU General Public License v2.0 or later", "spdxId": "GPL-2.0-or-later", "url": "https://spdx.org/licenses/GPL-2.0-or-later.html"}', '["x86_64-linux", "aarch64-linux"]');
+INSERT INTO meta VALUES ('vscodium', 0, 0, 0, 0, 'Open source source code editor developed by Microsoft for Windows, Linux and macOS', '', 'https://github.com/VSCodium/vscodium', '[{"email": null, "github": "nixpkgs-demo", "matrix": null, "name": "Nixpkgs Demo"}]', 'pkgs/applications/editors/vscode/vscodium.nix:30', '{"free": true, "fullName": "MIT License", "spdxId": "MIT", "url": "https://spdx.org/licenses/MIT.html"}', '["x86_64-linux", "aarch64-linux"]');
+INSERT INTO meta VALUES ('spotify', 0, 0, 0, 1, 'Play music from the Spotify music service', '', 'https://www.spotify.com/', '[{"email": null, "github": "nixpkgs-demo", "matrix": null, "name": "Nixpkgs Demo"}]', 'pkgs/applications/audio/spotify/linux.nix:170', '{"free": false, "fullName": "Unfree", "spdxId": null, "url": null}', '["x86_64-linux", "aarch64-linux"]');
+INSERT INTO meta VALUES ('gnome-calculator', 0, 0, 0, 0, 'Application that solves mathematical equations and is suitable as a default application in a Desktop environment', '', 'https://apps.gnome.org/Calculator/', '[{"email": null, "github": "nixpkgs-demo", "matrix": null, "name": "Nixpkgs Demo"}]', 'pkgs/by-name/gn/gnome-calculator/package.nix:73', '{"free": true, "fullName": "GNU General Public License v3.0 or later", "spdxId": "GPL-3.0-or-later", "url": "https://spdx.org/licenses/GPL-3.0-or-later.html"}', '["x86_64-linux", "aarch64-linux"]');
+INSERT INTO meta VALUES ('hello', 0, 0, 0, 0, 'Program that produces a familiar, friendly greeting', '', 'https://www.gnu.org/software/hello/manual/', '[{"email": null, "github": "nixpkgs-demo", "matrix": null, "name": "Nixpkgs Demo"}]', 'pkgs/by-name/he/hello/package.nix:44', '{"free": true, "fullName": "GNU General Public License v3.0 or later", "spdxId": "GPL-3.0-or-later", "url": "https://spdx.org/licenses/GPL-3.0-or-later.html"}', '["x86_64-linux", "aarch64-linux"]');
+INSERT INTO meta VALUES ('ripgrep', 0, 0, 0, 0, 'Utility that combines the usability of The Silver Searcher with the raw speed of grep', '', 'https://github.com/BurntSushi/ripgrep', '[{"email": null, "github": "nixpkgs-demo", "matrix": null, "name": "Nixpkgs Demo"}]', 'pkgs/by-name/ri/ripgrep/package.nix:44', '{"free": true, "fullName": "MIT License", "spdxId": "MIT", "url": "https://spdx.org/licenses/MIT.html"}', '["x86_64-linux", "aarch64-linux"]');
+INSERT INTO meta VALUES ('neovim', 0, 0, 0, 0, 'Vim text editor fork focused on extensibility and agility', '', 'https://www.neovim.io', '[{"email": null, "github": "nixpkgs-demo", "matrix": null, "name": "Nixpkgs Demo"}]', 'pkgs/applications/editors/neovim/wrapper.nix:1', '{"free": true, "fullName": "Apache License 2.0", "spdxId": "Apache-2.0", "url": "https://spdx.org/licenses/Apache-2.0.html"}', '["x86_64-linux", "aarch64-linux"]');
+INSERT INTO meta VALUES ('htop', 0, 0, 0, 0, 'Interactive process viewer', '', 'https://htop.dev', '[{"email": null, "github": "nixpkgs-demo", "matrix": null, "name": "Nixpkgs Demo"}]', 'pkgs/tools/system/htop/default.nix:48', '{"free": true, "fullName": "GNU General Public License v2.0 only", "spdxId": "GPL-2.0-only", "url": "https://spdx.org/licenses/GPL-2.0-only.html"}', '["x86_64-linux", "aarch64-linux"]');
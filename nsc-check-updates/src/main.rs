@@ -0,0 +1,139 @@
+use anyhow::Result;
+use clap::Parser;
+use log::*;
+use nix_data::config::configfile::NixDataConfig;
+use nsc_core::{
+    config::{getconfig, getnscconfig, syspkgtype, SystemPkgs},
+    power,
+    profile::{self, UserPkgs},
+    updates::{self, PendingUpgrade},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    process::Command,
+};
+
+/// Prints the pending upgrades of installed packages as JSON
+#[derive(Parser, Debug)]
+#[command(name = "nsc-check-updates")]
+struct Args {
+    /// Send a desktop notification when upgrades are available
+    #[arg(short, long)]
+    notify: bool,
+    /// Only check packages installed in the user profile
+    #[arg(short, long)]
+    user: bool,
+    /// Check even in power saver mode or on low battery
+    #[arg(short, long)]
+    force: bool,
+}
+
+/// Exit code when the check was skipped to save power, so timers can tell it apart from failures
+const EXIT_DEFERRED: i32 = 75;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    pretty_env_logger::init();
+    let args = Args::parse();
+    if !args.force && !getnscconfig().ignorepowersaving {
+        if let Some(reason) = power::deferreason() {
+            eprintln!("Not checking for updates, {}", reason);
+            std::process::exit(EXIT_DEFERRED);
+        }
+    }
+    match check(args.user).await {
+        Ok(upgrades) => {
+            match serde_json::to_string_pretty(&upgrades) {
+                Ok(json) => println!("{}", json),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+            if args.notify && !upgrades.is_empty() {
+                notify(&upgrades);
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn check(useronly: bool) -> Result<Vec<PendingUpgrade>> {
+    let config = getconfig().unwrap_or(NixDataConfig {
+        systemconfig: None,
+        flake: None,
+        flakearg: None,
+        generations: None,
+    });
+    let userpkgs = profile::userpkgtype();
+    let syspkgs = if useronly {
+        SystemPkgs::None
+    } else {
+        syspkgtype(&config)
+    };
+    debug!("userpkgtype: {:?}", userpkgs);
+    debug!("syspkgtype: {:?}", syspkgs);
+
+    let pkgdb = if Path::new("/etc/NIXOS").exists() {
+        nix_data::cache::nixos::nixospkgs().await?
+    } else {
+        nix_data::cache::nonnixos::nixpkgs().await?
+    };
+
+    let mut upgrades = vec![];
+    if let Some(upgrade) = updates::systemupgrade(&syspkgs) {
+        upgrades.push(upgrade);
+    }
+    if let Some(systemconfig) = &config.systemconfig {
+        let systemdb = match syspkgs {
+            SystemPkgs::Legacy => Some(nix_data::cache::channel::legacypkgs().await?),
+            SystemPkgs::Flake => Some(nix_data::cache::flakes::flakespkgs().await?),
+            SystemPkgs::None => None,
+        };
+        let installed = match syspkgs {
+            SystemPkgs::Legacy => nix_data::cache::channel::getlegacypkgs(&[systemconfig])
+                .await?
+                .into_keys()
+                .collect::<HashSet<_>>(),
+            SystemPkgs::Flake => nix_data::cache::flakes::getflakepkgs(&[systemconfig])
+                .await?
+                .into_keys()
+                .collect::<HashSet<_>>(),
+            SystemPkgs::None => HashSet::new(),
+        };
+        if let Some(systemdb) = systemdb {
+            upgrades.extend(updates::systemupgrades(&pkgdb, &systemdb, &installed).await?);
+        }
+    }
+
+    let (nixpkgsdb, installed): (Option<String>, HashMap<String, String>) = match userpkgs {
+        UserPkgs::Profile => (
+            Some(nix_data::cache::profile::nixpkgslatest().await?),
+            nix_data::cache::profile::getprofilepkgs_versioned().await?,
+        ),
+        UserPkgs::Env => (None, nix_data::cache::channel::getenvpkgs()?),
+    };
+    upgrades.extend(updates::userupgrades(&pkgdb, nixpkgsdb.as_deref(), &userpkgs, &installed).await?);
+    Ok(upgrades)
+}
+
+fn notify(upgrades: &[PendingUpgrade]) {
+    let body = if upgrades.len() == 1 {
+        format!("An update is available for {}", upgrades[0].pname)
+    } else {
+        format!("{} updates are available", upgrades.len())
+    };
+    if let Err(e) = Command::new("notify-send")
+        .arg("--app-name=Software Center")
+        .arg("--icon=dev.vlinkz.NixSoftwareCenter")
+        .arg("Updates Available")
+        .arg(body)
+        .status()
+    {
+        warn!("Could not send notification: {}", e);
+    }
+}
@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Result};
+use log::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    http,
+    packages::appsteamdatafrom,
+    popularity::now,
+    state::{self, NscState},
+};
+
+/// The appstream catalog changes much less often than the package set, so it is downloaded
+/// on its own schedule instead of with every package database refresh.
+const INTERVAL: u64 = 7 * 24 * 60 * 60;
+/// Time to wait after a failed download before trying again.
+const RETRY: u64 = 60 * 60;
+
+/// Key of the catalog in [`NscState::refreshed`] and [`NscState::refreshfailed`].
+pub const ARTIFACT: &str = "appstream";
+
+fn appstreamdir() -> Result<PathBuf> {
+    let cache = match std::env::var("XDG_CACHE_HOME") {
+        Ok(cache) => PathBuf::from(cache),
+        Err(_) => PathBuf::from(std::env::var("HOME")?).join(".cache"),
+    };
+    Ok(cache.join("nix-software-center/appstream"))
+}
+
+/// The last catalog that was downloaded and parsed.
+pub fn downloaded() -> Option<String> {
+    let path = appstreamdir().ok()?.join("appstream.yml.gz");
+    path.is_file().then(|| path.to_string_lossy().to_string())
+}
+
+/// Whether the catalog is old enough to download again, and a failed download isn't too recent.
+pub fn due(state: &NscState) -> bool {
+    let now = now();
+    if let Some(failed) = state.refreshfailed.get(ARTIFACT) {
+        if now.saturating_sub(*failed) < RETRY {
+            return false;
+        }
+    }
+    downloaded().is_none()
+        || state
+            .refreshed
+            .get(ARTIFACT)
+            .map(|x| now.saturating_sub(*x) >= INTERVAL)
+            .unwrap_or(true)
+}
+
+/// Downloads the gzipped appstream yaml at `url` next to the current copy, for [`install`].
+pub async fn download(url: &str) -> Result<PathBuf> {
+    let response = http::client().get(url).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Error: {}", response.status()));
+    }
+    let body = response.bytes().await?;
+    let dir = appstreamdir()?;
+    fs::create_dir_all(&dir)?;
+    // Keeps the `.gz` suffix, which decides how it is read
+    let tmp = dir.join("download.yml.gz");
+    fs::write(&tmp, &body)?;
+    Ok(tmp)
+}
+
+/// Replaces the current copy with a [`download`] if it parses, for the next load. Parsing blocks
+/// for a while, so this belongs on a blocking thread.
+pub fn install(tmp: &Path) -> Result<String> {
+    let path = tmp.to_string_lossy().to_string();
+    let apps = appsteamdatafrom(&path).inspect_err(|_| {
+        let _ = fs::remove_file(tmp);
+    })?;
+    if apps.is_empty() {
+        let _ = fs::remove_file(tmp);
+        return Err(anyhow!("No apps in {}", path));
+    }
+    let dest = appstreamdir()?.join("appstream.yml.gz");
+    fs::rename(tmp, &dest)?;
+    info!("Downloaded appstream data for {} apps", apps.len());
+    Ok(dest.to_string_lossy().to_string())
+}
+
+/// Saves the time of a refresh attempt, for [`due`].
+pub fn refreshed(success: bool) {
+    if let Err(e) = state::editnscstate(|x| {
+        if success {
+            x.refreshed.insert(ARTIFACT.to_string(), now());
+            x.refreshfailed.remove(ARTIFACT);
+        } else {
+            x.refreshfailed.insert(ARTIFACT.to_string(), now());
+        }
+    }) {
+        warn!("Failed to save appstream refresh time: {}", e);
+    }
+}
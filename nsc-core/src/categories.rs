@@ -0,0 +1,164 @@
+use log::*;
+use serde::Deserialize;
+use std::{fs, sync::OnceLock};
+
+/// Overrides for downstreams and themes, applied before the user's own.
+pub static SYSTEMCATEGORIES: &str = "/etc/nix-software-center/categories.toml";
+
+/// A category of apps on the front page.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Category {
+    pub id: String,
+    pub name: String,
+    /// Icon name of the category button
+    pub icon: String,
+    /// CSS color the category button is tinted with, e.g. `#3584e4`
+    pub color: Option<String>,
+    /// What "Browse All" searches for
+    pub search: String,
+    /// nixpkgs directories whose packages belong to the category
+    pub paths: Vec<String>,
+    /// Freedesktop categories of apps that belong to the category
+    pub xdg: Vec<String>,
+}
+
+impl Category {
+    /// Whether a package defined at `position` with the freedesktop categories `xdg` belongs here.
+    pub fn matches(&self, position: &str, xdg: &[String]) -> bool {
+        self.inpath(position) || self.xdg.iter().any(|x| xdg.contains(x))
+    }
+
+    pub fn inpath(&self, position: &str) -> bool {
+        self.paths.iter().any(|x| position.starts_with(x.as_str()))
+    }
+}
+
+/// One `[[category]]` of an override file. Fields left out keep their built-in value.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct CategoryOverride {
+    id: String,
+    name: Option<String>,
+    icon: Option<String>,
+    color: Option<String>,
+    search: Option<String>,
+    paths: Option<Vec<String>>,
+    xdg: Option<Vec<String>>,
+    /// Removes a built-in category
+    hidden: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct CategoryFile {
+    category: Vec<CategoryOverride>,
+}
+
+fn builtin(id: &str, name: &str, icon: &str, search: &str, paths: &[&str], xdg: &[&str]) -> Category {
+    Category {
+        id: id.to_string(),
+        name: name.to_string(),
+        icon: icon.to_string(),
+        color: None,
+        search: search.to_string(),
+        paths: paths.iter().map(|x| x.to_string()).collect(),
+        xdg: xdg.iter().map(|x| x.to_string()).collect(),
+    }
+}
+
+fn builtins() -> Vec<Category> {
+    vec![
+        builtin("audio", "Audio", "nsc-audio", "audio", &["pkgs/applications/audio"], &["Audio"]),
+        builtin(
+            "development",
+            "Development",
+            "nsc-development",
+            "development",
+            &["pkgs/development", "pkgs/applications/terminal-emulators"],
+            &["Development"],
+        ),
+        builtin(
+            "games",
+            "Games",
+            "nsc-gaming",
+            "game",
+            &["pkgs/games", "pkgs/applications/emulators", "pkgs/tools/games"],
+            &["Games"],
+        ),
+        builtin("graphics", "Graphics", "nsc-graphics", "graphics", &["pkgs/applications/graphics"], &["Graphics"]),
+        builtin("web", "Web", "nsc-web", "web", &["pkgs/applications/networking"], &["Network"]),
+        builtin("video", "Video", "nsc-video", "video", &["pkgs/applications/video"], &["Video"]),
+    ]
+}
+
+fn usercategories() -> Option<String> {
+    let config = std::env::var("XDG_CONFIG_HOME")
+        .ok()
+        .or_else(|| std::env::var("HOME").ok().map(|x| format!("{}/.config", x)))?;
+    Some(format!("{}/nix-software-center/categories.toml", config))
+}
+
+/// Applies the `[[category]]` entries of `contents` on top of `categories`.
+pub fn applyoverrides(categories: &mut Vec<Category>, contents: &str) -> Result<(), toml::de::Error> {
+    let file: CategoryFile = toml::from_str(contents)?;
+    for o in file.category {
+        if o.id.is_empty() {
+            continue;
+        }
+        if o.hidden {
+            categories.retain(|x| x.id != o.id);
+            continue;
+        }
+        let i = match categories.iter().position(|x| x.id == o.id) {
+            Some(i) => i,
+            None => {
+                categories.push(Category {
+                    id: o.id.clone(),
+                    name: o.id.clone(),
+                    icon: String::from("applications-other-symbolic"),
+                    search: o.id.clone(),
+                    ..Default::default()
+                });
+                categories.len() - 1
+            }
+        };
+        let c = &mut categories[i];
+        if let Some(name) = o.name {
+            c.name = name;
+        }
+        if let Some(icon) = o.icon {
+            c.icon = icon;
+        }
+        if o.color.is_some() {
+            c.color = o.color;
+        }
+        if let Some(search) = o.search {
+            c.search = search;
+        }
+        if let Some(paths) = o.paths {
+            c.paths = paths;
+        }
+        if let Some(xdg) = o.xdg {
+            c.xdg = xdg;
+        }
+    }
+    Ok(())
+}
+
+static CATEGORIES: OnceLock<Vec<Category>> = OnceLock::new();
+
+/// Categories on the front page in order: the built-in ones with the overrides of the
+/// system and then the user applied.
+pub fn categories() -> &'static [Category] {
+    CATEGORIES.get_or_init(|| {
+        let mut categories = builtins();
+        for path in std::iter::once(SYSTEMCATEGORIES.to_string()).chain(usercategories()) {
+            if let Ok(contents) = fs::read_to_string(&path) {
+                if let Err(e) = applyoverrides(&mut categories, &contents) {
+                    error!("Invalid categories in {}: {}", path, e);
+                }
+            }
+        }
+        categories
+    })
+}
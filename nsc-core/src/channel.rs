@@ -0,0 +1,23 @@
+use anyhow::{anyhow, Result};
+use std::time::Duration;
+
+use super::{http, util};
+
+/// How often to look for a newer channel release while running.
+pub const POLLINTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Channel the package database is built from.
+pub fn channel() -> String {
+    util::nixosrelease().unwrap_or_else(|| String::from("nixpkgs-unstable"))
+}
+
+/// Nixpkgs revision of the newest release of [`channel`]. Not cached, since it is polled to
+/// notice new releases.
+pub async fn latestrevision() -> Result<String> {
+    let url = format!("https://channels.nixos.org/{}/git-revision", channel());
+    let response = http::client().get(&url).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("{}: {}", url, response.status()));
+    }
+    Ok(response.text().await?.trim().to_string())
+}
@@ -0,0 +1,142 @@
+use super::config::NscConfig;
+use super::i18n::gettext;
+use super::nixversion;
+use log::*;
+use nix_data::config::configfile::NixDataConfig;
+use std::path::Path;
+
+/// Creates a command for `program`, wrapped in `nice`/`ionice` when low priority is enabled.
+/// Note that on multi-user installs the builds themselves run in nix-daemon,
+/// so only `--max-jobs` and `--cores` affect them.
+pub fn nixcmd(program: &str, config: &NscConfig) -> tokio::process::Command {
+    let mut cmd = if config.lowpriority {
+        let mut cmd = tokio::process::Command::new("nice");
+        cmd.arg("-n")
+            .arg("19")
+            .arg("ionice")
+            .arg("-c")
+            .arg("3")
+            .arg(program);
+        cmd
+    } else {
+        tokio::process::Command::new(program)
+    };
+    if program == "nix" {
+        cmd.args(nixversion::featureargs());
+    }
+    cmd
+}
+
+/// Build options understood by `nix`, `nix-env` and `nixos-rebuild`.
+pub fn nixargs(config: &NscConfig) -> Vec<String> {
+    let mut args = vec![];
+    if let Some(jobs) = config.maxjobs {
+        args.push(String::from("--max-jobs"));
+        args.push(jobs.to_string());
+    }
+    if let Some(cores) = config.cores {
+        args.push(String::from("--cores"));
+        args.push(cores.to_string());
+    }
+    args
+}
+
+/// `--impure` if enabled, for commands that evaluate nixpkgs through the new CLI or flakes.
+pub fn impureargs(config: &NscConfig) -> Vec<String> {
+    if config.impure {
+        vec![String::from("--impure")]
+    } else {
+        vec![]
+    }
+}
+
+/// Explains an evaluation error that is likely caused by running without `--impure`.
+pub fn purityhint(line: &str, config: &NscConfig) -> Option<String> {
+    if config.impure {
+        return None;
+    }
+    if line.contains("in pure evaluation mode") || line.contains("attribute 'currentSystem' missing") {
+        Some(gettext("Evaluation failed because it needs access to files or the environment outside the store. Enable \"Impure evaluation\" in the advanced options or preferences and try again."))
+    } else if line.contains("has an unfree license") || line.contains("is marked as insecure") || line.contains("is marked as broken") {
+        Some(gettext("Environment variables such as NIXPKGS_ALLOW_UNFREE are ignored in pure evaluation mode. Enable \"Impure evaluation\" in the advanced options or preferences and try again."))
+    } else {
+        None
+    }
+}
+
+/// Splits user supplied arguments the way a shell would, e.g. failing on an unclosed quote.
+pub fn extraargs(args: &str) -> Result<Vec<String>, glib::Error> {
+    if args.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(glib::shell_parse_argv(args)?
+        .into_iter()
+        .map(|x| x.to_string_lossy().to_string())
+        .collect())
+}
+
+/// The command line `nixcmd` would run, for display purposes.
+pub fn cmdline(program: &str, args: &[String], config: &NscConfig) -> String {
+    let mut out = vec![];
+    if config.lowpriority {
+        out.extend(["nice", "-n", "19", "ionice", "-c", "3"].map(String::from));
+    }
+    out.push(program.to_string());
+    let featureargs = if program == "nix" { nixversion::featureargs() } else { vec![] };
+    for arg in featureargs.iter().chain(args) {
+        if !arg.is_empty()
+            && arg
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_./#:=+,@".contains(c))
+        {
+            out.push(arg.to_string());
+        } else {
+            out.push(glib::shell_quote(arg).to_string_lossy().to_string());
+        }
+    }
+    out.join(" ")
+}
+
+/// Path to the privileged `nsc-helper`, preferring the one installed next to this binary.
+pub fn helperpath() -> String {
+    match std::env::current_exe() {
+        Ok(mut e) => {
+            e.pop(); // root/bin
+            e.pop(); // root/
+            e.push("libexec"); // root/libexec
+            e.push("nsc-helper");
+            let x = e.to_string_lossy().to_string();
+            info!("nsc-helper path: {}", x);
+            if Path::new(&x).is_file() {
+                x
+            } else {
+                String::from("nsc-helper")
+            }
+        }
+        Err(_) => String::from("nsc-helper"),
+    }
+}
+
+/// `--flake` arguments passed to `nixos-rebuild`, if the system is configured with a flake.
+pub fn rebuildargs(config: &NixDataConfig) -> Vec<String> {
+    let flakeargs = if let Some(flake) = &config.flake {
+        if let Some(flakearg) = &config.flakearg {
+            Some(format!("{}#{}", flake, flakearg))
+        } else {
+            Some(flake.to_string())
+        }
+    } else {
+        None
+    };
+    if let Some(x) = flakeargs {
+        let mut v = vec![String::from("--flake")];
+        for arg in x.split(' ') {
+            if !arg.is_empty() {
+                v.push(String::from(arg));
+            }
+        }
+        v
+    } else {
+        vec![]
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::Result;
+use log::*;
+use nix_data::config::configfile::NixDataConfig;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+pub fn getconfig() -> Option<NixDataConfig> {
+    if let Ok(c) = nix_data::config::configfile::getconfig() {
+        Some(c)
+    } else {
+        None
+    }
+}
+
+pub fn editconfig(config: NixDataConfig) -> Result<()> {
+    nix_data::config::configfile::setuserconfig(config)?;
+    Ok(())
+}
+
+/// How the system configuration is built
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SystemPkgs {
+    Legacy,
+    Flake,
+    None,
+}
+
+pub fn syspkgtype(config: &NixDataConfig) -> SystemPkgs {
+    let nixos = Path::new("/etc/NIXOS").exists();
+    if config.systemconfig.is_none() || !nixos {
+        SystemPkgs::None
+    } else {
+        match fs::read_to_string("/run/current-system/nixos-version") {
+            Ok(s) => {
+                if !Path::new("/nix/var/nix/profiles/per-user/root/channels/nixos").exists()
+                    || config.flake.is_some()
+                {
+                    SystemPkgs::Flake
+                } else if let Some(last) = s.split('.').last() {
+                    if last.len() == 7 || last == "dirty" || last == "git" {
+                        SystemPkgs::Flake
+                    } else {
+                        SystemPkgs::Legacy
+                    }
+                } else {
+                    SystemPkgs::Legacy
+                }
+            }
+            Err(_) => SystemPkgs::None,
+        }
+    }
+}
+
+/// Settings specific to Nix Software Center, stored next to the nix-data config.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct NscConfig {
+    pub maxjobs: Option<u32>,
+    pub cores: Option<u32>,
+    pub lowpriority: bool,
+    pub impure: bool,
+    /// Screenshots downloaded at the same time
+    pub maxdownloads: Option<u32>,
+    /// Preseeded metadata to fall back to when the databases can't be downloaded
+    pub bundle: Option<String>,
+    /// Opted in to sending anonymous popularity statistics
+    pub popularity: bool,
+    pub popularityurl: Option<String>,
+    /// Published popularity dataset used for ranking and the trending section
+    pub popularitydata: Option<String>,
+    /// Gzipped appstream yaml refreshed weekly, in place of the catalog installed with the application
+    pub appstreamurl: Option<String>,
+    /// Opted in to sending crash reports
+    pub crashreports: bool,
+    pub crashreportsurl: Option<String>,
+    /// Unfree packages the user approved, by name
+    pub allowunfree: Vec<String>,
+    /// Insecure packages the user approved, by name and version
+    pub allowinsecure: Vec<String>,
+    /// SSH hosts whose user profiles can be managed, as given to `ssh`
+    pub remotehosts: Vec<String>,
+    /// Refresh metadata and check for updates in power saver mode or on low battery too
+    pub ignorepowersaving: bool,
+}
+
+impl Default for NscConfig {
+    fn default() -> Self {
+        Self {
+            maxjobs: None,
+            cores: None,
+            lowpriority: false,
+            // Matches the behaviour before this was configurable
+            impure: true,
+            maxdownloads: None,
+            bundle: None,
+            popularity: false,
+            popularityurl: None,
+            popularitydata: None,
+            appstreamurl: None,
+            crashreports: false,
+            crashreportsurl: None,
+            allowunfree: vec![],
+            allowinsecure: vec![],
+            remotehosts: vec![],
+            ignorepowersaving: false,
+        }
+    }
+}
+
+fn nscconfigpath() -> Result<String> {
+    let home = std::env::var("HOME")?;
+    Ok(format!("{}/.config/nix-software-center/config.json", home))
+}
+
+pub fn getnscconfig() -> NscConfig {
+    if let Ok(path) = nscconfigpath() {
+        if let Ok(s) = fs::read_to_string(path) {
+            if let Ok(c) = serde_json::from_str(&s) {
+                return c;
+            }
+        }
+    }
+    NscConfig::default()
+}
+
+pub fn editnscconfig(config: NscConfig) -> Result<()> {
+    let path = nscconfigpath()?;
+    if let Some(parent) = Path::new(&path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&config)?)?;
+    Ok(())
+}
+
+/// Restrictions an administrator can set in `/etc/nix-software-center/policy.toml`, e.g.
+///
+/// ```toml
+/// allow = ["firefox", "libreoffice", "gimp"]
+/// deny = ["steam"]
+/// noremove = true
+/// nounfree = true
+/// nomature = true
+/// ```
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Policy {
+    /// Only these attributes are shown and can be installed, when set
+    pub allow: Option<Vec<String>>,
+    /// Attributes that are hidden and can't be installed
+    pub deny: Vec<String>,
+    /// Packages can't be removed
+    pub noremove: bool,
+    /// Packages with an unfree license can't be installed
+    pub nounfree: bool,
+    /// Screenshots of apps rated for mature content stay blurred
+    pub nomature: bool,
+}
+
+impl Policy {
+    /// Whether `attr` is part of the catalog.
+    pub fn allows(&self, attr: &str) -> bool {
+        !self.deny.iter().any(|x| x == attr)
+            && self.allow.as_ref().map(|x| x.iter().any(|x| x == attr)).unwrap_or(true)
+    }
+
+    /// Why installing `attr` is refused, if it is. `unfree` is whether any of its licenses is unfree.
+    pub fn blocksinstall(&self, attr: &str, unfree: bool) -> Option<PolicyBlock> {
+        if !self.allows(attr) {
+            Some(PolicyBlock::NotAllowed)
+        } else if self.nounfree && unfree {
+            Some(PolicyBlock::Unfree)
+        } else {
+            None
+        }
+    }
+
+    pub fn restricted(&self) -> bool {
+        *self != Policy::default()
+    }
+}
+
+/// A [`Policy`] rule that keeps a package from being installed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyBlock {
+    NotAllowed,
+    Unfree,
+}
+
+pub static POLICYPATH: &str = "/etc/nix-software-center/policy.toml";
+
+pub fn getpolicy() -> Policy {
+    match fs::read_to_string(POLICYPATH) {
+        Ok(s) => match toml::from_str(&s) {
+            Ok(policy) => policy,
+            Err(e) => {
+                error!("Invalid policy in {}: {}", POLICYPATH, e);
+                // Fail closed so a typo doesn't lift the restrictions
+                Policy {
+                    allow: Some(vec![]),
+                    deny: vec![],
+                    noremove: true,
+                    nounfree: true,
+                    nomature: true,
+                }
+            }
+        },
+        Err(_) => Policy::default(),
+    }
+}
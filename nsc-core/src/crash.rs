@@ -0,0 +1,180 @@
+use anyhow::{anyhow, Result};
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    backtrace::Backtrace,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+use super::{http, popularity::now, state, util};
+
+/// Failures of the same operation in one run before they are worth a report.
+const FAILURES: u32 = 3;
+
+static VERSION: OnceLock<String> = OnceLock::new();
+static FAILED: Mutex<Option<HashMap<String, u32>>> = Mutex::new(None);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CrashKind {
+    Panic,
+    /// The same operation failed again and again
+    Failure,
+}
+
+/// What went wrong, written to the state directory. Nothing is sent unless the user opted in.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CrashReport {
+    /// Unix time
+    pub time: u64,
+    pub kind: CrashKind,
+    /// Thread that panicked, or the operation that kept failing
+    pub component: String,
+    pub message: String,
+    pub location: Option<String>,
+    pub backtrace: Option<String>,
+    pub version: String,
+    pub system: String,
+    pub os: Option<String>,
+    /// Already sent to the crash report server
+    #[serde(default)]
+    pub sent: bool,
+}
+
+impl CrashReport {
+    fn new(kind: CrashKind, component: &str, message: &str) -> Self {
+        Self {
+            time: now(),
+            kind,
+            component: component.to_string(),
+            message: message.to_string(),
+            location: None,
+            backtrace: None,
+            version: VERSION.get().cloned().unwrap_or_default(),
+            system: util::currentsystem(),
+            os: osname(),
+            sent: false,
+        }
+    }
+}
+
+fn osname() -> Option<String> {
+    let release = fs::read_to_string("/etc/os-release").ok()?;
+    release
+        .lines()
+        .find_map(|x| x.strip_prefix("PRETTY_NAME="))
+        .map(|x| x.trim_matches('"').to_string())
+}
+
+pub fn crashdir() -> Result<PathBuf> {
+    Ok(state::statedir()?.join("crashes"))
+}
+
+pub fn write(report: &CrashReport) -> Result<PathBuf> {
+    let dir = crashdir()?;
+    fs::create_dir_all(&dir)?;
+    let mut path = dir.join(format!("{}.json", report.time));
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{}-{}.json", report.time, n));
+        n += 1;
+    }
+    fs::write(&path, serde_json::to_string_pretty(report)?)?;
+    Ok(path)
+}
+
+/// Saved reports, newest first.
+pub fn reports() -> Vec<(PathBuf, CrashReport)> {
+    let Ok(entries) = crashdir().and_then(|x| Ok(fs::read_dir(x)?)) else {
+        return vec![];
+    };
+    let mut reports = entries
+        .filter_map(|x| x.ok())
+        .map(|x| x.path())
+        .filter(|x| x.extension().map(|x| x == "json").unwrap_or(false))
+        .filter_map(|path| {
+            let report = serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+            Some((path, report))
+        })
+        .collect::<Vec<(PathBuf, CrashReport)>>();
+    reports.sort_by_key(|(_, x)| std::cmp::Reverse(x.time));
+    reports
+}
+
+pub fn remove(path: &Path) -> Result<()> {
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// Writes a report for every panic before the default hook prints it.
+pub fn installhook(version: &str) {
+    let _ = VERSION.set(version.to_string());
+    let default = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = if let Some(s) = info.payload().downcast_ref::<&str>() {
+            s.to_string()
+        } else if let Some(s) = info.payload().downcast_ref::<String>() {
+            s.clone()
+        } else {
+            String::from("Unknown panic")
+        };
+        let thread = std::thread::current();
+        let mut report = CrashReport::new(CrashKind::Panic, thread.name().unwrap_or("unnamed"), &message);
+        report.location = info.location().map(|x| x.to_string());
+        report.backtrace = Some(Backtrace::force_capture().to_string());
+        match write(&report) {
+            Ok(path) => error!("Crash report written to {}", path.display()),
+            Err(e) => error!("Failed to write crash report: {}", e),
+        }
+        default(info);
+    }));
+}
+
+/// Counts a failure of `component`, writing a report once it has failed `FAILURES` times this run.
+pub fn failure(component: &str, message: &str) {
+    let count = {
+        let mut failed = FAILED.lock().unwrap_or_else(|x| x.into_inner());
+        let count = failed.get_or_insert_with(HashMap::new).entry(component.to_string()).or_default();
+        *count += 1;
+        *count
+    };
+    if count % FAILURES == 0 {
+        let report = CrashReport::new(CrashKind::Failure, component, &format!("Failed {} times, last: {}", count, message));
+        match write(&report) {
+            Ok(path) => warn!("{} keeps failing, report written to {}", component, path.display()),
+            Err(e) => warn!("Failed to write crash report: {}", e),
+        }
+    }
+}
+
+/// Sends a report to `url` as JSON.
+pub async fn send(url: &str, report: &CrashReport) -> Result<()> {
+    let response = http::client()
+        .post(url)
+        .header("Content-Type", "application/json")
+        .body(serde_json::to_string(report)?)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Error: {}", response.status()));
+    }
+    Ok(())
+}
+
+/// Sends the reports that haven't been sent yet and marks them as sent.
+pub async fn sendpending(url: &str) {
+    for (path, mut report) in reports().into_iter().filter(|(_, x)| !x.sent) {
+        if let Err(e) = send(url, &report).await {
+            warn!("Failed to send crash report {}: {}", path.display(), e);
+            return;
+        }
+        info!("Sent crash report {}", path.display());
+        report.sent = true;
+        if let Err(e) = serde_json::to_string_pretty(&report).map_err(anyhow::Error::from).and_then(|x| Ok(fs::write(&path, x)?)) {
+            warn!("Failed to mark {} as sent: {}", path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use super::{
+    cmd::{impureargs, nixcmd},
+    config::NscConfig,
+};
+use anyhow::{anyhow, Result};
+use log::*;
+use serde_json::Value;
+use std::{collections::HashMap, path::Path};
+
+const SUBSTITUTER: &str = "https://cache.nixos.org";
+
+/// The runtime closure of a store path: every path it pulls in and what refers to what.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dependencies {
+    pub root: String,
+    /// Direct references of each path in the closure, without self references
+    pub references: HashMap<String, Vec<String>>,
+    /// Unpacked size of each path in bytes
+    pub narsize: HashMap<String, u64>,
+}
+
+impl Dependencies {
+    /// Paths the closure is made of, including the root.
+    pub fn paths(&self) -> usize {
+        self.references.len()
+    }
+
+    /// Unpacked size of the whole closure in bytes.
+    pub fn closuresize(&self) -> u64 {
+        self.narsize.values().sum()
+    }
+
+    pub fn children(&self, path: &str) -> &[String] {
+        self.references.get(path).map(|x| x.as_slice()).unwrap_or_default()
+    }
+}
+
+/// `/nix/store/<hash>-firefox-120.0` to `firefox-120.0`.
+pub fn pathname(path: &str) -> &str {
+    path.strip_prefix("/nix/store/")
+        .and_then(|x| x.get(33..))
+        .unwrap_or(path)
+}
+
+/// Store path the `nixpkgs#pkg` attribute evaluates to.
+pub async fn outpath(pkg: &str, nscconfig: &NscConfig) -> Result<String> {
+    let output = nixcmd("nix", nscconfig)
+        .arg("eval")
+        .arg("--raw")
+        .arg(format!("nixpkgs#{}.outPath", pkg))
+        .args(impureargs(nscconfig))
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!("Failed to evaluate {}", pkg));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Closure of `pkg`, read from the local store when it is installed and from the binary cache otherwise,
+/// so nothing has to be downloaded to see it.
+pub async fn fetchdependencies(pkg: &str, nscconfig: &NscConfig) -> Result<Dependencies> {
+    let root = outpath(pkg, nscconfig).await?;
+    let mut cmd = nixcmd("nix", nscconfig);
+    cmd.arg("path-info").arg("--recursive").arg("--json");
+    if !Path::new(&root).exists() {
+        cmd.arg("--store").arg(SUBSTITUTER);
+    }
+    let output = cmd.arg(&root).output().await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to query dependencies of {}: {}",
+            pkg,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let deps = parsepathinfo(&root, &serde_json::from_slice(&output.stdout)?);
+    debug!("{} depends on {} paths", pkg, deps.paths());
+    Ok(deps)
+}
+
+/// Reads `nix path-info --json`, which is a list of objects with a `path` before nix 2.19 and a map by path after.
+pub fn parsepathinfo(root: &str, json: &Value) -> Dependencies {
+    let entries: Vec<(String, &Value)> = match json {
+        Value::Array(list) => list
+            .iter()
+            .filter_map(|x| Some((x.get("path")?.as_str()?.to_string(), x)))
+            .collect(),
+        Value::Object(map) => map.iter().map(|(k, v)| (k.to_string(), v)).collect(),
+        _ => vec![],
+    };
+    let mut deps = Dependencies {
+        root: root.to_string(),
+        ..Default::default()
+    };
+    for (path, info) in entries {
+        let path = storepath(&path);
+        let references = info
+            .get("references")
+            .and_then(|x| x.as_array())
+            .map(|x| {
+                x.iter()
+                    .filter_map(|x| x.as_str())
+                    .map(storepath)
+                    .filter(|x| *x != path)
+                    .collect()
+            })
+            .unwrap_or_default();
+        if let Some(size) = info.get("narSize").and_then(|x| x.as_u64()) {
+            deps.narsize.insert(path.clone(), size);
+        }
+        deps.references.insert(path, references);
+    }
+    deps
+}
+
+/// Newer nix versions may leave out the store directory.
+fn storepath(path: &str) -> String {
+    if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/nix/store/{}", path)
+    }
+}
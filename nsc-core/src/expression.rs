@@ -0,0 +1,57 @@
+use anyhow::{anyhow, Result};
+
+use super::{channel, http};
+
+/// Where a package is defined in nixpkgs, from the `position` meta field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    /// Path relative to the root of nixpkgs, e.g. `pkgs/by-name/he/hello/package.nix`
+    pub path: String,
+    pub line: Option<usize>,
+}
+
+impl Position {
+    /// Parses `pkgs/tools/foo/default.nix:12`, also accepting absolute store paths to a nixpkgs source.
+    pub fn parse(position: &str) -> Option<Self> {
+        let (path, line) = match position.rsplit_once(':') {
+            Some((path, line)) if line.chars().all(|x| x.is_ascii_digit()) => (path, line.parse().ok()),
+            _ => (position, None),
+        };
+        let path = if path.starts_with('/') {
+            let start = path.find("/pkgs/").or_else(|| path.find("/nixos/"))?;
+            &path[start + 1..]
+        } else {
+            path
+        };
+        if path.is_empty() {
+            return None;
+        }
+        Some(Position {
+            path: path.to_string(),
+            line,
+        })
+    }
+
+    /// Link to the file on GitHub, on the branch the package database was built from.
+    pub fn weburl(&self) -> String {
+        let url = format!("https://github.com/NixOS/nixpkgs/blob/{}/{}", branch(), self.path);
+        match self.line {
+            Some(line) => format!("{}#L{}", url, line),
+            None => url,
+        }
+    }
+
+    fn rawurl(&self) -> String {
+        format!("https://raw.githubusercontent.com/NixOS/nixpkgs/{}/{}", branch(), self.path)
+    }
+}
+
+fn branch() -> String {
+    channel::channel()
+}
+
+/// Downloads the file a package is defined in. Responses are cached by [`http::get`].
+pub async fn fetchexpression(position: &Position) -> Result<String> {
+    let bytes = http::get(&position.rawurl()).await?;
+    String::from_utf8(bytes).map_err(|_| anyhow!("{} is not valid UTF-8", position.path))
+}
@@ -0,0 +1,66 @@
+use anyhow::{anyhow, Result};
+use image::{imageops::FilterType, ImageFormat};
+use std::{
+    env, fs,
+    time::{Duration, SystemTime},
+};
+
+use super::http;
+
+/// How long a site without a favicon is left alone before trying again.
+const RETRYAFTER: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Host of `url` without `www.`, e.g. `gnome.org` for `https://www.gnome.org/apps/`.
+pub fn domain(url: &str) -> Option<String> {
+    let rest = url.trim().split_once("://").map(|(_, x)| x).unwrap_or(url.trim());
+    let host = rest.split(['/', '?', '#']).next()?;
+    // Drop credentials and the port
+    let host = host.rsplit('@').next()?.split(':').next()?.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host).to_string();
+    if host.contains('.') {
+        Some(host)
+    } else {
+        None
+    }
+}
+
+/// Downloads the favicon of the site `url` is on, returning the path of a cached 32px png.
+/// Sites without one are remembered for a week, so pages can ask again on every open.
+pub async fn fetchfavicon(url: &str) -> Result<String> {
+    let domain = domain(url).ok_or_else(|| anyhow!("No host in {}", url))?;
+    let icondir = format!("{}/.cache/nix-software-center/favicons", env::var("HOME")?);
+    let pngpath = format!("{}/{}.png", icondir, domain);
+    let missingpath = format!("{}/{}.missing", icondir, domain);
+    if fs::metadata(&pngpath).is_ok() {
+        return Ok(pngpath);
+    }
+    let missing = fs::metadata(&missingpath)
+        .and_then(|x| x.modified())
+        .ok()
+        .and_then(|x| SystemTime::now().duration_since(x).ok());
+    if missing.map(|x| x < RETRYAFTER).unwrap_or(false) {
+        return Err(anyhow!("{} has no favicon", domain));
+    }
+
+    fs::create_dir_all(&icondir)?;
+    let slot = http::downloadslot().await?;
+    let bytes = http::get(&format!("https://{}/favicon.ico", domain)).await;
+    drop(slot);
+    let img = bytes.map_err(anyhow::Error::from).and_then(|x| {
+        let format = image::guess_format(&x)?;
+        Ok(image::load_from_memory_with_format(&x, format)?)
+    });
+    match img {
+        Ok(img) => {
+            let tmppng = format!("{}.{}", pngpath, rand::random::<u32>());
+            img.resize(32, 32, FilterType::Lanczos3)
+                .save_with_format(&tmppng, ImageFormat::Png)?;
+            fs::rename(&tmppng, &pngpath)?;
+            Ok(pngpath)
+        }
+        Err(e) => {
+            fs::write(&missingpath, "")?;
+            Err(e)
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use super::i18n::{gettext, gettextf};
+use std::{fs, sync::OnceLock};
+
+/// NixOS configuration a package needs beyond being installed, e.g. a daemon, group or setuid wrapper.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemNote {
+    pub reason: &'static str,
+    /// Lines to add to `configuration.nix`
+    pub snippet: &'static str,
+}
+
+/// Packages known to need system configuration, by `pname`.
+const SYSTEMNOTES: &[(&[&str], SystemNote)] = &[
+    (
+        &["steam"],
+        SystemNote {
+            reason: "Steam needs 32-bit graphics drivers and udev rules for controllers, which only the NixOS module sets up.",
+            snippet: "programs.steam.enable = true;",
+        },
+    ),
+    (
+        &["virtualbox"],
+        SystemNote {
+            reason: "VirtualBox needs its kernel modules and the vboxusers group.",
+            snippet: "virtualisation.virtualbox.host.enable = true;\nusers.extraGroups.vboxusers.members = [ \"<your user>\" ];",
+        },
+    ),
+    (
+        &["docker", "docker-client", "docker-compose"],
+        SystemNote {
+            reason: "The Docker client needs the Docker daemon running on the system.",
+            snippet: "virtualisation.docker.enable = true;\nusers.users.<your user>.extraGroups = [ \"docker\" ];",
+        },
+    ),
+    (
+        &["podman", "podman-compose", "podman-desktop"],
+        SystemNote {
+            reason: "Podman needs container policy files and user namespace mappings from the NixOS module.",
+            snippet: "virtualisation.podman.enable = true;",
+        },
+    ),
+    (
+        &["virt-manager", "libvirt"],
+        SystemNote {
+            reason: "Virtual Machine Manager needs the libvirt daemon running on the system.",
+            snippet: "virtualisation.libvirtd.enable = true;\nprograms.virt-manager.enable = true;\nusers.users.<your user>.extraGroups = [ \"libvirtd\" ];",
+        },
+    ),
+    (
+        &["wireshark", "wireshark-qt"],
+        SystemNote {
+            reason: "Capturing packets needs the dumpcap wrapper and the wireshark group.",
+            snippet: "programs.wireshark.enable = true;\nusers.users.<your user>.extraGroups = [ \"wireshark\" ];",
+        },
+    ),
+    (
+        &["gamemode"],
+        SystemNote {
+            reason: "GameMode needs its daemon and permissions to change the CPU governor.",
+            snippet: "programs.gamemode.enable = true;",
+        },
+    ),
+    (
+        &["kdeconnect-kde", "kdeconnect"],
+        SystemNote {
+            reason: "KDE Connect needs firewall ports opened to find other devices.",
+            snippet: "programs.kdeconnect.enable = true;",
+        },
+    ),
+    (
+        &["flatpak"],
+        SystemNote {
+            reason: "Flatpak needs its system service and portals to install applications.",
+            snippet: "services.flatpak.enable = true;",
+        },
+    ),
+    (
+        &["zsh"],
+        SystemNote {
+            reason: "Using Zsh as a login shell needs it listed in /etc/shells with its completions set up.",
+            snippet: "programs.zsh.enable = true;",
+        },
+    ),
+    (
+        &["fish"],
+        SystemNote {
+            reason: "Using fish as a login shell needs it listed in /etc/shells with its completions set up.",
+            snippet: "programs.fish.enable = true;",
+        },
+    ),
+    (
+        &["tailscale"],
+        SystemNote {
+            reason: "Tailscale needs its daemon running on the system.",
+            snippet: "services.tailscale.enable = true;",
+        },
+    ),
+    (
+        &["corectrl"],
+        SystemNote {
+            reason: "CoreCtrl needs a polkit helper to change hardware settings.",
+            snippet: "programs.corectrl.enable = true;",
+        },
+    ),
+    (
+        &["openrgb"],
+        SystemNote {
+            reason: "OpenRGB needs udev rules and kernel modules to reach lighting controllers.",
+            snippet: "services.hardware.openrgb.enable = true;",
+        },
+    ),
+    (
+        &["noisetorch"],
+        SystemNote {
+            reason: "NoiseTorch needs a capability wrapper to create its virtual microphone.",
+            snippet: "programs.noisetorch.enable = true;",
+        },
+    ),
+];
+
+/// System configuration `pname` needs to work, if it is one of the known packages.
+pub fn systemnote(pname: &str) -> Option<SystemNote> {
+    SYSTEMNOTES
+        .iter()
+        .find(|(pnames, _)| pnames.contains(&pname))
+        .map(|(_, note)| note.clone())
+}
+
+/// Display server of a graphical session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayServer {
+    X11,
+    Wayland,
+}
+
+/// Packages that only work with one display server. Appstream has no field for this, so it is
+/// kept by hand, leaving out apps that run fine through Xwayland.
+const X11ONLY: &[&str] = &[
+    "autokey", "awesome", "barrier", "bspwm", "dmenu", "i3", "nitrogen", "openbox", "picom", "polybar", "redshift", "rofi",
+    "screenkey", "simplescreenrecorder", "sxhkd", "wmctrl", "xbindkeys", "xcape", "xclip", "xdotool", "xkill", "xscreensaver",
+    "xsel",
+];
+const WAYLANDONLY: &[&str] = &[
+    "cage", "foot", "fuzzel", "grim", "hyprland", "hyprpaper", "kanshi", "mako", "niri", "rofi-wayland", "slurp", "sway",
+    "swaybg", "swayidle", "swaylock", "tofi", "waybar", "wdisplays", "wf-recorder", "wl-clipboard", "wlr-randr", "wlsunset",
+    "wofi", "wtype",
+];
+
+/// Display server of the running session, `None` outside a graphical session.
+pub fn session() -> Option<DisplayServer> {
+    match std::env::var("XDG_SESSION_TYPE").as_deref() {
+        Ok("wayland") => Some(DisplayServer::Wayland),
+        Ok("x11") => Some(DisplayServer::X11),
+        _ if std::env::var_os("WAYLAND_DISPLAY").is_some() => Some(DisplayServer::Wayland),
+        _ if std::env::var_os("DISPLAY").is_some() => Some(DisplayServer::X11),
+        _ => None,
+    }
+}
+
+/// The only display server `pname` works with, if it is limited to one.
+pub fn displayserver(pname: &str) -> Option<DisplayServer> {
+    if X11ONLY.contains(&pname) {
+        Some(DisplayServer::X11)
+    } else if WAYLANDONLY.contains(&pname) {
+        Some(DisplayServer::Wayland)
+    } else {
+        None
+    }
+}
+
+/// Warning for packages that won't work with the display server of the running session.
+pub fn displaynote(pname: &str) -> Option<String> {
+    match (displayserver(pname)?, session()?) {
+        (DisplayServer::X11, DisplayServer::Wayland) => Some(gettext(
+            "Made for X11, it may not start or only partly work in your Wayland session",
+        )),
+        (DisplayServer::Wayland, DisplayServer::X11) => Some(gettext("Needs a Wayland session and won't start under X11")),
+        _ => None,
+    }
+}
+
+/// Vendor of a graphics card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuVendor {
+    Nvidia,
+    Amd,
+    Intel,
+}
+
+static GPUS: OnceLock<Vec<GpuVendor>> = OnceLock::new();
+
+/// Vendors of the graphics cards in this computer, more than one on hybrid laptops.
+pub fn gpuvendors() -> &'static [GpuVendor] {
+    GPUS.get_or_init(|| {
+        let mut vendors = vec![];
+        let Ok(entries) = fs::read_dir("/sys/class/drm") else {
+            return vendors;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Connectors such as card0-HDMI-A-1 share the device of their card
+            if !name.starts_with("card") || name.contains('-') {
+                continue;
+            }
+            let vendor = match fs::read_to_string(entry.path().join("device/vendor")).as_deref().map(str::trim) {
+                Ok("0x10de") => GpuVendor::Nvidia,
+                Ok("0x1002") => GpuVendor::Amd,
+                Ok("0x8086") => GpuVendor::Intel,
+                _ => continue,
+            };
+            if !vendors.contains(&vendor) {
+                vendors.push(vendor);
+            }
+        }
+        vendors
+    })
+}
+
+/// Compatibility of a GPU dependent package with the graphics cards in this computer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GpuNote {
+    pub note: String,
+    /// Attribute of a variant built for the graphics card
+    pub variant: Option<String>,
+}
+
+/// Variants built for one vendor's compute stack, by `pname` of the generic package.
+const GPUVARIANTS: &[(&str, GpuVendor, &str)] = &[
+    ("blender", GpuVendor::Amd, "blender-hip"),
+    ("ollama", GpuVendor::Nvidia, "ollama-cuda"),
+    ("ollama", GpuVendor::Amd, "ollama-rocm"),
+    ("btop", GpuVendor::Nvidia, "btop-cuda"),
+    ("btop", GpuVendor::Amd, "btop-rocm"),
+    ("nvtop", GpuVendor::Nvidia, "nvtopPackages.nvidia"),
+    ("nvtop", GpuVendor::Amd, "nvtopPackages.amd"),
+    ("nvtop", GpuVendor::Intel, "nvtopPackages.intel"),
+];
+
+/// Notes on how `attr` fits the graphics cards found, for packages using CUDA or ROCm.
+pub fn gpunote(pname: &str, attr: &str) -> Option<GpuNote> {
+    let vendors = gpuvendors();
+    if vendors.is_empty() {
+        return None;
+    }
+    // e.g. ollama-cuda, cudaPackages.cudnn, rocmPackages.rocm-smi or blender-hip
+    let words: Vec<&str> = attr.split(['.', '-']).chain(pname.split('-')).collect();
+    let needs = if words.iter().any(|x| *x == "cuda" || *x == "cudaPackages") {
+        Some((GpuVendor::Nvidia, gettext("Built with CUDA, which needs an NVIDIA graphics card")))
+    } else if words.iter().any(|x| ["rocm", "hip", "rocmPackages"].contains(x)) {
+        Some((GpuVendor::Amd, gettext("Built with ROCm, which needs an AMD graphics card")))
+    } else {
+        None
+    };
+    if let Some((vendor, note)) = needs {
+        if !vendors.contains(&vendor) {
+            return Some(GpuNote {
+                note,
+                variant: None,
+            });
+        }
+        return None;
+    }
+    GPUVARIANTS
+        .iter()
+        .find(|(p, vendor, variant)| *p == pname && *variant != attr && vendors.contains(vendor))
+        .map(|(_, vendor, variant)| GpuNote {
+            note: gettextf(
+                "{} builds GPU acceleration for {} graphics cards",
+                &[
+                    variant,
+                    match vendor {
+                        GpuVendor::Nvidia => "NVIDIA",
+                        GpuVendor::Amd => "AMD",
+                        GpuVendor::Intel => "Intel",
+                    },
+                ],
+            ),
+            variant: Some(variant.to_string()),
+        })
+}
@@ -0,0 +1,138 @@
+use anyhow::{anyhow, Result};
+use log::*;
+use reqwest::{header, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha256::digest;
+use std::{
+    env, fs,
+    path::Path,
+    sync::{Arc, Mutex, OnceLock},
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+static DOWNLOADS: Mutex<Option<Arc<Semaphore>>> = Mutex::new(None);
+
+/// Concurrent image downloads allowed when not configured.
+pub const DEFAULT_MAXDOWNLOADS: u32 = 4;
+
+/// Validators of a cached response, stored next to the body.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct CacheEntry {
+    etag: Option<String>,
+    lastmodified: Option<String>,
+}
+
+/// Client shared by everything that talks to the network, so connections are reused.
+/// Responses are fetched compressed when the server supports it, the metadata is mostly text.
+pub fn client() -> &'static reqwest::Client {
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .user_agent(concat!("nix-software-center/", env!("CARGO_PKG_VERSION")))
+            .gzip(true)
+            .zstd(true)
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+/// Resizes the download pool. Downloads already waiting keep their place in the old pool.
+pub fn setmaxdownloads(max: Option<u32>) {
+    let permits = max.unwrap_or(DEFAULT_MAXDOWNLOADS).max(1) as usize;
+    if let Ok(mut downloads) = DOWNLOADS.lock() {
+        *downloads = Some(Arc::new(Semaphore::new(permits)));
+    }
+}
+
+/// Waits for a slot in the download pool. The slot is freed when the permit is dropped.
+pub async fn downloadslot() -> Result<OwnedSemaphorePermit> {
+    let pool = {
+        let mut downloads = DOWNLOADS.lock().map_err(|e| anyhow!("{}", e))?;
+        downloads
+            .get_or_insert_with(|| Arc::new(Semaphore::new(DEFAULT_MAXDOWNLOADS as usize)))
+            .clone()
+    };
+    Ok(pool.acquire_owned().await?)
+}
+
+fn cachepath(url: &str) -> Result<String> {
+    let cachedir = if let Ok(cache) = env::var("XDG_CACHE_HOME") {
+        format!("{}/nix-software-center/http", cache)
+    } else {
+        format!("{}/.cache/nix-software-center/http", env::var("HOME")?)
+    };
+    Ok(format!("{}/{}", cachedir, digest(url.to_string())))
+}
+
+/// Fetches `url`, revalidating any cached copy with its ETag or Last-Modified.
+/// The cached body is returned when the server reports it unchanged or can't be reached.
+pub async fn get(url: &str) -> Result<Vec<u8>> {
+    let path = cachepath(url)?;
+    let metapath = format!("{}.json", path);
+    let cached = fs::read_to_string(&metapath)
+        .ok()
+        .and_then(|x| serde_json::from_str::<CacheEntry>(&x).ok())
+        .filter(|_| Path::new(&path).exists());
+
+    let mut request = client().get(url);
+    if let Some(entry) = &cached {
+        if let Some(etag) = &entry.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(lastmodified) = &entry.lastmodified {
+            request = request.header(header::IF_MODIFIED_SINCE, lastmodified);
+        }
+    }
+
+    let response = match request.send().await {
+        Ok(x) => x,
+        Err(e) if cached.is_some() => {
+            debug!("Serving {} from cache: {}", url, e);
+            return Ok(fs::read(&path)?);
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    if response.status() == StatusCode::NOT_MODIFIED && cached.is_some() {
+        trace!("{} not modified", url);
+        return Ok(fs::read(&path)?);
+    }
+    if !response.status().is_success() {
+        if cached.is_some() && response.status().is_server_error() {
+            warn!("Serving {} from cache: {}", url, response.status());
+            return Ok(fs::read(&path)?);
+        }
+        return Err(anyhow!("Error: {}", response.status()));
+    }
+
+    let validator = |name: header::HeaderName| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|x| x.to_str().ok())
+            .map(|x| x.to_string())
+    };
+    let entry = CacheEntry {
+        etag: validator(header::ETAG),
+        lastmodified: validator(header::LAST_MODIFIED),
+    };
+    let body = response.bytes().await?.to_vec();
+
+    if entry.etag.is_some() || entry.lastmodified.is_some() {
+        if let Err(e) = store(&path, &metapath, &entry, &body) {
+            warn!("Failed to cache {}: {}", url, e);
+        }
+    }
+    Ok(body)
+}
+
+fn store(path: &str, metapath: &str, entry: &CacheEntry, body: &[u8]) -> Result<()> {
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmppath = format!("{}.{}", path, rand::random::<u32>());
+    fs::write(&tmppath, body)?;
+    fs::rename(&tmppath, path)?;
+    fs::write(metapath, serde_json::to_string(entry)?)?;
+    Ok(())
+}
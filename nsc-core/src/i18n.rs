@@ -0,0 +1,42 @@
+//! Translations of the messages this crate returns for display. They are looked up in the text
+//! domain the app binds at startup, and stay in English for tools that don't bind one.
+
+/// Fills the placeholders of a translated message. `{}` take the arguments in turn, and
+/// translators who need another order can number them from `{0}` instead.
+pub fn fill(msg: String, args: &[&str]) -> String {
+    let mut out = String::with_capacity(msg.len());
+    let mut next = 0;
+    let mut rest = msg.as_str();
+    while let Some(i) = rest.find('{') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        let Some(end) = rest.find('}') else {
+            out.push('{');
+            continue;
+        };
+        let index = if rest[..end].is_empty() {
+            next += 1;
+            Some(next - 1)
+        } else {
+            rest[..end].parse::<usize>().ok()
+        };
+        match index.and_then(|x| args.get(x)) {
+            Some(arg) => {
+                out.push_str(arg);
+                rest = &rest[end + 1..];
+            }
+            None => out.push('{'),
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn gettext(msgid: &str) -> String {
+    glib::dgettext(None, msgid).to_string()
+}
+
+/// `gettext` for messages with `{}` placeholders.
+pub fn gettextf(msgid: &str, args: &[&str]) -> String {
+    fill(gettext(msgid), args)
+}
@@ -0,0 +1,262 @@
+use anyhow::{anyhow, Result};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::profile;
+
+/// Files a profile exposes to the desktop, used to tell what an install added.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ShareSnapshot {
+    applications: HashSet<PathBuf>,
+    icons: HashSet<PathBuf>,
+    mime: HashSet<PathBuf>,
+}
+
+/// What an install added to the desktop.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DesktopIntegration {
+    /// Names of the new launcher entries
+    pub applications: Vec<String>,
+    pub icons: usize,
+    /// MIME types the new applications open
+    pub mimetypes: Vec<String>,
+    /// The profile is not in `XDG_DATA_DIRS` yet, so the launcher won't see it until the next login
+    pub needsrestart: bool,
+    /// New launchers that duplicate ones from elsewhere on the system
+    pub conflicts: Vec<LauncherConflict>,
+}
+
+/// A new launcher with the same name or desktop ID as one that was there already.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LauncherConflict {
+    pub name: String,
+    /// Desktop ID of the new launcher, e.g. `org.gnome.Maps.desktop`
+    pub added: String,
+    pub existing: String,
+    /// Both have the same ID, so only the one found first in `XDG_DATA_DIRS` shows up
+    pub masks: bool,
+}
+
+impl DesktopIntegration {
+    pub fn is_empty(&self) -> bool {
+        self.applications.is_empty() && self.icons == 0 && self.mimetypes.is_empty()
+    }
+}
+
+/// `share` of the user profile, or of the system when `system` is set.
+pub fn sharedir(system: bool) -> Option<PathBuf> {
+    if system {
+        Some(PathBuf::from("/run/current-system/sw/share"))
+    } else {
+        profile::userprofile().ok().map(|x| Path::new(&x).join("share"))
+    }
+}
+
+pub fn snapshot(share: &Path) -> ShareSnapshot {
+    let mut snapshot = ShareSnapshot::default();
+    listfiles(&share.join("applications"), &mut snapshot.applications);
+    listfiles(&share.join("icons"), &mut snapshot.icons);
+    listfiles(&share.join("mime/packages"), &mut snapshot.mime);
+    snapshot
+}
+
+fn listfiles(dir: &Path, files: &mut HashSet<PathBuf>) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                listfiles(&path, files);
+            } else {
+                files.insert(path);
+            }
+        }
+    }
+}
+
+/// Compares the share directory against a snapshot taken before installing.
+pub fn added(share: &Path, before: &ShareSnapshot) -> DesktopIntegration {
+    let after = snapshot(share);
+    let mut applications = vec![];
+    let mut mimetypes = vec![];
+    let mut conflicts = vec![];
+    let existing = existinglaunchers(share);
+    for path in after.applications.difference(&before.applications) {
+        if path.extension().and_then(|x| x.to_str()) != Some("desktop") {
+            continue;
+        }
+        let Ok(entry) = fs::read_to_string(path) else {
+            continue;
+        };
+        // Only the main group, actions have their own names
+        let main = entry
+            .lines()
+            .skip_while(|x| x.trim() != "[Desktop Entry]")
+            .skip(1)
+            .take_while(|x| !x.starts_with('['));
+        let mut name = None;
+        let mut hidden = false;
+        for line in main {
+            if let Some(n) = line.strip_prefix("Name=") {
+                name = Some(n.trim().to_string());
+            } else if let Some(m) = line.strip_prefix("MimeType=") {
+                mimetypes.extend(m.split(';').filter(|x| !x.is_empty()).map(|x| x.trim().to_string()));
+            } else if line.trim() == "NoDisplay=true" || line.trim() == "Hidden=true" {
+                hidden = true;
+            }
+        }
+        if !hidden {
+            if let Some(name) = name {
+                let id = desktopid(path);
+                if let Some((existingid, _)) = existing.iter().find(|(x, n)| *x == id || n.eq_ignore_ascii_case(&name)) {
+                    conflicts.push(LauncherConflict {
+                        name: name.clone(),
+                        added: id.clone(),
+                        existing: existingid.clone(),
+                        masks: *existingid == id,
+                    });
+                }
+                applications.push(name);
+            }
+        }
+    }
+    applications.sort();
+    applications.dedup();
+    mimetypes.sort();
+    mimetypes.dedup();
+    let needsrestart = !applications.is_empty() && !indatadirs(share);
+    DesktopIntegration {
+        applications,
+        icons: after.icons.difference(&before.icons).count(),
+        mimetypes,
+        needsrestart,
+        conflicts,
+    }
+}
+
+fn desktopid(path: &Path) -> String {
+    path.file_name().map(|x| x.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+/// Name of a launcher, `None` if it is hidden.
+fn launchername(entry: &str) -> Option<String> {
+    let mut name = None;
+    for line in entry
+        .lines()
+        .skip_while(|x| x.trim() != "[Desktop Entry]")
+        .skip(1)
+        .take_while(|x| !x.starts_with('['))
+    {
+        if let Some(n) = line.strip_prefix("Name=") {
+            name = Some(n.trim().to_string());
+        } else if line.trim() == "NoDisplay=true" || line.trim() == "Hidden=true" {
+            return None;
+        }
+    }
+    name
+}
+
+fn userdata() -> Option<PathBuf> {
+    std::env::var("XDG_DATA_HOME")
+        .ok()
+        .filter(|x| !x.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var("HOME").ok().map(|x| Path::new(&x).join(".local/share")))
+}
+
+/// Desktop IDs and names of the visible launchers outside `share`.
+fn existinglaunchers(share: &Path) -> Vec<(String, String)> {
+    let share = fs::canonicalize(share).unwrap_or_else(|_| share.to_path_buf());
+    let mut dirs: Vec<PathBuf> = userdata().into_iter().collect();
+    dirs.extend(
+        std::env::var("XDG_DATA_DIRS")
+            .unwrap_or_default()
+            .split(':')
+            .filter(|x| !x.is_empty())
+            .map(PathBuf::from),
+    );
+    dirs.push(PathBuf::from("/run/current-system/sw/share"));
+    let mut seen = HashSet::new();
+    let mut launchers = vec![];
+    for dir in dirs {
+        let Ok(canonical) = fs::canonicalize(&dir) else {
+            continue;
+        };
+        if canonical == share || !seen.insert(canonical) {
+            continue;
+        }
+        let mut files = HashSet::new();
+        listfiles(&dir.join("applications"), &mut files);
+        for path in files {
+            if path.extension().and_then(|x| x.to_str()) != Some("desktop") {
+                continue;
+            }
+            if let Some(name) = fs::read_to_string(&path).ok().as_deref().and_then(launchername) {
+                launchers.push((desktopid(&path), name));
+            }
+        }
+    }
+    launchers
+}
+
+/// Entry written over a launcher to hide it, anything else in its place was put there by someone else.
+const HIDDENENTRY: &str = "[Desktop Entry]\nType=Application\nName=Hidden\nHidden=true\n";
+
+fn overridepath(id: &str) -> Result<PathBuf> {
+    Ok(userdata()
+        .ok_or_else(|| anyhow!("Could not find the user data directory"))?
+        .join("applications")
+        .join(id))
+}
+
+/// Hides a launcher from the app menu for this user, by placing a hidden entry with the same
+/// desktop ID in `~/.local/share/applications`, which takes precedence over the system ones.
+pub fn hidelauncher(id: &str) -> Result<()> {
+    let path = overridepath(id)?;
+    if path.exists() {
+        return Err(anyhow!("{} exists already", path.display()));
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, HIDDENENTRY)?;
+    Ok(())
+}
+
+/// Shows a launcher hidden by [`hidelauncher`] again. Entries the user wrote themselves are left alone.
+pub fn unhidelauncher(id: &str) -> Result<()> {
+    let path = overridepath(id)?;
+    if fs::read_to_string(&path)? != HIDDENENTRY {
+        return Err(anyhow!("{} was not hidden by Nix Software Center", path.display()));
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// Desktop IDs of the launchers hidden by [`hidelauncher`].
+pub fn hiddenlaunchers() -> Vec<String> {
+    let Some(Ok(entries)) = userdata().map(|x| fs::read_dir(x.join("applications"))) else {
+        return vec![];
+    };
+    let mut ids = entries
+        .flatten()
+        .map(|x| x.path())
+        .filter(|x| fs::read_to_string(x).map(|x| x == HIDDENENTRY).unwrap_or(false))
+        .map(|x| desktopid(&x))
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids
+}
+
+fn indatadirs(share: &Path) -> bool {
+    let Ok(share) = fs::canonicalize(share) else {
+        return false;
+    };
+    std::env::var("XDG_DATA_DIRS")
+        .unwrap_or_default()
+        .split(':')
+        .filter_map(|x| fs::canonicalize(x).ok())
+        .any(|x| x == share)
+}
@@ -0,0 +1,58 @@
+//! Nix Software Center without the interface: the package and appstream metadata,
+//! nix command lines, profile operations and the caches behind them.
+//! Everything here is free of GTK so it can be shared with the command line tools.
+//!
+//! - [`packages`]: appstream data and package metadata
+//! - [`appstream`]: refreshing the appstream catalog apart from the package databases
+//! - [`profile`], [`nixversion`], [`nixpkgsconfig`]: managing the user profile across nix versions
+//! - [`cmd`], [`nixlog`]: building nix commands and reading their output
+//! - [`config`], [`state`]: settings and persistent state
+//! - [`crash`]: crash reports kept locally and sent when the user opted in
+//! - [`channel`], [`metadata`]: noticing new releases of the nixpkgs channel and rolling back bad ones
+//! - [`updates`]: finding installed packages with newer versions
+//! - [`http`], [`narinfo`], [`screenshot`], [`favicon`], [`expression`], [`popularity`], [`security`]: cached downloads
+//! - [`integration`]: what an install added to the desktop
+//! - [`deps`]: the runtime closure of a package
+//! - [`programs`]: which packages provide a command
+//! - [`categories`]: the categories on the front page, which downstreams and themes can change
+//! - [`hints`]: notes on packages that need system options, a particular display server or graphics card
+//! - [`power`]: holding back background work while the system saves power
+//! - [`remote`]: managing user profiles on other machines over SSH
+//! - [`workqueue`]: the order installs and removals run in, and which can run at the same time
+//! - [`i18n`]: translating the messages above for display
+//! - [`report`], [`util`]: everything else
+
+pub mod appstream;
+pub mod categories;
+pub mod channel;
+pub mod cmd;
+pub mod config;
+pub mod crash;
+pub mod deps;
+pub mod expression;
+pub mod favicon;
+pub mod hints;
+pub mod http;
+pub mod i18n;
+pub mod integration;
+pub mod metadata;
+pub mod narinfo;
+pub mod nixlog;
+pub mod nixpkgsconfig;
+pub mod nixversion;
+pub mod packages;
+pub mod popularity;
+pub mod power;
+pub mod profile;
+pub mod programs;
+pub mod remote;
+pub mod report;
+pub mod screenshot;
+pub mod security;
+pub mod state;
+pub mod updates;
+pub mod util;
+pub mod workqueue;
+
+/// Where the appstream data and icons are installed
+pub static APPINFO: &str = "/usr/share/app-info";
@@ -0,0 +1,94 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// Copies of the package databases that loaded successfully, so a corrupt download or a bad
+/// data push can be rolled back. `current` is the last one that loaded, `previous` the one before.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub pkgdb: String,
+    pub nixpkgsdb: Option<String>,
+    pub systemdb: Option<String>,
+}
+
+/// Size and modification time of the files a snapshot was copied from, to skip unchanged copies.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+struct Manifest {
+    sources: Vec<(String, u64, u64)>,
+}
+
+fn metadatadir() -> Result<PathBuf> {
+    let cache = match std::env::var("XDG_CACHE_HOME") {
+        Ok(cache) => PathBuf::from(cache),
+        Err(_) => PathBuf::from(std::env::var("HOME")?).join(".cache"),
+    };
+    Ok(cache.join("nix-software-center/metadata"))
+}
+
+fn source(path: &str) -> Option<(String, u64, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some((path.to_string(), meta.len(), modified))
+}
+
+fn open(dir: &Path) -> Option<Snapshot> {
+    let file = |name: &str| {
+        let path = dir.join(name);
+        path.is_file().then(|| path.to_string_lossy().to_string())
+    };
+    Some(Snapshot {
+        pkgdb: file("pkgs.db")?,
+        nixpkgsdb: file("nixpkgslatest.db"),
+        systemdb: file("system.db"),
+    })
+}
+
+pub fn current() -> Option<Snapshot> {
+    open(&metadatadir().ok()?.join("current"))
+}
+
+pub fn previous() -> Option<Snapshot> {
+    open(&metadatadir().ok()?.join("previous"))
+}
+
+/// Keeps a copy of databases that just loaded, moving the last copy to `previous`. The copy is
+/// written to a staging directory and renamed into place, so it is never left half written.
+pub fn keep(pkgdb: &str, nixpkgsdb: Option<&str>, systemdb: Option<&str>) -> Result<()> {
+    let dir = metadatadir()?;
+    let current = dir.join("current");
+    let files = [("pkgs.db", Some(pkgdb)), ("nixpkgslatest.db", nixpkgsdb), ("system.db", systemdb)];
+    let manifest = Manifest {
+        sources: files.iter().filter_map(|(_, path)| path.and_then(source)).collect(),
+    };
+    let kept = fs::read_to_string(current.join("manifest.json"))
+        .ok()
+        .and_then(|x| serde_json::from_str::<Manifest>(&x).ok());
+    if kept.as_ref() == Some(&manifest) {
+        return Ok(());
+    }
+
+    let staging = dir.join("staging");
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    fs::create_dir_all(&staging)?;
+    for (name, path) in files {
+        if let Some(path) = path {
+            fs::copy(path, staging.join(name))?;
+        }
+    }
+    fs::write(staging.join("manifest.json"), serde_json::to_string(&manifest)?)?;
+
+    let previous = dir.join("previous");
+    if current.exists() {
+        if previous.exists() {
+            fs::remove_dir_all(&previous)?;
+        }
+        fs::rename(&current, &previous)?;
+    }
+    fs::rename(&staging, &current).map_err(|e| anyhow!("Failed to keep metadata: {}", e))
+}
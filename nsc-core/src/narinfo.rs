@@ -0,0 +1,186 @@
+use super::{cmd::nixcmd, config::NscConfig, http, state};
+use anyhow::{anyhow, Result};
+use log::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+    sync::Mutex,
+};
+
+const SUBSTITUTER: &str = "https://cache.nixos.org";
+
+/// Attributes and nixpkgs revisions looked up this run, so failed lookups aren't evaluated again
+static LOOKEDUP: Mutex<Option<HashSet<(String, Option<String>)>>> = Mutex::new(None);
+/// Held while `narinfo.json` is read and written back, so concurrent lookups don't drop each other's sizes
+static WRITING: Mutex<()> = Mutex::new(());
+
+/// Download size of a package version, from its narinfo on the binary cache.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SizeEntry {
+    pub version: String,
+    /// Compressed size of the package itself, without dependencies
+    pub filesize: u64,
+    /// nixpkgs revision it was looked up at
+    #[serde(default)]
+    pub revision: Option<String>,
+}
+
+fn sizespath() -> Result<String> {
+    let home = std::env::var("HOME")?;
+    Ok(format!("{}/.cache/nix-software-center/narinfo.json", home))
+}
+
+/// Sizes looked up so far, by attribute.
+pub fn getsizes() -> HashMap<String, SizeEntry> {
+    sizespath()
+        .ok()
+        .and_then(|x| fs::read_to_string(x).ok())
+        .and_then(|x| serde_json::from_str(&x).ok())
+        .unwrap_or_default()
+}
+
+/// The cached download size of `pkg`, if it was looked up for this `version`.
+pub fn downloadsize(sizes: &HashMap<String, SizeEntry>, pkg: &str, version: &str) -> Option<u64> {
+    sizes
+        .get(pkg)
+        .filter(|x| x.version == version)
+        .map(|x| x.filesize)
+}
+
+/// What installing a package would fetch and build, from `nix build --dry-run`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClosureSize {
+    /// Store paths to download
+    pub paths: usize,
+    /// Compressed download size in bytes
+    pub download: u64,
+    /// Size on disk once unpacked, in bytes
+    pub unpacked: u64,
+    /// Derivations that aren't cached and will be built locally
+    pub builds: usize,
+    /// The store paths listed to be fetched and the derivations listed to be built
+    pub fetch: Vec<String>,
+    pub build: Vec<String>,
+}
+
+/// Reads the summary lines of `nix build --dry-run`, e.g.
+/// `these 12 paths will be fetched (40.21 MiB download, 180.50 MiB unpacked):`.
+pub fn parsedryrun(output: &str) -> ClosureSize {
+    let mut size = ClosureSize::default();
+    // Paths are listed below the summary line they belong to
+    let mut building = None;
+    for line in output.lines().map(|x| x.trim()) {
+        if line.starts_with("/nix/store/") {
+            match building {
+                Some(true) => size.build.push(line.to_string()),
+                Some(false) => size.fetch.push(line.to_string()),
+                None => {}
+            }
+            continue;
+        }
+        building = None;
+        let count = || {
+            if line.starts_with("this ") {
+                Some(1)
+            } else {
+                line.strip_prefix("these ")?.split_whitespace().next()?.parse().ok()
+            }
+        };
+        if line.contains("will be fetched") {
+            building = Some(false);
+            size.paths = count().unwrap_or_default();
+            if let Some((_, sizes)) = line.split_once('(') {
+                for part in sizes.trim_end_matches([')', ':']).split(',') {
+                    let mut words = part.split_whitespace();
+                    let (Some(n), Some(unit), Some(kind)) = (words.next(), words.next(), words.next()) else {
+                        continue;
+                    };
+                    let bytes = bytes(n, unit).unwrap_or_default();
+                    match kind {
+                        "download" => size.download = bytes,
+                        "unpacked" => size.unpacked = bytes,
+                        _ => {}
+                    }
+                }
+            }
+        } else if line.contains("will be built") {
+            building = Some(true);
+            size.builds = count().unwrap_or_default();
+        }
+    }
+    size
+}
+
+fn bytes(n: &str, unit: &str) -> Option<u64> {
+    let n = n.parse::<f64>().ok()?;
+    let scale = match unit {
+        "B" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "TiB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((n * scale) as u64)
+}
+
+/// Looks up the download size of `pkg` on the binary cache and remembers it for tiles and search.
+pub async fn fetchsize(pkg: &str, version: &str, nscconfig: &NscConfig) -> Result<u64> {
+    let revision = state::getnscstate().pkgsrevision;
+    if let Some(entry) = getsizes()
+        .remove(pkg)
+        .filter(|x| x.version == version && x.revision == revision)
+    {
+        return Ok(entry.filesize);
+    }
+    if !LOOKEDUP
+        .lock()
+        .map(|mut x| x.get_or_insert_with(HashSet::new).insert((pkg.to_string(), revision.clone())))
+        .unwrap_or(true)
+    {
+        return Err(anyhow!("Already looked up {} at this revision", pkg));
+    }
+    let output = nixcmd("nix", nscconfig)
+        .arg("eval")
+        .arg("--raw")
+        .arg(format!("nixpkgs#{}.outPath", pkg))
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!("Failed to evaluate {}", pkg));
+    }
+    let outpath = String::from_utf8_lossy(&output.stdout).to_string();
+    let hash = outpath
+        .strip_prefix("/nix/store/")
+        .and_then(|x| x.get(..32))
+        .ok_or_else(|| anyhow!("Invalid store path {}", outpath))?;
+    let narinfo = http::get(&format!("{}/{}.narinfo", SUBSTITUTER, hash)).await?;
+    let filesize = String::from_utf8_lossy(&narinfo)
+        .lines()
+        .find_map(|x| x.strip_prefix("FileSize: "))
+        .and_then(|x| x.trim().parse::<u64>().ok())
+        .ok_or_else(|| anyhow!("No file size in narinfo for {}", pkg))?;
+    debug!("Download size of {}: {}", pkg, filesize);
+
+    let path = sizespath()?;
+    let _writing = WRITING.lock().map_err(|_| anyhow!("Failed to lock {}", path))?;
+    let mut sizes = getsizes();
+    sizes.insert(
+        pkg.to_string(),
+        SizeEntry {
+            version: version.to_string(),
+            filesize,
+            revision,
+        },
+    );
+    if let Some(parent) = Path::new(&path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Written next to it and moved over, so readers never see half a file
+    let tmp = format!("{}.tmp", path);
+    fs::write(&tmp, serde_json::to_string(&sizes)?)?;
+    fs::rename(tmp, path)?;
+    Ok(filesize)
+}
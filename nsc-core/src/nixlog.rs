@@ -0,0 +1,238 @@
+use super::profile;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Activity type of a store path being copied, fields are the path, where from and where to.
+/// Its progress is the number of bytes copied.
+const ACTCOPYPATH: u64 = 100;
+/// Activity type of a derivation being built, fields start with the derivation path.
+const ACTBUILD: u64 = 105;
+/// Activity type of a store path being fetched from a binary cache, fields are the path and the
+/// substituter. The copy doing the work is started as its child.
+const ACTSUBSTITUTE: u64 = 108;
+
+/// Makes the new `nix` CLI print structured JSON log events instead of text.
+pub fn logargs() -> Vec<String> {
+    vec![String::from("--log-format"), String::from("internal-json")]
+}
+
+/// A structured event from `--log-format internal-json`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NixEvent {
+    /// A log message; level 0 is an error, 1 a warning
+    Msg { level: u64, text: String },
+    /// An activity such as a build or download started, with its type and fields, and the
+    /// activity that started it or 0
+    Start {
+        id: u64,
+        parent: u64,
+        text: String,
+        activity: u64,
+        fields: Vec<String>,
+    },
+    Stop { id: u64 },
+    /// Progress of an activity in its own units
+    Progress { id: u64, done: u64, expected: u64 },
+}
+
+impl NixEvent {
+    pub fn iserror(&self) -> bool {
+        matches!(self, NixEvent::Msg { level: 0, .. })
+    }
+
+    /// Warning nixpkgs printed while evaluating, e.g. about a renamed or deprecated package.
+    pub fn warning(&self) -> Option<String> {
+        match self {
+            NixEvent::Msg { level: 1, text } => evalwarning(text).or_else(|| Some(text.trim().to_string())).filter(|x| !isnoise(x)),
+            NixEvent::Msg { text, .. } => evalwarning(text),
+            _ => None,
+        }
+    }
+
+    /// Human readable text for the event, if it is worth showing.
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            NixEvent::Msg { text, .. } | NixEvent::Start { text, .. } if !text.is_empty() => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// Parses one line of `internal-json` output. Lines that aren't events are returned as messages.
+pub fn parse(line: &str) -> Option<NixEvent> {
+    let Some(json) = line.strip_prefix("@nix ") else {
+        return Some(NixEvent::Msg {
+            level: 3,
+            text: line.to_string(),
+        });
+    };
+    let v: Value = serde_json::from_str(json).ok()?;
+    let id = v["id"].as_u64().unwrap_or_default();
+    match v["action"].as_str()? {
+        "msg" => Some(NixEvent::Msg {
+            level: v["level"].as_u64().unwrap_or(3),
+            text: stripansi(v["msg"].as_str().unwrap_or_default()),
+        }),
+        "start" => Some(NixEvent::Start {
+            id,
+            parent: v["parent"].as_u64().unwrap_or_default(),
+            text: stripansi(v["text"].as_str().unwrap_or_default()),
+            activity: v["type"].as_u64().unwrap_or_default(),
+            fields: v["fields"]
+                .as_array()
+                .map(|x| x.iter().filter_map(|x| x.as_str()).map(String::from).collect())
+                .unwrap_or_default(),
+        }),
+        "stop" => Some(NixEvent::Stop { id }),
+        // resProgress
+        "result" if v["type"].as_u64() == Some(105) => {
+            let fields = v["fields"].as_array()?;
+            Some(NixEvent::Progress {
+                id,
+                done: fields.first()?.as_u64()?,
+                expected: fields.get(1)?.as_u64()?,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Warnings of nix itself rather than of the package being evaluated.
+const NOISE: &[&str] = &["Git tree", "ignoring untrusted", "not writing modified lock file", "ignoring the client-specified setting"];
+
+fn isnoise(warning: &str) -> bool {
+    warning.is_empty() || NOISE.iter().any(|x| warning.contains(x))
+}
+
+/// Text of a warning line in plain nix output: `warning: ...`, `evaluation warning: ...`
+/// or `trace: warning: ...` from `lib.warn`.
+pub fn evalwarning(line: &str) -> Option<String> {
+    let line = stripansi(line);
+    let line = line.trim();
+    let warning = ["evaluation warning:", "trace: warning:", "warning:"]
+        .iter()
+        .find_map(|x| line.strip_prefix(x))?
+        .trim()
+        .to_string();
+    (!isnoise(&warning)).then_some(warning)
+}
+
+/// Store paths fetched during an operation and the binary caches they came from, and the number
+/// of derivations built.
+#[derive(Debug, Default)]
+pub struct Substitutions {
+    /// Substituter of each running or finished substitution, by activity id
+    sources: HashMap<u64, String>,
+    /// Substitution each copy belongs to, by the copy's activity id
+    copies: HashMap<u64, u64>,
+    /// Size of each substitution in bytes as far as nix reported it
+    sizes: HashMap<u64, u64>,
+    builds: usize,
+}
+
+/// Paths and bytes fetched from one substituter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubstituterUse {
+    /// Host of the substituter, e.g. `cache.nixos.org`
+    pub host: String,
+    pub paths: usize,
+    pub bytes: u64,
+}
+
+impl Substitutions {
+    pub fn event(&mut self, event: &NixEvent) {
+        match event {
+            NixEvent::Start { id, activity: ACTSUBSTITUTE, fields, .. } => {
+                if let Some(from) = fields.get(1) {
+                    self.sources.insert(*id, substituterhost(from));
+                }
+            }
+            NixEvent::Start { id, parent, activity: ACTCOPYPATH, .. } if self.sources.contains_key(parent) => {
+                self.copies.insert(*id, *parent);
+            }
+            NixEvent::Start { activity: ACTBUILD, .. } => self.builds += 1,
+            NixEvent::Progress { id, done, expected } => {
+                if let Some(substitution) = self.copies.get(id) {
+                    self.sizes.insert(*substitution, *expected.max(done));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Bytes fetched from all substituters.
+    pub fn bytes(&self) -> u64 {
+        self.sizes.values().sum()
+    }
+
+    pub fn substituted(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn built(&self) -> usize {
+        self.builds
+    }
+
+    /// What each substituter served, the one that served the most first.
+    pub fn bysubstituter(&self) -> Vec<SubstituterUse> {
+        let mut uses: Vec<SubstituterUse> = vec![];
+        for (id, host) in &self.sources {
+            let bytes = self.sizes.get(id).copied().unwrap_or_default();
+            match uses.iter_mut().find(|x| x.host == *host) {
+                Some(x) => {
+                    x.paths += 1;
+                    x.bytes += bytes;
+                }
+                None => uses.push(SubstituterUse {
+                    host: host.to_string(),
+                    paths: 1,
+                    bytes,
+                }),
+            }
+        }
+        uses.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.host.cmp(&b.host)));
+        uses
+    }
+}
+
+/// `https://cache.nixos.org/` to `cache.nixos.org`, leaving other store URIs as they are.
+fn substituterhost(uri: &str) -> String {
+    uri.split_once("://")
+        .map(|(_, rest)| rest)
+        .unwrap_or(uri)
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Package an activity or message is about, from the first quoted store path or name, e.g.
+/// `building '/nix/store/…-firefox-120.0.drv'` or nix-env's `upgrading 'firefox-119.0' to 'firefox-120.0'`.
+pub fn pkgname(text: &str) -> Option<String> {
+    let quoted = text.split(['\'', '‘', '’']).nth(1)?;
+    let name = match quoted.strip_prefix("/nix/store/") {
+        Some(path) => path.split('/').next()?.get(33..)?,
+        None => quoted,
+    };
+    let name = name.strip_suffix(".drv").unwrap_or(name);
+    if name.is_empty() || name.contains(['/', ' ']) {
+        return None;
+    }
+    Some(profile::pnamefromname(name))
+}
+
+fn stripansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // Skip until the end of the escape sequence
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
@@ -0,0 +1,157 @@
+use super::{cmd, config::NscConfig, profile};
+use anyhow::Result;
+use std::{fs, path::Path};
+
+/// First line of a `config.nix` written by us. Files without it are left alone.
+const MARKER: &str = "# Generated by Nix Software Center. Remove this line to manage this file yourself.";
+
+/// Why nixpkgs refused to evaluate a package.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AllowKind {
+    Unfree,
+    Insecure,
+}
+
+/// Finds the package nixpkgs refused in an evaluation error, e.g.
+/// `Package ‘steam-1.0.0.79’ in /nix/store/... has an unfree license (‘unfree’), refusing to evaluate.`
+/// Returns the package name for unfree packages and the name with its version for insecure ones,
+/// the same way nixpkgs matches them.
+pub fn refused(message: &str) -> Option<(AllowKind, String)> {
+    let kind = if message.contains("has an unfree license") {
+        AllowKind::Unfree
+    } else if message.contains("is marked as insecure") {
+        AllowKind::Insecure
+    } else {
+        return None;
+    };
+    let name = message
+        .split("Package ")
+        .nth(1)?
+        .split_whitespace()
+        .next()?
+        .trim_matches(|c: char| "‘’'\"`".contains(c));
+    if name.is_empty() {
+        return None;
+    }
+    match kind {
+        AllowKind::Unfree => Some((kind, profile::pnamefromname(name))),
+        AllowKind::Insecure => Some((kind, name.to_string())),
+    }
+}
+
+/// The `config.nix` nixpkgs reads: `$NIXPKGS_CONFIG`, `~/.config/nixpkgs/config.nix` or the older
+/// `~/.nixpkgs/config.nix`, whichever exists first. A new file goes in `~/.config/nixpkgs`.
+pub fn configpath() -> Result<String> {
+    if let Ok(path) = std::env::var("NIXPKGS_CONFIG") {
+        if !path.is_empty() && Path::new(&path).exists() {
+            return Ok(path);
+        }
+    }
+    let home = std::env::var("HOME")?;
+    let path = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(config) if !config.is_empty() => format!("{}/nixpkgs/config.nix", config),
+        _ => format!("{}/.config/nixpkgs/config.nix", home),
+    };
+    let legacy = format!("{}/.nixpkgs/config.nix", home);
+    if !Path::new(&path).exists() && Path::new(&legacy).exists() {
+        return Ok(legacy);
+    }
+    Ok(path)
+}
+
+/// Whether `config.nix` is missing or was written by us, so it can be replaced.
+pub fn managed() -> bool {
+    match configpath() {
+        Ok(path) => match fs::read_to_string(path) {
+            Ok(s) => s.lines().next() == Some(MARKER),
+            Err(_) => true,
+        },
+        Err(_) => false,
+    }
+}
+
+/// Whether unfree packages are allowed without asking, through `NIXPKGS_ALLOW_UNFREE`,
+/// `allowUnfree` in a hand written `config.nix` or an earlier approval of `pname`.
+pub fn allowsunfree(config: &NscConfig, pname: &str) -> bool {
+    if config.allowunfree.iter().any(|x| x == pname) || std::env::var("NIXPKGS_ALLOW_UNFREE").as_deref() == Ok("1") {
+        return true;
+    }
+    !managed()
+        && configpath()
+            .ok()
+            .and_then(|x| fs::read_to_string(x).ok())
+            .map(|x| x.split_whitespace().collect::<String>().contains("allowUnfree=true"))
+            .unwrap_or(false)
+}
+
+fn hasexceptions(config: &NscConfig) -> bool {
+    !config.allowunfree.is_empty() || !config.allowinsecure.is_empty()
+}
+
+fn nixlist(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|x| format!("{:?}", x))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Writes the approved packages into `config.nix`, or removes it when there are none left.
+/// Returns false if the file belongs to the user, in which case `envvars` is used instead.
+pub fn writeconfig(config: &NscConfig) -> Result<bool> {
+    if !managed() {
+        return Ok(false);
+    }
+    let path = configpath()?;
+    if !hasexceptions(config) {
+        if Path::new(&path).exists() {
+            fs::remove_file(&path)?;
+        }
+        return Ok(true);
+    }
+    let contents = format!(
+        "{}\n{{\n  allowUnfreePredicate = pkg: builtins.elem (pkg.pname or (builtins.parseDrvName pkg.name).name) [ {} ];\n  permittedInsecurePackages = [ {} ];\n}}\n",
+        MARKER,
+        nixlist(&config.allowunfree),
+        nixlist(&config.allowinsecure),
+    );
+    if let Some(parent) = Path::new(&path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)?;
+    Ok(true)
+}
+
+/// Environment allowing approved packages when `config.nix` can't be managed.
+/// With `pname` only that package's approvals count, otherwise any approval does.
+pub fn envvars(config: &NscConfig, pname: Option<&str>) -> Vec<(&'static str, &'static str)> {
+    if !hasexceptions(config) || managed() {
+        return vec![];
+    }
+    let mut vars = vec![];
+    if config
+        .allowunfree
+        .iter()
+        .any(|x| pname.map(|p| p == x).unwrap_or(true))
+    {
+        vars.push(("NIXPKGS_ALLOW_UNFREE", "1"));
+    }
+    if config
+        .allowinsecure
+        .iter()
+        .any(|x| pname.map(|p| profile::pnamefromname(x) == p).unwrap_or(true))
+    {
+        vars.push(("NIXPKGS_ALLOW_INSECURE", "1"));
+    }
+    vars
+}
+
+/// `impureargs`, plus `--impure` whenever there are approvals, since flakes only read
+/// `config.nix` and `NIXPKGS_ALLOW_*` in impure mode.
+pub fn impureargs(config: &NscConfig) -> Vec<String> {
+    if !config.impure && hasexceptions(config) {
+        vec![String::from("--impure")]
+    } else {
+        cmd::impureargs(config)
+    }
+}
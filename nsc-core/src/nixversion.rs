@@ -0,0 +1,116 @@
+use log::*;
+use std::sync::OnceLock;
+
+static VERSION: OnceLock<Option<NixVersion>> = OnceLock::new();
+
+/// Implementation of Nix providing the `nix` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NixVariant {
+    Nix,
+    Lix,
+    Determinate,
+}
+
+impl std::fmt::Display for NixVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NixVariant::Nix => write!(f, "Nix"),
+            NixVariant::Lix => write!(f, "Lix"),
+            NixVariant::Determinate => write!(f, "Determinate Nix"),
+        }
+    }
+}
+
+/// Version of the `nix` command on the PATH.
+/// For Determinate Nix this is the upstream Nix version it is based on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NixVersion {
+    pub variant: NixVariant,
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl std::fmt::Display for NixVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}.{}.{}", self.variant, self.major, self.minor, self.patch)
+    }
+}
+
+impl NixVersion {
+    /// From the output of `nix --version`, e.g. `nix (Nix) 2.18.1`, `nix (Lix, like Nix) 2.91.0`
+    /// or `nix (Determinate Nix 3.0.0) 2.26.3`.
+    pub fn parse(output: &str) -> Option<Self> {
+        let variant = if output.contains("(Lix") {
+            NixVariant::Lix
+        } else if output.contains("(Determinate Nix") {
+            NixVariant::Determinate
+        } else {
+            NixVariant::Nix
+        };
+        let version = output.split_whitespace().last()?;
+        let mut parts = version.split('.').map(|x| {
+            x.chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect::<String>()
+                .parse::<u64>()
+                .ok()
+        });
+        Some(NixVersion {
+            variant,
+            major: parts.next()??,
+            minor: parts.next().flatten().unwrap_or_default(),
+            patch: parts.next().flatten().unwrap_or_default(),
+        })
+    }
+
+    /// Nix 2.20 addresses profile elements by name, and only takes regexes with `--regex`.
+    /// Older versions take indices or a regex matching the attribute path.
+    /// Lix forked from 2.18 and continues its numbering at 2.90.
+    pub fn profilenames(&self) -> bool {
+        match self.variant {
+            NixVariant::Lix => (self.major, self.minor) >= (2, 90),
+            NixVariant::Nix | NixVariant::Determinate => (self.major, self.minor) >= (2, 20),
+        }
+    }
+
+    /// Determinate Nix ships with `nix-command` and `flakes` enabled.
+    pub fn presetfeatures(&self) -> bool {
+        self.variant == NixVariant::Determinate
+    }
+}
+
+/// Runs `nix --version` the first time it is called.
+pub fn detect() -> Option<NixVersion> {
+    *VERSION.get_or_init(|| {
+        let output = std::process::Command::new("nix").arg("--version").output().ok()?;
+        let version = NixVersion::parse(&String::from_utf8_lossy(&output.stdout));
+        info!("Detected nix version: {:?}", version);
+        version
+    })
+}
+
+/// Whether `nix profile` takes element names. Assumed when the version is unknown.
+pub fn profilenames() -> bool {
+    detect().map(|x| x.profilenames()).unwrap_or(true)
+}
+
+/// Enables the new CLI and flakes for a `nix` invocation where they aren't always on,
+/// so profile commands work without changes to nix.conf.
+pub fn featureargs() -> Vec<String> {
+    match detect() {
+        Some(version) if version.presetfeatures() => vec![],
+        _ => vec![
+            String::from("--extra-experimental-features"),
+            String::from("nix-command flakes"),
+        ],
+    }
+}
+
+/// Readable name and version for display, e.g. in the about window.
+pub fn describe() -> String {
+    match detect() {
+        Some(version) => version.to_string(),
+        None => String::from("Unknown"),
+    }
+}
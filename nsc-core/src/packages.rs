@@ -0,0 +1,829 @@
+use flate2::bufread::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::{self, fs::File, collections::HashMap, io::{BufReader, Read}, path::Path, sync::OnceLock};
+use log::*;
+use anyhow::{anyhow, Result};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::APPINFO;
+use crate::i18n::{gettext, gettextf};
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+#[serde(untagged)]
+pub enum StrOrVec {
+    Single(String),
+    List(Vec<String>),
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+#[serde(untagged)]
+pub enum Platform {
+    Single(String),
+    List(Vec<String>),
+    ListList(Vec<Vec<String>>),
+}
+
+/// Whether a package can be built for `current` according to its `meta.platforms`.
+/// Falls back to the evaluated `system` when no platforms are listed.
+pub fn supportsplatform(system: &str, platformsjson: &str, current: &str) -> bool {
+    let platforms = match serde_json::from_str::<Platform>(platformsjson) {
+        Ok(Platform::Single(p)) => vec![p],
+        Ok(Platform::List(v)) => v,
+        Ok(Platform::ListList(vv)) => vv.into_iter().flatten().collect(),
+        // Unknown format, don't hide anything
+        Err(_) => return true,
+    };
+    if platforms.is_empty() {
+        system.is_empty() || system == current
+    } else {
+        platforms.iter().any(|x| x == current)
+    }
+}
+
+/// Maps appstream categories to the section an application is listed under.
+pub fn appcategory(categories: &[String]) -> Option<String> {
+    categories.iter().find_map(|x| {
+        let name = match x.as_str() {
+            "AudioVideo" | "Audio" | "Video" => "Audio & Video",
+            "Development" => "Development",
+            "Education" => "Education",
+            "Game" => "Games",
+            "Graphics" => "Graphics",
+            "Network" => "Networking",
+            "Office" => "Productivity",
+            "Science" => "Science",
+            "Settings" | "System" => "System",
+            "Utility" => "Utilities",
+            _ => return None,
+        };
+        Some(name.to_string())
+    })
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LicenseFilter {
+    #[default]
+    Any,
+    Free,
+    Unfree,
+}
+
+/// Restrictions the search query is run with.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct SearchFilters {
+    pub license: LicenseFilter,
+    /// Only apps listed under this section
+    pub category: Option<String>,
+    /// Only packages with appstream data, which are graphical apps
+    pub gui: bool,
+    pub installed: bool,
+}
+
+/// How a search query matches packages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchMode {
+    /// Attribute or description containing every word
+    Words(Vec<String>),
+    /// `=attr`, the attribute or pname exactly
+    Exact(String),
+    /// `/regex/`, matched against the attribute and pname with SQLite `REGEXP`
+    Regex(String),
+}
+
+pub fn searchmode(query: &str) -> SearchMode {
+    let query = query.trim();
+    if let Some(attr) = query.strip_prefix('=') {
+        return SearchMode::Exact(attr.trim().to_string());
+    }
+    if let Some(re) = query.strip_prefix('/').and_then(|x| x.strip_suffix('/')) {
+        if !re.is_empty() {
+            return SearchMode::Regex(re.to_string());
+        }
+    }
+    SearchMode::Words(query.split(' ').filter(|x| x.len() > 1).map(|x| x.to_string()).collect())
+}
+
+/// Full-text index over the attribute, pname and description of the package database,
+/// with stemming so that e.g. "editing" finds "editor".
+const SEARCHINDEX: &str = "CREATE VIRTUAL TABLE IF NOT EXISTS pkgsearch USING fts5(attribute, pname, description, tokenize = 'porter unicode61 remove_diacritics 2')";
+
+/// Builds the `pkgsearch` index of `pkgdb`, unless it already covers every package.
+/// Bundled databases may be read-only, searches then fall back to `LIKE`.
+pub async fn buildsearchindex(pkgdb: &str) -> Result<()> {
+    let pool = sqlx::SqlitePool::connect(&format!("sqlite://{}", pkgdb)).await?;
+    // Indexes made by older versions hold text that isn't normalized
+    let schema: Option<(String,)> = sqlx::query_as("SELECT sql FROM sqlite_master WHERE name = 'pkgsearch'")
+        .fetch_optional(&pool)
+        .await?;
+    if schema.is_some_and(|(sql,)| !sql.contains("remove_diacritics 2")) {
+        debug!("Replacing outdated search index");
+        sqlx::query("DROP TABLE pkgsearch").execute(&pool).await?;
+    }
+    sqlx::query(SEARCHINDEX).execute(&pool).await?;
+    let (indexed,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM pkgsearch").fetch_one(&pool).await?;
+    let (pkgs,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute)")
+        .fetch_one(&pool)
+        .await?;
+    if indexed != pkgs {
+        debug!("Indexing {} packages for search", pkgs);
+        let rows: Vec<(String, String, String)> = sqlx::query_as(
+            "SELECT pkgs.attribute, pkgs.pname, meta.description FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute)",
+        )
+        .fetch_all(&pool)
+        .await?;
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM pkgsearch").execute(&mut *tx).await?;
+        // Attributes are ASCII and joined on, so they are stored as they are
+        for (attribute, pname, description) in rows {
+            sqlx::query("INSERT INTO pkgsearch (attribute, pname, description) VALUES ($1, $2, $3)")
+                .bind(attribute)
+                .bind(searchnormalize(&pname))
+                .bind(searchnormalize(&description))
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+    }
+    pool.close().await;
+    Ok(())
+}
+
+/// Folds `text` for the search index and queries alike: compatibility forms and case are folded,
+/// accents dropped and a few letters transliterated, so "Über" and "uber" are the same word.
+/// Chinese and Japanese aren't written with spaces, their characters become words of their own.
+pub fn searchnormalize(text: &str) -> String {
+    let folded: String = text
+        .nfkd()
+        .filter(|x| !is_combining_mark(*x))
+        .flat_map(|x| x.to_lowercase())
+        .collect();
+    let mut out = String::with_capacity(folded.len());
+    for c in folded.nfc() {
+        match c {
+            'ß' => out.push_str("ss"),
+            'æ' => out.push_str("ae"),
+            'œ' => out.push_str("oe"),
+            'ø' => out.push('o'),
+            'ł' => out.push('l'),
+            'đ' | 'ð' => out.push('d'),
+            'þ' => out.push_str("th"),
+            c if isideographic(c) => {
+                out.push(' ');
+                out.push(c);
+                out.push(' ');
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Han, Hiragana and Katakana.
+fn isideographic(c: char) -> bool {
+    matches!(c, '\u{3040}'..='\u{30ff}' | '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{f900}'..='\u{faff}' | '\u{20000}'..='\u{2fa1f}')
+}
+
+/// FTS5 `MATCH` expression finding packages with every word, each also as a prefix.
+/// Ideographic words are phrases of their characters.
+pub fn searchmatch(words: &[String]) -> String {
+    words
+        .iter()
+        .map(|x| searchnormalize(x).split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|x| !x.is_empty())
+        .map(|x| format!("\"{}\"*", x.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" AND ")
+}
+
+/// Suffixes of builds that are variants of the same program.
+const VARIANTSUFFIXES: &[&str] = &[
+    "bin", "wayland", "qt", "qt5", "qt6", "gtk", "gtk2", "gtk3", "gtk4", "fhs", "unwrapped", "wrapped", "git", "unstable",
+    "nightly", "beta", "insiders", "full", "minimal", "nox",
+];
+
+/// Programs that are builds of another one under a different name.
+const VARIANTALIASES: &[(&str, &str)] = &[("vscodium", "vscode"), ("librewolf", "firefox"), ("ungoogled-chromium", "chromium")];
+
+/// Attribute shared by the variants of a program, e.g. `vscode` for `vscode-fhs` and `vscodium`.
+pub fn variantbase(attr: &str) -> String {
+    let (prefix, mut base) = match attr.rsplit_once('.') {
+        Some((prefix, name)) => (Some(prefix), name),
+        None => (None, attr),
+    };
+    while let Some((rest, suffix)) = base.rsplit_once('-') {
+        if rest.is_empty() || !VARIANTSUFFIXES.contains(&suffix) {
+            break;
+        }
+        base = rest;
+    }
+    let base = VARIANTALIASES
+        .iter()
+        .find(|(alias, _)| *alias == base)
+        .map(|(_, program)| *program)
+        .unwrap_or(base);
+    match prefix {
+        Some(prefix) => format!("{}.{}", prefix, base),
+        None => base.to_string(),
+    }
+}
+
+/// Interpreter a library attribute belongs to and the name of the library in its package set,
+/// e.g. `python3` and `requests` for `python3Packages.requests`.
+pub fn interpreterlibrary(attr: &str) -> Option<(String, String)> {
+    let (set, name) = attr.split_once('.')?;
+    if name.contains('.') {
+        return None;
+    }
+    let interpreter = match set {
+        "rPackages" => String::from("R"),
+        "perlPackages" => String::from("perl"),
+        "haskellPackages" => String::from("ghc"),
+        "luaPackages" => String::from("lua"),
+        "luajitPackages" => String::from("luajit"),
+        // lua54Packages belongs to lua5_4
+        _ if set.starts_with("lua5") => format!("lua5_{}", set.strip_prefix("lua5")?.strip_suffix("Packages")?),
+        _ if set.starts_with("python") => set.strip_suffix("Packages")?.to_string(),
+        _ => return None,
+    };
+    Some((interpreter, name.to_string()))
+}
+
+/// Nix expression for `interpreter` bundled with `libraries`, to be evaluated with nixpkgs in scope.
+pub fn withpackagesexpr(interpreter: &str, libraries: &[String]) -> String {
+    if interpreter == "R" {
+        format!("rWrapper.override {{ packages = with rPackages; [ {} ]; }}", libraries.join(" "))
+    } else {
+        format!("{}.withPackages (ps: with ps; [ {} ])", interpreter, libraries.join(" "))
+    }
+}
+
+/// Extracts the new attribute from a nixpkgs alias error, such as
+/// "'foo' has been renamed to/replaced by 'foo-ng'".
+pub fn aliastarget(message: &str) -> Option<String> {
+    for marker in ["renamed to/replaced by", "renamed to", "replaced by", "superseded by"] {
+        if let Some((_, rest)) = message.split_once(marker) {
+            let target = rest
+                .split_whitespace()
+                .next()?
+                .trim_matches(|c: char| "'\"`‘’.,;:".contains(c));
+            if !target.is_empty() {
+                return Some(target.strip_prefix("pkgs.").unwrap_or(target).to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Why an installed package can no longer be evaluated.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum UnavailableReason {
+    Renamed(String),
+    Removed,
+    Broken,
+    Unfree,
+    Insecure,
+    Unknown,
+}
+
+impl UnavailableReason {
+    pub fn describe(&self) -> String {
+        match self {
+            UnavailableReason::Renamed(x) => gettextf("Renamed to {}", &[x]),
+            UnavailableReason::Removed => gettext("Removed from nixpkgs"),
+            UnavailableReason::Broken => gettext("Marked as broken"),
+            UnavailableReason::Unfree => gettext("Now has an unfree license"),
+            UnavailableReason::Insecure => gettext("Marked as insecure"),
+            UnavailableReason::Unknown => gettext("Unknown reason"),
+        }
+    }
+
+    /// A page explaining the change, when one can be derived.
+    pub fn url(&self, pkg: &str, message: &str) -> Option<String> {
+        if let Some(url) = message
+            .split_whitespace()
+            .find(|x| x.starts_with("https://") || x.starts_with("http://"))
+        {
+            return Some(url.trim_end_matches(|c: char| "'\"`‘’.,;:)".contains(c)).to_string());
+        }
+        match self {
+            UnavailableReason::Renamed(_) | UnavailableReason::Removed => Some(format!(
+                "https://github.com/NixOS/nixpkgs/pulls?q=is%3Apr+is%3Amerged+{}",
+                pkg
+            )),
+            UnavailableReason::Broken => Some(format!(
+                "https://github.com/NixOS/nixpkgs/issues?q=is%3Aissue+{}+broken",
+                pkg
+            )),
+            UnavailableReason::Insecure => message
+                .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+                .find(|x| x.starts_with("CVE-"))
+                .map(|x| format!("https://nvd.nist.gov/vuln/detail/{}", x)),
+            _ => None,
+        }
+    }
+}
+
+/// Works out why `pkg` is unavailable from the evaluation error and its entry in the new package set.
+/// `exists` is whether the attribute is still present, and `licensejson` its license metadata.
+pub fn unavailablereason(message: &str, replacement: Option<&str>, exists: bool, licensejson: Option<&str>) -> UnavailableReason {
+    if let Some(r) = replacement {
+        return UnavailableReason::Renamed(r.to_string());
+    }
+    let lower = message.to_lowercase();
+    if lower.contains("marked as broken") {
+        UnavailableReason::Broken
+    } else if lower.contains("marked as insecure") {
+        UnavailableReason::Insecure
+    } else if lower.contains("unfree license") {
+        UnavailableReason::Unfree
+    } else if lower.contains("has been removed") || lower.contains("was removed") || !exists {
+        UnavailableReason::Removed
+    } else if licensejson.map(isunfree).unwrap_or(false) {
+        UnavailableReason::Unfree
+    } else {
+        UnavailableReason::Unknown
+    }
+}
+
+/// Whether any of the licenses in `meta.license` json is marked as not free.
+pub fn isunfree(licensejson: &str) -> bool {
+    fn check(v: &serde_json::Value) -> bool {
+        match v {
+            serde_json::Value::Array(a) => a.iter().any(check),
+            serde_json::Value::Object(o) => o.get("free") == Some(&serde_json::Value::Bool(false)),
+            _ => false,
+        }
+    }
+    serde_json::from_str::<serde_json::Value>(licensejson)
+        .map(|x| check(&x))
+        .unwrap_or(false)
+}
+
+/// Short identifier of the first license in `meta.license` json, e.g. `MIT`.
+pub fn licenseshort(licensejson: &str) -> Option<String> {
+    fn first(license: &LicenseEnum) -> Option<String> {
+        match license {
+            LicenseEnum::Single(l) => l.spdxid.clone(),
+            LicenseEnum::List(v) => v.iter().find_map(|l| l.spdxid.clone()),
+            LicenseEnum::SingleStr(s) => Some(s.to_string()),
+            LicenseEnum::VecStr(v) => v.first().cloned(),
+            LicenseEnum::Mixed(v) => v.iter().find_map(first),
+        }
+    }
+    serde_json::from_str::<LicenseEnum>(licensejson)
+        .ok()
+        .and_then(|x| first(&x))
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+#[serde(untagged)]
+pub enum LicenseEnum {
+    Single(License),
+    List(Vec<License>),
+    SingleStr(String),
+    VecStr(Vec<String>),
+    Mixed(Vec<LicenseEnum>)
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct License {
+    pub free: Option<bool>,
+    #[serde(rename = "fullName")]
+    pub fullname: Option<String>,
+    #[serde(rename = "spdxId")]
+    pub spdxid: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct PkgMaintainer {
+    pub email: Option<String>,
+    pub github: Option<String>,
+    pub matrix: Option<String>,
+    pub name: Option<String>
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct AppData {
+    #[serde(rename = "Type")]
+    pub metatype: String,
+    #[serde(rename = "ID")]
+    pub id: String,
+    #[serde(rename = "Package")]
+    pub package: String,
+    #[serde(rename = "Name")]
+    pub name: Option<HashMap<String, String>>,
+    #[serde(rename = "Description")]
+    pub description: Option<HashMap<String, String>>,
+    #[serde(rename = "Summary")]
+    pub summary: Option<HashMap<String, String>>,
+    #[serde(rename = "Url")]
+    pub url: Option<AppUrl>,
+    #[serde(rename = "Icon")]
+    pub icon: Option<AppIconList>,
+    #[serde(rename = "Launchable")]
+    pub launchable: Option<AppLaunchable>,
+    #[serde(rename = "Provides")]
+    pub provides: Option<AppProvides>,
+    #[serde(rename = "Screenshots")]
+    pub screenshots: Option<Vec<AppScreenshot>>,
+    #[serde(rename = "Categories")]
+    pub categories: Option<Vec<String>>,
+    #[serde(rename = "Releases")]
+    pub releases: Option<Vec<AppRelease>>,
+    #[serde(rename = "DeveloperName")]
+    pub developername: Option<HashMap<String, String>>,
+    /// Replaces `DeveloperName` since AppStream 1.0
+    #[serde(rename = "Developer")]
+    pub developer: Option<AppDeveloper>,
+    /// OARS attributes and their intensity, by rating scheme such as `oars-1.1`
+    #[serde(rename = "ContentRating")]
+    pub contentrating: Option<HashMap<String, HashMap<String, String>>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AppDeveloper {
+    pub id: Option<String>,
+    pub name: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AppUrl {
+    pub homepage: Option<String>,
+    pub bugtracker: Option<String>,
+    pub help: Option<String>,
+    pub donation: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AppIconList {
+    pub cached: Option<Vec<AppIcon>>,
+    pub stock: Option<String>,
+    // TODO: add support for other icon types
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct AppIcon {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AppLaunchable {
+    #[serde(rename = "desktop-id")]
+    pub desktopid: Vec<String>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AppProvides {
+    pub binaries: Option<Vec<String>>,
+    pub ids: Option<Vec<String>>,
+    pub mediatypes: Option<Vec<String>>,
+    pub libraries: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AppScreenshot {
+    pub default: Option<bool>,
+    pub caption: Option<HashMap<String, String>>,
+    pub thumbnails: Option<Vec<String>>,
+    #[serde(rename = "source-image")]
+    pub sourceimage: Option<AppScreenshotImage>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AppScreenshotImage {
+    pub url: String,
+    /// Language shown in the image, unset for images that fit every language
+    pub lang: Option<String>,
+}
+
+/// A screenshot picked for the user's language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screenshot {
+    pub url: String,
+    pub caption: Option<String>,
+}
+
+static LOCALES: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Languages of the user in order of preference, e.g. `de_DE`, `de` and `C` last.
+pub fn locales() -> &'static [String] {
+    LOCALES.get_or_init(|| {
+        let mut out = vec![];
+        for name in glib::language_names() {
+            // The encoding doesn't matter for appstream
+            let name = name.split('.').next().unwrap_or_default().to_string();
+            if !name.is_empty() && !out.contains(&name) {
+                out.push(name);
+            }
+        }
+        out
+    })
+}
+
+/// Value of an appstream translation map for the first of `locales` it has, or `C`.
+pub fn localized<'a>(map: &'a HashMap<String, String>, locales: &[String]) -> Option<&'a String> {
+    locales.iter().find_map(|x| map.get(x)).or_else(|| map.get("C"))
+}
+
+/// Value of an appstream translation map in the user's language.
+pub fn translated(map: &HashMap<String, String>) -> Option<&String> {
+    localized(map, locales())
+}
+
+/// Screenshots in display order, the default one first. Of images made for different languages
+/// only those in the first of `locales` that has any are kept, falling back to untranslated ones.
+pub fn screenshots(appdata: &AppData, locales: &[String]) -> Vec<Screenshot> {
+    let Some(list) = &appdata.screenshots else {
+        return vec![];
+    };
+    let lang = locales.iter().find(|locale| {
+        list.iter()
+            .any(|x| x.sourceimage.as_ref().and_then(|x| x.lang.as_ref()) == Some(*locale))
+    });
+    let mut out: Vec<Screenshot> = vec![];
+    for s in list {
+        let Some(image) = &s.sourceimage else {
+            continue;
+        };
+        let keep = match (&image.lang, lang) {
+            (None, _) => true,
+            (Some(x), Some(lang)) => x == lang,
+            (Some(x), None) => x == "C",
+        };
+        if !keep || out.iter().any(|x| x.url == image.url) {
+            continue;
+        }
+        let screenshot = Screenshot {
+            url: image.url.clone(),
+            caption: s
+                .caption
+                .as_ref()
+                .and_then(|x| localized(x, locales))
+                .map(|x| x.trim().to_string())
+                .filter(|x| !x.is_empty()),
+        };
+        if s.default == Some(true) {
+            out.insert(0, screenshot);
+        } else {
+            out.push(screenshot);
+        }
+    }
+    out
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AppRelease {
+    pub version: Option<String>,
+    pub url: Option<AppReleaseUrl>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AppReleaseUrl {
+    pub details: Option<String>,
+}
+
+/// Developer of an app as shown on tiles and the package page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Developer {
+    pub name: String,
+    /// The domain of the developer or app ID belongs to the homepage
+    pub verified: bool,
+}
+
+/// Forges that host projects under `<user>.<forge domain>`, e.g. `io.github.user`.
+const FORGEPAGES: &[(&str, &str)] = &[("github.io", "github.com"), ("gitlab.io", "gitlab.com"), ("codeberg.page", "codeberg.org")];
+
+/// Domain of a reverse DNS ID, e.g. `gnome.org` for `org.gnome.Calculator`. IDs on a forge
+/// name the user's pages, e.g. `user.github.io` for both `io.github.user.App` and
+/// `com.github.user.App`, since the forge itself says nothing about who made the app.
+fn iddomain(id: &str) -> Option<String> {
+    let parts: Vec<String> = id.trim_end_matches(".desktop").split('.').map(|x| x.to_lowercase()).collect();
+    if parts.len() < 2 {
+        return None;
+    }
+    let isdomain = |domain: &str| domain.split('.').rev().eq(parts.iter().take(2).map(|x| x.as_str()));
+    match FORGEPAGES.iter().find(|(pages, forge)| isdomain(pages) || isdomain(forge)) {
+        Some((pages, _)) if parts.len() >= 3 => Some(format!("{}.{}", parts[2], pages)),
+        Some(_) => None,
+        None => Some(format!("{}.{}", parts[1], parts[0])),
+    }
+}
+
+/// Whether `homepage` is on `domain`, counting forge pages as the forge profile of their user.
+fn ondomain(domain: &str, homepage: &str) -> bool {
+    let url = homepage.trim().trim_start_matches("https://").trim_start_matches("http://").to_lowercase();
+    let host = url.split('/').next().unwrap_or_default();
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    if host == domain || host.ends_with(&format!(".{}", domain)) {
+        return true;
+    }
+    FORGEPAGES.iter().any(|(pages, forge)| {
+        domain
+            .strip_suffix(&format!(".{}", pages))
+            .map(|user| url.trim_start_matches("www.").starts_with(&format!("{}/{}/", forge, user)) || url == format!("{}/{}", forge, user))
+            .unwrap_or(false)
+    })
+}
+
+/// Developer named in the appstream data. Verified when the developer ID, or the app ID without
+/// one, is the reverse DNS of the homepage, like other stores check it.
+pub fn developer(appdata: &AppData, homepage: Option<&str>) -> Option<Developer> {
+    let name = appdata
+        .developer
+        .as_ref()
+        .and_then(|x| x.name.as_ref())
+        .or(appdata.developername.as_ref())
+        .and_then(translated)
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty());
+    let domain = appdata
+        .developer
+        .as_ref()
+        .and_then(|x| x.id.as_deref())
+        .filter(|x| x.contains('.'))
+        .and_then(iddomain)
+        .or_else(|| iddomain(&appdata.id));
+    let homepage = homepage.map(|x| x.to_string()).or_else(|| appdata.url.as_ref().and_then(|x| x.homepage.clone()));
+    let verified = match (&domain, &homepage) {
+        (Some(domain), Some(homepage)) => ondomain(domain, homepage),
+        _ => false,
+    };
+    match name {
+        Some(name) => Some(Developer { name, verified }),
+        None if verified => domain.map(|name| Developer { name, verified }),
+        None => None,
+    }
+}
+
+/// OARS attributes that make an app unsuitable for children at `intense`, or already at
+/// `moderate` for sexual content, roughly where other stores put an 18+ age rating.
+const MATUREOARS: &[(&str, &str)] = &[
+    ("violence-realistic", "intense"),
+    ("violence-bloodshed", "intense"),
+    ("violence-sexual", "moderate"),
+    ("violence-desecration", "intense"),
+    ("violence-slavery", "intense"),
+    ("violence-worship", "intense"),
+    ("drugs-narcotics", "intense"),
+    ("sex-nudity", "moderate"),
+    ("sex-themes", "intense"),
+    ("sex-prostitution", "intense"),
+    ("sex-adultery", "intense"),
+    ("sex-appearance", "intense"),
+];
+
+/// Whether the content rating of an app marks it as mature.
+pub fn mature(appdata: &AppData) -> bool {
+    fn level(value: &str) -> u8 {
+        match value {
+            "mild" => 1,
+            "moderate" => 2,
+            "intense" => 3,
+            _ => 0,
+        }
+    }
+    appdata.contentrating.iter().flat_map(|x| x.values()).any(|attrs| {
+        MATUREOARS
+            .iter()
+            .any(|(attr, min)| attrs.get(*attr).map(|x| level(x) >= level(min)).unwrap_or(false))
+    })
+}
+
+/// Best guess at a page describing what changed in `version`.
+/// Prefers appstream release notes, then the releases page of known forges, then the homepage.
+pub fn changelogurl(appdata: Option<&AppData>, homepage: Option<&str>, version: &str) -> Option<String> {
+    if let Some(releases) = appdata.and_then(|x| x.releases.as_ref()) {
+        if let Some(url) = releases
+            .iter()
+            .find(|x| x.version.as_deref() == Some(version))
+            .and_then(|x| x.url.as_ref())
+            .and_then(|x| x.details.clone())
+        {
+            return Some(url);
+        }
+    }
+    let homepage = homepage
+        .map(|x| x.to_string())
+        .or_else(|| appdata.and_then(|x| x.url.as_ref()).and_then(|x| x.homepage.clone()))?;
+    let trimmed = homepage.trim_end_matches('/');
+    for (forge, releases) in [("https://github.com/", "releases"), ("https://gitlab.com/", "-/releases"), ("https://codeberg.org/", "releases")] {
+        if let Some(repo) = trimmed.strip_prefix(forge) {
+            let parts = repo.split('/').collect::<Vec<&str>>();
+            if parts.len() >= 2 {
+                return Some(format!("{}{}/{}/{}", forge, parts[0], parts[1], releases));
+            }
+        }
+    }
+    Some(homepage)
+}
+
+/// Desktop ids a `.desktop`, `.metainfo.xml` or `.appdata.xml` file stands for.
+pub fn fileids(path: &Path) -> Result<Vec<String>> {
+    let name = path.file_name().map(|x| x.to_string_lossy().to_string()).unwrap_or_default();
+    if name.ends_with(".desktop") {
+        return Ok(vec![name]);
+    }
+    if !name.ends_with(".xml") {
+        return Err(anyhow!("Not a .desktop or appstream file: {}", path.display()));
+    }
+    let xml = std::fs::read_to_string(path)?;
+    let mut ids = vec![];
+    let mut rest = xml.as_str();
+    while let Some(start) = [rest.find("<id>"), rest.find("<launchable type=\"desktop-id\">")].into_iter().flatten().min() {
+        let tagend = start + rest[start..].find('>').unwrap_or(0) + 1;
+        let Some(len) = rest[tagend..].find('<') else {
+            break;
+        };
+        ids.push(rest[tagend..tagend + len].trim().to_string());
+        rest = &rest[tagend + len..];
+    }
+    ids.retain(|x| !x.is_empty());
+    if ids.is_empty() {
+        return Err(anyhow!("No component id in {}", path.display()));
+    }
+    Ok(ids)
+}
+
+/// What an `appstream://` or `nix://` link points to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PkgUri {
+    /// `appstream://org.gimp.GIMP`, an appstream component id
+    Appstream(String),
+    /// `nix://nixpkgs#gimp`, an attribute of nixpkgs
+    Attribute(String),
+}
+
+/// Parses a package link, with or without the `//`. Only `nixpkgs` is supported as a flake reference.
+pub fn parseuri(uri: &str) -> Option<PkgUri> {
+    let (scheme, rest) = uri.trim().split_once(':')?;
+    let rest = rest.trim_start_matches('/').trim_end_matches('/');
+    match scheme.to_lowercase().as_str() {
+        "appstream" if !rest.is_empty() => Some(PkgUri::Appstream(rest.to_string())),
+        "nix" => {
+            let (flake, attr) = rest.split_once('#')?;
+            if flake != "nixpkgs" {
+                return None;
+            }
+            // `nixpkgs#legacyPackages.x86_64-linux.gimp` names the same package
+            let attr = match attr.strip_prefix("legacyPackages.") {
+                Some(x) => x.split_once('.').map(|(_, x)| x).unwrap_or_default(),
+                None => attr,
+            };
+            (!attr.is_empty()).then(|| PkgUri::Attribute(attr.to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// The attribute whose appstream data owns one of `ids`, through its launchables or component id.
+pub fn ownerof(appdata: &HashMap<String, AppData>, ids: &[String]) -> Option<String> {
+    let matches = |x: &str| {
+        ids.iter()
+            .any(|id| id == x || id.trim_end_matches(".desktop") == x.trim_end_matches(".desktop"))
+    };
+    let mut owners = appdata
+        .iter()
+        .filter(|(_, data)| {
+            matches(&data.id)
+                || data.launchable.as_ref().map(|l| l.desktopid.iter().any(|x| matches(x))).unwrap_or(false)
+                || data.provides.as_ref().and_then(|p| p.ids.as_ref()).map(|x| x.iter().any(|x| matches(x))).unwrap_or(false)
+        })
+        .map(|(pkg, _)| pkg)
+        .collect::<Vec<_>>();
+    // Several variants can ship the same launcher, prefer the plainest attribute
+    owners.sort_by_key(|x| (x.len(), x.to_string()));
+    owners.first().map(|x| x.to_string())
+}
+
+pub fn appsteamdata() ->  Result<HashMap<String, AppData>> {
+    appsteamdatafrom(&format!("{}/xmls/nixos_x86_64_linux.yml.gz", APPINFO))
+}
+
+/// Reads appstream yaml, gzip compressed unless the file name doesn't end in `.gz`.
+pub fn appsteamdatafrom(path: &str) ->  Result<HashMap<String, AppData>> {
+    let appdata = File::open(path)?;
+    let mut appreader = BufReader::new(appdata);
+    let mut s = String::new();
+    if path.ends_with(".gz") {
+        GzDecoder::new(appreader).read_to_string(&mut s)?;
+    } else {
+        appreader.read_to_string(&mut s)?;
+    }
+    let mut files = s.split("\n---\n").collect::<Vec<_>>();
+    files.remove(0);
+
+    let mut out = HashMap::new();
+
+    for f in files {
+        if let Ok(appstream) = serde_yaml::from_str::<AppData>(f) {
+            out.insert(appstream.package.to_string(), appstream);
+        } else {
+            warn!("Failed to parse some appstream data");
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,73 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{http, state::NscState, util};
+
+/// Minimum time between two reports.
+const INTERVAL: u64 = 7 * 24 * 60 * 60;
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default()
+}
+
+/// Whether enough time has passed since the last report.
+pub fn due(state: &NscState) -> bool {
+    state
+        .lastpopularity
+        .map(|x| now().saturating_sub(x) >= INTERVAL)
+        .unwrap_or(true)
+}
+
+/// Sends the installed attribute names to `url`.
+/// Only the sorted names and the platform are sent, nothing that identifies the machine or user.
+pub async fn report(url: &str, mut pkgs: Vec<String>) -> Result<()> {
+    pkgs.sort();
+    pkgs.dedup();
+    let body = serde_json::json!({
+        "system": util::currentsystem(),
+        "packages": pkgs,
+    });
+    let response = http::client()
+        .post(url)
+        .header("Content-Type", "application/json")
+        .body(body.to_string())
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("Error: {}", response.status()));
+    }
+    Ok(())
+}
+
+/// Aggregated statistics published by the popularity server.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct PopularityData {
+    /// Attribute to the number of machines it is installed on
+    pub installs: HashMap<String, u64>,
+    /// Attributes gaining the most installs this week, most popular first
+    pub trending: Vec<String>,
+}
+
+impl PopularityData {
+    /// Search ranking bonus, growing with the order of magnitude of installs.
+    pub fn weight(&self, pkg: &str) -> usize {
+        match self.installs.get(pkg) {
+            Some(x) if *x > 0 => (*x as f64).log10() as usize * 2,
+            _ => 0,
+        }
+    }
+}
+
+/// Fetches the published dataset. Falls back to the last downloaded copy when offline.
+pub async fn popularitydata(url: &str) -> Result<PopularityData> {
+    let body = http::get(url).await?;
+    Ok(serde_json::from_slice(&body)?)
+}
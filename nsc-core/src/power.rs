@@ -0,0 +1,63 @@
+use super::i18n::{gettext, gettextf};
+use gio::prelude::*;
+
+/// Battery charge in percent below which background work waits while unplugged.
+pub const LOWBATTERY: f64 = 20.0;
+
+/// Bus names and paths of power-profiles-daemon, newest first.
+const POWERPROFILES: &[(&str, &str)] = &[
+    ("org.freedesktop.UPower.PowerProfiles", "/org/freedesktop/UPower/PowerProfiles"),
+    ("net.hadess.PowerProfiles", "/net/hadess/PowerProfiles"),
+];
+
+fn systemproxy(name: &str, path: &str, interface: &str) -> Option<gio::DBusProxy> {
+    gio::DBusProxy::for_bus_sync(
+        gio::BusType::System,
+        gio::DBusProxyFlags::DO_NOT_CONNECT_SIGNALS | gio::DBusProxyFlags::DO_NOT_AUTO_START,
+        None,
+        name,
+        path,
+        interface,
+        gio::Cancellable::NONE,
+    )
+    .ok()
+}
+
+/// Whether power-profiles-daemon is in power saver mode. False when it isn't running.
+pub fn powersaver() -> bool {
+    POWERPROFILES.iter().any(|(name, path)| {
+        systemproxy(name, path, name)
+            .and_then(|x| x.cached_property("ActiveProfile"))
+            .and_then(|x| x.get::<String>())
+            .map(|x| x == "power-saver")
+            .unwrap_or(false)
+    })
+}
+
+/// Battery charge in percent from UPower, `None` when running on AC power or without a battery.
+pub fn discharging() -> Option<f64> {
+    let upower = systemproxy("org.freedesktop.UPower", "/org/freedesktop/UPower", "org.freedesktop.UPower")?;
+    if !upower.cached_property("OnBattery")?.get::<bool>()? {
+        return None;
+    }
+    systemproxy(
+        "org.freedesktop.UPower",
+        "/org/freedesktop/UPower/devices/DisplayDevice",
+        "org.freedesktop.UPower.Device",
+    )?
+    .cached_property("Percentage")?
+    .get::<f64>()
+}
+
+/// Why background work like metadata refreshes and update checks should wait, if it should.
+pub fn deferreason() -> Option<String> {
+    if powersaver() {
+        return Some(gettext("power saver mode is on"));
+    }
+    match discharging() {
+        Some(percentage) if percentage < LOWBATTERY => {
+            Some(gettextf("the battery is at {}%", &[&format!("{:.0}", percentage)]))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,390 @@
+use super::nixversion;
+use anyhow::{anyhow, Result};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// How user packages are installed
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum UserPkgs {
+    Env,
+    Profile,
+}
+
+/// The profile link that is managed: `NIX_PROFILE` if set, otherwise `~/.nix-profile`,
+/// or the XDG location used with `use-xdg-base-directories`.
+pub fn userprofile() -> Result<String> {
+    if let Ok(profile) = std::env::var("NIX_PROFILE") {
+        if !profile.is_empty() {
+            return Ok(profile);
+        }
+    }
+    let home = std::env::var("HOME")?;
+    let legacy = format!("{}/.nix-profile", home);
+    if fs::symlink_metadata(&legacy).is_ok() {
+        return Ok(legacy);
+    }
+    let state = std::env::var("XDG_STATE_HOME").unwrap_or_else(|_| format!("{}/.local/state", home));
+    Ok(format!("{}/nix/profile", state))
+}
+
+/// Whether user packages are managed with `nix profile` or `nix-env`.
+pub fn userpkgtype() -> UserPkgs {
+    if let Ok(p) = userprofile() {
+        if Path::new(&format!("{}/manifest.json", p)).exists()
+            || !Path::new("/nix/var/nix/profiles/per-user/root/channels/nixos").exists()
+            || !Path::new(&format!("{}/manifest.nix", p)).exists()
+            || if let Ok(m) = fs::read_to_string(format!("{}/manifest.nix", p)) {
+                m == "[ ]"
+            } else {
+                false
+            }
+        {
+            UserPkgs::Profile
+        } else {
+            UserPkgs::Env
+        }
+    } else {
+        UserPkgs::Env
+    }
+}
+
+/// `--profile` for nix-env and nix profile when a non-default profile is managed.
+pub fn profileargs() -> Vec<String> {
+    match std::env::var("NIX_PROFILE") {
+        Ok(profile) if !profile.is_empty() => vec![String::from("--profile"), profile],
+        _ => vec![],
+    }
+}
+
+/// Whether `NIX_PROFILE` points at a profile other than the default one.
+pub fn customprofile() -> bool {
+    std::env::var("NIX_PROFILE").is_ok_and(|x| !x.is_empty())
+}
+
+/// Versions of the packages in the managed profile, keyed like [`generationpkgs`].
+/// nix-data only reads the default profile, so this stands in for it under `NIX_PROFILE`.
+pub async fn installedversions(userpkgs: &UserPkgs) -> Result<HashMap<String, String>> {
+    let mut pkgs = HashMap::new();
+    match userpkgs {
+        UserPkgs::Env => {
+            let output = tokio::process::Command::new("nix-env")
+                .arg("--profile")
+                .arg(userprofile()?)
+                .arg("--query")
+                .output()
+                .await?;
+            for name in String::from_utf8_lossy(&output.stdout).split_whitespace() {
+                let pname = pnamefromname(name);
+                let version = name.strip_prefix(&pname).unwrap_or_default().trim_start_matches('-').to_string();
+                pkgs.insert(pname, version);
+            }
+        }
+        UserPkgs::Profile => {
+            for element in elements() {
+                if let (Some(attr), Some(storename)) = (element.attr, element.storename) {
+                    let version = versionfrompath(&storename, &pnamefromname(&storename));
+                    pkgs.insert(attr, version);
+                }
+            }
+        }
+    }
+    Ok(pkgs)
+}
+
+/// Path of the profile whose generations are `<path>-<n>-link`, e.g. `~/.local/state/nix/profiles/profile`.
+/// Follows `userprofile()` until it reaches it.
+pub fn profilepath() -> Result<String> {
+    let mut path = PathBuf::from(userprofile()?);
+    for _ in 0..8 {
+        let link = fs::read_link(&path)?;
+        let target = match path.parent() {
+            Some(parent) if link.is_relative() => parent.join(&link),
+            _ => link,
+        };
+        let isgeneration = target
+            .file_name()
+            .and_then(|x| x.to_str())
+            .and_then(|x| x.strip_suffix("-link"))
+            .and_then(|x| x.rsplit_once('-'))
+            .map(|(_, gen)| gen.parse::<u64>().is_ok())
+            .unwrap_or(false);
+        if isgeneration {
+            return Ok(path.to_string_lossy().to_string());
+        }
+        path = target;
+    }
+    Err(anyhow!("Could not find the profile behind {}", userprofile()?))
+}
+
+/// Whether another process, e.g. `nix profile` in a terminal, holds the lock nix takes on the user profile
+/// while changing it. Nix would wait for it without saying so.
+pub fn profilelocked() -> bool {
+    let Ok(profile) = profilepath() else {
+        return false;
+    };
+    let Ok(file) = fs::File::open(format!("{}.lock", profile)) else {
+        return false;
+    };
+    match file.try_lock() {
+        Ok(()) => {
+            let _ = file.unlock();
+            false
+        }
+        Err(fs::TryLockError::WouldBlock) => true,
+        Err(_) => false,
+    }
+}
+
+/// All generation numbers of the user profile in ascending order, along with the current one.
+pub fn generations() -> Result<(Vec<u64>, u64)> {
+    let profile = profilepath()?;
+    let path = Path::new(&profile);
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid profile path"))?
+        .to_string_lossy()
+        .to_string();
+    let dir = path.parent().ok_or_else(|| anyhow!("Invalid profile path"))?;
+    let genfromlink = |link: &str| -> Option<u64> {
+        link.strip_prefix(&format!("{}-", name))?
+            .strip_suffix("-link")?
+            .parse::<u64>()
+            .ok()
+    };
+
+    let mut gens = fs::read_dir(dir)?
+        .filter_map(|x| x.ok())
+        .filter_map(|x| genfromlink(&x.file_name().to_string_lossy()))
+        .collect::<Vec<u64>>();
+    gens.sort_unstable();
+    let current = genfromlink(
+        &fs::read_link(&profile)?
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy(),
+    )
+    .ok_or_else(|| anyhow!("Could not determine current generation"))?;
+    Ok((gens, current))
+}
+
+pub fn generationpath(gen: u64) -> Result<String> {
+    Ok(format!("{}-{}-link", profilepath()?, gen))
+}
+
+/// Packages in a profile generation mapped to their store path.
+/// Keys are attribute names for `nix profile` and package names for `nix-env`.
+pub async fn generationpkgs(gen: u64, userpkgs: &UserPkgs) -> Result<HashMap<String, String>> {
+    let genpath = generationpath(gen)?;
+    let mut pkgs = HashMap::new();
+    match userpkgs {
+        UserPkgs::Env => {
+            let output = tokio::process::Command::new("nix-env")
+                .arg("--profile")
+                .arg(&genpath)
+                .arg("--query")
+                .arg("--out-path")
+                .output()
+                .await?;
+            for line in String::from_utf8_lossy(&output.stdout).lines() {
+                let split = line.split_whitespace().collect::<Vec<&str>>();
+                if let (Some(name), Some(path)) = (split.first(), split.last()) {
+                    pkgs.insert(pnamefromname(name), path.to_string());
+                }
+            }
+        }
+        UserPkgs::Profile => {
+            let manifest: serde_json::Value =
+                serde_json::from_str(&fs::read_to_string(format!("{}/manifest.json", genpath))?)?;
+            let elements = match &manifest["elements"] {
+                serde_json::Value::Array(a) => a.iter().collect::<Vec<_>>(),
+                serde_json::Value::Object(o) => o.values().collect::<Vec<_>>(),
+                _ => vec![],
+            };
+            for element in elements {
+                let attr = elementattr(element);
+                let path = element["storePaths"]
+                    .as_array()
+                    .and_then(|x| x.first())
+                    .and_then(|x| x.as_str());
+                if let (Some(attr), Some(path)) = (attr, path) {
+                    pkgs.insert(attr, path.to_string());
+                }
+            }
+        }
+    }
+    Ok(pkgs)
+}
+
+/// The nixpkgs attribute a `nix profile` manifest element was installed from.
+fn elementattr(element: &serde_json::Value) -> Option<String> {
+    element["attrPath"]
+        .as_str()
+        .and_then(|x| x.strip_prefix("legacyPackages."))
+        .and_then(|x| x.split_once('.'))
+        .map(|(_, x)| x.to_string())
+}
+
+fn manifest() -> serde_json::Value {
+    userprofile()
+        .ok()
+        .and_then(|x| fs::read_to_string(format!("{}/manifest.json", x)).ok())
+        .and_then(|x| serde_json::from_str::<serde_json::Value>(&x).ok())
+        .unwrap_or_default()
+}
+
+/// An element of the user's `nix profile`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileElement {
+    /// Name `nix profile` addresses it by, or its index before Nix 2.20
+    pub name: String,
+    /// nixpkgs attribute, if it was installed from nixpkgs
+    pub attr: Option<String>,
+    /// Flake reference it was installed from, e.g. `github:owner/repo#packages.x86_64-linux.default`
+    pub flakeref: Option<String>,
+    /// Name of its first store path, e.g. `hello-2.12.1`
+    pub storename: Option<String>,
+}
+
+/// Every element of the user's `nix profile`, including ones not installed from nixpkgs.
+pub fn elements() -> Vec<ProfileElement> {
+    elementsof(&manifest())
+}
+
+/// Every element of a `nix profile` manifest, as printed by `nix profile list --json`.
+pub fn elementsof(manifest: &serde_json::Value) -> Vec<ProfileElement> {
+    let elements = match &manifest["elements"] {
+        serde_json::Value::Array(a) => a.iter().enumerate().map(|(i, e)| (i.to_string(), e)).collect::<Vec<_>>(),
+        serde_json::Value::Object(o) => o.iter().map(|(k, e)| (k.to_string(), e)).collect::<Vec<_>>(),
+        _ => vec![],
+    };
+    elements
+        .into_iter()
+        .map(|(name, element)| ProfileElement {
+            name,
+            attr: elementattr(element),
+            flakeref: element["originalUrl"]
+                .as_str()
+                .map(|url| match element["attrPath"].as_str() {
+                    Some(attrpath) => format!("{}#{}", url, attrpath),
+                    None => url.to_string(),
+                }),
+            storename: element["storePaths"]
+                .as_array()
+                .and_then(|x| x.first())
+                .and_then(|x| x.as_str())
+                .and_then(|x| x.strip_prefix("/nix/store/"))
+                .and_then(|x| x.get(33..))
+                .map(|x| x.to_string()),
+        })
+        .collect()
+}
+
+/// Whether `nix profile` addresses elements by name. The manifest written by the installed
+/// nix is trusted over its version, which isn't comparable between implementations.
+fn profilenames(manifest: &serde_json::Value) -> bool {
+    match &manifest["elements"] {
+        serde_json::Value::Object(_) => true,
+        serde_json::Value::Array(_) => false,
+        _ => nixversion::profilenames(),
+    }
+}
+
+/// Arguments selecting the elements installed from `attrs` for `nix profile remove` and `upgrade`.
+/// Uses element names on Nix 2.20 and newer, and indices or an attribute path regex before that.
+pub fn elementargs(attrs: &[String]) -> Vec<String> {
+    elementargsof(&manifest(), attrs)
+}
+
+/// Like [`elementargs`], for the profile described by `manifest`.
+pub fn elementargsof(manifest: &serde_json::Value, attrs: &[String]) -> Vec<String> {
+    let names = profilenames(manifest);
+    attrs
+        .iter()
+        .map(|attr| {
+            let ismatch = |element: &serde_json::Value| elementattr(element).as_ref() == Some(attr);
+            let found = match &manifest["elements"] {
+                serde_json::Value::Object(o) if names => {
+                    o.iter().find(|(_, e)| ismatch(e)).map(|(name, _)| name.to_string())
+                }
+                serde_json::Value::Array(a) if !names => {
+                    a.iter().position(ismatch).map(|i| i.to_string())
+                }
+                _ => None,
+            };
+            // Elements not installed from nixpkgs are given by their name or index
+            let found = found.or_else(|| match &manifest["elements"] {
+                serde_json::Value::Object(o) if names && o.contains_key(attr) => Some(attr.to_string()),
+                serde_json::Value::Array(a) if !names => attr
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|i| a.get(*i).map(|e| elementattr(e).is_none()).unwrap_or(false))
+                    .map(|i| i.to_string()),
+                _ => None,
+            });
+            found.unwrap_or_else(|| {
+                if names {
+                    attr.rsplit('.').next().unwrap_or(attr).to_string()
+                } else {
+                    format!("legacyPackages\\.[^.]+\\.{}", attr.replace('.', "\\."))
+                }
+            })
+        })
+        .collect()
+}
+
+/// Arguments selecting every element for `nix profile upgrade`.
+pub fn allelementargs() -> Vec<String> {
+    allelementargsof(&manifest())
+}
+
+/// Like [`allelementargs`], for the profile described by `manifest`.
+pub fn allelementargsof(manifest: &serde_json::Value) -> Vec<String> {
+    if profilenames(manifest) {
+        vec![String::from("--all")]
+    } else {
+        vec![String::from(".*")]
+    }
+}
+
+/// Finds the most recent older generation that has a different build of `key` installed.
+/// Returns the version and store path of that build.
+pub async fn previousversion(key: &str, pname: &str, userpkgs: &UserPkgs) -> Result<Option<(String, String)>> {
+    let (gens, current) = generations()?;
+    let currentpath = generationpkgs(current, userpkgs).await?.get(key).cloned();
+    for gen in gens.iter().rev().filter(|x| **x < current) {
+        if let Some(path) = generationpkgs(*gen, userpkgs).await?.get(key) {
+            if Some(path) != currentpath.as_ref() && Path::new(path).exists() {
+                return Ok(Some((versionfrompath(path, pname), path.to_string())));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Equivalent of `builtins.parseDrvName`: the name ends at the first dash followed by a non-letter.
+pub fn pnamefromname(name: &str) -> String {
+    let bytes = name.as_bytes();
+    for i in 0..bytes.len() {
+        if bytes[i] == b'-' && bytes.get(i + 1).map(|x| !x.is_ascii_alphabetic()).unwrap_or(false) {
+            return name[..i].to_string();
+        }
+    }
+    name.to_string()
+}
+
+fn versionfrompath(path: &str, pname: &str) -> String {
+    let name = path
+        .strip_prefix("/nix/store/")
+        .and_then(|x| x.get(33..))
+        .unwrap_or(path);
+    if let Some(v) = name.strip_prefix(&format!("{}-", pname)) {
+        v.to_string()
+    } else {
+        name.strip_prefix(&pnamefromname(name))
+            .map(|x| x.trim_start_matches('-').to_string())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,101 @@
+use super::util;
+use anyhow::Result;
+use log::*;
+use sqlx::{sqlite::SqliteConnectOptions, SqlitePool};
+use std::{
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+use tokio::{process::Command, sync::Notify};
+
+/// Signals the running `nix-locate`, which is dropped and killed once a newer lookup starts
+static LOCATING: Mutex<Option<Arc<Notify>>> = Mutex::new(None);
+
+/// Places NixOS channels keep their `command-not-found` database.
+const PROGRAMSDBS: &[&str] = &[
+    "/nix/var/nix/profiles/per-user/root/channels/nixos/programs.sqlite",
+    "/nix/var/nix/profiles/per-user/root/channels/nixpkgs/programs.sqlite",
+];
+
+/// The `programs.sqlite` of the system's channel, flake systems have none.
+pub fn programsdb() -> Option<String> {
+    let home = std::env::var("HOME")
+        .ok()
+        .map(|x| format!("{}/.nix-defexpr/channels/nixos/programs.sqlite", x));
+    PROGRAMSDBS
+        .iter()
+        .map(|x| x.to_string())
+        .chain(home)
+        .find(|x| Path::new(x).is_file())
+}
+
+/// Attributes of the packages with a program called `command` in `bin`, from the channel's
+/// `programs.sqlite` and else from `nix-locate` when nix-index is installed.
+pub async fn providers(command: &str) -> Vec<String> {
+    if command.is_empty() || command.contains('/') {
+        return vec![];
+    }
+    let mut pkgs = match programsdb() {
+        Some(db) => match fromprogramsdb(&db, command).await {
+            Ok(pkgs) => pkgs,
+            Err(e) => {
+                warn!("Failed to read {}: {}", db, e);
+                vec![]
+            }
+        },
+        None => fromnixlocate(command).await,
+    };
+    pkgs.sort();
+    pkgs.dedup();
+    pkgs
+}
+
+async fn fromprogramsdb(db: &str, command: &str) -> Result<Vec<String>> {
+    let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", db))?.read_only(true);
+    let pool = SqlitePool::connect_with(options).await?;
+    let rows: Vec<(String,)> = sqlx::query_as("SELECT package FROM Programs WHERE name = $1 AND system = $2")
+        .bind(command)
+        .bind(util::currentsystem())
+        .fetch_all(&pool)
+        .await?;
+    pool.close().await;
+    Ok(rows.into_iter().map(|(x,)| x).collect())
+}
+
+/// `nix-locate --minimal` prints the attribute with the output, e.g. `imagemagick.out`.
+/// Only the latest lookup runs, searching while typing would otherwise pile them up.
+async fn fromnixlocate(command: &str) -> Vec<String> {
+    let cancel = Arc::new(Notify::new());
+    if let Some(previous) = LOCATING.lock().ok().and_then(|mut x| x.replace(cancel.clone())) {
+        previous.notify_one();
+    }
+    let locate = Command::new("nix-locate")
+        .arg("--minimal")
+        .arg("--top-level")
+        .arg("--whole-name")
+        .arg("--at-root")
+        .arg(format!("/bin/{}", command))
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::select! {
+        output = locate => output,
+        _ = cancel.notified() => {
+            debug!("nix-locate for {} superseded", command);
+            return vec![];
+        }
+    };
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            debug!("nix-locate failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+            return vec![];
+        }
+        // nix-index isn't installed
+        Err(_) => return vec![],
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|x| x.trim().rsplit_once('.').map(|(attr, _)| attr.to_string()))
+        .collect()
+}
@@ -0,0 +1,116 @@
+use super::{cmd, config::NscConfig, profile};
+use anyhow::{anyhow, Result};
+use std::{collections::HashMap, fs};
+
+/// Hosts named in `~/.ssh/config`, leaving out patterns.
+pub fn sshhosts() -> Vec<String> {
+    let Ok(home) = std::env::var("HOME") else {
+        return vec![];
+    };
+    let Ok(config) = fs::read_to_string(format!("{}/.ssh/config", home)) else {
+        return vec![];
+    };
+    let mut hosts: Vec<String> = vec![];
+    for line in config.lines() {
+        let mut words = line.split_whitespace();
+        if !words.next().map(|x| x.eq_ignore_ascii_case("host")).unwrap_or(false) {
+            continue;
+        }
+        for host in words {
+            if !host.contains(['*', '?', '!']) && !hosts.iter().any(|x| x == host) {
+                hosts.push(host.to_string());
+            }
+        }
+    }
+    hosts
+}
+
+/// Runs `program` on `host` through ssh. `BatchMode` makes ssh fail instead of asking for a
+/// password, so the host needs key based authentication or a running agent.
+pub fn remotecmd(
+    host: &str,
+    program: &str,
+    args: &[String],
+    envs: &[(&str, &str)],
+    config: &NscConfig,
+) -> tokio::process::Command {
+    let mut remote = String::new();
+    if !envs.is_empty() {
+        remote.push_str("env ");
+        for (key, value) in envs {
+            remote.push_str(&format!("{}={} ", key, glib::shell_quote(value).to_string_lossy()));
+        }
+    }
+    remote.push_str(&cmd::cmdline(program, args, config));
+    let mut command = tokio::process::Command::new("ssh");
+    command
+        .arg("-o")
+        .arg("BatchMode=yes")
+        .arg("--")
+        .arg(host)
+        .arg(remote);
+    command
+}
+
+/// The `nix profile` manifest of the user on `host`.
+pub async fn manifest(host: &str, config: &NscConfig) -> Result<serde_json::Value> {
+    let args = ["profile", "list", "--json"].map(String::from);
+    let output = remotecmd(host, "nix", &args, &[], config)
+        .kill_on_drop(true)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{}: {}",
+            host,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// Packages installed from nixpkgs in the profile on `host`, mapped to their version.
+pub async fn installed(host: &str, config: &NscConfig) -> Result<HashMap<String, String>> {
+    let manifest = manifest(host, config).await?;
+    Ok(profile::elementsof(&manifest)
+        .into_iter()
+        .filter_map(|element| {
+            let version = element
+                .storename
+                .map(|name| {
+                    let pname = profile::pnamefromname(&name);
+                    name.get(pname.len() + 1..).unwrap_or_default().to_string()
+                })
+                .unwrap_or_default();
+            Some((element.attr?, version))
+        })
+        .collect())
+}
+
+/// `nix profile` arguments installing or removing `pkg` on `host`.
+pub async fn profileargs(host: &str, pkg: &str, remove: bool, config: &NscConfig) -> Result<Vec<String>> {
+    if remove {
+        let manifest = manifest(host, config).await?;
+        let mut args = vec![String::from("profile"), String::from("remove")];
+        args.extend(profile::elementargsof(&manifest, &[pkg.to_string()]));
+        Ok(args)
+    } else {
+        Ok(vec![
+            String::from("profile"),
+            String::from("install"),
+            format!("nixpkgs#{}", pkg),
+        ])
+    }
+}
+
+/// `nix profile upgrade` arguments for `targets` on `host`, or every package if there are none.
+pub async fn upgradeargs(host: &str, targets: &[String], config: &NscConfig) -> Result<Vec<String>> {
+    let manifest = manifest(host, config).await?;
+    let mut args = vec![String::from("profile"), String::from("upgrade")];
+    if targets.is_empty() {
+        args.extend(profile::allelementargsof(&manifest));
+    } else {
+        args.extend(profile::elementargsof(&manifest, targets));
+    }
+    Ok(args)
+}
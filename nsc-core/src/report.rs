@@ -0,0 +1,103 @@
+use std::fmt::Write;
+
+use super::packages::LicenseEnum;
+
+/// An installed package as listed in an exported report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportItem {
+    pub name: String,
+    pub attribute: Option<String>,
+    pub version: String,
+    pub licenses: Vec<String>,
+    pub homepage: Option<String>,
+    /// "User" or "System"
+    pub source: String,
+}
+
+/// Readable license names from the `meta.license` json of a package.
+pub fn licensenames(licensejson: &str) -> Vec<String> {
+    fn add(license: &LicenseEnum, out: &mut Vec<String>) {
+        match license {
+            LicenseEnum::Single(l) => {
+                if let Some(n) = l.spdxid.as_ref().or(l.fullname.as_ref()) {
+                    out.push(n.to_string());
+                }
+            }
+            LicenseEnum::List(v) => {
+                for l in v {
+                    add(&LicenseEnum::Single(l.clone()), out);
+                }
+            }
+            LicenseEnum::SingleStr(s) => out.push(s.to_string()),
+            LicenseEnum::VecStr(v) => out.extend(v.iter().cloned()),
+            LicenseEnum::Mixed(v) => {
+                for l in v {
+                    add(l, out);
+                }
+            }
+        }
+    }
+    let mut out = vec![];
+    if let Ok(license) = serde_json::from_str::<LicenseEnum>(licensejson) {
+        add(&license, &mut out);
+    }
+    out.dedup();
+    out
+}
+
+fn escapemd(s: &str) -> String {
+    s.replace('|', "\\|").replace('\n', " ")
+}
+
+fn escapehtml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn markdown(items: &[ReportItem], system: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# Installed Software\n");
+    let _ = writeln!(out, "System: {}\n", system);
+    let _ = writeln!(out, "| Name | Attribute | Version | License | Homepage | Installed |");
+    let _ = writeln!(out, "| --- | --- | --- | --- | --- | --- |");
+    for item in items {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {} |",
+            escapemd(&item.name),
+            item.attribute.as_deref().map(|x| format!("`{}`", x)).unwrap_or_default(),
+            escapemd(&item.version),
+            escapemd(&item.licenses.join(", ")),
+            item.homepage.as_deref().map(|x| format!("<{}>", x)).unwrap_or_default(),
+            item.source,
+        );
+    }
+    out
+}
+
+pub fn html(items: &[ReportItem], system: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Installed Software</title>");
+    let _ = writeln!(out, "<style>body {{ font-family: sans-serif; }} table {{ border-collapse: collapse; }} th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; }}</style>");
+    let _ = writeln!(out, "</head>\n<body>\n<h1>Installed Software</h1>\n<p>System: {}</p>", escapehtml(system));
+    let _ = writeln!(out, "<table>\n<tr><th>Name</th><th>Attribute</th><th>Version</th><th>License</th><th>Homepage</th><th>Installed</th></tr>");
+    for item in items {
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escapehtml(&item.name),
+            escapehtml(item.attribute.as_deref().unwrap_or_default()),
+            escapehtml(&item.version),
+            escapehtml(&item.licenses.join(", ")),
+            item.homepage
+                .as_deref()
+                .map(|x| format!("<a href=\"{0}\">{0}</a>", escapehtml(x)))
+                .unwrap_or_default(),
+            item.source,
+        );
+    }
+    let _ = writeln!(out, "</table>\n</body>\n</html>");
+    out
+}
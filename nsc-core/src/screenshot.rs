@@ -0,0 +1,62 @@
+use anyhow::Result;
+use image::{imageops::FilterType, ImageFormat};
+use log::*;
+use sha256::digest;
+use std::{
+    env,
+    fs::{self, File},
+    io::{BufReader, Cursor},
+    path::Path,
+};
+
+use super::http;
+
+/// Downloads and scales the screenshot at `url`, returning the path of the cached png.
+/// Safe to call concurrently for the same url, e.g. from a prefetch and the package page.
+pub async fn fetchscreenshot(url: &str) -> Result<String> {
+    let home = env::var("HOME")?;
+    let scrndir = format!("{}/.cache/nix-software-center/screenshots", home);
+    let scrnpath = format!("{}/{}", scrndir, digest(url.to_string()));
+    let pngpath = format!("{}.png", scrnpath);
+    if Path::new(&pngpath).exists() {
+        return Ok(pngpath);
+    }
+
+    let slot = http::downloadslot().await?;
+    // Another caller may have finished the same screenshot while we waited
+    if Path::new(&pngpath).exists() {
+        return Ok(pngpath);
+    }
+    let bytes = http::get(url).await?;
+    drop(slot);
+    fs::create_dir_all(&scrndir)?;
+    let tmppath = format!("{}.{}", scrnpath, rand::random::<u32>());
+    let mut file = File::create(&tmppath)?;
+    std::io::copy(&mut Cursor::new(bytes), &mut file)?;
+
+    let result = scale(&tmppath, &pngpath);
+    if let Err(e) = fs::remove_file(&tmppath) {
+        warn!("{}", e);
+    }
+    result.map(|_| pngpath)
+}
+
+fn scale(path: &str, pngpath: &str) -> Result<()> {
+    let img = if let Ok(x) = image::load(BufReader::new(File::open(path)?), ImageFormat::Png) {
+        x
+    } else if let Ok(x) = image::load(BufReader::new(File::open(path)?), ImageFormat::Jpeg) {
+        x
+    } else if let Ok(x) = image::load(BufReader::new(File::open(path)?), ImageFormat::WebP) {
+        x
+    } else {
+        let imgdata = BufReader::new(File::open(path)?);
+        let format = image::guess_format(imgdata.buffer())?;
+        image::load(imgdata, format)?
+    };
+    let scaled = img.resize(640, 360, FilterType::Lanczos3);
+    let tmppng = format!("{}.{}", pngpath, rand::random::<u32>());
+    let mut output = File::create(&tmppng)?;
+    scaled.write_to(&mut output, ImageFormat::Png)?;
+    fs::rename(&tmppng, pngpath)?;
+    Ok(())
+}
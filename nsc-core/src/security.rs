@@ -0,0 +1,152 @@
+use anyhow::Result;
+use log::*;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::http;
+
+const OSV_QUERYBATCH: &str = "https://api.osv.dev/v1/querybatch";
+const OSV_VULN: &str = "https://api.osv.dev/v1/vulns/";
+/// Most queries OSV accepts in one batch
+const BATCHSIZE: usize = 1000;
+const SEVERITIES: [&str; 4] = ["CRITICAL", "HIGH", "MODERATE", "LOW"];
+/// Distributions patch and version packages their own way, so their advisories say nothing
+/// about the nixpkgs build of the same name.
+const DISTROS: &[&str] = &[
+    "AlmaLinux",
+    "Alpine",
+    "Android",
+    "Chainguard",
+    "Debian",
+    "Mageia",
+    "openSUSE",
+    "Photon OS",
+    "Red Hat",
+    "Rocky Linux",
+    "SUSE",
+    "Ubuntu",
+    "Wolfi",
+];
+
+/// Ranks a severity label so that lower is more severe.
+pub fn severityrank(severity: &str) -> usize {
+    SEVERITIES
+        .iter()
+        .position(|x| *x == severity)
+        .unwrap_or(SEVERITIES.len())
+}
+
+/// Ids of the vulnerabilities OSV lists for each (pname, version), in the same order.
+async fn querybatch(client: &reqwest::Client, pkgs: &[(String, String)]) -> Result<Vec<Vec<String>>> {
+    let queries = pkgs
+        .iter()
+        .map(|(pname, version)| {
+            serde_json::json!({
+                "package": { "name": pname },
+                "version": version,
+            })
+        })
+        .collect::<Vec<_>>();
+    let body = serde_json::json!({ "queries": queries });
+    let response = client
+        .post(OSV_QUERYBATCH)
+        .header("Content-Type", "application/json")
+        .body(body.to_string())
+        .send()
+        .await?
+        .text()
+        .await?;
+    let json: Value = serde_json::from_str(&response)?;
+    let results = json["results"].as_array().cloned().unwrap_or_default();
+    Ok(results
+        .iter()
+        .map(|x| {
+            x["vulns"]
+                .as_array()
+                .map(|v| v.iter().filter_map(|x| x["id"].as_str()).map(String::from).collect())
+                .unwrap_or_default()
+        })
+        .collect())
+}
+
+async fn queryvuln(client: &reqwest::Client, id: &str) -> Result<Value> {
+    let response = client.get(format!("{}{}", OSV_VULN, id)).send().await?.text().await?;
+    Ok(serde_json::from_str(&response)?)
+}
+
+/// Whether the vulnerability lists `version` of `pname` as affected in an upstream ecosystem.
+fn affects(vuln: &Value, pname: &str, version: &str) -> bool {
+    vuln["affected"].as_array().into_iter().flatten().any(|a| {
+        let ecosystem = a["package"]["ecosystem"].as_str().unwrap_or_default();
+        a["package"]["name"].as_str().is_some_and(|x| x.eq_ignore_ascii_case(pname))
+            && !DISTROS.iter().any(|x| ecosystem.starts_with(x))
+            && a["versions"]
+                .as_array()
+                .is_some_and(|x| x.iter().any(|x| x.as_str() == Some(version)))
+    })
+}
+
+fn severity(vuln: &Value) -> String {
+    let mut severity = String::from("UNKNOWN");
+    let mut labels = vec![vuln["database_specific"]["severity"].as_str()];
+    if let Some(affected) = vuln["affected"].as_array() {
+        for a in affected {
+            labels.push(a["ecosystem_specific"]["severity"].as_str());
+            labels.push(a["database_specific"]["severity"].as_str());
+        }
+    }
+    for label in labels.into_iter().flatten() {
+        let label = match label.to_uppercase().as_str() {
+            "MEDIUM" => String::from("MODERATE"),
+            x => x.to_string(),
+        };
+        if severityrank(&label) < severityrank(&severity) {
+            severity = label;
+        }
+    }
+    severity
+}
+
+/// Looks up known vulnerabilities for the given (pname, version) pairs on OSV.
+/// Returns the highest severity found for each vulnerable pname.
+pub async fn vulnerablepkgs(pkgs: Vec<(String, String)>) -> HashMap<String, String> {
+    let client = http::client();
+    let mut out = HashMap::new();
+    // The same advisory often covers several packages
+    let mut vulns: HashMap<String, Option<Value>> = HashMap::new();
+    for chunk in pkgs.chunks(BATCHSIZE) {
+        let results = match querybatch(client, chunk).await {
+            Ok(results) => results,
+            Err(e) => {
+                warn!("Failed to query vulnerabilities: {}", e);
+                continue;
+            }
+        };
+        for ((pname, version), ids) in chunk.iter().zip(results) {
+            for id in ids {
+                if !vulns.contains_key(&id) {
+                    let vuln = queryvuln(client, &id)
+                        .await
+                        .inspect_err(|e| warn!("Failed to query vulnerability {}: {}", id, e))
+                        .ok();
+                    vulns.insert(id.to_string(), vuln);
+                }
+                let Some(Some(vuln)) = vulns.get(&id) else {
+                    continue;
+                };
+                if !affects(vuln, pname, version) {
+                    continue;
+                }
+                let severity = severity(vuln);
+                let worse = out
+                    .get(pname)
+                    .map(|x: &String| severityrank(&severity) < severityrank(x))
+                    .unwrap_or(true);
+                if worse {
+                    out.insert(pname.to_string(), severity);
+                }
+            }
+        }
+    }
+    out
+}
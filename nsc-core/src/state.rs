@@ -0,0 +1,146 @@
+use super::{nixlog::Substitutions, packages::SearchFilters, popularity::now};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+    time::Instant,
+};
+
+/// Number of finished operations whose statistics are kept.
+const MAXOPERATIONS: usize = 500;
+
+/// Local state kept between runs that isn't a user setting.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct NscState {
+    /// Package to the update version the user chose to skip
+    pub skippedupdates: HashMap<String, String>,
+    /// Unix time of the last popularity report
+    pub lastpopularity: Option<u64>,
+    /// User packages installed through Nix Software Center, by attribute for nix profile and by
+    /// name for nix-env. Unset until the first run that tracks them.
+    pub managed: Option<HashSet<String>>,
+    /// Load the previous metadata snapshot instead of downloading, after the user reverted a bad update
+    pub pinnedmetadata: bool,
+    /// Private notes on packages, by attribute, or by name for packages without one
+    pub notes: HashMap<String, String>,
+    /// Unix time each downloaded artifact was last refreshed, e.g. [`crate::appstream::ARTIFACT`]
+    /// for the appstream catalog
+    pub refreshed: HashMap<String, u64>,
+    /// Unix time of the last failed refresh of each artifact, cleared when one succeeds
+    pub refreshfailed: HashMap<String, u64>,
+    /// Channel revision when the package databases were last refreshed, to notice newer releases
+    pub pkgsrevision: Option<String>,
+    pub ui: UiState,
+    /// Statistics of the latest installs, removals and updates, oldest first
+    pub operations: Vec<OperationRecord>,
+}
+
+/// What one install, removal or update took.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct OperationRecord {
+    /// Unix time it finished
+    pub time: u64,
+    /// Seconds it ran
+    pub duration: u64,
+    /// Bytes downloaded from binary caches
+    pub downloaded: u64,
+    /// Store paths fetched from binary caches
+    pub substituted: usize,
+    /// Store paths built locally
+    pub built: usize,
+    pub success: bool,
+}
+
+/// Window layout saved on close and restored at the next start.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct UiState {
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub maximized: bool,
+    /// Name of the visible page of the main view stack
+    pub page: Option<String>,
+    /// Scroll position in pixels of the main pages, by name
+    pub scroll: HashMap<String, u32>,
+    /// Updates listed under their categories
+    pub groupupdates: bool,
+    /// Installed packages narrowed to the ones with notes
+    pub notedonly: bool,
+    pub searchfilters: SearchFilters,
+}
+
+/// Sums the statistics of `operations`, `time` is the latest one and `success` whether all succeeded.
+pub fn operationtotals(operations: &[OperationRecord]) -> OperationRecord {
+    let start = OperationRecord { success: true, ..Default::default() };
+    operations.iter().fold(start, |total, x| OperationRecord {
+        time: total.time.max(x.time),
+        duration: total.duration + x.duration,
+        downloaded: total.downloaded + x.downloaded,
+        substituted: total.substituted + x.substituted,
+        built: total.built + x.built,
+        success: total.success && x.success,
+    })
+}
+
+/// Saves the statistics of an operation that started at `started` and just finished.
+pub fn recordoperation(started: Instant, substitutions: &Substitutions, success: bool) -> Result<()> {
+    let record = OperationRecord {
+        time: now(),
+        duration: started.elapsed().as_secs(),
+        downloaded: substitutions.bytes(),
+        substituted: substitutions.substituted(),
+        built: substitutions.built(),
+        success,
+    };
+    editnscstate(|x| {
+        x.operations.push(record);
+        let excess = x.operations.len().saturating_sub(MAXOPERATIONS);
+        x.operations.drain(..excess);
+    })
+}
+
+/// Whether the user package `key` was installed through Nix Software Center.
+/// Everything counts as managed until tracking has started.
+pub fn ismanaged(state: &NscState, key: &str) -> bool {
+    state.managed.as_ref().map(|x| x.contains(key)).unwrap_or(true)
+}
+
+/// Where state and crash reports are kept, `$XDG_STATE_HOME/nix-software-center`.
+pub fn statedir() -> Result<PathBuf> {
+    if let Ok(state) = std::env::var("XDG_STATE_HOME") {
+        return Ok(PathBuf::from(state).join("nix-software-center"));
+    }
+    let home = std::env::var("HOME")?;
+    Ok(PathBuf::from(home).join(".local/state/nix-software-center"))
+}
+
+fn nscstatepath() -> Result<PathBuf> {
+    Ok(statedir()?.join("state.json"))
+}
+
+pub fn getnscstate() -> NscState {
+    if let Ok(path) = nscstatepath() {
+        if let Ok(s) = fs::read_to_string(path) {
+            if let Ok(c) = serde_json::from_str(&s) {
+                return c;
+            }
+        }
+    }
+    NscState::default()
+}
+
+/// Applies `edit` to the state on disk, so that parts kept by different pages don't overwrite each other.
+pub fn editnscstate(edit: impl FnOnce(&mut NscState)) -> Result<()> {
+    let path = nscstatepath()?;
+    let mut state = getnscstate();
+    edit(&mut state);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&state)?)?;
+    Ok(())
+}
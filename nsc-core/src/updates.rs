@@ -0,0 +1,155 @@
+use super::{config::SystemPkgs, profile::UserPkgs};
+use anyhow::Result;
+use log::*;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+
+/// An installed package with a different version available.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PendingUpgrade {
+    /// nixpkgs attribute, unknown when several attributes share the package name
+    pub attribute: Option<String>,
+    pub pname: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub system: bool,
+}
+
+/// Upgrades for user packages. `installed` maps package names to versions for `nix-env`
+/// and attributes to versions for `nix profile`, which is compared against `nixpkgsdb`.
+pub async fn userupgrades(
+    pkgdb: &str,
+    nixpkgsdb: Option<&str>,
+    userpkgs: &UserPkgs,
+    installed: &HashMap<String, String>,
+) -> Result<Vec<PendingUpgrade>> {
+    let pool = SqlitePool::connect(&format!("sqlite://{}", pkgdb)).await?;
+    let mut upgrades = vec![];
+    match userpkgs {
+        UserPkgs::Env => {
+            for (pname, installedver) in installed {
+                let attrs: Vec<(String,)> = sqlx::query_as("SELECT attribute FROM pkgs WHERE pname = $1")
+                    .bind(pname)
+                    .fetch_all(&pool)
+                    .await?;
+                match attrs.len() {
+                    1 => {
+                        let (attr,) = &attrs[0];
+                        let (newver,): (String,) = sqlx::query_as("SELECT version FROM pkgs WHERE attribute = $1")
+                            .bind(attr)
+                            .fetch_one(&pool)
+                            .await?;
+                        if *installedver != newver {
+                            upgrades.push(PendingUpgrade {
+                                attribute: Some(attr.to_string()),
+                                pname: pname.to_string(),
+                                from: Some(installedver.to_string()),
+                                to: Some(newver),
+                                system: false,
+                            });
+                        }
+                    }
+                    2.. => {
+                        let versions: Vec<(String,)> = sqlx::query_as("SELECT version FROM pkgs WHERE pname = $1")
+                            .bind(pname)
+                            .fetch_all(&pool)
+                            .await?;
+                        if !versions.contains(&(installedver.to_string(),)) {
+                            upgrades.push(PendingUpgrade {
+                                attribute: None,
+                                pname: pname.to_string(),
+                                from: Some(installedver.to_string()),
+                                to: None,
+                                system: false,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        UserPkgs::Profile => {
+            let latest = match nixpkgsdb {
+                Some(db) => SqlitePool::connect(&format!("sqlite://{}", db)).await?,
+                None => return Ok(upgrades),
+            };
+            for attr in installed.keys() {
+                let current: sqlx::Result<(String, String)> =
+                    sqlx::query_as("SELECT pname, version FROM pkgs WHERE attribute = $1")
+                        .bind(attr)
+                        .fetch_one(&pool)
+                        .await;
+                let newver: sqlx::Result<(String,)> = sqlx::query_as("SELECT version FROM pkgs WHERE attribute = $1")
+                    .bind(attr)
+                    .fetch_one(&latest)
+                    .await;
+                match (current, newver) {
+                    (Ok((pname, version)), Ok((newver,))) => {
+                        if version != newver {
+                            upgrades.push(PendingUpgrade {
+                                attribute: Some(attr.to_string()),
+                                pname,
+                                from: Some(version),
+                                to: Some(newver),
+                                system: false,
+                            });
+                        }
+                    }
+                    _ => debug!("{} is not in nixpkgs", attr),
+                }
+            }
+        }
+    }
+    Ok(upgrades)
+}
+
+/// Upgrades for packages in the system configuration, comparing the currently built
+/// system in `systemdb` against `pkgdb`.
+pub async fn systemupgrades(pkgdb: &str, systemdb: &str, installed: &HashSet<String>) -> Result<Vec<PendingUpgrade>> {
+    let pool = SqlitePool::connect(&format!("sqlite://{}", pkgdb)).await?;
+    let currentpool = SqlitePool::connect(&format!("sqlite://{}", systemdb)).await?;
+    let mut upgrades = vec![];
+    for attr in installed {
+        let new: sqlx::Result<(String, String)> =
+            sqlx::query_as("SELECT pname, version FROM pkgs WHERE attribute = $1")
+                .bind(attr)
+                .fetch_one(&pool)
+                .await;
+        let current: sqlx::Result<(String,)> = sqlx::query_as("SELECT version FROM pkgs WHERE attribute = $1")
+            .bind(attr)
+            .fetch_one(&currentpool)
+            .await;
+        if let (Ok((pname, version)), Ok((currver,))) = (new, current) {
+            if version != currver {
+                upgrades.push(PendingUpgrade {
+                    attribute: Some(attr.to_string()),
+                    pname,
+                    from: Some(currver),
+                    to: Some(version),
+                    system: true,
+                });
+            }
+        }
+    }
+    Ok(upgrades)
+}
+
+/// The NixOS system itself, when its channel or flake input is behind.
+pub fn systemupgrade(syspkgs: &SystemPkgs) -> Option<PendingUpgrade> {
+    let uptodate = match syspkgs {
+        SystemPkgs::Legacy => nix_data::cache::channel::uptodate(),
+        SystemPkgs::Flake => nix_data::cache::flakes::uptodate(),
+        SystemPkgs::None => return None,
+    };
+    match uptodate {
+        Ok(Some((old, new))) => Some(PendingUpgrade {
+            attribute: None,
+            pname: String::from("nixos"),
+            from: Some(old),
+            to: Some(new),
+            system: true,
+        }),
+        _ => None,
+    }
+}
@@ -0,0 +1,43 @@
+pub fn checkonline() -> bool {
+    reqwest::blocking::get("https://nmcheck.gnome.org/check_network_status.txt").is_ok()
+}
+
+/// The nix system double of this machine, e.g. `x86_64-linux`.
+pub fn currentsystem() -> String {
+    format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS)
+}
+
+/// Release channel named after the running NixOS version, e.g. `nixos-24.05`. The system may
+/// follow another channel, [`nixoschannel`] reads the one it does.
+pub fn nixosrelease() -> Option<String> {
+    let osrelease = std::fs::read_to_string("/etc/os-release").ok()?;
+    osrelease
+        .lines()
+        .find_map(|x| x.strip_prefix("VERSION_ID="))
+        .map(|x| format!("nixos-{}", x.trim_matches('"')))
+}
+
+/// The channel the system nixpkgs comes from, e.g. `nixos-unstable`: the branch of the `nixpkgs`
+/// input in the lock file of `flake`, or the `nixos` channel of root. `None` when neither says.
+pub fn nixoschannel(flake: Option<&str>) -> Option<String> {
+    if let Some(flake) = flake {
+        let path = std::path::Path::new(flake);
+        let dir = if path.is_dir() { path } else { path.parent()? };
+        let lock: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("flake.lock")).ok()?).ok()?;
+        let nodes = &lock["nodes"];
+        let input = nodes[lock["root"].as_str()?]["inputs"]["nixpkgs"].as_str()?;
+        return nodes[input]["original"]["ref"].as_str().map(String::from);
+    }
+    // Only readable when root's home is, the store path of the channel doesn't name it
+    let channels = std::fs::read_to_string("/root/.nix-channels").ok()?;
+    channels.lines().find_map(|x| match x.split_whitespace().collect::<Vec<_>>()[..] {
+        [url, "nixos"] => url.trim_end_matches('/').rsplit('/').next().map(String::from),
+        _ => None,
+    })
+}
+
+/// Sort key that orders names the way the user's locale does, so that accented letters sort
+/// next to their base letter and non-Latin scripts follow their own order.
+pub fn sortkey(name: &str) -> glib::CollationKey {
+    glib::CollationKey::from(name)
+}
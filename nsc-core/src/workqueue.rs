@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+
+/// Where a package operation is in the queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkState {
+    Pending,
+    Running,
+    Done,
+    Failed,
+}
+
+/// Finished operations kept to show what happened recently.
+const KEEPFINISHED: usize = 20;
+
+/// What an operation changes. Operations in different lanes don't conflict and can run at the same
+/// time, while the user profile and the system configuration each only take one change at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WorkLane {
+    User,
+    System,
+}
+
+/// An operation on a package that [`WorkQueue`] schedules.
+pub trait Work: Clone + PartialEq {
+    fn lane(&self) -> WorkLane;
+    /// Package the operation changes, operations on the same package run in the order requested
+    fn pkg(&self) -> &str;
+    /// Whether the operation changes the package known as `key` in `lane`.
+    fn matches(&self, key: &str, lane: WorkLane) -> bool {
+        self.lane() == lane && self.pkg() == key
+    }
+}
+
+/// Package operations in the order they were requested, run one at a time per [`WorkLane`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkQueue<W> {
+    items: Vec<(W, WorkState)>,
+}
+
+impl<W> Default for WorkQueue<W> {
+    fn default() -> Self {
+        WorkQueue { items: vec![] }
+    }
+}
+
+impl<W: Work> WorkQueue<W> {
+    /// Adds `work` unless the same operation is already waiting or running.
+    pub fn push(&mut self, work: W) -> bool {
+        if self.active().any(|x| *x == work) {
+            return false;
+        }
+        self.items.push((work, WorkState::Pending));
+        true
+    }
+
+    /// Marks the pending operations that can start now as running. Each lane runs its operations
+    /// in order, and an operation waits for earlier ones on the same package in any lane.
+    pub fn startready(&mut self) -> Vec<W> {
+        let mut blocked = HashSet::new();
+        let mut ahead = HashSet::new();
+        let mut started = vec![];
+        for (work, state) in self.items.iter_mut() {
+            if isfinished(state) {
+                continue;
+            }
+            let lane = work.lane();
+            if *state == WorkState::Pending && !blocked.contains(&lane) && !ahead.contains(work.pkg()) {
+                *state = WorkState::Running;
+                started.push(work.clone());
+            }
+            blocked.insert(lane);
+            ahead.insert(work.pkg().to_string());
+        }
+        started
+    }
+
+    pub fn finish(&mut self, work: &W, success: bool) {
+        if let Some((_, state)) = self
+            .items
+            .iter_mut()
+            .find(|(x, state)| x == work && *state == WorkState::Running)
+        {
+            *state = if success { WorkState::Done } else { WorkState::Failed };
+        }
+        let finished = self.items.iter().filter(|(_, state)| isfinished(state)).count();
+        let mut drop = finished.saturating_sub(KEEPFINISHED);
+        self.items.retain(|(_, state)| {
+            if drop > 0 && isfinished(state) {
+                drop -= 1;
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Drops the waiting and running operations on `pkg`. The caller stops a running process itself.
+    pub fn cancel(&mut self, pkg: &str) {
+        self.items
+            .retain(|(x, state)| x.pkg() != pkg || isfinished(state));
+    }
+
+    pub fn running(&self) -> impl Iterator<Item = &W> {
+        self.items
+            .iter()
+            .filter(|(_, state)| *state == WorkState::Running)
+            .map(|(x, _)| x)
+    }
+
+    /// Operations that are waiting or running.
+    pub fn active(&self) -> impl Iterator<Item = &W> {
+        self.items
+            .iter()
+            .filter(|(_, state)| !isfinished(state))
+            .map(|(x, _)| x)
+    }
+
+    pub fn items(&self) -> &[(W, WorkState)] {
+        &self.items
+    }
+
+    /// Whether an operation on the package known as `key` in `lane` is waiting or running.
+    pub fn isbusy(&self, key: &str, lane: WorkLane) -> bool {
+        self.active().any(|x| x.matches(key, lane))
+    }
+}
+
+fn isfinished(state: &WorkState) -> bool {
+    matches!(state, WorkState::Done | WorkState::Failed)
+}
@@ -0,0 +1,29 @@
+use crate::config::{GETTEXT_PACKAGE, LOCALEDIR};
+use crate::parse::i18n::fill;
+use log::*;
+
+pub use gettextrs::{gettext, ngettext};
+
+/// Loads the translations for the user's locale, has to run before any window is built.
+pub fn init() {
+    gettextrs::setlocale(gettextrs::LocaleCategory::LcAll, "");
+    if let Err(e) = gettextrs::bindtextdomain(GETTEXT_PACKAGE, LOCALEDIR) {
+        warn!("Failed to bind the text domain: {}", e);
+    }
+    if let Err(e) = gettextrs::bind_textdomain_codeset(GETTEXT_PACKAGE, "UTF-8") {
+        warn!("Failed to set the text domain codeset: {}", e);
+    }
+    if let Err(e) = gettextrs::textdomain(GETTEXT_PACKAGE) {
+        warn!("Failed to set the text domain: {}", e);
+    }
+}
+
+/// `gettext` for messages with `{}` placeholders.
+pub fn gettextf(msgid: &str, args: &[&str]) -> String {
+    fill(gettext(msgid), args)
+}
+
+/// `ngettext` for messages with `{}` placeholders.
+pub fn ngettextf(msgid: &str, plural: &str, n: u32, args: &[&str]) -> String {
+    fill(ngettext(msgid, plural, n), args)
+}
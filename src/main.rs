@@ -1,21 +1,49 @@
 use adw::gio;
-use gtk::{prelude::ApplicationExt, glib};
+use gtk::{prelude::{ApplicationExt, FileExt}, glib};
 use log::{error, info};
-use nix_software_center::{ui::window::AppModel, config::RESOURCES_FILE};
+use nix_software_center::{ui::{dbus, window::{AppModel, AppMsg, WINDOW_BROKER}}, config::{self, RESOURCES_FILE}, i18n, parse::{crash, demo}};
 use relm4::*;
 fn main() {
-    gtk::init().unwrap();
     pretty_env_logger::init();
-	glib::set_application_name("Software Center");
+    crash::installhook(config::VERSION);
+    i18n::init();
+    gtk::init().unwrap();
+	glib::set_application_name(&i18n::gettext("Software Center"));
     if let Ok(res) = gio::Resource::load(RESOURCES_FILE) {
         info!("Resource loaded: {}", RESOURCES_FILE);
         gio::resources_register(&res);
     } else {
         error!("Failed to load resources");
     }
+    nix_software_center::ui::categories::loadstyles();
     gtk::Window::set_default_icon_name(nix_software_center::config::APP_ID);
-    let app = adw::Application::new(Some(nix_software_center::config::APP_ID), gio::ApplicationFlags::empty());
+    let app = adw::Application::new(Some(nix_software_center::config::APP_ID), gio::ApplicationFlags::HANDLES_OPEN);
     app.set_resource_base_path(Some("/dev/vlinkz/NixSoftwareCenter"));
-    let app = RelmApp::from_app(app);
+    // Only the primary instance has the connection, later launches forward to it
+    app.connect_startup(|app| {
+        if let Some(connection) = app.dbus_connection() {
+            dbus::register(&connection);
+        }
+    });
+    // `.desktop` and appstream files open the page of the package that owns them, as do
+    // `appstream://` and `nix://` links
+    app.connect_open(|app, files, _| {
+        app.activate();
+        for file in files {
+            if matches!(file.uri_scheme().as_deref(), Some("appstream" | "nix")) {
+                WINDOW_BROKER.send(AppMsg::OpenUri(file.uri().to_string()));
+            } else if let Some(path) = file.path() {
+                WINDOW_BROKER.send(AppMsg::OpenFile(path));
+            }
+        }
+    });
+    // Not a GApplication option, so it has to be taken out before gio sees the arguments
+    let (demo, args): (Vec<String>, Vec<String>) = std::env::args().partition(|x| x == "--demo");
+    if !demo.is_empty() {
+        demo::enable();
+    }
+    let app = RelmApp::from_app(app)
+        .with_broker(&WINDOW_BROKER)
+        .with_args(args);
     app.run::<AppModel>(());
 }
@@ -0,0 +1,115 @@
+use crate::{config::PKGDATADIR, parse::config::NscConfig};
+use anyhow::{anyhow, Result};
+use log::*;
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+/// Preseeded package metadata, used when the databases can't be downloaded, such as when offline on first launch.
+///
+/// A bundle is a directory containing `pkgs.db`, and optionally `nixpkgslatest.db`, `system.db` and
+/// `appstream.yml.gz`. The databases may also be zstd compressed, as `pkgs.db.zst` and so on. The configured
+/// path may also point at the package database itself, in which case the other files are looked up next to it.
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    dir: PathBuf,
+    pkgdb: String,
+}
+
+impl Bundle {
+    fn open(path: &Path) -> Option<Self> {
+        if path.is_file() {
+            let dir = path.parent()?.to_path_buf();
+            let pkgdb = if path.extension().map(|x| x == "zst").unwrap_or(false) {
+                decompressed(path).map_err(|e| warn!("Failed to decompress {}: {}", path.display(), e)).ok()?
+            } else {
+                path.to_string_lossy().to_string()
+            };
+            Some(Self { dir, pkgdb })
+        } else {
+            let dir = path.to_path_buf();
+            let pkgdb = file(&dir, "pkgs.db")?;
+            Some(Self { dir, pkgdb })
+        }
+    }
+
+    pub fn pkgdb(&self) -> String {
+        self.pkgdb.clone()
+    }
+
+    pub fn nixpkgsdb(&self) -> Option<String> {
+        file(&self.dir, "nixpkgslatest.db")
+    }
+
+    pub fn systemdb(&self) -> Option<String> {
+        file(&self.dir, "system.db")
+    }
+
+    pub fn appstream(&self) -> Option<String> {
+        file(&self.dir, "appstream.yml.gz")
+    }
+}
+
+fn file(dir: &Path, name: &str) -> Option<String> {
+    let path = dir.join(name);
+    let compressed = dir.join(format!("{}.zst", name));
+    if path.is_file() {
+        Some(path.to_string_lossy().to_string())
+    } else if compressed.is_file() {
+        decompressed(&compressed)
+            .map_err(|e| warn!("Failed to decompress {}: {}", compressed.display(), e))
+            .ok()
+    } else {
+        None
+    }
+}
+
+/// The application's cache directory, created if missing.
+pub fn cachedir() -> Result<PathBuf> {
+    let cache = match std::env::var("XDG_CACHE_HOME") {
+        Ok(cache) => PathBuf::from(cache),
+        Err(_) => PathBuf::from(std::env::var("HOME")?).join(".cache"),
+    }
+    .join("nix-software-center");
+    fs::create_dir_all(&cache)?;
+    Ok(cache)
+}
+
+/// Decompresses a zstd file into the cache, reusing an earlier copy unless the compressed file is newer.
+pub fn decompressed(path: &Path) -> Result<String> {
+    let name = path
+        .file_stem()
+        .ok_or_else(|| anyhow!("Invalid file name: {}", path.display()))?;
+    let cache = cachedir()?;
+    let dest = cache.join(name);
+    let stale = match (
+        fs::metadata(path).and_then(|x| x.modified()),
+        fs::metadata(&dest).and_then(|x| x.modified()),
+    ) {
+        (Ok(src), Ok(dst)) => src > dst,
+        _ => true,
+    };
+    if stale {
+        info!("Decompressing {}", path.display());
+        let tmp = cache.join(format!("{}.part", name.to_string_lossy()));
+        let mut decoder = zstd::Decoder::new(File::open(path)?)?;
+        std::io::copy(&mut decoder, &mut File::create(&tmp)?)?;
+        fs::rename(&tmp, &dest)?;
+    }
+    Ok(dest.to_string_lossy().to_string())
+}
+
+/// Finds a bundle, preferring one set by the user over one shipped with the application.
+pub fn findbundle(config: &NscConfig) -> Option<Bundle> {
+    let mut paths = vec![];
+    if let Some(bundle) = &config.bundle {
+        paths.push(PathBuf::from(bundle));
+    }
+    if let Ok(bundle) = std::env::var("NSC_METADATA_BUNDLE") {
+        paths.push(PathBuf::from(bundle));
+    }
+    paths.push(PathBuf::from("/etc/nix-software-center/bundle"));
+    paths.push(Path::new(PKGDATADIR).join("bundle"));
+    paths.iter().find_map(|x| Bundle::open(x))
+}
@@ -0,0 +1,72 @@
+use crate::{config::PKGDATADIR, parse::bundle::cachedir};
+use anyhow::Result;
+use log::*;
+use sqlx::{sqlite::SqliteConnectOptions, SqlitePool};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Set by `--demo`, which runs the application on a small bundled package set instead of the
+/// real package databases, with a made up user profile. Nothing gets installed or downloaded.
+static DEMO: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    info!("Running in demo mode");
+    DEMO.store(true, Ordering::SeqCst);
+}
+
+pub fn enabled() -> bool {
+    DEMO.load(Ordering::SeqCst)
+}
+
+/// The demo data: `pkgs.sql`, `appstream.yml` and `installed`. `NSC_DEMO_DATA` points
+/// elsewhere, such as `data/demo` when running from the source tree.
+fn datadir() -> PathBuf {
+    match std::env::var("NSC_DEMO_DATA") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => Path::new(PKGDATADIR).join("demo"),
+    }
+}
+
+/// Builds the demo package database from `pkgs.sql` into the cache, fresh on every start.
+pub async fn pkgdb() -> Result<String> {
+    let sql = fs::read_to_string(datadir().join("pkgs.sql"))?;
+    let db = cachedir()?.join("demo.db");
+    if db.exists() {
+        fs::remove_file(&db)?;
+    }
+    let db = db.to_string_lossy().to_string();
+    let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", db))?.create_if_missing(true);
+    let pool = SqlitePool::connect_with(options).await?;
+    sqlx::raw_sql(&sql).execute(&pool).await?;
+    pool.close().await;
+    Ok(db)
+}
+
+pub fn appstream() -> Option<String> {
+    let path = datadir().join("appstream.yml");
+    path.is_file().then(|| path.to_string_lossy().to_string())
+}
+
+/// The packages in the made up user profile and their versions, from `installed`, one
+/// `attribute version` pair per line.
+pub fn installed() -> HashMap<String, String> {
+    let path = datadir().join("installed");
+    match fs::read_to_string(&path) {
+        Ok(s) => s
+            .lines()
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty() && !x.starts_with('#'))
+            .filter_map(|x| x.split_once(' '))
+            .map(|(attr, version)| (attr.to_string(), version.trim().to_string()))
+            .collect(),
+        Err(e) => {
+            warn!("Failed to read {}: {}", path.display(), e);
+            HashMap::new()
+        }
+    }
+}
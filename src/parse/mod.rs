@@ -1,3 +1,6 @@
-pub mod packages;
-pub mod config;
-pub mod util;
\ No newline at end of file
+pub mod bundle;
+pub mod demo;
+pub use nsc_core::{
+    appstream, categories, channel, cmd, config, crash, deps, expression, favicon, hints, http, i18n, integration, metadata, narinfo, nixlog, nixpkgsconfig, nixversion, packages, popularity, power, profile, programs, remote, report,
+    screenshot, security, state, util, workqueue,
+};
@@ -1,3 +0,0 @@
-pub fn checkonline() -> bool {
-    reqwest::blocking::get("https://nmcheck.gnome.org/check_network_status.txt").is_ok()
-}
\ No newline at end of file
@@ -2,6 +2,8 @@ use adw::prelude::*;
 use relm4::*;
 
 use crate::config;
+use crate::i18n::gettextf;
+use crate::parse::nixversion;
 
 #[derive(Debug)]
 pub struct AboutPageModel {}
@@ -41,6 +43,7 @@ impl SimpleComponent for AboutPageModel {
         let dialog = adw::AboutWindow::builder()
             .application_icon(config::APP_ID)
             .application_name("Nix Software Center")
+            .comments(gettextf("Managing packages with {}", &[&nixversion::describe()]))
             .developer_name("Victor Fuentes")
             .developers(vec!["Victor Fuentes https://github.com/vlinkz"])
             .issue_url("https://github.com/vlinkz/nix-software-center/issues")
@@ -0,0 +1,216 @@
+use crate::i18n::gettext;
+use std::collections::HashSet;
+
+use super::searchpage::{SearchItem, SearchItemModel};
+use super::window::*;
+use adw::prelude::*;
+use log::*;
+use relm4::{factory::*, *};
+
+#[tracker::track]
+#[derive(Debug)]
+pub struct AllPkgsPageModel {
+    #[tracker::no_eq]
+    items: FactoryVecDeque<SearchItemModel>,
+    /// Index entry the list starts at, empty for the beginning
+    letter: String,
+    /// Attribute of the last loaded row
+    last: Option<String>,
+    more: bool,
+    loading: bool,
+}
+
+/// Number of rows loaded at a time.
+pub const PAGESIZE: usize = 100;
+
+/// Entries of the alphabetical index. Attributes starting with anything else sort before `a`.
+const INDEX: &str = "abcdefghijklmnopqrstuvwxyz";
+
+#[derive(Debug)]
+pub enum AllPkgsPageMsg {
+    Open,
+    Close,
+    Jump(String),
+    LoadMore,
+    /// Rows following the given index entry, and whether there are more
+    Append(String, Vec<SearchItem>, bool),
+    OpenRow(gtk::ListBoxRow),
+    UpdateInstalled(HashSet<String>, HashSet<String>),
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for AllPkgsPageModel {
+    type Init = ();
+    type Input = AllPkgsPageMsg;
+    type Output = AppMsg;
+
+    view! {
+        gtk::Box {
+            set_orientation: gtk::Orientation::Vertical,
+            adw::HeaderBar {
+                pack_start = &gtk::Button {
+                    add_css_class: "flat",
+                    gtk::Image {
+                        set_icon_name: Some("go-previous-symbolic"),
+                    },
+                    connect_clicked[sender] => move |_| {
+                        sender.input(AllPkgsPageMsg::Close)
+                    },
+                },
+                #[wrap(Some)]
+                set_title_widget = &gtk::Label {
+                    set_label: &gettext("All Packages"),
+                },
+            },
+            adw::Clamp {
+                set_maximum_size: 1000,
+                #[local_ref]
+                indexbox -> gtk::FlowBox {
+                    set_margin_all: 10,
+                    set_selection_mode: gtk::SelectionMode::None,
+                    set_homogeneous: true,
+                    set_min_children_per_line: 9,
+                    set_max_children_per_line: 27,
+                },
+            },
+            gtk::ScrolledWindow {
+                set_vexpand: true,
+                set_hexpand: true,
+                set_hscrollbar_policy: gtk::PolicyType::Never,
+                #[track(model.changed(AllPkgsPageModel::letter()))]
+                set_vadjustment: gtk::Adjustment::NONE,
+                connect_edge_reached[sender] => move |_, pos| {
+                    if pos == gtk::PositionType::Bottom {
+                        sender.input(AllPkgsPageMsg::LoadMore);
+                    }
+                },
+                adw::Clamp {
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_margin_all: 20,
+                        set_spacing: 15,
+                        #[local_ref]
+                        pkglist -> gtk::ListBox {
+                            set_valign: gtk::Align::Start,
+                            add_css_class: "boxed-list",
+                            set_selection_mode: gtk::SelectionMode::None,
+                            #[watch]
+                            set_visible: !model.items.is_empty(),
+                            connect_row_activated[sender] => move |_, row| {
+                                sender.input(AllPkgsPageMsg::OpenRow(row.clone()));
+                            }
+                        },
+                        gtk::Spinner {
+                            set_halign: gtk::Align::Center,
+                            #[watch]
+                            set_visible: model.loading,
+                            #[watch]
+                            set_spinning: model.loading,
+                        },
+                        gtk::Label {
+                            add_css_class: "dim-label",
+                            #[watch]
+                            set_visible: !model.loading && !model.more,
+                            #[watch]
+                            set_label: &if model.items.is_empty() { gettext("No packages") } else { gettext("End of the list") },
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn init(
+        (): Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = AllPkgsPageModel {
+            items: FactoryVecDeque::builder().launch(gtk::ListBox::new()).detach(),
+            letter: String::new(),
+            last: None,
+            more: true,
+            loading: false,
+            tracker: 0,
+        };
+
+        let pkglist = model.items.widget();
+        let indexbox = gtk::FlowBox::new();
+        for letter in std::iter::once(String::new()).chain(INDEX.chars().map(|x| x.to_string())) {
+            let button = gtk::Button::builder()
+                .label(if letter.is_empty() { "#" } else { letter.as_str() })
+                .css_classes(["flat"])
+                .build();
+            let sender = sender.clone();
+            button.connect_clicked(move |_| sender.input(AllPkgsPageMsg::Jump(letter.clone())));
+            indexbox.insert(&button, -1);
+        }
+
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
+        self.reset();
+        match msg {
+            AllPkgsPageMsg::Open => {
+                if self.items.is_empty() && !self.loading {
+                    sender.input(AllPkgsPageMsg::Jump(String::new()));
+                }
+            }
+            AllPkgsPageMsg::Close => {
+                sender.output(AppMsg::FrontFrontPage);
+            }
+            AllPkgsPageMsg::Jump(letter) => {
+                self.items.guard().clear();
+                self.update_letter(|x| *x = letter.clone());
+                self.last = None;
+                self.more = true;
+                self.loading = true;
+                sender.output(AppMsg::LoadAllPkgs(letter, None));
+            }
+            AllPkgsPageMsg::LoadMore => {
+                if self.loading || !self.more {
+                    return;
+                }
+                self.loading = true;
+                sender.output(AppMsg::LoadAllPkgs(self.letter.clone(), self.last.clone()));
+            }
+            AllPkgsPageMsg::Append(letter, items, more) => {
+                // Results of a page that was left already
+                if letter != self.letter {
+                    return;
+                }
+                debug!("Loaded {} packages after {:?}", items.len(), self.last);
+                if let Some(item) = items.last() {
+                    self.last = Some(item.pkg.to_string());
+                }
+                let mut guard = self.items.guard();
+                for item in items {
+                    guard.push_back(item);
+                }
+                guard.drop();
+                self.more = more;
+                self.loading = false;
+            }
+            AllPkgsPageMsg::OpenRow(row) => {
+                let guard = self.items.guard();
+                if let Some(item) = guard.get(row.index() as usize) {
+                    sender.output(AppMsg::OpenPkg(item.get_item().pkg.to_string()));
+                }
+            }
+            AllPkgsPageMsg::UpdateInstalled(installeduserpkgs, installedsystempkgs) => {
+                let mut guard = self.items.guard();
+                for i in 0..guard.len() {
+                    if let Some(item) = guard.get_mut(i) {
+                        let pkgitem = item.get_mut_item();
+                        pkgitem.installeduser = installeduserpkgs.contains(&pkgitem.pname)
+                            || installeduserpkgs.contains(&pkgitem.pkg);
+                        pkgitem.installedsystem = installedsystempkgs.contains(&pkgitem.pkg);
+                    }
+                }
+            }
+        }
+    }
+}
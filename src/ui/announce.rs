@@ -0,0 +1,22 @@
+use log::*;
+#[cfg(feature = "announce")]
+use gtk::prelude::*;
+
+/// Reads `message` out to screen readers, interrupting other speech when `important`.
+/// Needs GTK 4.14, so builds without the `announce` feature only log it.
+pub fn announce(message: &str, important: bool) {
+    debug!("Announce: {}", message);
+    #[cfg(feature = "announce")]
+    if let Some(window) = relm4::main_application().active_window() {
+        window.announce(
+            message,
+            if important {
+                gtk::AccessibleAnnouncementPriority::High
+            } else {
+                gtk::AccessibleAnnouncementPriority::Medium
+            },
+        );
+    }
+    #[cfg(not(feature = "announce"))]
+    let _ = important;
+}
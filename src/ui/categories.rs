@@ -1,22 +1,48 @@
+use crate::i18n::{gettext, ngettextf};
 use relm4::adw::prelude::*;
 use relm4::gtk::pango;
 use relm4::{factory::*, *};
 
 use super::window::AppMsg;
 
+pub use crate::parse::categories::Category as PkgCategory;
+
+/// The name of a category in the user's language. Names set in an override file are shown as written.
+pub fn categoryname(category: &PkgCategory) -> String {
+    match category.name.as_str() {
+        "Audio" => gettext("Audio"),
+        "Development" => gettext("Development"),
+        "Games" => gettext("Games"),
+        "Graphics" => gettext("Graphics"),
+        "Web" => gettext("Web"),
+        "Video" => gettext("Video"),
+        name => name.to_string(),
+    }
+}
+
+/// The name of a section from `packages::appcategory` in the user's language.
+pub fn sectionname(section: &str) -> String {
+    match section {
+        "Audio & Video" => gettext("Audio & Video"),
+        "Development" => gettext("Development"),
+        "Education" => gettext("Education"),
+        "Games" => gettext("Games"),
+        "Graphics" => gettext("Graphics"),
+        "Networking" => gettext("Networking"),
+        "Productivity" => gettext("Productivity"),
+        "Science" => gettext("Science"),
+        "System" => gettext("System"),
+        "Utilities" => gettext("Utilities"),
+        "Other" => gettext("Other"),
+        name => name.to_string(),
+    }
+}
+
 #[derive(Debug)]
 pub struct PkgGroup {
     pub category: PkgCategory,
-}
-
-#[derive(Debug, Hash, Eq, PartialEq, Clone)]
-pub enum PkgCategory {
-    Audio,
-    Development,
-    Games,
-    Graphics,
-    Web,
-    Video,
+    /// Number of apps in the category
+    pub count: usize,
 }
 
 #[derive(Debug)]
@@ -27,7 +53,7 @@ pub enum PkgCategoryMsg {
 #[relm4::factory(pub)]
 impl FactoryComponent for PkgGroup {
     type CommandOutput = ();
-    type Init = PkgCategory;
+    type Init = (PkgCategory, usize);
     type Input = ();
     type Output = PkgCategoryMsg;
     type ParentWidget = gtk::FlowBox;
@@ -36,8 +62,13 @@ impl FactoryComponent for PkgGroup {
         gtk::FlowBoxChild {
             set_width_request: 210,
             set_height_request: 70,
+            connect_activate[sender, category = self.category.clone()] => move |_| {
+                let _ = sender.output(PkgCategoryMsg::Open(category.clone()));
+            },
             gtk::Button {
                 add_css_class: "card",
+                add_css_class: &categoryclass(&self.category),
+                set_can_focus: false,
                 gtk::Box {
                     set_margin_start: 15,
                     set_margin_end: 15,
@@ -47,32 +78,28 @@ impl FactoryComponent for PkgGroup {
                     set_halign: gtk::Align::Center,
                     gtk::Image {
                         add_css_class: "icon-dropshadow",
-                        set_icon_name: match self.category {
-                            PkgCategory::Audio => Some("nsc-audio"),
-                            PkgCategory::Development => Some("nsc-development"),
-                            PkgCategory::Games => Some("nsc-gaming"),
-                            PkgCategory::Graphics => Some("nsc-graphics"),
-                            PkgCategory::Web => Some("nsc-web"),
-                            PkgCategory::Video => Some("nsc-video"),
-                        },
+                        set_icon_name: Some(&self.category.icon),
                         set_pixel_size: 40,
                     },
-                    gtk::Label {
-                        add_css_class: "title-2",
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
                         set_valign: gtk::Align::Center,
                         set_hexpand: true,
-                        set_label: match self.category {
-                            PkgCategory::Audio => "Audio",
-                            PkgCategory::Development => "Development",
-                            PkgCategory::Games => "Games",
-                            PkgCategory::Graphics => "Graphics",
-                            PkgCategory::Web => "Web",
-                            PkgCategory::Video => "Video",
+                        gtk::Label {
+                            add_css_class: "title-2",
+                            set_halign: gtk::Align::Start,
+                            set_label: &categoryname(&self.category),
+                            set_ellipsize: pango::EllipsizeMode::End,
+                            set_lines: 1,
+                            set_wrap: true,
+                            set_max_width_chars: 0,
                         },
-                        set_ellipsize: pango::EllipsizeMode::End,
-                        set_lines: 1,
-                        set_wrap: true,
-                        set_max_width_chars: 0,
+                        gtk::Label {
+                            add_css_class: "caption",
+                            add_css_class: "dim-label",
+                            set_halign: gtk::Align::Start,
+                            set_label: &ngettextf("{} app", "{} apps", self.count as u32, &[&self.count.to_string()]),
+                        }
                     }
                 },
                 connect_clicked[sender, category = self.category.clone()] => move |_| {
@@ -83,13 +110,47 @@ impl FactoryComponent for PkgGroup {
     }
 
     fn init_model(
-        parent: Self::Init,
+        (category, count): Self::Init,
         _index: &DynamicIndex,
         _sender: FactorySender<Self>,
     ) -> Self {
         Self {
-            category: parent,
+            category,
+            count,
         }
     }
 
 }
+
+fn categoryclass(category: &PkgCategory) -> String {
+    let id: String = category
+        .id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    format!("category-{}", id)
+}
+
+/// Tints the buttons of categories that set a color in the registry.
+pub fn loadstyles() {
+    let css = crate::parse::categories::categories()
+        .iter()
+        .filter_map(|c| {
+            let color = c.color.as_ref()?;
+            // Colors come from files anyone may write, so keep them from closing the rule
+            if color.contains(['{', '}', ';']) {
+                return None;
+            }
+            Some(format!(".{} {{ background-color: alpha({}, 0.25); }}\n", categoryclass(c), color))
+        })
+        .collect::<String>();
+    if css.is_empty() {
+        return;
+    }
+    let Some(display) = gtk::gdk::Display::default() else {
+        return;
+    };
+    let provider = gtk::CssProvider::new();
+    provider.load_from_data(&css);
+    gtk::style_context_add_provider_for_display(&display, &provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+}
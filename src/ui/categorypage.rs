@@ -1,4 +1,5 @@
-use super::{categories::PkgCategory, categorytile::{CategoryTile, CategoryTileMsg}, window::*};
+use crate::i18n::gettext;
+use super::{categories::{categoryname, PkgCategory}, categorytile::{CategoryTile, CategoryTileMsg}, window::*};
 use adw::prelude::*;
 use log::*;
 use relm4::{factory::*, *};
@@ -11,16 +12,30 @@ pub struct CategoryPageModel {
     recommendedapps: FactoryVecDeque<CategoryTile>,
     #[tracker::no_eq]
     apps: FactoryVecDeque<CategoryTile>,
+    /// Packages of the "Other" section that haven't been loaded yet
+    remaining: Vec<String>,
+    loadingmore: bool,
     busy: bool,
+    /// Nothing in the category matched, not even outside the curated apps
+    empty: bool,
 }
 
+/// Number of "Other" tiles loaded at a time.
+pub const PAGESIZE: usize = 50;
+
 #[derive(Debug)]
 pub enum CategoryPageMsg {
     Close,
     OpenPkg(String),
-    Open(PkgCategory, Vec<CategoryTile>, Vec<CategoryTile>),
+    PrefetchPkg(String),
+    BrowseAll,
+    /// Recommended tiles, the first page of other tiles and the packages left to load
+    Open(PkgCategory, Vec<CategoryTile>, Vec<CategoryTile>, Vec<String>),
     Loading(PkgCategory),
-    UpdateInstalled(Vec<String>, Vec<String>),
+    LoadMore,
+    Append(PkgCategory, Vec<CategoryTile>),
+    /// Installed user packages, installed system packages and packages with an update
+    UpdateInstalled(Vec<String>, Vec<String>, Vec<String>),
 }
 
 #[derive(Debug)]
@@ -52,14 +67,7 @@ impl Component for CategoryPageModel {
                 #[wrap(Some)]
                 set_title_widget = &gtk::Label {
                     #[watch]
-                    set_label: match model.category {
-                        PkgCategory::Audio => "Audio",
-                        PkgCategory::Development => "Development",
-                        PkgCategory::Games => "Games",
-                        PkgCategory::Graphics => "Graphics",
-                        PkgCategory::Web => "Web",
-                        PkgCategory::Video => "Video",
-                    },
+                    set_label: &categoryname(&model.category),
                 },
             },
             gtk::ScrolledWindow {
@@ -69,6 +77,11 @@ impl Component for CategoryPageModel {
                 set_vscrollbar_policy: gtk::PolicyType::Automatic,
                 #[track(model.changed(CategoryPageModel::category()))]
                 set_vadjustment: gtk::Adjustment::NONE,
+                connect_edge_reached[sender] => move |_, pos| {
+                    if pos == gtk::PositionType::Bottom {
+                        sender.input(CategoryPageMsg::LoadMore);
+                    }
+                },
                 adw::Clamp {
                     set_maximum_size: 1000,
                     set_tightening_threshold: 750,
@@ -82,6 +95,22 @@ impl Component for CategoryPageModel {
                             set_spinning: true,
                             set_size_request: (64, 64),
                         }
+                    } else if model.empty {
+                        adw::StatusPage {
+                            set_vexpand: true,
+                            set_icon_name: Some("system-search-symbolic"),
+                            set_title: &gettext("No Apps in This Category"),
+                            set_description: Some(&gettext("Packages without app metadata aren't listed here, but can still be found by searching")),
+                            #[wrap(Some)]
+                            set_child = &gtk::Button {
+                                add_css_class: "pill",
+                                set_halign: gtk::Align::Center,
+                                set_label: &gettext("Browse All Packages"),
+                                connect_clicked[sender] => move |_| {
+                                    sender.input(CategoryPageMsg::BrowseAll)
+                                }
+                            }
+                        }
                     } else {
                         gtk::Box {
                             set_orientation: gtk::Orientation::Vertical,
@@ -89,12 +118,16 @@ impl Component for CategoryPageModel {
                             set_margin_all: 15,
                             set_spacing: 15,
                             gtk::Label {
+                                #[watch]
+                                set_visible: !model.recommendedapps.is_empty(),
                                 set_halign: gtk::Align::Start,
                                 add_css_class: "title-4",
-                                set_label: "Recommended",
+                                set_label: &gettext("Recommended"),
                             },
                             #[local_ref]
                             recbox -> gtk::FlowBox {
+                                #[watch]
+                                set_visible: !model.recommendedapps.is_empty(),
                                 set_halign: gtk::Align::Fill,
                                 set_hexpand: true,
                                 set_valign: gtk::Align::Center,
@@ -107,12 +140,16 @@ impl Component for CategoryPageModel {
                                 set_row_spacing: 14,
                             },
                             gtk::Label {
+                                #[watch]
+                                set_visible: !model.apps.is_empty(),
                                 set_halign: gtk::Align::Start,
                                 add_css_class: "title-4",
-                                set_label: "Other",
+                                set_label: &gettext("Other"),
                             },
                             #[local_ref]
                             allbox -> gtk::FlowBox {
+                                #[watch]
+                                set_visible: !model.apps.is_empty(),
                                 set_halign: gtk::Align::Fill,
                                 set_hexpand: true,
                                 set_valign: gtk::Align::Center,
@@ -123,6 +160,13 @@ impl Component for CategoryPageModel {
                                 set_min_children_per_line: 1,
                                 set_column_spacing: 14,
                                 set_row_spacing: 14,
+                            },
+                            gtk::Spinner {
+                                set_halign: gtk::Align::Center,
+                                #[watch]
+                                set_visible: model.loadingmore,
+                                #[watch]
+                                set_spinning: model.loadingmore,
                             }
                         }
                     }
@@ -137,14 +181,19 @@ impl Component for CategoryPageModel {
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         let model = CategoryPageModel {
-            category: PkgCategory::Audio,
+            category: PkgCategory::default(),
             recommendedapps: FactoryVecDeque::builder().launch(gtk::FlowBox::new()).forward(sender.input_sender(), |output| match output {
                 CategoryTileMsg::Open(x) => CategoryPageMsg::OpenPkg(x),
+                CategoryTileMsg::Prefetch(x) => CategoryPageMsg::PrefetchPkg(x),
             }),
             apps: FactoryVecDeque::builder().launch(gtk::FlowBox::new()).forward(sender.input_sender(), |output| match output {
                 CategoryTileMsg::Open(x) => CategoryPageMsg::OpenPkg(x),
+                CategoryTileMsg::Prefetch(x) => CategoryPageMsg::PrefetchPkg(x),
             }),
+            remaining: vec![],
+            loadingmore: false,
             busy: true,
+            empty: false,
             tracker: 0,
         };
 
@@ -165,9 +214,19 @@ impl Component for CategoryPageModel {
             CategoryPageMsg::OpenPkg(pkg) => {
                 sender.output(AppMsg::OpenPkg(pkg));
             },
-            CategoryPageMsg::Open(category, catrec, catall) => {
+            CategoryPageMsg::PrefetchPkg(pkg) => {
+                sender.output(AppMsg::PrefetchPkg(pkg));
+            },
+            CategoryPageMsg::BrowseAll => {
+                sender.output(AppMsg::FrontFrontPage);
+                sender.output(AppMsg::SearchFor(self.category.search.to_string()));
+            },
+            CategoryPageMsg::Open(category, catrec, catall, remaining) => {
                 info!("CategoryPageMsg::Open");
                 self.set_category(category);
+                self.empty = catrec.is_empty() && catall.is_empty() && remaining.is_empty();
+                self.remaining = remaining;
+                self.loadingmore = false;
                 let mut recapps_guard = self.recommendedapps.guard();
                 recapps_guard.clear();
                 recapps_guard.drop();
@@ -200,12 +259,34 @@ impl Component for CategoryPageModel {
                 self.busy = false;
                 info!("DONE CategoryPageMsg::Open");
             }
+            CategoryPageMsg::LoadMore => {
+                if self.busy || self.loadingmore || self.remaining.is_empty() {
+                    return;
+                }
+                let page = self
+                    .remaining
+                    .drain(..PAGESIZE.min(self.remaining.len()))
+                    .collect::<Vec<_>>();
+                self.loadingmore = true;
+                sender.output(AppMsg::LoadMoreCategory(self.category.clone(), page));
+            }
+            CategoryPageMsg::Append(category, tiles) => {
+                if category != self.category {
+                    return;
+                }
+                let mut apps_guard = self.apps.guard();
+                for tile in tiles {
+                    apps_guard.push_back(tile);
+                }
+                apps_guard.drop();
+                self.loadingmore = false;
+            }
             CategoryPageMsg::Loading(category) => {
                 info!("CategoryPageMsg::Loading");
                 self.set_category(category);
                 self.busy = true;
             }
-            CategoryPageMsg::UpdateInstalled(installeduserpkgs, installedsystempkgs) => {
+            CategoryPageMsg::UpdateInstalled(installeduserpkgs, installedsystempkgs, updatablepkgs) => {
                 let mut recapps_guard = self.recommendedapps.guard();
                 for i in 0..recapps_guard.len() {
                     let app = recapps_guard.get_mut(i).unwrap();
@@ -219,6 +300,7 @@ impl Component for CategoryPageModel {
                     } else {
                         app.installedsystem = false;
                     }
+                    app.update = updatablepkgs.contains(&app.pkg);
                 }
                 let mut apps_guard = self.apps.guard();
                 for i in 0..apps_guard.len() {
@@ -233,6 +315,7 @@ impl Component for CategoryPageModel {
                     } else {
                         app.installedsystem = false;
                     }
+                    app.update = updatablepkgs.contains(&app.pkg);
                 }
             }
         }
@@ -0,0 +1,101 @@
+use crate::i18n::gettext;
+use relm4::adw::prelude::*;
+use relm4::{factory::*, *};
+use std::convert::identity;
+
+use super::{
+    categories::{categoryname, PkgCategory},
+    pkgtile::{PkgTile, PkgTileMsg},
+    window::AppMsg,
+};
+
+#[derive(Debug)]
+pub struct CategoryStripInit {
+    pub category: PkgCategory,
+    pub tiles: Vec<PkgTile>,
+}
+
+/// A row of top apps from one category on the explore page.
+#[derive(Debug)]
+pub struct CategoryStrip {
+    pub category: PkgCategory,
+    pub tiles: FactoryVecDeque<PkgTile>,
+}
+
+#[relm4::factory(pub)]
+impl FactoryComponent for CategoryStrip {
+    type CommandOutput = ();
+    type Init = CategoryStripInit;
+    type Input = PkgTileMsg;
+    type Output = AppMsg;
+    type ParentWidget = gtk::Box;
+
+    view! {
+        gtk::Box {
+            set_orientation: gtk::Orientation::Vertical,
+            set_spacing: 10,
+            gtk::Box {
+                set_orientation: gtk::Orientation::Horizontal,
+                gtk::Label {
+                    set_halign: gtk::Align::Start,
+                    set_hexpand: true,
+                    add_css_class: "title-4",
+                    set_label: &categoryname(&self.category),
+                },
+                gtk::Button {
+                    add_css_class: "flat",
+                    set_label: &gettext("See All"),
+                    connect_clicked[sender, category = self.category.clone()] => move |_| {
+                        let _ = sender.output(AppMsg::OpenCategoryPage(category.clone()));
+                    }
+                }
+            },
+            gtk::ScrolledWindow {
+                set_hscrollbar_policy: gtk::PolicyType::Automatic,
+                set_vscrollbar_policy: gtk::PolicyType::Never,
+                set_propagate_natural_height: true,
+                set_child: Some(self.tiles.widget()),
+            }
+        }
+    }
+
+    fn init_model(
+        init: Self::Init,
+        _index: &DynamicIndex,
+        sender: FactorySender<Self>,
+    ) -> Self {
+        let mut tiles = FactoryVecDeque::builder()
+            .launch(gtk::FlowBox::new())
+            .forward(sender.input_sender(), identity);
+        // A single row that scrolls sideways
+        let flowbox = tiles.widget();
+        flowbox.set_orientation(gtk::Orientation::Vertical);
+        flowbox.set_selection_mode(gtk::SelectionMode::None);
+        flowbox.set_homogeneous(true);
+        flowbox.set_max_children_per_line(1);
+        flowbox.set_min_children_per_line(1);
+        flowbox.set_column_spacing(14);
+        flowbox.set_row_spacing(14);
+        flowbox.set_margin_bottom(10);
+        let mut tiles_guard = tiles.guard();
+        for tile in init.tiles {
+            tiles_guard.push_back(tile);
+        }
+        tiles_guard.drop();
+        Self {
+            category: init.category,
+            tiles,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: FactorySender<Self>) {
+        match msg {
+            PkgTileMsg::Open(pkg) => {
+                let _ = sender.output(AppMsg::OpenPkg(pkg));
+            }
+            PkgTileMsg::Prefetch(pkg) => {
+                let _ = sender.output(AppMsg::PrefetchPkg(pkg));
+            }
+        }
+    }
+}
@@ -1,8 +1,10 @@
+use crate::i18n::gettext;
 use std::path::Path;
 
-use crate::APPINFO;
+use crate::{parse::packages::Developer, APPINFO};
 
 use super::categorypage::CategoryPageMsg;
+use super::pkgtile::tilemeta;
 use relm4::adw::prelude::*;
 use relm4::gtk::pango;
 use relm4::{factory::*, *};
@@ -16,11 +18,19 @@ pub struct CategoryTile {
     pub icon: Option<String>,
     pub installeduser: bool,
     pub installedsystem: bool,
+    pub update: bool,
+    pub unfree: bool,
+    /// Short license id
+    pub license: Option<String>,
+    /// Download size, when it has been looked up
+    pub size: Option<u64>,
+    pub developer: Option<Developer>,
 }
 
 #[derive(Debug)]
 pub enum CategoryTileMsg {
     Open(String),
+    Prefetch(String),
 }
 
 #[relm4::factory(pub)]
@@ -34,11 +44,71 @@ impl FactoryComponent for CategoryTile {
     view! {
         gtk::FlowBoxChild {
             set_width_request: 270,
+            connect_activate[sender, pkg = self.pkg.clone()] => move |_| {
+                let _ = sender.output(CategoryTileMsg::Open(pkg.to_string()));
+            },
+            add_controller = gtk::EventControllerMotion {
+                connect_enter[sender, pkg = self.pkg.clone()] => move |_, _, _| {
+                    let _ = sender.output(CategoryTileMsg::Prefetch(pkg.to_string()));
+                }
+            },
+            add_controller = gtk::EventControllerFocus {
+                connect_enter[sender, pkg = self.pkg.clone()] => move |_| {
+                    let _ = sender.output(CategoryTileMsg::Prefetch(pkg.to_string()));
+                }
+            },
+            // Long press on touchscreens, right click with a mouse
+            add_controller = gtk::GestureLongPress {
+                set_touch_only: true,
+                connect_pressed[menu] => move |_, _, _| {
+                    menu.popup();
+                }
+            },
+            add_controller = gtk::GestureClick {
+                set_button: 3,
+                connect_pressed[menu] => move |_, _, _, _| {
+                    menu.popup();
+                }
+            },
             gtk::Overlay {
                 add_overlay = &gtk::Box {
                     set_orientation: gtk::Orientation::Horizontal,
                     set_valign: gtk::Align::Start,
                     set_halign: gtk::Align::End,
+                    #[name(menu)]
+                    gtk::Popover {
+                        gtk::Box {
+                            set_orientation: gtk::Orientation::Vertical,
+                            gtk::Button {
+                                add_css_class: "flat",
+                                set_label: &gettext("Open"),
+                                connect_clicked[sender, menu, pkg = self.pkg.clone()] => move |_| {
+                                    menu.popdown();
+                                    let _ = sender.output(CategoryTileMsg::Open(pkg.to_string()));
+                                }
+                            },
+                            gtk::Button {
+                                add_css_class: "flat",
+                                set_label: &gettext("Copy Attribute Name"),
+                                connect_clicked[menu, pkg = self.pkg.clone()] => move |x| {
+                                    menu.popdown();
+                                    x.clipboard().set_text(&pkg);
+                                }
+                            }
+                        }
+                    },
+                    gtk::Image {
+                        add_css_class: "warning",
+                        set_valign: gtk::Align::Start,
+                        set_halign: gtk::Align::End,
+                        set_pixel_size: 16,
+                        set_margin_top: 8,
+                        set_margin_end: 8,
+                        set_icon_name: Some("software-update-available-symbolic"),
+                        set_tooltip_text: Some(&gettext("Update available")),
+                        #[watch]
+                        set_visible: self.update,
+                    },
                     gtk::Image {
                         add_css_class: "accent",
                         set_valign: gtk::Align::Start,
@@ -118,15 +188,49 @@ impl FactoryComponent for CategoryTile {
                                 set_wrap: true,
                                 set_max_width_chars: 0,
                             },
-                            gtk::Label {
-                                set_halign: gtk::Align::Start,
-                                add_css_class: "dim-label",
-                                add_css_class: "caption",
-                                set_label: &self.pkg,
-                                set_ellipsize: pango::EllipsizeMode::End,
-                                set_lines: 1,
-                                set_wrap: true,
-                                set_max_width_chars: 0,
+                            gtk::Box {
+                                set_orientation: gtk::Orientation::Horizontal,
+                                set_spacing: 4,
+                                set_visible: self.developer.is_some(),
+                                gtk::Label {
+                                    set_halign: gtk::Align::Start,
+                                    add_css_class: "caption",
+                                    set_label: self.developer.as_ref().map(|x| x.name.as_str()).unwrap_or_default(),
+                                    set_ellipsize: pango::EllipsizeMode::End,
+                                },
+                                gtk::Image {
+                                    add_css_class: "accent",
+                                    set_pixel_size: 12,
+                                    set_icon_name: Some("emblem-ok-symbolic"),
+                                    set_tooltip_text: Some(&gettext("Verified developer")),
+                                    set_visible: self.developer.as_ref().map(|x| x.verified).unwrap_or(false),
+                                }
+                            },
+                            gtk::Box {
+                                set_orientation: gtk::Orientation::Horizontal,
+                                set_spacing: 6,
+                                gtk::Label {
+                                    set_halign: gtk::Align::Start,
+                                    add_css_class: "dim-label",
+                                    add_css_class: "caption",
+                                    set_label: &self.pkg,
+                                    set_ellipsize: pango::EllipsizeMode::End,
+                                    set_lines: 1,
+                                    set_wrap: true,
+                                    set_max_width_chars: 0,
+                                },
+                                gtk::Label {
+                                    add_css_class: "caption",
+                                    add_css_class: "warning",
+                                    set_label: &gettext("Unfree"),
+                                    set_visible: self.unfree,
+                                },
+                                gtk::Label {
+                                    add_css_class: "dim-label",
+                                    add_css_class: "caption",
+                                    set_label: &tilemeta(self.license.as_deref(), self.size),
+                                    set_visible: self.license.is_some() || self.size.is_some(),
+                                }
                             },
                             gtk::Label {
                                 set_halign: gtk::Align::Start,
@@ -171,6 +275,11 @@ impl FactoryComponent for CategoryTile {
             icon: parent.icon,
             installeduser: parent.installeduser,
             installedsystem: parent.installedsystem,
+            update: parent.update,
+            unfree: parent.unfree,
+            license: parent.license,
+            size: parent.size,
+            developer: parent.developer,
         }
     }
 }
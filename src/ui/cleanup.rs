@@ -0,0 +1,524 @@
+use crate::i18n::{gettext, gettextf};
+use super::window::{AppMsg, UserPkgs};
+use crate::parse::{demo, nixversion};
+use crate::parse::profile::{self, profilepath};
+use adw::prelude::*;
+use anyhow::Result;
+use gtk::glib;
+use log::*;
+use relm4::{factory::*, *};
+use sqlx::{sqlite::SqliteConnectOptions, SqlitePool};
+use std::{
+    collections::HashSet,
+    fs,
+    process::Stdio,
+    time::{Duration, SystemTime},
+};
+use tokio::io::AsyncBufReadExt;
+
+const AGES: [u64; 4] = [7, 14, 30, 90];
+
+#[tracker::track]
+#[derive(Debug)]
+pub struct CleanupModel {
+    hidden: bool,
+    #[tracker::no_eq]
+    generations: FactoryVecDeque<GenerationItem>,
+    days: u64,
+    reclaimable: u64,
+    /// Space taken by everything in the nix store
+    storesize: Option<u64>,
+    /// Last line printed by the running cleanup
+    progress: String,
+    status: CleanupStatus,
+    userpkgs: UserPkgs,
+}
+
+#[derive(Debug)]
+pub enum CleanupMsg {
+    Show(UserPkgs),
+    SetAge(u32),
+    Refresh,
+    Clean,
+    Close,
+}
+
+#[derive(Debug)]
+pub enum CleanupAsyncMsg {
+    Generations(Vec<GenerationItem>, u64),
+    StoreSize(Option<u64>),
+    Progress(String),
+    Finished(bool),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum CleanupStatus {
+    Loading,
+    Ready,
+    Cleaning,
+    Done,
+    Error,
+}
+
+#[relm4::component(pub)]
+impl Component for CleanupModel {
+    type Init = gtk::Window;
+    type Input = CleanupMsg;
+    type Output = AppMsg;
+    type CommandOutput = CleanupAsyncMsg;
+
+    view! {
+        dialog = adw::Window {
+            set_transient_for: Some(&parent_window),
+            set_modal: true,
+            set_default_width: 500,
+            set_default_height: 500,
+            #[watch]
+            set_visible: !model.hidden,
+            connect_close_request[sender] => move |_| {
+                sender.input(CleanupMsg::Close);
+                glib::Propagation::Stop
+            },
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                adw::HeaderBar {
+                    add_css_class: "flat",
+                    #[wrap(Some)]
+                    set_title_widget = &gtk::Label {
+                        set_label: &gettext("Free Up Space")
+                    }
+                },
+                gtk::ScrolledWindow {
+                    set_vexpand: true,
+                    set_hscrollbar_policy: gtk::PolicyType::Never,
+                    adw::Clamp {
+                        gtk::Box {
+                            set_orientation: gtk::Orientation::Vertical,
+                            set_margin_all: 15,
+                            set_spacing: 15,
+                            gtk::Label {
+                                set_wrap: true,
+                                add_css_class: "dim-label",
+                                set_label: &gettext("Every install, removal and update creates a new generation of your user profile. Old generations keep their packages on disk so you can roll back to them. Removing them and collecting garbage frees that space."),
+                            },
+                            gtk::ListBox {
+                                add_css_class: "boxed-list",
+                                set_selection_mode: gtk::SelectionMode::None,
+                                adw::ComboRow {
+                                    set_title: &gettext("Remove generations older than"),
+                                    set_model: Some(&gtk::StringList::new(&["1 week", "2 weeks", "1 month", "3 months"])),
+                                    set_selected: 2,
+                                    #[watch]
+                                    set_sensitive: model.status == CleanupStatus::Ready,
+                                    connect_selected_notify[sender] => move |x| {
+                                        sender.input(CleanupMsg::SetAge(x.selected()))
+                                    }
+                                },
+                                adw::ActionRow {
+                                    set_title: &gettext("Nix store"),
+                                    set_subtitle: &gettext("All packages on this computer, including other users' and the system's"),
+                                    #[watch]
+                                    set_visible: model.storesize.is_some(),
+                                    add_suffix = &gtk::Label {
+                                        #[watch]
+                                        set_label: &glib::format_size(model.storesize.unwrap_or_default()),
+                                    }
+                                },
+                                adw::ActionRow {
+                                    set_title: &gettext("Reclaimable space"),
+                                    set_subtitle: &gettext("Packages still used by other profiles are kept"),
+                                    add_suffix = &gtk::Label {
+                                        #[watch]
+                                        set_label: &if model.status == CleanupStatus::Loading {
+                                            gettext("Calculating...")
+                                        } else {
+                                            gettextf("Up to {}", &[&glib::format_size(model.reclaimable)])
+                                        },
+                                    }
+                                },
+                            },
+                            gtk::Label {
+                                set_halign: gtk::Align::Start,
+                                add_css_class: "title-4",
+                                set_label: &gettext("Generations to remove"),
+                                #[watch]
+                                set_visible: !model.generations.is_empty(),
+                            },
+                            #[local_ref]
+                            generationlist -> gtk::ListBox {
+                                add_css_class: "boxed-list",
+                                set_selection_mode: gtk::SelectionMode::None,
+                                #[watch]
+                                set_visible: !model.generations.is_empty(),
+                            },
+                            gtk::Label {
+                                add_css_class: "dim-label",
+                                #[watch]
+                                set_visible: model.generations.is_empty() && model.status == CleanupStatus::Ready,
+                                set_label: &gettext("No generations are older than the chosen age"),
+                            },
+                            gtk::Label {
+                                add_css_class: "dim-label",
+                                set_ellipsize: gtk::pango::EllipsizeMode::Middle,
+                                #[watch]
+                                set_visible: model.status == CleanupStatus::Cleaning,
+                                #[watch]
+                                set_label: &model.progress,
+                            },
+                            gtk::Label {
+                                #[watch]
+                                set_visible: model.status == CleanupStatus::Done || model.status == CleanupStatus::Error,
+                                #[watch]
+                                set_label: &if model.status == CleanupStatus::Done {
+                                    gettext("Old generations removed and garbage collected")
+                                } else {
+                                    gettext("Cleaning up failed")
+                                },
+                            },
+                        }
+                    }
+                },
+                gtk::Box {
+                    add_css_class: "dialog-action-area",
+                    set_orientation: gtk::Orientation::Horizontal,
+                    set_homogeneous: true,
+                    gtk::Button {
+                        set_label: &gettext("Close"),
+                        #[watch]
+                        set_sensitive: model.status != CleanupStatus::Cleaning,
+                        connect_clicked[sender] => move |_| {
+                            sender.input(CleanupMsg::Close)
+                        }
+                    },
+                    gtk::Button {
+                        add_css_class: "destructive-action",
+                        #[watch]
+                        set_sensitive: model.status == CleanupStatus::Ready && !model.generations.is_empty(),
+                        gtk::Box {
+                            set_halign: gtk::Align::Center,
+                            set_spacing: 10,
+                            gtk::Spinner {
+                                #[watch]
+                                set_visible: model.status == CleanupStatus::Cleaning,
+                                #[watch]
+                                set_spinning: model.status == CleanupStatus::Cleaning,
+                            },
+                            gtk::Label {
+                                set_label: &gettext("Free Up Space"),
+                            }
+                        },
+                        connect_clicked[sender] => move |_| {
+                            sender.input(CleanupMsg::Clean)
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn init(
+        parent_window: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = CleanupModel {
+            hidden: true,
+            generations: FactoryVecDeque::builder()
+                .launch(gtk::ListBox::new())
+                .detach(),
+            days: AGES[2],
+            reclaimable: 0,
+            storesize: None,
+            progress: String::new(),
+            status: CleanupStatus::Loading,
+            userpkgs: UserPkgs::Env,
+            tracker: 0,
+        };
+
+        let generationlist = model.generations.widget();
+
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
+        self.reset();
+        match msg {
+            CleanupMsg::Show(userpkgs) => {
+                self.userpkgs = userpkgs;
+                self.hidden = false;
+                sender.input(CleanupMsg::Refresh);
+            }
+            CleanupMsg::SetAge(i) => {
+                if let Some(days) = AGES.get(i as usize) {
+                    self.days = *days;
+                    sender.input(CleanupMsg::Refresh);
+                }
+            }
+            CleanupMsg::Refresh => {
+                self.status = CleanupStatus::Loading;
+                self.generations.guard().clear();
+                sender.oneshot_command(async move { CleanupAsyncMsg::StoreSize(storesize().await) });
+                let days = self.days;
+                sender.oneshot_command(async move {
+                    match oldgenerations(days).await {
+                        Ok((gens, size)) => CleanupAsyncMsg::Generations(gens, size),
+                        Err(e) => {
+                            warn!("Failed to list generations: {}", e);
+                            CleanupAsyncMsg::Generations(vec![], 0)
+                        }
+                    }
+                });
+            }
+            CleanupMsg::Clean => {
+                if demo::enabled() || nixversion::detect().is_none() {
+                    warn!("Not cleaning up, packages can't be changed");
+                    return;
+                }
+                self.status = CleanupStatus::Cleaning;
+                self.progress = String::new();
+                let days = self.days;
+                let userpkgs = self.userpkgs.clone();
+                sender.command(move |out, shutdown| {
+                    shutdown
+                        .register(async move {
+                            let success = match cleanup(days, userpkgs, &out).await {
+                                Ok(b) => b,
+                                Err(e) => {
+                                    warn!("Failed to clean up generations: {}", e);
+                                    false
+                                }
+                            };
+                            out.emit(CleanupAsyncMsg::Finished(success));
+                        })
+                        .drop_on_shutdown()
+                });
+            }
+            CleanupMsg::Close => {
+                if self.status != CleanupStatus::Cleaning {
+                    self.hidden = true;
+                }
+            }
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        msg: Self::CommandOutput,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        self.reset();
+        match msg {
+            CleanupAsyncMsg::Generations(gens, size) => {
+                let mut generations_guard = self.generations.guard();
+                generations_guard.clear();
+                for gen in gens {
+                    generations_guard.push_back(gen);
+                }
+                generations_guard.drop();
+                self.set_reclaimable(size);
+                self.set_status(CleanupStatus::Ready);
+            }
+            CleanupAsyncMsg::StoreSize(size) => {
+                self.set_storesize(size);
+            }
+            CleanupAsyncMsg::Progress(line) => {
+                self.set_progress(line);
+            }
+            CleanupAsyncMsg::Finished(success) => {
+                self.generations.guard().clear();
+                self.set_reclaimable(0);
+                sender.oneshot_command(async move { CleanupAsyncMsg::StoreSize(storesize().await) });
+                self.set_status(if success {
+                    CleanupStatus::Done
+                } else {
+                    CleanupStatus::Error
+                });
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct GenerationItem {
+    pub number: u64,
+    pub date: String,
+    pub size: u64,
+}
+
+#[relm4::factory(pub)]
+impl FactoryComponent for GenerationItem {
+    type CommandOutput = ();
+    type Init = GenerationItem;
+    type Input = ();
+    type Output = ();
+    type ParentWidget = gtk::ListBox;
+
+    view! {
+        adw::ActionRow {
+            set_title: &gettextf("Generation {}", &[&self.number.to_string()]),
+            set_subtitle: &self.date,
+            add_suffix = &gtk::Label {
+                add_css_class: "dim-label",
+                set_label: &glib::format_size(self.size),
+            }
+        }
+    }
+
+    fn init_model(init: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+        init
+    }
+}
+
+async fn closure(path: &str) -> Result<HashSet<String>> {
+    let output = tokio::process::Command::new("nix-store")
+        .arg("--query")
+        .arg("--requisites")
+        .arg(path)
+        .output()
+        .await?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|x| x.to_string())
+        .collect())
+}
+
+async fn pathsizes(paths: &[String]) -> Result<u64> {
+    let mut total = 0;
+    for chunk in paths.chunks(500) {
+        let output = tokio::process::Command::new("nix-store")
+            .arg("--query")
+            .arg("--size")
+            .args(chunk)
+            .output()
+            .await?;
+        total += String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|x| x.trim().parse::<u64>().ok())
+            .sum::<u64>();
+    }
+    Ok(total)
+}
+
+/// Returns the generations older than `days` along with the space only they are using.
+async fn oldgenerations(days: u64) -> Result<(Vec<GenerationItem>, u64)> {
+    let profile = profilepath()?;
+    let output = tokio::process::Command::new("nix-env")
+        .arg("--profile")
+        .arg(&profile)
+        .arg("--list-generations")
+        .output()
+        .await?;
+    let cutoff = SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60);
+
+    let mut old = vec![];
+    let mut kept = HashSet::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let split = line.split_whitespace().collect::<Vec<&str>>();
+        let number = match split.first().and_then(|x| x.parse::<u64>().ok()) {
+            Some(n) => n,
+            None => continue,
+        };
+        let link = format!("{}-{}-link", profile, number);
+        let modified = fs::symlink_metadata(&link).and_then(|x| x.modified());
+        let current = line.contains("(current)");
+        match modified {
+            Ok(m) if m < cutoff && !current => {
+                old.push((number, split.get(1..3).map(|x| x.join(" ")).unwrap_or_default(), link))
+            }
+            _ => {
+                kept.extend(closure(&link).await?);
+            }
+        }
+    }
+
+    let mut gens = vec![];
+    let mut reclaimable = HashSet::new();
+    for (number, date, link) in old {
+        let unique = closure(&link)
+            .await?
+            .into_iter()
+            .filter(|x| !kept.contains(x))
+            .collect::<Vec<String>>();
+        let size = pathsizes(&unique).await?;
+        reclaimable.extend(unique);
+        gens.push(GenerationItem { number, date, size });
+    }
+    gens.reverse();
+    let size = pathsizes(&reclaimable.into_iter().collect::<Vec<String>>()).await?;
+    Ok((gens, size))
+}
+
+/// Total size of the valid paths in the nix store, from the nix database rather than walking the store.
+async fn storesize() -> Option<u64> {
+    // The database belongs to root, so it can only be read without taking locks
+    let options = SqliteConnectOptions::new()
+        .filename("/nix/var/nix/db/db.sqlite")
+        .read_only(true)
+        .immutable(true);
+    let pool = match SqlitePool::connect_with(options).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            warn!("Failed to open the nix database: {}", e);
+            return None;
+        }
+    };
+    let size: sqlx::Result<(i64,)> = sqlx::query_as("SELECT COALESCE(SUM(narSize), 0) FROM ValidPaths")
+        .fetch_one(&pool)
+        .await;
+    pool.close().await;
+    size.ok().map(|(x,)| x as u64)
+}
+
+/// Runs `cmd`, passing each line it prints on to the dialog.
+async fn streamed(cmd: &mut tokio::process::Command, out: &relm4::Sender<CleanupAsyncMsg>) -> Result<bool> {
+    let mut p = cmd.stdout(Stdio::null()).stderr(Stdio::piped()).kill_on_drop(true).spawn()?;
+    if let Some(stderr) = p.stderr.take() {
+        let mut lines = tokio::io::BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            trace!("CAUGHT LINE: {}", line);
+            if !line.trim().is_empty() {
+                out.emit(CleanupAsyncMsg::Progress(line.trim().to_string()));
+            }
+        }
+    }
+    Ok(p.wait().await?.success())
+}
+
+async fn cleanup(days: u64, userpkgs: UserPkgs, out: &relm4::Sender<CleanupAsyncMsg>) -> Result<bool> {
+    let older = format!("{}d", days);
+    let success = match userpkgs {
+        UserPkgs::Env => {
+            streamed(
+                tokio::process::Command::new("nix-env")
+                    .arg("--profile")
+                    .arg(profilepath()?)
+                    .arg("--delete-generations")
+                    .arg(&older),
+                out,
+            )
+            .await?
+        }
+        UserPkgs::Profile => {
+            streamed(
+                tokio::process::Command::new("nix")
+                    .arg("profile")
+                    .arg("wipe-history")
+                    .arg("--older-than")
+                    .arg(&older)
+                    .args(profile::profileargs()),
+                out,
+            )
+            .await?
+        }
+    };
+    if !success {
+        return Ok(false);
+    }
+    match userpkgs {
+        UserPkgs::Env => streamed(tokio::process::Command::new("nix-store").arg("--gc"), out).await,
+        UserPkgs::Profile => streamed(tokio::process::Command::new("nix").arg("store").arg("gc"), out).await,
+    }
+}
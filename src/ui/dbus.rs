@@ -0,0 +1,133 @@
+use super::pkgpage::{PkgAction, WorkPkg};
+use super::window::{AppMsg, WINDOW_BROKER};
+use log::*;
+use relm4::gtk::{gio, glib, prelude::*};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+};
+
+/// Object path of the application, where GApplication exports its own interfaces too
+pub const PATH: &str = "/dev/vlinkz/NixSoftwareCenter";
+pub const INTERFACE: &str = "dev.nix.SoftwareCenter";
+const ERROR: &str = "dev.nix.SoftwareCenter.Error.Failed";
+
+const XML: &str = r#"
+<node>
+  <interface name="dev.nix.SoftwareCenter">
+    <method name="InstallPackage">
+      <arg type="s" name="attribute" direction="in"/>
+    </method>
+    <method name="RemovePackage">
+      <arg type="s" name="attribute" direction="in"/>
+    </method>
+    <method name="ListInstalled">
+      <arg type="a(sss)" name="packages" direction="out"/>
+    </method>
+    <method name="SearchPackages">
+      <arg type="s" name="query" direction="in"/>
+      <arg type="a(ssss)" name="packages" direction="out"/>
+    </method>
+    <signal name="OperationChanged">
+      <arg type="s" name="attribute"/>
+      <arg type="s" name="action"/>
+      <arg type="s" name="state"/>
+    </signal>
+  </interface>
+</node>
+"#;
+
+/// A method call waiting for the main window to answer it with [`reply`] or [`fail`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DbusCall {
+    /// Queue installing an attribute to the user profile
+    InstallPackage(String),
+    /// Queue removing an installed attribute
+    RemovePackage(String),
+    /// Installed packages as (key, version, "user" or "system")
+    ListInstalled,
+    /// Matching packages as (attribute, pname, version, description)
+    SearchPackages(String),
+}
+
+thread_local! {
+    static CONNECTION: RefCell<Option<gio::DBusConnection>> = const { RefCell::new(None) };
+    static PENDING: RefCell<HashMap<u32, gio::DBusMethodInvocation>> = RefCell::new(HashMap::new());
+    static NEXTID: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Exports the interface on the session bus connection of the primary instance.
+pub fn register(connection: &gio::DBusConnection) {
+    let info = match gio::DBusNodeInfo::for_xml(XML) {
+        Ok(node) => node.lookup_interface(INTERFACE),
+        Err(e) => {
+            error!("Invalid D-Bus interface: {}", e);
+            return;
+        }
+    };
+    let Some(info) = info else {
+        return;
+    };
+    let registered = connection
+        .register_object(PATH, &info)
+        .method_call(|_, _, _, _, method, params, invocation| {
+            let call = match method {
+                "InstallPackage" => params.get::<(String,)>().map(|(x,)| DbusCall::InstallPackage(x)),
+                "RemovePackage" => params.get::<(String,)>().map(|(x,)| DbusCall::RemovePackage(x)),
+                "ListInstalled" => Some(DbusCall::ListInstalled),
+                "SearchPackages" => params.get::<(String,)>().map(|(x,)| DbusCall::SearchPackages(x)),
+                _ => None,
+            };
+            let Some(call) = call else {
+                invocation.return_dbus_error("org.freedesktop.DBus.Error.UnknownMethod", method);
+                return;
+            };
+            debug!("D-Bus call {:?}", call);
+            let id = NEXTID.with(|x| {
+                let id = x.get();
+                x.set(id.wrapping_add(1));
+                id
+            });
+            PENDING.with(|x| x.borrow_mut().insert(id, invocation));
+            WINDOW_BROKER.send(AppMsg::Dbus(id, call));
+        })
+        .build();
+    match registered {
+        Ok(_) => {
+            info!("Exported {} on the session bus", INTERFACE);
+            CONNECTION.with(|x| *x.borrow_mut() = Some(connection.clone()));
+        }
+        Err(e) => warn!("Failed to export {}: {}", INTERFACE, e),
+    }
+}
+
+/// Answers call `id`, with `value` a tuple of the out arguments.
+pub fn reply(id: u32, value: Option<glib::Variant>) {
+    if let Some(invocation) = PENDING.with(|x| x.borrow_mut().remove(&id)) {
+        invocation.return_value(value.as_ref());
+    }
+}
+
+pub fn fail(id: u32, message: &str) {
+    if let Some(invocation) = PENDING.with(|x| x.borrow_mut().remove(&id)) {
+        invocation.return_dbus_error(ERROR, message);
+    }
+}
+
+/// Emits `OperationChanged` for a queued operation, `state` is one of "pending", "running",
+/// "done", "failed" or "cancelled".
+pub fn operationchanged(work: &WorkPkg, state: &str) {
+    let action = match work.action {
+        PkgAction::Install | PkgAction::InstallEnv(_) => "install",
+        PkgAction::Remove => "remove",
+        PkgAction::Revert(_) => "revert",
+    };
+    CONNECTION.with(|x| {
+        if let Some(connection) = x.borrow().as_ref() {
+            let params = (work.pkg.as_str(), action, state).to_variant();
+            if let Err(e) = connection.emit_signal(None, PATH, INTERFACE, "OperationChanged", Some(&params)) {
+                warn!("Failed to emit OperationChanged: {}", e);
+            }
+        }
+    });
+}
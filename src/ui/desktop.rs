@@ -0,0 +1,9 @@
+use gtk::prelude::*;
+
+/// Whether animations are allowed, off with GNOME's "Reduce Animations" setting.
+/// GTK and libadwaita skip their own transitions then, this covers the ones started from code.
+pub fn animations() -> bool {
+    gtk::Settings::default()
+        .map(|x| x.is_gtk_enable_animations())
+        .unwrap_or(true)
+}
@@ -1,7 +1,9 @@
-use std::path::Path;
-use crate::APPINFO;
+use log::*;
+use crate::i18n::{gettext, gettextf};
+use std::{collections::HashMap, path::Path};
+use crate::{parse::state, APPINFO};
 
-use super::{window::*, pkgpage::{InstallType, WorkPkg, PkgAction, NotifyPage}};
+use super::{window::*, pkgpage::{InstallType, WorkPkg, PkgAction}, workqueue::{lane, WorkQueue}};
 use adw::prelude::*;
 use relm4::{factory::*, *, gtk::pango};
 
@@ -12,23 +14,40 @@ pub struct InstalledPageModel {
     installeduserlist: FactoryVecDeque<InstalledItemModel>,
     #[tracker::no_eq]
     installedsystemlist: FactoryVecDeque<InstalledItemModel>,
+    /// User packages that aren't in the package database
+    #[tracker::no_eq]
+    installedotherlist: FactoryVecDeque<InstalledItemModel>,
     userpkgtype: UserPkgs,
     systempkgtype: SystemPkgs,
+    noremove: bool,
+    /// Remote hosts that can be picked instead of this computer
+    hosts: Vec<String>,
+    host: Option<String>,
+    /// Private notes from the package pages, by attribute or name
+    notes: HashMap<String, String>,
+    /// Only list packages with a note
+    notedonly: bool,
     updatetracker: u8,
 }
 
 #[derive(Debug)]
 pub enum InstalledPageMsg {
-    Update(Vec<InstalledItem>, Vec<InstalledItem>),
+    Update(Vec<InstalledItem>, Vec<InstalledItem>, Vec<InstalledItem>),
     UpdatePkgTypes(SystemPkgs, UserPkgs),
+    SetHosts(Vec<String>),
+    SelectHost(u32),
     OpenRow(usize, InstallType),
     Remove(InstalledItem),
-    UnsetBusy(WorkPkg),
+    /// Marks the rows with an operation waiting or running
+    SetQueue(WorkQueue),
+    SetNote(String, Option<String>),
+    SetNotedOnly(bool),
 }
 
 #[relm4::component(pub)]
 impl SimpleComponent for InstalledPageModel {
-    type Init = (SystemPkgs, UserPkgs);
+    /// Package types and whether removing is disabled by policy
+    type Init = (SystemPkgs, UserPkgs, bool);
     type Input = InstalledPageMsg;
     type Output = AppMsg;
     type Widgets = InstalledPageWidgets;
@@ -44,14 +63,53 @@ impl SimpleComponent for InstalledPageModel {
                     set_valign: gtk::Align::Start,
                     set_margin_all: 15,
                     set_spacing: 15,
+                    gtk::ListBox {
+                        #[watch]
+                        set_visible: !model.hosts.is_empty(),
+                        add_css_class: "boxed-list",
+                        set_selection_mode: gtk::SelectionMode::None,
+                        adw::ComboRow {
+                            set_title: &gettext("Manage Packages On"),
+                            #[track(model.changed(InstalledPageModel::hosts()))]
+                            #[block_signal(hostselected)]
+                            set_model: Some(&gtk::StringList::new(&model.hostnames().iter().map(|x| x.as_str()).collect::<Vec<_>>())),
+                            #[track(model.changed(InstalledPageModel::hosts()))]
+                            #[block_signal(hostselected)]
+                            set_selected: model.hostindex(),
+                            connect_selected_notify[sender] => move |row| {
+                                sender.input(InstalledPageMsg::SelectHost(row.selected()))
+                            } @hostselected
+                        }
+                    },
+                    gtk::ListBox {
+                        #[watch]
+                        set_visible: !model.notes.is_empty(),
+                        add_css_class: "boxed-list",
+                        set_selection_mode: gtk::SelectionMode::None,
+                        adw::ActionRow {
+                            set_title: &gettext("Only Packages with Notes"),
+                            add_suffix = &gtk::Switch {
+                                set_valign: gtk::Align::Center,
+                                connect_state_set[sender] => move |_, b| {
+                                    sender.input(InstalledPageMsg::SetNotedOnly(b));
+                                    gtk::glib::Propagation::Proceed
+                                } @notedhandler,
+                                #[track(model.changed(InstalledPageModel::notedonly()))]
+                                #[block_signal(notedhandler)]
+                                set_state: model.notedonly,
+                            }
+                        }
+                    },
                     gtk::Label {
                         #[watch]
                         set_visible: !model.installeduserlist.is_empty(),
                         set_halign: gtk::Align::Start,
                         add_css_class: "title-4",
-                        set_label: match model.userpkgtype {
-                            UserPkgs::Env => "User (nix-env)",
-                            UserPkgs::Profile => "User (nix profile)",
+                        #[watch]
+                        set_label: &match (&model.host, &model.userpkgtype) {
+                            (Some(host), _) => gettextf("User on {} (nix profile)", &[host]),
+                            (None, UserPkgs::Env) => gettext("User (nix-env)"),
+                            (None, UserPkgs::Profile) => gettext("User (nix profile)"),
                         },
                     },
                     #[local_ref]
@@ -72,7 +130,7 @@ impl SimpleComponent for InstalledPageModel {
                         set_visible: !model.installedsystemlist.is_empty(),
                         set_halign: gtk::Align::Start,
                         add_css_class: "title-4",
-                        set_label: "System (configuration.nix)",
+                        set_label: &gettext("System (configuration.nix)"),
                     },
                     #[local_ref]
                     installedsystemlist -> gtk::ListBox {
@@ -86,6 +144,21 @@ impl SimpleComponent for InstalledPageModel {
                                 sender.input(InstalledPageMsg::OpenRow(i as usize, InstallType::System))
                             }
                         }
+                    },
+                    gtk::Label {
+                        #[watch]
+                        set_visible: !model.installedotherlist.is_empty(),
+                        set_halign: gtk::Align::Start,
+                        add_css_class: "title-4",
+                        set_label: &gettext("Other Packages"),
+                    },
+                    #[local_ref]
+                    installedotherlist -> gtk::ListBox {
+                        #[watch]
+                        set_visible: !model.installedotherlist.is_empty(),
+                        set_valign: gtk::Align::Start,
+                        add_css_class: "boxed-list",
+                        set_selection_mode: gtk::SelectionMode::None,
                     }
                 }
             }
@@ -93,10 +166,11 @@ impl SimpleComponent for InstalledPageModel {
     }
 
     fn init(
-        (systempkgtype, userpkgtype): Self::Init,
+        (systempkgtype, userpkgtype, noremove): Self::Init,
         root: Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
+        let nscstate = state::getnscstate();
         let model = InstalledPageModel {
             installeduserlist: FactoryVecDeque::builder().launch(gtk::ListBox::new()).forward(sender.input_sender(), |output| match output {
                 InstalledItemMsg::Delete(item) => InstalledPageMsg::Remove(item),
@@ -104,14 +178,24 @@ impl SimpleComponent for InstalledPageModel {
             installedsystemlist: FactoryVecDeque::builder().launch(gtk::ListBox::new()).forward(sender.input_sender(), |output| match output {
                 InstalledItemMsg::Delete(item) => InstalledPageMsg::Remove(item),
             }),
+            installedotherlist: FactoryVecDeque::builder().launch(gtk::ListBox::new()).forward(sender.input_sender(), |output| match output {
+                InstalledItemMsg::Delete(item) => InstalledPageMsg::Remove(item),
+            }),
             updatetracker: 0,
             userpkgtype,
             systempkgtype,
+            noremove,
+            hosts: vec![],
+            host: None,
+            // Nothing would be listed with no notes left
+            notedonly: nscstate.ui.notedonly && !nscstate.notes.is_empty(),
+            notes: nscstate.notes,
             tracker: 0
         };
 
         let installeduserlist = model.installeduserlist.widget();
         let installedsystemlist = model.installedsystemlist.widget();
+        let installedotherlist = model.installedotherlist.widget();
 
         let widgets = view_output!();
 
@@ -121,23 +205,52 @@ impl SimpleComponent for InstalledPageModel {
     fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
         self.reset();
         match msg {
-            InstalledPageMsg::Update(installeduserlist, installedsystemlist) => {
+            InstalledPageMsg::Update(installeduserlist, installedsystemlist, installedotherlist) => {
                 self.update_updatetracker(|_| ());
                 let mut installeduserlist_guard = self.installeduserlist.guard();
                 installeduserlist_guard.clear();
-                for installeduser in installeduserlist {
-                    installeduserlist_guard.push_back(installeduser);
+                for mut installeduser in installeduserlist {
+                    installeduser.note = self.notes.get(&notekey(&installeduser)).cloned();
+                    installeduserlist_guard.push_back((installeduser, !self.noremove));
                 }
                 let mut installedsystemlist_guard = self.installedsystemlist.guard();
                 installedsystemlist_guard.clear();
-                for installedsystem in installedsystemlist {
-                    installedsystemlist_guard.push_back(installedsystem);
+                for mut installedsystem in installedsystemlist {
+                    installedsystem.note = self.notes.get(&notekey(&installedsystem)).cloned();
+                    installedsystemlist_guard.push_back((installedsystem, !self.noremove));
+                }
+                let mut installedotherlist_guard = self.installedotherlist.guard();
+                installedotherlist_guard.clear();
+                for mut installedother in installedotherlist {
+                    installedother.note = self.notes.get(&notekey(&installedother)).cloned();
+                    installedotherlist_guard.push_back((installedother, !self.noremove));
                 }
+                installeduserlist_guard.drop();
+                installedsystemlist_guard.drop();
+                installedotherlist_guard.drop();
+                self.filternoted();
             }
             InstalledPageMsg::UpdatePkgTypes(systempkgtype, userpkgtype) => {
                 self.systempkgtype = systempkgtype;
                 self.userpkgtype = userpkgtype;
             }
+            InstalledPageMsg::SetHosts(hosts) => {
+                // Switch back to this computer if the selected host was removed
+                if let Some(host) = &self.host {
+                    if !hosts.contains(host) {
+                        self.host = None;
+                        sender.output(AppMsg::SetHost(None));
+                    }
+                }
+                self.set_hosts(hosts);
+            }
+            InstalledPageMsg::SelectHost(i) => {
+                let host = i.checked_sub(1).and_then(|i| self.hosts.get(i as usize)).cloned();
+                if host != self.host {
+                    self.host = host.clone();
+                    sender.output(AppMsg::SetHost(host));
+                }
+            }
             InstalledPageMsg::OpenRow(row, pkgtype) => {
                 match pkgtype {
                     InstallType::User => {
@@ -159,39 +272,60 @@ impl SimpleComponent for InstalledPageModel {
                 }
             }
             InstalledPageMsg::Remove(item) => {
+                if self.noremove {
+                    return;
+                }
                 let work = WorkPkg {
                     pkg: item.pkg.unwrap_or_default(),
                     pname: item.pname,
                     pkgtype: item.pkgtype,
                     action: PkgAction::Remove,
                     block: false,
-                    notify: Some(NotifyPage::Installed)
+                    extraargs: vec![],
+                    impure: None,
                 };
-                sender.output(AppMsg::AddInstalledToWorkQueue(work));
+                sender.output(AppMsg::QueueWork(work));
             }
-            InstalledPageMsg::UnsetBusy(work) => {
-                match work.pkgtype {
-                    InstallType::User => {
-                        let mut installeduserlist_guard = self.installeduserlist.guard();
-                        for i in 0..installeduserlist_guard.len() {
-                            if let Some(item) = installeduserlist_guard.get_mut(i) {
-                                if item.item.pname == work.pname && item.item.pkgtype == work.pkgtype {
-                                    item.item.busy = false;
-                                }
-                            }
+            InstalledPageMsg::SetQueue(workqueue) => {
+                for list in [&mut self.installeduserlist, &mut self.installedotherlist, &mut self.installedsystemlist] {
+                    let mut guard = list.guard();
+                    for i in 0..guard.len() {
+                        if let Some(item) = guard.get_mut(i) {
+                            let pkgtype = item.item.pkgtype.clone();
+                            let busy = (pkgtype == InstallType::User && workqueue.isbusy(&item.item.pname, lane(&pkgtype)))
+                                || item.item.pkg.as_ref().map(|x| workqueue.isbusy(x, lane(&pkgtype))).unwrap_or(false);
+                            item.item.busy = busy;
                         }
                     }
-                    InstallType::System => {
-                        let mut installedsystemlist_guard = self.installedsystemlist.guard();
-                        for i in 0..installedsystemlist_guard.len() {
-                            if let Some(item) = installedsystemlist_guard.get_mut(i) {
-                                if item.item.pkg == Some(work.pkg.clone()) && item.item.pkgtype == work.pkgtype {
-                                    item.item.busy = false;
-                                }
+                }
+            }
+            InstalledPageMsg::SetNote(key, note) => {
+                match &note {
+                    Some(note) => {
+                        self.notes.insert(key.to_string(), note.to_string());
+                    }
+                    None => {
+                        self.notes.remove(&key);
+                    }
+                }
+                for list in [&mut self.installeduserlist, &mut self.installedotherlist, &mut self.installedsystemlist] {
+                    let mut guard = list.guard();
+                    for i in 0..guard.len() {
+                        if let Some(item) = guard.get_mut(i) {
+                            if notekey(&item.item) == key {
+                                item.item.note = note.clone();
                             }
                         }
                     }
                 }
+                if self.notes.is_empty() {
+                    self.setnotedonly(false);
+                }
+                self.filternoted();
+            }
+            InstalledPageMsg::SetNotedOnly(notedonly) => {
+                self.setnotedonly(notedonly);
+                self.filternoted();
             }
         }
     }
@@ -200,6 +334,41 @@ impl SimpleComponent for InstalledPageModel {
 
 
 
+impl InstalledPageModel {
+    /// Hides the rows without a note while only noted packages are listed.
+    fn filternoted(&mut self) {
+        for list in [&mut self.installeduserlist, &mut self.installedotherlist, &mut self.installedsystemlist] {
+            let mut guard = list.guard();
+            for i in 0..guard.len() {
+                if let Some(item) = guard.get_mut(i) {
+                    item.hidden = self.notedonly && item.item.note.is_none();
+                }
+            }
+        }
+    }
+
+    fn setnotedonly(&mut self, notedonly: bool) {
+        if let Err(e) = state::editnscstate(|x| x.ui.notedonly = notedonly) {
+            warn!("Failed to save the notes filter: {}", e);
+        }
+        self.set_notedonly(notedonly);
+    }
+
+    fn hostnames(&self) -> Vec<String> {
+        let mut names = vec![gettext("This Computer")];
+        names.extend(self.hosts.iter().cloned());
+        names
+    }
+
+    fn hostindex(&self) -> u32 {
+        self.host
+            .as_ref()
+            .and_then(|host| self.hosts.iter().position(|x| x == host))
+            .map(|i| i as u32 + 1)
+            .unwrap_or(0)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct InstalledItem {
     pub name: String,
@@ -209,11 +378,21 @@ pub struct InstalledItem {
     pub icon: Option<String>,
     pub pkgtype: InstallType,
     pub busy: bool,
+    /// Installed with the nix CLI rather than through the Software Center
+    pub external: bool,
+    pub note: Option<String>,
+}
+
+/// Key of a package's note, the one the package page saves it under.
+fn notekey(item: &InstalledItem) -> String {
+    item.pkg.clone().unwrap_or_else(|| item.pname.to_string())
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct InstalledItemModel {
     pub item: InstalledItem,
+    removable: bool,
+    hidden: bool,
 }
 
 #[derive(Debug)]
@@ -229,7 +408,7 @@ pub enum InstalledItemInputMsg {
 #[relm4::factory(pub)]
 impl FactoryComponent for InstalledItemModel {
     type CommandOutput = ();
-    type Init = InstalledItem;
+    type Init = (InstalledItem, bool);
     type Input = InstalledItemInputMsg;
     type Output = InstalledItemMsg;
     type ParentWidget = adw::gtk::ListBox;
@@ -238,6 +417,8 @@ impl FactoryComponent for InstalledItemModel {
         adw::PreferencesRow {
             set_activatable: self.item.pkg.is_some(),
             set_can_focus: false,
+            #[watch]
+            set_visible: !self.hidden,
             #[wrap(Some)]
             set_child = &gtk::Box {
                 set_orientation: gtk::Orientation::Horizontal,
@@ -301,6 +482,32 @@ impl FactoryComponent for InstalledItemModel {
                         set_wrap: true,
                         set_max_width_chars: 0,
                     },
+                    gtk::Label {
+                        set_halign: gtk::Align::Start,
+                        add_css_class: "dim-label",
+                        add_css_class: "caption",
+                        set_label: &gettext("Managed externally"),
+                        set_tooltip_text: Some(&gettext("Installed with the nix command line")),
+                        set_visible: self.item.external,
+                    },
+                    gtk::Box {
+                        set_spacing: 5,
+                        #[watch]
+                        set_visible: self.item.note.is_some(),
+                        gtk::Image {
+                            add_css_class: "accent",
+                            set_icon_name: Some("document-edit-symbolic"),
+                            set_pixel_size: 12,
+                        },
+                        gtk::Label {
+                            set_halign: gtk::Align::Start,
+                            add_css_class: "accent",
+                            add_css_class: "caption",
+                            #[watch]
+                            set_label: self.item.note.as_deref().unwrap_or_default(),
+                            set_ellipsize: pango::EllipsizeMode::End,
+                        },
+                    },
                     gtk::Label {
                         set_halign: gtk::Align::Start,
                         set_label: self.item.summary.as_deref().unwrap_or(""),
@@ -315,6 +522,8 @@ impl FactoryComponent for InstalledItemModel {
                     gtk::Spinner {
                         set_spinning: true,
                     }
+                } else if !self.removable {
+                    gtk::Box {}
                 } else {
                     gtk::Button {
                         add_css_class: "destructive-action",
@@ -333,7 +542,7 @@ impl FactoryComponent for InstalledItemModel {
     }
 
     fn init_model(
-        parent: Self::Init,
+        (parent, removable): Self::Init,
         _index: &DynamicIndex,
         _sender: FactorySender<Self>,
     ) -> Self {
@@ -358,10 +567,14 @@ impl FactoryComponent for InstalledItemModel {
             icon: parent.icon,
             pkgtype: parent.pkgtype,
             busy: parent.busy,
+            external: parent.external,
+            note: parent.note,
         };
 
         Self {
             item,
+            removable,
+            hidden: false,
         }
     }
 
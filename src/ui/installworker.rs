@@ -1,12 +1,24 @@
+use crate::i18n::{gettext, gettextf};
 use super::pkgpage::{InstallType, PkgAction, PkgMsg, WorkPkg};
+use crate::parse::cmd::{cmdline, helperpath, impureargs, nixargs, nixcmd, purityhint, rebuildargs};
+use crate::parse::config::NscConfig;
+use crate::parse::narinfo::{self, ClosureSize};
+use crate::parse::nixlog::{self, NixEvent, Substitutions};
+use crate::parse::nixpkgsconfig::{self, AllowKind};
+use crate::parse::profile;
+use crate::parse::remote;
+use crate::parse::state;
+use crate::parse::util;
 use super::rebuild::RebuildMsg;
-use super::window::{SystemPkgs, UserPkgs, REBUILD_BROKER};
+use super::window::{SystemPkgs, UserPkgs, REBUILD_BROKER, USER_REBUILD_BROKER};
 use log::*;
 use nix_data::config::configfile::NixDataConfig;
 use relm4::*;
 use anyhow::{Result, anyhow};
-use std::path::Path;
+use std::collections::HashSet;
 use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use std::fs;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
 
@@ -17,15 +29,20 @@ pub struct InstallAsyncHandler {
     process: Option<JoinHandle<()>>,
     work: Option<WorkPkg>,
     config: NixDataConfig,
+    nscconfig: NscConfig,
     pid: Option<u32>,
     syspkgs: SystemPkgs,
     userpkgs: UserPkgs,
+    /// SSH host whose user profile is managed instead of the local one
+    host: Option<String>,
 }
 
 #[derive(Debug)]
 pub enum InstallAsyncHandlerMsg {
     SetConfig(NixDataConfig),
+    SetNscConfig(NscConfig),
     SetPkgTypes(SystemPkgs, UserPkgs),
+    SetHost(Option<String>),
     Process(WorkPkg),
     CancelProcess,
     SetPid(Option<u32>),
@@ -52,9 +69,11 @@ impl Worker for InstallAsyncHandler {
                 flakearg: None,
                 generations: None
             },
+            nscconfig: NscConfig::default(),
             pid: None,
             syspkgs: params.syspkgs,
             userpkgs: params.userpkgs,
+            host: None,
             tracker: 0,
         }
     }
@@ -65,198 +84,201 @@ impl Worker for InstallAsyncHandler {
             InstallAsyncHandlerMsg::SetConfig(config) => {
                 self.config = config;
             }
+            InstallAsyncHandlerMsg::SetNscConfig(nscconfig) => {
+                self.nscconfig = nscconfig;
+            }
             InstallAsyncHandlerMsg::SetPkgTypes(syspkgs, userpkgs) => {
                 self.syspkgs = syspkgs;
                 self.userpkgs = userpkgs;
             }
-            
+            InstallAsyncHandlerMsg::SetHost(host) => {
+                self.host = host;
+            }
+
             InstallAsyncHandlerMsg::Process(work) => {
                 if work.block {
                     return;
                 }
+                self.work = Some(work.clone());
                 let config = self.config.clone();
+                let mut nscconfig = self.nscconfig.clone();
+                if let Some(impure) = work.impure {
+                    nscconfig.impure = impure;
+                }
                 match work.pkgtype {
                     InstallType::User => match work.action {
-                        PkgAction::Install => {
-                            info!("Installing user package: {}", work.pkg);
-                            match self.userpkgs {
-                                UserPkgs::Env => {
-                                    self.process = Some(relm4::spawn(async move {
-                                        let mut p = tokio::process::Command::new("nix-env")
-                                            .arg("-iA")
-                                            .arg(format!("nixos.{}", work.pkg))
-                                            .kill_on_drop(true)
-                                            .stdout(Stdio::piped())
-                                            .stderr(Stdio::piped())
-                                            .spawn()
-                                            .expect("Failed to run nix-env");
-
-                                        let stderr = p.stderr.take().unwrap();
-                                        let reader = tokio::io::BufReader::new(stderr);
-
-                                        let mut lines = reader.lines();
-                                        while let Ok(Some(line)) = lines.next_line().await {
-                                            trace!("CAUGHT LINE: {}", line);
-                                        }
-
-                                        match p.wait().await {
-                                            Ok(o) => {
-                                                if o.success() {
-                                                    info!(
-                                                        "Removed user package: {} success",
-                                                        work.pkg
-                                                    );
-                                                    sender.output(PkgMsg::FinishedProcess(work));
-                                                } else {
-                                                    warn!(
-                                                        "Removed user package: {} failed",
-                                                        work.pkg
-                                                    );
-                                                    sender.output(PkgMsg::FailedProcess(work));
-                                                }
-                                            }
-                                            Err(e) => {
-                                                warn!("Error removing user package: {}", e);
-                                                sender.output(PkgMsg::FailedProcess(work));
-                                            }
-                                        }
-                                    }));
+                        PkgAction::Install | PkgAction::InstallEnv(_) | PkgAction::Remove => {
+                            info!("{:?} user package: {}", work.action, work.pkg);
+                            let (program, mut args) = usercmd(
+                                &work.pkg,
+                                &work.pname,
+                                &work.action,
+                                &self.userpkgs,
+                                &nscconfig,
+                                &work.extraargs,
+                            );
+                            if let Some(host) = &self.host {
+                                info!("On {}", host);
+                            } else {
+                                info!("Running: {}", cmdline(program, &args, &nscconfig));
+                            }
+                            if program == "nix" {
+                                args.extend(nixlog::logargs());
+                            }
+                            let host = self.host.clone();
+                            let resolve = host.is_none() && self.userpkgs == UserPkgs::Profile && work.action == PkgAction::Install;
+                            self.process = Some(relm4::spawn(async move {
+                                if host.is_none() && profile::profilelocked() {
+                                    info!("User profile is locked, waiting to {:?} {}", work.action, work.pkg);
+                                    sender.output(PkgMsg::ProfileLocked(work.clone()));
+                                    while profile::profilelocked() {
+                                        tokio::time::sleep(Duration::from_secs(1)).await;
+                                    }
+                                    sender.output(PkgMsg::ProfileUnlocked(work.clone()));
                                 }
-                                UserPkgs::Profile => {
-                                    self.process = Some(relm4::spawn(async move {
-                                        let mut p = tokio::process::Command::new("nix")
-                                            .arg("profile")
-                                            .arg("install")
-                                            .arg(format!("nixpkgs#{}", work.pkg))
-                                            .arg("--impure")
-                                            .kill_on_drop(true)
-                                            .stdout(Stdio::piped())
-                                            .stderr(Stdio::piped())
-                                            .spawn()
-                                            .expect("Failed to run nix profile");
+                                let started = Instant::now();
+                                if resolve {
+                                    if let Err(msg) = resolveattr(&work.pkg, &nscconfig).await {
+                                        warn!("Failed to resolve {}: {}", work.pkg, msg);
+                                        USER_REBUILD_BROKER.send(RebuildMsg::Show);
+                                        USER_REBUILD_BROKER.send(RebuildMsg::FinishError(Some(msg)));
+                                        sender.output(PkgMsg::FailedProcess(work));
+                                        return;
+                                    }
+                                }
+                                let envs = nixpkgsconfig::envvars(&nscconfig, Some(&work.pname));
+                                let mut command = match &host {
+                                    Some(host) => match remoteargs(host, &work, &nscconfig).await {
+                                        Ok(args) => remote::remotecmd(host, "nix", &args, &envs, &nscconfig),
+                                        Err(e) => {
+                                            warn!("Failed to read the profile on {}: {}", host, e);
+                                            USER_REBUILD_BROKER.send(RebuildMsg::Show);
+                                            USER_REBUILD_BROKER.send(RebuildMsg::FinishError(Some(e.to_string())));
+                                            sender.output(PkgMsg::FailedProcess(work));
+                                            return;
+                                        }
+                                    },
+                                    None => {
+                                        let mut command = nixcmd(program, &nscconfig);
+                                        command.args(&args).envs(envs);
+                                        command
+                                    }
+                                };
+                                let mut p = command
+                                    .kill_on_drop(true)
+                                    .stdout(Stdio::piped())
+                                    .stderr(Stdio::piped())
+                                    .spawn()
+                                    .unwrap_or_else(|_| panic!("Failed to run {}", program));
 
-                                        let stderr = p.stderr.take().unwrap();
-                                        let reader = tokio::io::BufReader::new(stderr);
+                                let stderr = p.stderr.take().unwrap();
+                                let reader = tokio::io::BufReader::new(stderr);
 
-                                        let mut lines = reader.lines();
-                                        while let Ok(Some(line)) = lines.next_line().await {
-                                            trace!("CAUGHT LINE: {}", line);
+                                let mut lines = reader.lines();
+                                let mut hint = None;
+                                let mut error = None;
+                                let mut refused = None;
+                                let mut substitutions = Substitutions::default();
+                                let mut warnings: Vec<String> = vec![];
+                                while let Ok(Some(line)) = lines.next_line().await {
+                                    trace!("CAUGHT LINE: {}", line);
+                                    let Some(event) = nixlog::parse(&line) else {
+                                        continue;
+                                    };
+                                    substitutions.event(&event);
+                                    if let Some(warning) = event.warning() {
+                                        if !warnings.contains(&warning) {
+                                            warnings.push(warning);
                                         }
-
-                                        match p.wait().await {
-                                            Ok(o) => {
-                                                if o.success() {
-                                                    info!(
-                                                        "Removed user package: {} success",
-                                                        work.pkg
-                                                    );
-                                                    sender.output(PkgMsg::FinishedProcess(work));
-                                                } else {
-                                                    warn!(
-                                                        "Removed user package: {} failed",
-                                                        work.pkg
-                                                    );
-                                                    sender.output(PkgMsg::FailedProcess(work));
-                                                }
-                                            }
-                                            Err(e) => {
-                                                warn!("Error removing user package: {}", e);
-                                                sender.output(PkgMsg::FailedProcess(work));
-                                            }
+                                    }
+                                    if let NixEvent::Progress { done, expected, .. } = event {
+                                        trace!("Progress: {}/{}", done, expected);
+                                    }
+                                    if let Some(text) = event.text() {
+                                        hint = hint.or(purityhint(text, &nscconfig));
+                                        refused = refused.or(nixpkgsconfig::refused(text));
+                                        if event.iserror() && error.is_none() {
+                                            error = Some(text.to_string());
                                         }
-                                    }));
+                                    }
                                 }
-                            }
-                        }
-                        PkgAction::Remove => {
-                            info!("Removing user package: {}", work.pkg);
-                            match self.userpkgs {
-                                UserPkgs::Env => {
-                                    self.process = Some(relm4::spawn(async move {
-                                        let mut p = tokio::process::Command::new("nix-env")
-                                            .arg("-e")
-                                            .arg(&work.pname)
-                                            .kill_on_drop(true)
-                                            .stdout(Stdio::piped())
-                                            .stderr(Stdio::piped())
-                                            .spawn()
-                                            .expect("Failed to run nix-env");
-                                        let stderr = p.stderr.take().unwrap();
-                                        let reader = tokio::io::BufReader::new(stderr);
 
-                                        let mut lines = reader.lines();
-                                        while let Ok(Some(line)) = lines.next_line().await {
-                                            trace!("CAUGHT LINE: {}", line);
+                                if !warnings.is_empty() {
+                                    sender.output(PkgMsg::SetWarnings(work.pkg.to_string(), warnings));
+                                }
+                                match p.wait().await {
+                                    Ok(o) => {
+                                        if let Err(e) = state::recordoperation(started, &substitutions, o.success()) {
+                                            warn!("Failed to save operation statistics: {}", e);
                                         }
-                                        match p.wait().await {
-                                            Ok(o) => {
-                                                if o.success() {
-                                                    info!(
-                                                        "Removed user package: {} success",
-                                                        work.pkg
-                                                    );
-                                                    sender.output(PkgMsg::FinishedProcess(work));
-                                                } else {
-                                                    warn!(
-                                                        "Removed user package: {} failed",
-                                                        work.pkg
-                                                    );
-                                                    sender.output(PkgMsg::FailedProcess(work));
-                                                }
+                                        if o.success() {
+                                            info!("{:?} user package: {} success", work.action, work.pkg);
+                                            let downloads = substitutions.bysubstituter();
+                                            for x in &downloads {
+                                                info!("Downloaded {} paths ({} bytes) from {}", x.paths, x.bytes, x.host);
                                             }
-                                            Err(e) => {
-                                                warn!("Error removing user package: {}", e);
-                                                sender.output(PkgMsg::FailedProcess(work));
+                                            if !downloads.is_empty() {
+                                                sender.output(PkgMsg::SetDownloads(work.pkgtype.clone(), downloads));
                                             }
-                                        }
-                                    }));
-                                }
-                                UserPkgs::Profile => {
-                                    self.process = Some(relm4::spawn(async move {
-                                        let mut p = tokio::process::Command::new("nix")
-                                            .arg("profile")
-                                            .arg("remove")
-                                            .arg(&format!(
-                                                "legacyPackages.x86_64-linux.{}",
-                                                work.pkg
-                                            ))
-                                            .kill_on_drop(true)
-                                            .stdout(Stdio::piped())
-                                            .stderr(Stdio::piped())
-                                            .spawn()
-                                            .expect("Failed to run nix profile");
-                                        let stderr = p.stderr.take().unwrap();
-                                        let reader = tokio::io::BufReader::new(stderr);
-
-                                        let mut lines = reader.lines();
-                                        while let Ok(Some(line)) = lines.next_line().await {
-                                            trace!("CAUGHT LINE: {}", line);
-                                        }
-                                        match p.wait().await {
-                                            Ok(o) => {
-                                                if o.success() {
-                                                    info!(
-                                                        "Removed user package: {} success",
-                                                        work.pkg
-                                                    );
-                                                    sender.output(PkgMsg::FinishedProcess(work));
-                                                } else {
-                                                    warn!(
-                                                        "Removed user package: {} failed",
-                                                        work.pkg
-                                                    );
-                                                    sender.output(PkgMsg::FailedProcess(work));
-                                                }
+                                            sender.output(PkgMsg::FinishedProcess(work));
+                                        } else {
+                                            warn!("{:?} user package: {} failed", work.action, work.pkg);
+                                            // Ask to allow the package rather than showing the error, unless it already was
+                                            let refused = refused.filter(|(kind, name)| {
+                                                work.action == PkgAction::Install
+                                                    && !match kind {
+                                                        AllowKind::Unfree => &nscconfig.allowunfree,
+                                                        AllowKind::Insecure => &nscconfig.allowinsecure,
+                                                    }
+                                                    .contains(name)
+                                            });
+                                            if let Some((kind, name)) = refused {
+                                                sender.output(PkgMsg::FailedProcess(work.clone()));
+                                                sender.output(PkgMsg::RequestAllow(work, kind, name));
+                                                return;
                                             }
-
-                                            Err(e) => {
-                                                warn!("Error removing user package: {}", e);
-                                                sender.output(PkgMsg::FailedProcess(work));
+                                            if let Some(msg) = hint.or(error) {
+                                                USER_REBUILD_BROKER.send(RebuildMsg::Show);
+                                                USER_REBUILD_BROKER.send(RebuildMsg::FinishError(Some(msg)));
                                             }
+                                            sender.output(PkgMsg::FailedProcess(work));
                                         }
-                                    }));
+                                    }
+                                    Err(e) => {
+                                        warn!("Error running {}: {}", program, e);
+                                        sender.output(PkgMsg::FailedProcess(work));
+                                    }
                                 }
-                            }
+                            }));
+                        }
+                        PkgAction::Revert(_) if self.host.is_some() => {
+                            warn!("Reverting packages on remote hosts is not supported");
+                            USER_REBUILD_BROKER.send(RebuildMsg::Show);
+                            USER_REBUILD_BROKER.send(RebuildMsg::FinishError(Some(gettext(
+                                "Reverting packages on remote hosts is not supported",
+                            ))));
+                            sender.output(PkgMsg::FailedProcess(work));
+                        }
+                        PkgAction::Revert(ref path) => {
+                            info!("Reverting user package: {} to {}", work.pkg, path);
+                            let path = path.to_string();
+                            let userpkgs = self.userpkgs.clone();
+                            self.process = Some(relm4::spawn(async move {
+                                match revertuser(&work.pkg, &path, userpkgs, nscconfig, &work.extraargs).await {
+                                    Ok(true) => {
+                                        info!("Reverted user package: {} success", work.pkg);
+                                        sender.output(PkgMsg::FinishedProcess(work));
+                                    }
+                                    Ok(false) => {
+                                        warn!("Reverted user package: {} failed", work.pkg);
+                                        sender.output(PkgMsg::FailedProcess(work));
+                                    }
+                                    Err(e) => {
+                                        warn!("Error reverting user package: {}", e);
+                                        sender.output(PkgMsg::FailedProcess(work));
+                                    }
+                                }
+                            }));
                         }
                     },
                     InstallType::System => {
@@ -270,6 +292,8 @@ impl Worker for InstallAsyncHandler {
                                             work.pkg.to_string(),
                                             work.action.clone(),
                                             config,
+                                            nscconfig,
+                                            work.extraargs.clone(),
                                             sender.clone(),
                                         )
                                         .await
@@ -291,6 +315,16 @@ impl Worker for InstallAsyncHandler {
                                         }
                                     }));
                                 }
+                                PkgAction::Revert(_) => {
+                                    warn!("Reverting system packages is not supported");
+                                    REBUILD_BROKER.send(RebuildMsg::FinishError(Some(gettext("Reverting system packages is not supported"))));
+                                    sender.output(PkgMsg::FailedProcess(work));
+                                }
+                                PkgAction::InstallEnv(_) => {
+                                    warn!("Installing interpreter environments as system packages is not supported");
+                                    REBUILD_BROKER.send(RebuildMsg::FinishError(Some(gettext("Add the environment to configuration.nix to install it for the whole system"))));
+                                    sender.output(PkgMsg::FailedProcess(work));
+                                }
                                 PkgAction::Remove => {
                                     info!("Removing system package: {}", work.pkg);
                                     self.process = Some(relm4::spawn(async move {
@@ -298,6 +332,8 @@ impl Worker for InstallAsyncHandler {
                                             work.pkg.to_string(),
                                             work.action.clone(),
                                             config,
+                                            nscconfig,
+                                            work.extraargs.clone(),
                                             sender.clone(),
                                         )
                                         .await
@@ -331,7 +367,9 @@ impl Worker for InstallAsyncHandler {
                 }
                 self.process = None;
                 self.pid = None;
-                sender.output(PkgMsg::CancelFinished);
+                if let Some(work) = self.work.take() {
+                    sender.output(PkgMsg::CancelFinished(work));
+                }
             }
             InstallAsyncHandlerMsg::SetPid(p) => self.pid = p,
         }
@@ -342,18 +380,11 @@ async fn installsys(
     pkg: String,
     action: PkgAction,
     config: NixDataConfig,
+    nscconfig: NscConfig,
+    extraargs: Vec<String>,
     _sender: ComponentSender<InstallAsyncHandler>,
 ) -> Result<bool> {
-    let systemconfig = config.systemconfig.unwrap_or_default();
-    let flakeargs = if let Some(flake) = config.flake {
-        if let Some(flakearg) = config.flakearg {
-            Some(format!("{}#{}", flake, flakearg))
-        } else {
-            Some(flake)
-        }
-    } else {
-        None
-    };
+    let systemconfig = config.systemconfig.clone().unwrap_or_default();
     let mut p = pkg;
     let f = fs::read_to_string(&systemconfig)?;
     if let Ok(s) = nix_editor::read::getwithvalue(&f, "environment.systemPackages") {
@@ -381,48 +412,23 @@ async fn installsys(
                 }
             }
         }
-    };
-
-    let exe = match std::env::current_exe() {
-        Ok(mut e) => {
-            e.pop(); // root/bin
-            e.pop(); // root/
-            e.push("libexec"); // root/libexec
-            e.push("nsc-helper");
-            let x = e.to_string_lossy().to_string();
-            info!("nsc-helper path: {}", x);
-            if Path::new(&x).is_file() {
-                x
-            } else {
-                String::from("nsc-helper")
-            }
+        PkgAction::Revert(_) => {
+            return Err(anyhow!("Cannot revert system packages"));
         }
-        Err(_) => String::from("nsc-helper"),
-    };
-
-    let rebuildargs = if let Some(x) = flakeargs {
-        let mut v = vec![String::from("--flake")];
-        for arg in x.split(' ') {
-            if !arg.is_empty() {
-                v.push(String::from(arg));
-            }
+        PkgAction::InstallEnv(_) => {
+            return Err(anyhow!("Cannot install interpreter environments as system packages"));
         }
-        v
-    } else {
-        vec![]
     };
 
-    let mut cmd = tokio::process::Command::new("pkexec")
-        .arg(&exe)
+    let mut cmd = nixcmd("pkexec", &nscconfig)
+        .arg(helperpath())
         .arg("config")
         .arg("--generations")
         .arg(config.generations.unwrap_or(0).to_string())
         .arg("--output")
         .arg(&systemconfig)
         .arg("--")
-        .arg("switch")
-        .arg("--impure")
-        .args(&rebuildargs)
+        .args(sysargs(&config, &nscconfig, &extraargs))
         .stderr(Stdio::piped())
         .stdin(Stdio::piped())
         .spawn()?;
@@ -432,13 +438,223 @@ async fn installsys(
     let reader = tokio::io::BufReader::new(stderr);
 
     let mut lines = reader.lines();
+    let mut hint = None;
     while let Ok(Some(line)) = lines.next_line().await {
         trace!("CAUGHT LINE: {}", line);
+        hint = hint.or(purityhint(&line, &nscconfig));
         REBUILD_BROKER.send(RebuildMsg::UpdateText(line));
     }
     if cmd.wait().await?.success() {
         Ok(true)
     } else {
+        if let Some(hint) = hint {
+            REBUILD_BROKER.send(RebuildMsg::UpdateText(hint));
+        }
         Ok(false)
     }
 }
+
+/// Attributes that evaluated successfully during this session.
+static RESOLVED: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+/// What installing `pkg` into the user profile would download and build, without installing it,
+/// and the warnings evaluating it printed.
+pub async fn closuresize(pkg: &str, pname: &str, nscconfig: &NscConfig) -> Result<(ClosureSize, Vec<String>)> {
+    let output = nixcmd("nix", nscconfig)
+        .arg("build")
+        .arg("--dry-run")
+        .arg("--no-link")
+        .arg(format!("nixpkgs#{}", pkg))
+        .args(impureargs(nscconfig))
+        .envs(nixpkgsconfig::envvars(nscconfig, Some(pname)))
+        .kill_on_drop(true)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!("Failed to evaluate {}", pkg));
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut warnings: Vec<String> = stderr.lines().filter_map(nixlog::evalwarning).collect();
+    warnings.dedup();
+    Ok((narinfo::parsedryrun(&stderr), warnings))
+}
+
+/// Checks that `pkg` exists in nixpkgs for this system before installing it,
+/// so that a missing attribute gives a readable message instead of a failure deep in `nix profile install`.
+async fn resolveattr(pkg: &str, nscconfig: &NscConfig) -> Result<(), String> {
+    if let Ok(resolved) = RESOLVED.lock() {
+        if resolved.as_ref().map(|x| x.contains(pkg)).unwrap_or(false) {
+            return Ok(());
+        }
+    }
+    let output = nixcmd("nix", nscconfig)
+        .arg("eval")
+        .arg("--json")
+        .arg(format!("nixpkgs#{}.meta.position", pkg))
+        .args(impureargs(nscconfig))
+        .kill_on_drop(true)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run nix eval: {}", e))?;
+    if output.status.success() {
+        if let Ok(mut resolved) = RESOLVED.lock() {
+            resolved.get_or_insert_with(HashSet::new).insert(pkg.to_string());
+        }
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let error = stderr
+        .lines()
+        .find(|x| x.trim_start().starts_with("error:"))
+        .map(|x| x.trim_start().trim_start_matches("error:").trim().to_string())
+        .unwrap_or_else(|| stderr.trim().to_string());
+    if let Some(hint) = purityhint(&stderr, nscconfig) {
+        Err(hint)
+    } else if error.contains("does not provide attribute") {
+        Err(gettextf("{} is not available in nixpkgs for {}.", &[pkg, &util::currentsystem()]))
+    } else {
+        Err(gettextf("{} could not be evaluated: {}", &[pkg, &error]))
+    }
+}
+
+/// Program and arguments used to apply `action` to a user package.
+pub fn usercmd(
+    pkg: &str,
+    pname: &str,
+    action: &PkgAction,
+    userpkgs: &UserPkgs,
+    nscconfig: &NscConfig,
+    extraargs: &[String],
+) -> (&'static str, Vec<String>) {
+    let (program, mut args) = match (userpkgs, action) {
+        (UserPkgs::Env, PkgAction::Install) => (
+            "nix-env",
+            vec![String::from("-iA"), format!("nixos.{}", pkg)],
+        ),
+        (UserPkgs::Env, PkgAction::InstallEnv(expr)) => (
+            "nix-env",
+            vec![String::from("-iE"), format!("_: with import <nixos> {{}}; {}", expr)],
+        ),
+        (UserPkgs::Env, PkgAction::Remove) => ("nix-env", vec![String::from("-e"), pname.to_string()]),
+        // nix-env replaces the installed element with the same name
+        (UserPkgs::Env, PkgAction::Revert(path)) => {
+            ("nix-env", vec![String::from("--install"), path.to_string()])
+        }
+        (UserPkgs::Profile, PkgAction::Install) => (
+            "nix",
+            vec![
+                String::from("profile"),
+                String::from("install"),
+                format!("nixpkgs#{}", pkg),
+            ],
+        ),
+        (UserPkgs::Profile, PkgAction::InstallEnv(expr)) => ("nix", profileenvargs(expr)),
+        (UserPkgs::Profile, PkgAction::Remove) => {
+            let mut args = vec![String::from("profile"), String::from("remove")];
+            args.extend(profile::elementargs(&[pkg.to_string()]));
+            ("nix", args)
+        }
+        (UserPkgs::Profile, PkgAction::Revert(path)) => (
+            "nix",
+            vec![
+                String::from("profile"),
+                String::from("install"),
+                path.to_string(),
+            ],
+        ),
+    };
+    if *userpkgs == UserPkgs::Profile && matches!(action, PkgAction::Install | PkgAction::InstallEnv(_)) {
+        args.extend(nixpkgsconfig::impureargs(nscconfig));
+    }
+    if *action != PkgAction::Remove {
+        args.extend(nixargs(nscconfig));
+    }
+    args.extend(profile::profileargs());
+    args.extend(extraargs.iter().cloned());
+    (program, args)
+}
+
+/// `nix profile` arguments applying a user install or removal on a remote host.
+/// Remote profiles are always `nix profile`, and their own `NIX_PROFILE` is left alone.
+async fn remoteargs(host: &str, work: &WorkPkg, nscconfig: &NscConfig) -> Result<Vec<String>> {
+    let mut args = match &work.action {
+        PkgAction::InstallEnv(expr) => profileenvargs(expr),
+        _ => remote::profileargs(host, &work.pkg, work.action == PkgAction::Remove, nscconfig).await?,
+    };
+    if matches!(work.action, PkgAction::Install | PkgAction::InstallEnv(_)) {
+        args.extend(nixpkgsconfig::impureargs(nscconfig));
+        args.extend(nixargs(nscconfig));
+    }
+    args.extend(work.extraargs.iter().cloned());
+    args.extend(nixlog::logargs());
+    Ok(args)
+}
+
+/// `nix profile install` arguments for an expression from [`crate::parse::packages::withpackagesexpr`].
+/// Reading the nixpkgs flake from the registry and the current system needs `--impure`.
+fn profileenvargs(expr: &str) -> Vec<String> {
+    vec![
+        String::from("profile"),
+        String::from("install"),
+        String::from("--impure"),
+        String::from("--expr"),
+        format!(
+            "with (builtins.getFlake \"nixpkgs\").legacyPackages.${{builtins.currentSystem}}; {}",
+            expr
+        ),
+    ]
+}
+
+/// Arguments passed through `nsc-helper` to `nixos-rebuild`.
+pub fn sysargs(config: &NixDataConfig, nscconfig: &NscConfig, extraargs: &[String]) -> Vec<String> {
+    let mut args = vec![String::from("switch")];
+    args.extend(impureargs(nscconfig));
+    args.extend(rebuildargs(config));
+    args.extend(nixargs(nscconfig));
+    args.extend(extraargs.iter().cloned());
+    args
+}
+
+async fn revertuser(
+    pkg: &str,
+    path: &str,
+    userpkgs: UserPkgs,
+    nscconfig: NscConfig,
+    extraargs: &[String],
+) -> Result<bool> {
+    let action = PkgAction::Revert(path.to_string());
+    // nix-env replaces the element in one step. With nix profile the old version is installed next
+    // to the current one before that is removed, so a failed install leaves the package in place.
+    let current = (userpkgs == UserPkgs::Profile).then(|| profile::elementargs(&[pkg.to_string()]));
+    let (program, mut args) = usercmd(pkg, pkg, &action, &userpkgs, &nscconfig, extraargs);
+    if current.is_some() {
+        // Below the default priority of 5, so the two don't conflict until the current one is gone
+        args.extend([String::from("--priority"), String::from("6")]);
+    }
+    let status = nixcmd(program, &nscconfig)
+        .args(&args)
+        .kill_on_drop(true)
+        .stderr(Stdio::null())
+        .status()
+        .await?;
+    if !status.success() {
+        return Ok(false);
+    }
+    if let Some(current) = current {
+        let status = nixcmd("nix", &nscconfig)
+            .arg("profile")
+            .arg("remove")
+            .args(current)
+            .args(profile::profileargs())
+            .kill_on_drop(true)
+            .stderr(Stdio::null())
+            .status()
+            .await?;
+        return Ok(status.success());
+    }
+    Ok(true)
+}
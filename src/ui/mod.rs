@@ -1,7 +1,13 @@
 pub mod about;
+pub mod allpkgspage;
+pub mod announce;
 pub mod categories;
 pub mod categorypage;
+pub mod categorystrip;
 pub mod categorytile;
+pub mod cleanup;
+pub mod dbus;
+pub mod desktop;
 pub mod installedpage;
 pub mod installworker;
 pub mod pkgpage;
@@ -16,3 +22,4 @@ pub mod updateworker;
 pub mod welcome;
 pub mod window;
 pub mod windowloading;
+pub mod workqueue;
@@ -1,37 +1,48 @@
+use crate::i18n::{gettext, gettextf, ngettext, ngettextf};
 use adw::gio;
 use adw::prelude::*;
 use html2pango;
-use image::{imageops::FilterType, ImageFormat};
 use nix_data::config::configfile::NixDataConfig;
 use relm4::actions::RelmAction;
 use relm4::actions::RelmActionGroup;
 use relm4::gtk::pango;
 use relm4::{factory::FactoryVecDeque, *};
-use sha256::digest;
-use std::collections::HashSet;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use std::convert::identity;
-use std::io::Cursor;
 use std::process::Command;
-use std::{
-    env,
-    error::Error,
-    fmt::Write,
-    fs::{self, File},
-    io::BufReader,
-    path::Path,
-    time::Duration,
-};
+use std::{fmt::Write, time::Duration};
 use log::*;
 
-use crate::parse::packages::PkgMaintainer;
+use crate::parse::cmd;
+use crate::parse::config::{NscConfig, Policy, PolicyBlock};
+use crate::parse::crash;
+use crate::parse::deps::{self, Dependencies};
+use crate::parse::expression::{self, Position};
+use crate::parse::favicon;
+use crate::parse::hints::{self, GpuNote, SystemNote};
+use crate::parse::integration::{self, DesktopIntegration, ShareSnapshot};
+use crate::parse::narinfo::{self, ClosureSize};
+use crate::parse::nixlog::SubstituterUse;
+use crate::parse::nixpkgsconfig::{self, AllowKind};
+use crate::parse::packages::{self, Developer, PkgMaintainer, Screenshot};
+use crate::parse::profile;
+use crate::parse::screenshot;
+use crate::parse::state;
 use crate::parse::util;
-use crate::ui::installworker::InstallAsyncHandlerMsg;
+use crate::parse::workqueue::WorkLane;
+use crate::ui::installworker::{closuresize, sysargs, usercmd, InstallAsyncHandlerMsg};
 
+use super::announce::announce;
+use super::rebuild::RebuildMsg;
 use super::installworker::InstallAsyncHandler;
 use super::installworker::InstallAsyncHandlerInit;
-use super::window::SystemPkgs;
+use super::window::{SystemPkgs, USER_REBUILD_BROKER};
 use super::window::UserPkgs;
-use super::{screenshotfactory::ScreenshotItem, window::AppMsg};
+use super::workqueue::{lane, WorkQueue};
+use super::desktop;
+use super::{screenshotfactory::{ScreenshotItem, Sensitivity}, window::AppMsg};
 
 #[tracker::track]
 #[derive(Debug)]
@@ -46,10 +57,32 @@ pub struct PkgModel {
     version: Option<String>,
 
     homepage: Option<String>,
+    bugtracker: Option<String>,
+    /// Private note the user kept on the package
+    note: String,
+    /// Cached favicons of the links, by domain
+    favicons: HashMap<String, String>,
+    /// Install buttons were swapped out, so keyboard focus may need to be put back
+    #[tracker::do_not_track]
+    refocus: bool,
     licenses: Vec<License>,
     platforms: Vec<String>,
+    supported: bool,
     maintainers: Vec<PkgMaintainer>,
     launchable: Option<Launch>,
+    developer: Option<Developer>,
+    versions: PkgVersions,
+    position: Option<Position>,
+    expression: NixExpression,
+    /// Runtime closure, fetched when first shown
+    dependencies: DependencyTree,
+    /// NixOS options the package needs besides the install
+    systemnote: Option<SystemNote>,
+    /// The package doesn't work with the display server of this session
+    displaynote: Option<String>,
+    gpunote: Option<GpuNote>,
+    /// Warnings nixpkgs printed while evaluating the package
+    warnings: Vec<String>,
 
     syspkgtype: SystemPkgs,
     userpkgtype: UserPkgs,
@@ -58,14 +91,39 @@ pub struct PkgModel {
     screenshots: FactoryVecDeque<ScreenshotItem>,
     #[tracker::no_eq]
     installworker: WorkerController<InstallAsyncHandler>,
+    /// Runs system operations, so they don't wait for the user profile
+    #[tracker::no_eq]
+    systemworker: WorkerController<InstallAsyncHandler>,
     carpage: CarouselPage,
     installtype: InstallType,
     installeduserpkgs: HashSet<String>,
     installedsystempkgs: HashSet<String>,
 
-    workqueue: HashSet<WorkPkg>,
+    /// Copy of the queue owned by the main window
+    #[tracker::no_eq]
+    workqueue: WorkQueue,
+    previousversion: Option<(String, String)>,
+    nscconfig: NscConfig,
+    policy: Policy,
+    readonly: bool,
+    extraargs: String,
+    /// Why the extra arguments can't be split, nothing runs until they can
+    argserror: Option<String>,
+    impure: bool,
     visible: bool,
     online: bool,
+    /// Desktop files before the running installs, to summarize what they added
+    #[tracker::no_eq]
+    sharesnapshot: HashMap<InstallType, ShareSnapshot>,
+    #[tracker::no_eq]
+    downloads: HashMap<InstallType, Vec<SubstituterUse>>,
+    /// Working out what an install would download before asking to confirm it
+    sizing: bool,
+    /// Shown while waiting for another nix process to finish with the user profile
+    #[tracker::do_not_track]
+    lockdialog: Option<adw::MessageDialog>,
+    /// SSH host whose user profile is managed, where the local store says nothing about sizes
+    host: Option<String>,
 }
 
 #[derive(Debug, Hash, Eq, PartialEq, Clone)]
@@ -75,18 +133,17 @@ pub struct WorkPkg {
     pub pkgtype: InstallType,
     pub action: PkgAction,
     pub block: bool,
-    pub notify: Option<NotifyPage>,
-}
-
-#[derive(Debug, Hash, Eq, PartialEq, Clone)]
-pub enum NotifyPage {
-    Installed,
+    pub extraargs: Vec<String>,
+    pub impure: Option<bool>,
 }
 
 #[derive(Debug, Hash, Eq, PartialEq, Clone)]
 pub enum PkgAction {
     Install,
-    Remove
+    /// Install an interpreter bundled with libraries, from [`packages::withpackagesexpr`]
+    InstallEnv(String),
+    Remove,
+    Revert(String),
 }
 
 
@@ -96,6 +153,24 @@ pub enum Launch {
     TerminalApp(String),
 }
 
+/// Source of the file the package is defined in, fetched when first shown.
+#[derive(Debug, PartialEq, Eq)]
+pub enum NixExpression {
+    Unloaded,
+    Loading,
+    Loaded(String),
+    Failed,
+}
+
+/// What the package pulls in, fetched when first shown.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DependencyTree {
+    Unloaded,
+    Loading,
+    Loaded(Arc<Dependencies>),
+    Failed,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum CarouselPage {
     First,
@@ -129,37 +204,89 @@ pub struct PkgInitModel {
     pub description: Option<String>,
     pub version: Option<String>,
     pub icon: Option<String>,
-    pub screenshots: Vec<String>,
+    pub screenshots: Vec<Screenshot>,
+    /// The content rating marks it as mature
+    pub mature: bool,
     pub homepage: Option<String>,
+    pub bugtracker: Option<String>,
     pub licenses: Vec<License>,
     pub platforms: Vec<String>,
+    pub supported: bool,
     pub maintainers: Vec<PkgMaintainer>,
     pub launchable: Option<String>,
+    pub developer: Option<Developer>,
+    pub versions: PkgVersions,
+    pub position: Option<Position>,
+}
+
+/// Versions of a package across the cached package sets.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PkgVersions {
+    pub unstable: Option<String>,
+    /// Release channel name and the version in it
+    pub stable: Option<(String, String)>,
+    pub user: Option<String>,
+    pub system: Option<String>,
 }
 
 #[derive(Debug)]
 pub enum PkgMsg {
     UpdateConfig(NixDataConfig),
+    UpdateNscConfig(NscConfig),
     UpdatePkgTypes(SystemPkgs, UserPkgs),
+    SetHost(Option<String>),
     Open(Box<PkgInitModel>),
     LoadScreenshot(String, usize, String),
     SetError(String, usize),
     SetCarouselPage(CarouselPage),
     OpenHomepage,
+    /// Ctrl+I, installs with the current install type
+    InstallShortcut,
+    /// Ctrl+Delete, removes with the current install type
+    RemoveShortcut,
+    OpenBugtracker,
+    SetNote(String),
+    CopyInstallCommand,
+    CopySystemNote,
+    OpenGpuVariant,
+    LoadExpression,
+    OpenExpression,
+    LoadDependencies,
     Close,
     InstallUser,
+    /// Install as is, or bundled with its interpreter
+    QueueInstallUser(PkgAction),
     RemoveUser,
+    RevertUser,
     InstallSystem,
     RemoveSystem,
     Cancel,
-    CancelFinished,
+    CancelFinished(WorkPkg),
+    /// Another process holds the user profile, the work starts once it lets go
+    ProfileLocked(WorkPkg),
+    ProfileUnlocked(WorkPkg),
+    /// Evaluation warnings printed while working on a package
+    SetWarnings(String, Vec<String>),
+    DismissWarnings,
+    /// Binary caches the running operation fetched from, sent just before it finishes
+    SetDownloads(InstallType, Vec<SubstituterUse>),
+    /// Desktop IDs of launchers that clash with the ones an install added
+    HideLaunchers(Vec<String>),
+    UnhideLaunchers(Vec<String>),
     FinishedProcess(WorkPkg),
     FailedProcess(WorkPkg),
+    /// nixpkgs refused to install the package until it is allowed
+    RequestAllow(WorkPkg, AllowKind, String),
+    Allow(WorkPkg, AllowKind, String),
     Launch,
     NixRun,
     NixShell,
     SetInstallType(InstallType),
-    AddToQueue(WorkPkg),
+    SetExtraArgs(String),
+    SetImpure(bool),
+    /// Run an operation the main window took off the queue
+    Process(WorkPkg),
+    SetQueue(WorkQueue),
     UpdateOnline(bool)
 }
 
@@ -167,6 +294,19 @@ pub enum PkgMsg {
 pub enum PkgAsyncMsg {
     LoadScreenshot(String, usize, String),
     SetError(String, usize),
+    SetPreviousVersion(String, Option<(String, String)>),
+    SetExpression(String, NixExpression),
+    SetDependencies(String, DependencyTree),
+    /// Package, domain and path of its favicon
+    SetFavicon(String, String, String),
+    /// What installing the package would download, if that could be worked out
+    SetClosureSize(String, Option<ClosureSize>, Vec<String>),
+    /// Launchers that were hidden, so the user can bring them back
+    LaunchersHidden(Vec<String>),
+    /// Work to start once the share directory was read, along with what it had
+    StartWork(WorkPkg, Option<ShareSnapshot>),
+    /// What an install added to the desktop, with the caches it downloaded from
+    SetIntegration(String, DesktopIntegration, Vec<SubstituterUse>),
 }
 
 #[derive(Debug)]
@@ -174,6 +314,10 @@ pub struct PkgPageInit {
     pub syspkgs: SystemPkgs,
     pub userpkgs: UserPkgs,
     pub config: NixDataConfig,
+    pub nscconfig: NscConfig,
+    pub policy: Policy,
+    /// Nix isn't available, so packages can only be browsed
+    pub readonly: bool,
     pub online: bool
 }
 
@@ -189,6 +333,17 @@ impl Component for PkgModel {
         #[name(pkg_window)]
         gtk::Box {
             set_orientation: gtk::Orientation::Vertical,
+            add_controller = gtk::ShortcutController {
+                set_scope: gtk::ShortcutScope::Global,
+                add_shortcut = gtk::Shortcut {
+                    set_trigger: gtk::ShortcutTrigger::parse_string("<Control>i"),
+                    set_action: Some(shortcutaction(&sender, || PkgMsg::InstallShortcut)),
+                },
+                add_shortcut = gtk::Shortcut {
+                    set_trigger: gtk::ShortcutTrigger::parse_string("<Control>Delete"),
+                    set_action: Some(shortcutaction(&sender, || PkgMsg::RemoveShortcut)),
+                },
+            },
             adw::HeaderBar {
                 pack_start = &gtk::Button {
                     add_css_class: "flat",
@@ -210,17 +365,17 @@ impl Component for PkgModel {
                     set_visible: model.syspkgtype != SystemPkgs::None,
 
                     #[watch]
-                    set_label: match model.userpkgtype {
+                    set_label: &match model.userpkgtype {
                         UserPkgs::Env => {
                             match model.installtype {
-                                InstallType::User => "User (nix-env)",
-                                InstallType::System => "System (configuration.nix)",
+                                InstallType::User => gettext("User (nix-env)"),
+                                InstallType::System => gettext("System (configuration.nix)"),
                             }
                         }
                         UserPkgs::Profile => {
                             match model.installtype {
-                                InstallType::User => "User (nix profile)",
-                                InstallType::System => "System (configuration.nix)",
+                                InstallType::User => gettext("User (nix profile)"),
+                                InstallType::System => gettext("System (configuration.nix)"),
                             }
                         }
                     },
@@ -299,6 +454,24 @@ impl Component for PkgModel {
                                             #[watch]
                                             set_label: &model.pkg,
                                         },
+                                        gtk::Box {
+                                            set_spacing: 5,
+                                            #[watch]
+                                            set_visible: model.developer.is_some(),
+                                            gtk::Label {
+                                                set_halign: gtk::Align::Start,
+                                                set_wrap: true,
+                                                #[watch]
+                                                set_label: model.developer.as_ref().map(|x| x.name.as_str()).unwrap_or_default(),
+                                            },
+                                            gtk::Image {
+                                                add_css_class: "accent",
+                                                set_icon_name: Some("emblem-ok-symbolic"),
+                                                set_tooltip_text: Some(&gettext("Verified developer, the app ID matches the homepage")),
+                                                #[watch]
+                                                set_visible: model.developer.as_ref().map(|x| x.verified).unwrap_or(false),
+                                            },
+                                        },
                                         gtk::Label {
                                             add_css_class: "dim-label",
                                             set_halign: gtk::Align::Start,
@@ -306,7 +479,47 @@ impl Component for PkgModel {
                                             set_wrap_mode: pango::WrapMode::WordChar,
                                             set_natural_wrap_mode: gtk::NaturalWrapMode::Word,
                                             #[watch]
-                                            set_label: &model.version.clone().unwrap_or_else(|| "Unknown".to_string()),
+                                            set_label: &model.version.clone().unwrap_or_else(|| gettext("Unknown")),
+                                        },
+                                        gtk::Label {
+                                            add_css_class: "warning",
+                                            set_halign: gtk::Align::Start,
+                                            set_wrap: true,
+                                            #[watch]
+                                            set_visible: !model.supported,
+                                            set_label: &gettextf("Not available for {}", &[&util::currentsystem()]),
+                                        },
+                                        gtk::Label {
+                                            add_css_class: "warning",
+                                            set_halign: gtk::Align::Start,
+                                            set_wrap: true,
+                                            #[watch]
+                                            set_visible: model.displaynote.is_some(),
+                                            #[watch]
+                                            set_label: model.displaynote.as_deref().unwrap_or_default(),
+                                        },
+                                        gtk::Box {
+                                            set_spacing: 5,
+                                            #[watch]
+                                            set_visible: model.gpunote.is_some(),
+                                            gtk::Label {
+                                                #[watch]
+                                                set_css_classes: if model.gpunote.as_ref().map(|x| x.variant.is_some()).unwrap_or_default() { &["dim-label"] } else { &["warning"] },
+                                                set_halign: gtk::Align::Start,
+                                                set_wrap: true,
+                                                #[watch]
+                                                set_label: model.gpunote.as_ref().map(|x| x.note.as_str()).unwrap_or_default(),
+                                            },
+                                            gtk::Button {
+                                                add_css_class: "flat",
+                                                set_valign: gtk::Align::Center,
+                                                set_label: &gettext("View"),
+                                                #[watch]
+                                                set_visible: model.gpunote.as_ref().map(|x| x.variant.is_some()).unwrap_or_default(),
+                                                connect_clicked[sender] => move |_| {
+                                                    sender.input(PkgMsg::OpenGpuVariant);
+                                                }
+                                            }
                                         },
                                     },
                                 },
@@ -315,218 +528,251 @@ impl Component for PkgModel {
                                 append = &gtk::FlowBoxChild {
                                     set_halign: gtk::Align::End,
                                     gtk::Box {
-                                        set_halign: gtk::Align::End,
-                                        set_spacing: 5,
-                                        match model.installtype {
-                                            InstallType::User => {
-                                                gtk::Box {
-                                                    #[name(userinstallstack)]
-                                                    if model.workqueue.iter().any(|x| x.pkg == model.pkg && x.pkgtype == InstallType::User) /*model.installinguserpkgs.contains(&model.pkg)*/ {
-                                                        gtk::Box {
-                                                            gtk::Spinner {
-                                                                set_halign: gtk::Align::End,
-                                                                #[watch]
-                                                                set_spinning: true, //model.installinguserpkgs.contains(&model.pkg),
-                                                                set_size_request: (32, 32),
-                                                                set_can_focus: false,
-                                                            },
-                                                            gtk::Button {
-                                                                set_halign: gtk::Align::End,
-                                                                set_valign: gtk::Align::Center,
-                                                                set_can_focus: false,
-                                                                set_width_request: 105,
-                                                                set_label: "Cancel",
-                                                                connect_clicked[sender] => move |_| {
-                                                                    sender.input(PkgMsg::Cancel)
+                                        gtk::Button {
+                                            add_css_class: "suggested-action",
+                                            set_valign: gtk::Align::Center,
+                                            set_label: &gettext("Copy Install Command"),
+                                            set_tooltip_text: Some(&gettext("Nix is not available on this computer")),
+                                            #[watch]
+                                            set_visible: model.readonly,
+                                            connect_clicked[sender] => move |_| {
+                                                sender.input(PkgMsg::CopyInstallCommand);
+                                            }
+                                        },
+                                        gtk::Box {
+                                        #[watch]
+                                        set_visible: !model.readonly,
+                                            set_halign: gtk::Align::End,
+                                            set_spacing: 5,
+                                            match model.installtype {
+                                                InstallType::User => {
+                                                    gtk::Box {
+                                                        #[name(userinstallstack)]
+                                                        if model.workqueue.isbusy(&model.pkg, WorkLane::User) {
+                                                            gtk::Box {
+                                                                gtk::Spinner {
+                                                                    set_halign: gtk::Align::End,
+                                                                    #[watch]
+                                                                    set_spinning: true, //model.installinguserpkgs.contains(&model.pkg),
+                                                                    set_size_request: (32, 32),
+                                                                    set_can_focus: false,
                                                                 },
-                                                            }
-                                                        }                                                   
-                                                    } else if model.installeduserpkgs.contains(match model.userpkgtype { UserPkgs::Env => &model.pname, UserPkgs::Profile => &model.pkg }) {
-                                                        gtk::Box {
-                                                            set_halign: gtk::Align::End,
-                                                            set_valign: gtk::Align::Center,
-                                                            set_spacing: 10,
-                                                            gtk::Button {
-                                                                #[watch]
-                                                                set_css_classes: if model.launchable.is_some() { &["suggested-action"] } else { &[] },
+                                                                gtk::Button {
+                                                                    set_halign: gtk::Align::End,
+                                                                    set_valign: gtk::Align::Center,
+                                                                    set_width_request: 105,
+                                                                    set_label: &gettext("Cancel"),
+                                                                    connect_clicked[sender] => move |_| {
+                                                                        sender.input(PkgMsg::Cancel)
+                                                                    },
+                                                                }
+                                                            }                                                   
+                                                        } else if model.installeduserpkgs.contains(match model.userpkgtype { UserPkgs::Env => &model.pname, UserPkgs::Profile => &model.pkg }) {
+                                                            gtk::Box {
                                                                 set_halign: gtk::Align::End,
                                                                 set_valign: gtk::Align::Center,
-                                                                set_can_focus: false,
-                                                                set_width_request: 105,
-                                                                #[watch]
-                                                                set_label: if model.launchable.is_some() { "Open" } else { "Installed" },
-                                                                #[watch]
-                                                                set_sensitive: model.launchable.is_some(),
-                                                                connect_clicked[sender] => move |_| {
-                                                                    sender.input(PkgMsg::Launch)
+                                                                set_spacing: 10,
+                                                                gtk::Button {
+                                                                    #[watch]
+                                                                    set_css_classes: if model.launchable.is_some() { &["suggested-action"] } else { &[] },
+                                                                    set_halign: gtk::Align::End,
+                                                                    set_valign: gtk::Align::Center,
+                                                                    set_width_request: 105,
+                                                                    #[watch]
+                                                                    set_label: &if model.launchable.is_some() { gettext("_Open") } else { gettext("Installed") },
+                                                                    set_use_underline: true,
+                                                                    #[watch]
+                                                                    set_sensitive: model.launchable.is_some(),
+                                                                    connect_clicked[sender] => move |_| {
+                                                                        sender.input(PkgMsg::Launch)
+                                                                    }
+                                                                },
+                                                                gtk::Button {
+                                                                    set_halign: gtk::Align::End,
+                                                                    set_icon_name: "edit-undo-symbolic",
+                                                                    #[watch]
+                                                                    set_visible: model.previousversion.is_some() && !model.policy.noremove,
+                                                                    #[watch]
+                                                                    set_tooltip_text: model.previousversion.as_ref().map(|(v, _)| gettextf("Revert to previous version ({})", &[v])).as_deref(),
+                                                                    connect_clicked[sender] => move |_| {
+                                                                        sender.input(PkgMsg::RevertUser)
+                                                                    }
+                                                                },
+                                                                gtk::Button {
+                                                                    set_halign: gtk::Align::End,
+                                                                    add_css_class: "destructive-action",
+                                                                    set_icon_name: "user-trash-symbolic",
+                                                                    set_tooltip_text: Some(&gettext("Remove (Ctrl+Delete)")),
+                                                                    set_visible: !model.policy.noremove,
+                                                                    connect_clicked[sender] => move |_| {
+                                                                        sender.input(PkgMsg::RemoveUser)
+                                                                    }
                                                                 }
-                                                            },
-                                                            gtk::Button {
+                                                            }
+                                                        // } else if !model.installinguserpkgs.is_empty() {
+                                                        //     gtk::Box {
+                                                        //         gtk::Button {
+                                                        //             set_halign: gtk::Align::End,
+                                                        //             set_valign: gtk::Align::Center,
+                                                        //             set_can_focus: false,
+                                                        //             set_width_request: 105,
+                                                        //             set_label: "Busy",
+                                                        //             set_sensitive: false,
+                                                        //         }
+                                                        //     }
+                                                        } else if !model.online {
+                                                            gtk::Box {
+                                                                set_orientation: gtk::Orientation::Horizontal,
+                                                                set_spacing: 10,
                                                                 set_halign: gtk::Align::End,
-                                                                add_css_class: "destructive-action",
-                                                                set_icon_name: "user-trash-symbolic",
-                                                                set_can_focus: false,
-                                                                connect_clicked[sender] => move |_| {
-                                                                    sender.input(PkgMsg::RemoveUser)
+                                                                gtk::Button {
+                                                                    set_halign: gtk::Align::End,
+                                                                    set_valign: gtk::Align::Center,
+                                                                    add_css_class: "error",
+                                                                    set_label: &gettext("Offline"),
+                                                                    set_can_target: false,
+                                                                },
+                                                                gtk::Button {
+                                                                    set_halign: gtk::Align::End,
+                                                                    set_valign: gtk::Align::Center,
+                                                                    set_icon_name: "nsc-refresh-symbolic",
+                                                                    connect_clicked[sender] => move |_| {
+                                                                        sender.output(AppMsg::CheckNetwork);
+                                                                    }
                                                                 }
                                                             }
-                                                        }
-                                                    // } else if !model.installinguserpkgs.is_empty() {
-                                                    //     gtk::Box {
-                                                    //         gtk::Button {
-                                                    //             set_halign: gtk::Align::End,
-                                                    //             set_valign: gtk::Align::Center,
-                                                    //             set_can_focus: false,
-                                                    //             set_width_request: 105,
-                                                    //             set_label: "Busy",
-                                                    //             set_sensitive: false,
-                                                    //         }
-                                                    //     }
-                                                    } else if !model.online {
-                                                        gtk::Box {
-                                                            set_orientation: gtk::Orientation::Horizontal,
-                                                            set_spacing: 10,
-                                                            set_halign: gtk::Align::End,
-                                                            gtk::Button {
-                                                                set_halign: gtk::Align::End,
-                                                                set_valign: gtk::Align::Center,
-                                                                add_css_class: "error",
-                                                                set_label: "Offline",
-                                                                set_can_target: false,
-                                                            },
-                                                            gtk::Button {
+                                                        } else {
+                                                            adw::SplitButton {
+                                                                add_css_class: "suggested-action",
                                                                 set_halign: gtk::Align::End,
                                                                 set_valign: gtk::Align::Center,
-                                                                set_icon_name: "nsc-refresh-symbolic",
+                                                                #[watch]
+                                                                set_label: &if model.sizing { gettext("Checking…") } else { gettext("_Install") },
+                                                                set_use_underline: true,
+                                                                set_width_request: 105,
+                                                                #[watch]
+                                                                set_sensitive: model.supported && model.blockedbypolicy().is_none() && !model.sizing,
+                                                                #[watch]
+                                                                set_tooltip_text: model.blockedbypolicy().as_deref(),
                                                                 connect_clicked[sender] => move |_| {
-                                                                    sender.output(AppMsg::CheckNetwork);
-                                                                }
+                                                                    sender.input(PkgMsg::InstallUser);
+                                                                },
+                                                                // #[watch]
+                                                                // set_visible: !model.installeduserpkgs.contains(&model.pname) && !model.installinguserpkgs.contains(&model.pkg),
+                                                                #[wrap(Some)]
+                                                                set_popover = &gtk::PopoverMenu::from_model(Some(&runaction)) {}
                                                             }
                                                         }
-                                                    } else {
-                                                        adw::SplitButton {
-                                                            add_css_class: "suggested-action",
-                                                            set_halign: gtk::Align::End,
-                                                            set_valign: gtk::Align::Center,
-                                                            set_can_focus: false,
-                                                            set_label: "Install",
-                                                            set_width_request: 105,
-                                                            connect_clicked[sender] => move |_| {
-                                                                sender.input(PkgMsg::InstallUser);
-                                                            },
-                                                            // #[watch]
-                                                            // set_visible: !model.installeduserpkgs.contains(&model.pname) && !model.installinguserpkgs.contains(&model.pkg),
-                                                            #[wrap(Some)]
-                                                            set_popover = &gtk::PopoverMenu::from_model(Some(&runaction)) {}
-                                                        }
                                                     }
                                                 }
-                                            }
-                                            InstallType::System => {
-                                                gtk::Box {
-                                                    #[name(systeminstallstack)]
-                                                    if model.workqueue.iter().any(|x| x.pkg == model.pkg && x.pkgtype == InstallType::System) {
-                                                        gtk::Box {
-                                                            gtk::Spinner {
-                                                                set_halign: gtk::Align::End,
-                                                                #[watch]
-                                                                set_spinning: true, //model.installingsystempkgs.contains(&model.pkg),
-                                                                set_size_request: (32, 32),
-                                                                set_can_focus: false,
-                                                            },
-                                                            gtk::Button {
+                                                InstallType::System => {
+                                                    gtk::Box {
+                                                        #[name(systeminstallstack)]
+                                                        if model.workqueue.isbusy(&model.pkg, WorkLane::System) {
+                                                            gtk::Box {
+                                                                gtk::Spinner {
+                                                                    set_halign: gtk::Align::End,
+                                                                    #[watch]
+                                                                    set_spinning: true, //model.installingsystempkgs.contains(&model.pkg),
+                                                                    set_size_request: (32, 32),
+                                                                    set_can_focus: false,
+                                                                },
+                                                                gtk::Button {
+                                                                    set_halign: gtk::Align::End,
+                                                                    set_valign: gtk::Align::Center,
+                                                                    set_width_request: 105,
+                                                                    set_label: &gettext("Cancel"),
+                                                                    #[watch]
+                                                                    set_sensitive: !model.workqueue.running().any(|w| w.pkg == model.pkg && w.pkgtype == InstallType::System),
+                                                                    connect_clicked[sender] => move |_| {
+                                                                        sender.input(PkgMsg::Cancel)
+                                                                    },
+                                                                }
+                                                            }                                                   
+                                                        } else if model.installedsystempkgs.contains(&model.pkg) {
+                                                            gtk::Box {
                                                                 set_halign: gtk::Align::End,
                                                                 set_valign: gtk::Align::Center,
-                                                                set_can_focus: false,
-                                                                set_width_request: 105,
-                                                                set_label: "Cancel",
-                                                                #[watch]
-                                                                set_sensitive: if let Some(w) = model.workqueue.iter().next() { w.pkg != model.pkg } else {
-                                                                    false
+                                                                set_spacing: 10,
+                                                                gtk::Button {
+                                                                    #[watch]
+                                                                    set_css_classes: if model.launchable.is_some() { &["suggested-action"] } else { &[] },
+                                                                    set_halign: gtk::Align::End,
+                                                                    set_valign: gtk::Align::Center,
+                                                                    set_width_request: 105,
+                                                                    #[watch]
+                                                                    set_label: &if model.launchable.is_some() { gettext("_Open") } else { gettext("Installed") },
+                                                                    set_use_underline: true,
+                                                                    #[watch]
+                                                                    set_sensitive: model.launchable.is_some(),
+                                                                    connect_clicked[sender] => move |_| {
+                                                                        sender.input(PkgMsg::Launch)
+                                                                    }
                                                                 },
-                                                                connect_clicked[sender] => move |_| {
-                                                                    sender.input(PkgMsg::Cancel)
+                                                                gtk::Button {
+                                                                    set_halign: gtk::Align::End,
+                                                                    add_css_class: "destructive-action",
+                                                                    set_icon_name: "user-trash-symbolic",
+                                                                    set_tooltip_text: Some(&gettext("Remove (Ctrl+Delete)")),
+                                                                    set_visible: !model.policy.noremove,
+                                                                    connect_clicked[sender] => move |_| {
+                                                                        sender.input(PkgMsg::RemoveSystem)
+                                                                    }
+                                                                }
+                                                            }
+                                                        // } else if !model.installingsystempkgs.is_empty() {
+                                                        //     gtk::Box {
+                                                        //         gtk::Button {
+                                                        //             set_halign: gtk::Align::End,
+                                                        //             set_valign: gtk::Align::Center,
+                                                        //             set_can_focus: false,
+                                                        //             set_width_request: 105,
+                                                        //             set_label: "Busy",
+                                                        //             set_sensitive: false,
+                                                        //         }
+                                                        //     }
+                                                        } else if !model.online {
+                                                            gtk::Box {
+                                                                set_orientation: gtk::Orientation::Horizontal,
+                                                                set_spacing: 10,
+                                                                set_halign: gtk::Align::End,
+                                                                gtk::Button {
+                                                                    set_halign: gtk::Align::End,
+                                                                    set_valign: gtk::Align::Center,
+                                                                    add_css_class: "error",
+                                                                    set_label: &gettext("Offline"),
+                                                                    set_can_target: false,
                                                                 },
+                                                                gtk::Button {
+                                                                    set_halign: gtk::Align::End,
+                                                                    set_valign: gtk::Align::Center,
+                                                                    set_icon_name: "nsc-refresh-symbolic",
+                                                                    connect_clicked[sender] => move |_| {
+                                                                        sender.output(AppMsg::CheckNetwork);
+                                                                    }
+                                                                }
                                                             }
-                                                        }                                                   
-                                                    } else if model.installedsystempkgs.contains(&model.pkg) {
-                                                        gtk::Box {
-                                                            set_halign: gtk::Align::End,
-                                                            set_valign: gtk::Align::Center,
-                                                            set_spacing: 10,
-                                                            gtk::Button {
-                                                                #[watch]
-                                                                set_css_classes: if model.launchable.is_some() { &["suggested-action"] } else { &[] },
+                                                        } else {
+                                                            adw::SplitButton {
+                                                                add_css_class: "suggested-action",
                                                                 set_halign: gtk::Align::End,
                                                                 set_valign: gtk::Align::Center,
-                                                                set_can_focus: false,
+                                                                set_label: &gettext("_Install"),
+                                                                set_use_underline: true,
                                                                 set_width_request: 105,
                                                                 #[watch]
-                                                                set_label: if model.launchable.is_some() { "Open" } else { "Installed" },
+                                                                set_sensitive: model.supported && model.blockedbypolicy().is_none(),
                                                                 #[watch]
-                                                                set_sensitive: model.launchable.is_some(),
-                                                                connect_clicked[sender] => move |_| {
-                                                                    sender.input(PkgMsg::Launch)
-                                                                }
-                                                            },
-                                                            gtk::Button {
-                                                                set_halign: gtk::Align::End,
-                                                                add_css_class: "destructive-action",
-                                                                set_icon_name: "user-trash-symbolic",
-                                                                set_can_focus: false,
+                                                                set_tooltip_text: model.blockedbypolicy().as_deref(),
                                                                 connect_clicked[sender] => move |_| {
-                                                                    sender.input(PkgMsg::RemoveSystem)
-                                                                }
-                                                            }
-                                                        }
-                                                    // } else if !model.installingsystempkgs.is_empty() {
-                                                    //     gtk::Box {
-                                                    //         gtk::Button {
-                                                    //             set_halign: gtk::Align::End,
-                                                    //             set_valign: gtk::Align::Center,
-                                                    //             set_can_focus: false,
-                                                    //             set_width_request: 105,
-                                                    //             set_label: "Busy",
-                                                    //             set_sensitive: false,
-                                                    //         }
-                                                    //     }
-                                                    } else if !model.online {
-                                                        gtk::Box {
-                                                            set_orientation: gtk::Orientation::Horizontal,
-                                                            set_spacing: 10,
-                                                            set_halign: gtk::Align::End,
-                                                            gtk::Button {
-                                                                set_halign: gtk::Align::End,
-                                                                set_valign: gtk::Align::Center,
-                                                                add_css_class: "error",
-                                                                set_label: "Offline",
-                                                                set_can_target: false,
-                                                            },
-                                                            gtk::Button {
-                                                                set_halign: gtk::Align::End,
-                                                                set_valign: gtk::Align::Center,
-                                                                set_icon_name: "nsc-refresh-symbolic",
-                                                                connect_clicked[sender] => move |_| {
-                                                                    sender.output(AppMsg::CheckNetwork);
-                                                                }
+                                                                    sender.input(PkgMsg::InstallSystem);
+                                                                },
+                                                                // #[watch]
+                                                                // set_visible: !model.installedsystempkgs.contains(&model.pname) && !model.installingsystempkgs.contains(&model.pkg),
+                                                                #[wrap(Some)]
+                                                                set_popover = &gtk::PopoverMenu::from_model(Some(&runaction)) {}
                                                             }
                                                         }
-                                                    } else {
-                                                        adw::SplitButton {
-                                                            add_css_class: "suggested-action",
-                                                            set_halign: gtk::Align::End,
-                                                            set_valign: gtk::Align::Center,
-                                                            set_can_focus: false,
-                                                            set_label: "Install",
-                                                            set_width_request: 105,
-                                                            connect_clicked[sender] => move |_| {
-                                                                sender.input(PkgMsg::InstallSystem);
-                                                            },
-                                                            // #[watch]
-                                                            // set_visible: !model.installedsystempkgs.contains(&model.pname) && !model.installingsystempkgs.contains(&model.pkg),
-                                                            #[wrap(Some)]
-                                                            set_popover = &gtk::PopoverMenu::from_model(Some(&runaction)) {}
-                                                        }
                                                     }
                                                 }
                                             }
@@ -536,450 +782,893 @@ impl Component for PkgModel {
                             }
                         }
                     },
-                    gtk::Box {
-                        set_orientation: gtk::Orientation::Vertical,
-                        set_valign: gtk::Align::Start,
-                        add_css_class: "view",
-                        add_css_class: "frame",
-                        add_css_class: "scrnbox",
+                    adw::Clamp {
+                        set_maximum_size: 1000,
+                        set_margin_start: 15,
+                        set_margin_end: 15,
+                        set_margin_bottom: 10,
                         #[watch]
-                        set_visible: !model.screenshots.is_empty(),
-                        gtk::Overlay {
-                            set_valign: gtk::Align::Start,
-                            #[local_ref]
-                            scrnfactory -> adw::Carousel {
-                                set_valign: gtk::Align::Fill,
+                        set_visible: !model.warnings.is_empty(),
+                        gtk::Box {
+                            add_css_class: "card",
+                            set_spacing: 10,
+                            gtk::Image {
+                                add_css_class: "warning",
+                                set_margin_top: 15,
+                                set_margin_start: 15,
+                                set_valign: gtk::Align::Start,
+                                set_icon_name: Some("dialog-warning-symbolic"),
+                            },
+                            gtk::Box {
+                                set_orientation: gtk::Orientation::Vertical,
                                 set_hexpand: true,
-                                set_vexpand: true,
-                                set_height_request: 400,
-                                set_allow_scroll_wheel: false,
-                                connect_page_changed[sender] => move |x, _| {
-                                    let n = adw::Carousel::n_pages(x);
-                                    let i = adw::Carousel::position(x) as u32;
-                                    if i == 0 && n == 1 {
-                                        sender.input(PkgMsg::SetCarouselPage(CarouselPage::Single));
-                                    } else if i == 0 {
-                                        sender.input(PkgMsg::SetCarouselPage(CarouselPage::First));
-                                    } else if i == n - 1 {
-                                        sender.input(PkgMsg::SetCarouselPage(CarouselPage::Last));
-                                    } else {
-                                        sender.input(PkgMsg::SetCarouselPage(CarouselPage::Middle));
-                                    }
+                                set_spacing: 5,
+                                set_margin_top: 15,
+                                set_margin_bottom: 15,
+                                gtk::Label {
+                                    add_css_class: "heading",
+                                    set_halign: gtk::Align::Start,
+                                    set_wrap: true,
+                                    set_label: &gettext("Evaluation Warnings"),
                                 },
-                            },
-                            add_overlay = &gtk::Revealer {
-                                set_transition_type: gtk::RevealerTransitionType::Crossfade,
-                                #[watch]
-                                set_reveal_child: model.carpage != CarouselPage::First && model.carpage != CarouselPage::Single,
-                                set_halign: gtk::Align::Start,
-                                set_valign: gtk::Align::Fill,
-                                gtk::Button {
-                                    set_can_focus: false,
-                                    set_margin_all: 15,
-                                    set_height_request: 40,
-                                    set_width_request: 40,
-                                    add_css_class: "circular",
-                                    add_css_class: "osd",
+                                gtk::Label {
                                     set_halign: gtk::Align::Start,
-                                    set_valign: gtk::Align::Center,
-                                    set_icon_name: "go-previous-symbolic",
-                                    connect_clicked[sender, scrnfactory] => move |_| {
-                                        let i = adw::Carousel::position(&scrnfactory) as u32;
-                                        if i > 0 {
-                                            let w = scrnfactory.nth_page(i-1);
-                                            scrnfactory.scroll_to(&w, true);
-                                        }
-                                        if i == 1 {
-                                            sender.input(PkgMsg::SetCarouselPage(CarouselPage::First));
-                                        } else if i > 0 {
-                                            sender.input(PkgMsg::SetCarouselPage(CarouselPage::Middle));
-                                        }
-                                    }
-                                }
+                                    set_xalign: 0.0,
+                                    set_wrap: true,
+                                    set_wrap_mode: pango::WrapMode::WordChar,
+                                    set_selectable: true,
+                                    #[watch]
+                                    set_label: &model.warnings.join("\n"),
+                                },
                             },
-                            add_overlay = &gtk::Revealer {
-                                set_transition_type: gtk::RevealerTransitionType::Crossfade,
-                                #[watch]
-                                set_reveal_child: model.carpage != CarouselPage::Last && model.carpage != CarouselPage::Single,
-                                set_halign: gtk::Align::End,
-                                set_valign: gtk::Align::Fill,
-                                gtk::Button {
-                                    set_can_focus: false,
-                                    set_margin_all: 15,
-                                    set_height_request: 40,
-                                    set_width_request: 40,
-                                    add_css_class: "circular",
-                                    add_css_class: "osd",
-                                    set_halign: gtk::Align::End,
-                                    set_valign: gtk::Align::Center,
-                                    set_icon_name: "go-next-symbolic",
-                                    connect_clicked[sender, scrnfactory] => move |_| {
-                                        let i = adw::Carousel::position(&scrnfactory) as u32;
-                                        if i < scrnfactory.n_pages() -1 {
-                                            let w = scrnfactory.nth_page(i+1);
-                                            scrnfactory.scroll_to(&w, true);
-                                        }
-                                        let n = scrnfactory.n_pages() as u32;
-                                        if i == n - 2 {
-                                            sender.input(PkgMsg::SetCarouselPage(CarouselPage::Last));
-                                        } else if i <= n - 2 {
-                                            sender.input(PkgMsg::SetCarouselPage(CarouselPage::Middle));
-                                        } else {
-                                            sender.input(PkgMsg::SetCarouselPage(CarouselPage::Last));
-                                        }
-                                    }
+                            gtk::Button {
+                                add_css_class: "flat",
+                                add_css_class: "circular",
+                                set_margin_top: 10,
+                                set_margin_end: 10,
+                                set_valign: gtk::Align::Start,
+                                set_icon_name: "window-close-symbolic",
+                                set_tooltip_text: Some(&gettext("Dismiss")),
+                                connect_clicked[sender] => move |_| {
+                                    sender.input(PkgMsg::DismissWarnings);
                                 }
                             }
-                        },
-                        adw::CarouselIndicatorDots {
-                            set_halign: gtk::Align::Fill,
-                            set_valign: gtk::Align::End,
-                            set_carousel: Some(scrnfactory)
                         }
                     },
                     adw::Clamp {
-                        set_halign: gtk::Align::Fill,
-                        set_valign: gtk::Align::Start,
-                        set_vexpand_set: true,
                         set_maximum_size: 1000,
+                        set_margin_start: 15,
+                        set_margin_end: 15,
+                        set_margin_bottom: 10,
                         #[watch]
-                        set_visible: !(model.summary.is_none() && model.description.is_none()),
+                        set_visible: model.systemnote.is_some() && model.syspkgtype != SystemPkgs::None,
                         gtk::Box {
-                            set_vexpand: true,
-                            set_valign: gtk::Align::Start,
+                            add_css_class: "card",
                             set_orientation: gtk::Orientation::Vertical,
-                            set_margin_all: 15,
                             set_spacing: 10,
-                            gtk::Label {
-                                add_css_class: "title-2",
-                                set_valign: gtk::Align::Start,
-                                set_halign: gtk::Align::Start,
-                                #[watch]
-                                set_label: if let Some(s) = model.summary.as_ref() { s } else { "" },
-                                #[watch]
-                                set_visible: model.summary.is_some(),
-                                set_wrap: true,
-                                set_xalign: 0.0,
-                            },
-                            gtk::Label {
-                                set_valign: gtk::Align::Start,
-                                set_halign: gtk::Align::Start,
-                                #[watch]
-                                set_markup: {
-                                    if let Some(d) = model.description.as_ref() {
-                                        d
-                                    } else { "" }
+                            gtk::Box {
+                                set_margin_top: 15,
+                                set_margin_start: 15,
+                                set_margin_end: 15,
+                                set_spacing: 10,
+                                gtk::Image {
+                                    add_css_class: "accent",
+                                    set_valign: gtk::Align::Start,
+                                    set_icon_name: Some("dialog-information-symbolic"),
                                 },
-                                #[watch]
-                                set_visible: model.description.is_some(),
-                                set_wrap: true,
-                                set_xalign: 0.0,
+                                gtk::Box {
+                                    set_orientation: gtk::Orientation::Vertical,
+                                    set_spacing: 5,
+                                    gtk::Label {
+                                        add_css_class: "heading",
+                                        set_halign: gtk::Align::Start,
+                                        set_wrap: true,
+                                        set_label: &gettext("Needs System Configuration"),
+                                    },
+                                    gtk::Label {
+                                        set_halign: gtk::Align::Start,
+                                        set_xalign: 0.0,
+                                        set_wrap: true,
+                                        #[watch]
+                                        set_label: &format!("{} Installing the package alone is not enough, add this to your configuration.nix and rebuild:", model.systemnote.as_ref().map(|x| x.reason).unwrap_or_default()),
+                                    },
+                                }
                             },
-                        },
+                            gtk::Box {
+                                set_margin_bottom: 15,
+                                set_margin_start: 15,
+                                set_margin_end: 15,
+                                set_spacing: 10,
+                                gtk::Label {
+                                    add_css_class: "monospace",
+                                    set_hexpand: true,
+                                    set_halign: gtk::Align::Start,
+                                    set_xalign: 0.0,
+                                    set_wrap: true,
+                                    set_wrap_mode: pango::WrapMode::WordChar,
+                                    set_selectable: true,
+                                    #[watch]
+                                    set_label: model.systemnote.as_ref().map(|x| x.snippet).unwrap_or_default(),
+                                },
+                                gtk::Button {
+                                    add_css_class: "flat",
+                                    set_valign: gtk::Align::End,
+                                    set_icon_name: "edit-copy-symbolic",
+                                    set_tooltip_text: Some(&gettext("Copy configuration")),
+                                    connect_clicked[sender] => move |_| {
+                                        sender.input(PkgMsg::CopySystemNote);
+                                    }
+                                }
+                            }
+                        }
                     },
                     adw::Clamp {
-                        set_vexpand: true,
-                        set_halign: gtk::Align::Fill,
-                        set_valign: gtk::Align::Start,
                         set_maximum_size: 1000,
-                        #[name(btnbox)]
-                        gtk::FlowBox {
-                            add_css_class: "linked",
-                            set_halign: gtk::Align::Fill,
-                            set_hexpand: true,
-                            set_margin_bottom: 10,
-                            set_homogeneous: true,
-                            set_row_spacing: 5,
-                            set_column_spacing: 4,
-                            set_selection_mode: gtk::SelectionMode::None,
-                            set_max_children_per_line: 2,
-                            append = &gtk::FlowBoxChild {
-                                set_hexpand: true,
-                                gtk::Box {
-                                    set_spacing: 10,
-                                    set_hexpand: true,
-                                    set_homogeneous: true,
-                                    gtk::Button {
+                        set_margin_start: 15,
+                        set_margin_end: 15,
+                        set_margin_bottom: 10,
+                        adw::ViewSwitcher {
+                            set_policy: adw::ViewSwitcherPolicy::Wide,
+                            set_stack: Some(&pkgstack),
+                        }
+                    },
+                    #[name(pkgstack)]
+                    adw::ViewStack {
+                        set_vhomogeneous: false,
+                        add_titled[Some("overview"), &gettext("Overview")] = &gtk::Box {
+                            set_orientation: gtk::Orientation::Vertical,
+                            gtk::Box {
+                                set_orientation: gtk::Orientation::Vertical,
+                                set_valign: gtk::Align::Start,
+                                add_css_class: "view",
+                                add_css_class: "frame",
+                                add_css_class: "scrnbox",
+                                #[watch]
+                                set_visible: !model.screenshots.is_empty(),
+                                gtk::Overlay {
+                                    set_valign: gtk::Align::Start,
+                                    #[local_ref]
+                                    scrnfactory -> adw::Carousel {
+                                        set_valign: gtk::Align::Fill,
                                         set_hexpand: true,
-                                        add_css_class: "card",
-                                        set_height_request: 100,
-                                        set_width_request: 100,
-                                        connect_clicked[sender] => move |_| {
-                                            sender.input(PkgMsg::OpenHomepage)
+                                        set_vexpand: true,
+                                        set_height_request: 400,
+                                        set_allow_scroll_wheel: false,
+                                        connect_page_changed[sender] => move |x, _| {
+                                            let n = adw::Carousel::n_pages(x);
+                                            let i = adw::Carousel::position(x) as u32;
+                                            if i == 0 && n == 1 {
+                                                sender.input(PkgMsg::SetCarouselPage(CarouselPage::Single));
+                                            } else if i == 0 {
+                                                sender.input(PkgMsg::SetCarouselPage(CarouselPage::First));
+                                            } else if i == n - 1 {
+                                                sender.input(PkgMsg::SetCarouselPage(CarouselPage::Last));
+                                            } else {
+                                                sender.input(PkgMsg::SetCarouselPage(CarouselPage::Middle));
+                                            }
                                         },
-                                        gtk::Box {
-                                            set_orientation: gtk::Orientation::Vertical,
-                                            set_halign: gtk::Align::Fill,
-                                            set_valign: gtk::Align::Center,
-                                            set_spacing: 10,
+                                    },
+                                    add_overlay = &gtk::Revealer {
+                                        set_transition_type: gtk::RevealerTransitionType::Crossfade,
+                                        #[watch]
+                                        set_reveal_child: model.carpage != CarouselPage::First && model.carpage != CarouselPage::Single,
+                                        set_halign: gtk::Align::Start,
+                                        set_valign: gtk::Align::Fill,
+                                        gtk::Button {
                                             set_margin_all: 15,
-                                            gtk::Image {
-                                                add_css_class: "accent",
-                                                set_halign: gtk::Align::Center,
-                                                set_icon_name: Some("user-home-symbolic"),
-                                                set_pixel_size: 24,
-                                            },
-                                            gtk::Box {
-                                                set_orientation: gtk::Orientation::Vertical,
-                                                set_halign: gtk::Align::Fill,
-                                                set_valign: gtk::Align::Center,
-                                                set_hexpand: true,
-                                                set_spacing: 5,
-                                                gtk::Label {
-                                                    set_halign: gtk::Align::Center,
-                                                    set_valign: gtk::Align::Center,
-                                                    add_css_class: "heading",
-                                                    set_label: "Homepage"
-                                                },
-                                                gtk::Label {
-                                                    set_halign: gtk::Align::Fill,
-                                                    set_valign: gtk::Align::Center,
-                                                    add_css_class: "caption",
-                                                    add_css_class: "dim-label",
-                                                    set_ellipsize: pango::EllipsizeMode::End,
-                                                    set_lines: 2,
-                                                    set_wrap: true,
-                                                    set_max_width_chars: 0,
-                                                    set_justify: gtk::Justification::Center,
-                                                    #[watch]
-                                                    set_label: if let Some(u) = &model.homepage {
-                                                        u
-                                                    } else {
-                                                        ""
-                                                    },
-                                                    #[watch]
-                                                    set_visible: model.homepage.is_some(),
+                                            set_height_request: 40,
+                                            set_width_request: 40,
+                                            add_css_class: "circular",
+                                            add_css_class: "osd",
+                                            set_halign: gtk::Align::Start,
+                                            set_valign: gtk::Align::Center,
+                                            set_icon_name: "go-previous-symbolic",
+                                            connect_clicked[sender, scrnfactory] => move |_| {
+                                                let i = adw::Carousel::position(&scrnfactory) as u32;
+                                                if i > 0 {
+                                                    let w = scrnfactory.nth_page(i-1);
+                                                    scrnfactory.scroll_to(&w, desktop::animations());
+                                                }
+                                                if i == 1 {
+                                                    sender.input(PkgMsg::SetCarouselPage(CarouselPage::First));
+                                                } else if i > 0 {
+                                                    sender.input(PkgMsg::SetCarouselPage(CarouselPage::Middle));
                                                 }
                                             }
-
                                         }
                                     },
-                                    gtk::Button {
-                                        set_hexpand: true,
-                                        add_css_class: "card",
-                                        set_height_request: 100,
-                                        set_width_request: 100,
-                                        gtk::Box {
-                                            set_orientation: gtk::Orientation::Vertical,
-                                            set_halign: gtk::Align::Fill,
-                                            set_valign: gtk::Align::Center,
-                                            set_spacing: 10,
+                                    add_overlay = &gtk::Revealer {
+                                        set_transition_type: gtk::RevealerTransitionType::Crossfade,
+                                        #[watch]
+                                        set_reveal_child: model.carpage != CarouselPage::Last && model.carpage != CarouselPage::Single,
+                                        set_halign: gtk::Align::End,
+                                        set_valign: gtk::Align::Fill,
+                                        gtk::Button {
                                             set_margin_all: 15,
-                                            gtk::Image {
-                                                #[watch]
-                                                set_css_classes: &[ if model.licenses.iter().any(|x| x.free == Some(false)) { "error" } else if model.licenses.iter().all(|x| x.free == Some(true)) { "success" } else { "warning" } ],
-                                                set_halign: gtk::Align::Center,
-                                                #[watch]
-                                                set_icon_name : if model.licenses.iter().any(|x| x.free == Some(false)) { Some("dialog-warning-symbolic") } else if model.licenses.iter().all(|x| x.free == Some(true)) { Some("emblem-default-symbolic") } else { Some("dialog-question-symbolic") },
-                                                set_pixel_size: 24,
-                                            },
-                                            gtk::Box {
-                                                set_orientation: gtk::Orientation::Vertical,
-                                                set_halign: gtk::Align::Fill,
-                                                set_valign: gtk::Align::Center,
-                                                set_spacing: 5,
-                                                gtk::Label {
-                                                    set_halign: gtk::Align::Center,
-                                                    add_css_class: "heading",
-                                                    #[watch]
-                                                    set_label: if model.licenses.len() > 1 { "Licenses" } else { "License" }
-                                                },
-                                                gtk::Label {
-                                                    set_halign: gtk::Align::Fill,
-                                                    set_hexpand: true,
-                                                    add_css_class: "caption",
-                                                    add_css_class: "dim-label",
-                                                    set_ellipsize: pango::EllipsizeMode::End,
-                                                    set_lines: 2,
-                                                    set_wrap: true,
-                                                    set_max_width_chars: 0,
-                                                    set_justify: gtk::Justification::Center,
-                                                    #[watch]
-                                                    set_label: {
-                                                        let mut s = String::new();
-                                                        for license in model.licenses.iter() {
-                                                            if model.licenses.iter().len() == 1 {
-                                                                if let Some(id) = &license.spdxid {
-                                                                    s.push_str(id)
-                                                                } else {
-                                                                    s.push_str(&license.fullname)
-                                                                }
-                                                            } else if model.licenses.iter().len() == 2 && model.licenses.get(0) == Some(license) {
-                                                                if let Some(id) = &license.spdxid {
-                                                                    let _ = write!(s, "{} ", id);
-                                                                } else {
-                                                                    let _ = write!(s, "{} ", license.fullname);
-                                                                }
-                                                            } else if Some(license) == model.licenses.iter().last() {
-                                                                if let Some(id) = &license.spdxid {
-                                                                    let _ = write!(s, "and {}", id);
-                                                                } else {
-                                                                    let _ = write!(s, "and {}", license.fullname);
-                                                                }
-                                                            } else if let Some(id) = &license.spdxid {
-                                                                let _ = write!(s, "{}, ", id);
-                                                            } else {
-                                                                let _ = write!(s, "{}, ", license.fullname);
-                                                            }
-                                                        }
-                                                        if model.licenses.is_empty() {
-                                                            s.push_str("Unknown");
-                                                        }
-                                                        &s.to_string()
-                                                    },
-                                                    #[watch]
-                                                    set_visible: !model.licenses.is_empty()
+                                            set_height_request: 40,
+                                            set_width_request: 40,
+                                            add_css_class: "circular",
+                                            add_css_class: "osd",
+                                            set_halign: gtk::Align::End,
+                                            set_valign: gtk::Align::Center,
+                                            set_icon_name: "go-next-symbolic",
+                                            connect_clicked[sender, scrnfactory] => move |_| {
+                                                let i = adw::Carousel::position(&scrnfactory) as u32;
+                                                if i < scrnfactory.n_pages() -1 {
+                                                    let w = scrnfactory.nth_page(i+1);
+                                                    scrnfactory.scroll_to(&w, desktop::animations());
+                                                }
+                                                let n = scrnfactory.n_pages() as u32;
+                                                if i == n - 2 {
+                                                    sender.input(PkgMsg::SetCarouselPage(CarouselPage::Last));
+                                                } else if i <= n - 2 {
+                                                    sender.input(PkgMsg::SetCarouselPage(CarouselPage::Middle));
+                                                } else {
+                                                    sender.input(PkgMsg::SetCarouselPage(CarouselPage::Last));
                                                 }
                                             }
                                         }
-                                    },
+                                    }
+                                },
+                                adw::CarouselIndicatorDots {
+                                    set_halign: gtk::Align::Fill,
+                                    set_valign: gtk::Align::End,
+                                    set_carousel: Some(scrnfactory)
                                 }
                             },
-                            append = &gtk::FlowBoxChild {
-                                set_hexpand: true,
+                            adw::Clamp {
+                                set_halign: gtk::Align::Fill,
+                                set_valign: gtk::Align::Start,
+                                set_vexpand_set: true,
+                                set_maximum_size: 1000,
+                                #[watch]
+                                set_visible: !(model.summary.is_none() && model.description.is_none()),
                                 gtk::Box {
+                                    set_vexpand: true,
+                                    set_valign: gtk::Align::Start,
+                                    set_orientation: gtk::Orientation::Vertical,
+                                    set_margin_all: 15,
                                     set_spacing: 10,
+                                    gtk::Label {
+                                        add_css_class: "title-2",
+                                        set_valign: gtk::Align::Start,
+                                        set_halign: gtk::Align::Start,
+                                        #[watch]
+                                        set_label: if let Some(s) = model.summary.as_ref() { s } else { "" },
+                                        #[watch]
+                                        set_visible: model.summary.is_some(),
+                                        set_wrap: true,
+                                        set_xalign: 0.0,
+                                    },
+                                    gtk::Label {
+                                        set_valign: gtk::Align::Start,
+                                        set_halign: gtk::Align::Start,
+                                        #[watch]
+                                        set_markup: {
+                                            if let Some(d) = model.description.as_ref() {
+                                                d
+                                            } else { "" }
+                                        },
+                                        #[watch]
+                                        set_visible: model.description.is_some(),
+                                        set_wrap: true,
+                                        set_xalign: 0.0,
+                                    },
+                                },
+                            },
+                        },
+                        add_titled[Some("details"), &gettext("Details")] = &gtk::Box {
+                            set_orientation: gtk::Orientation::Vertical,
+                            adw::Clamp {
+                                set_vexpand: true,
+                                set_halign: gtk::Align::Fill,
+                                set_valign: gtk::Align::Start,
+                                set_maximum_size: 1000,
+                                #[name(btnbox)]
+                                gtk::FlowBox {
+                                    add_css_class: "linked",
+                                    set_halign: gtk::Align::Fill,
                                     set_hexpand: true,
+                                    set_margin_bottom: 10,
                                     set_homogeneous: true,
-                                    gtk::Button {
+                                    set_row_spacing: 5,
+                                    set_column_spacing: 4,
+                                    set_selection_mode: gtk::SelectionMode::None,
+                                    set_max_children_per_line: 2,
+                                    append = &gtk::FlowBoxChild {
                                         set_hexpand: true,
-                                        add_css_class: "card",
-                                        set_height_request: 100,
-                                        set_width_request: 100,
                                         gtk::Box {
-                                            set_orientation: gtk::Orientation::Vertical,
-                                            set_valign: gtk::Align::Center,
                                             set_spacing: 10,
-                                            set_margin_all: 15,
-                                            gtk::Image {
-                                                add_css_class: "success",
-                                                set_icon_name: Some("video-display-symbolic"),
-                                                set_pixel_size: 24,
-                                            },
-                                            gtk::Box {
-                                                set_orientation: gtk::Orientation::Vertical,
-                                                set_valign: gtk::Align::Center,
-                                                set_spacing: 5,
-                                                gtk::Label {
-                                                    set_halign: gtk::Align::Center,
-                                                    add_css_class: "heading",
-                                                    set_label: "Platforms"
+                                            set_hexpand: true,
+                                            set_homogeneous: true,
+                                            gtk::Button {
+                                                set_hexpand: true,
+                                                add_css_class: "card",
+                                                set_height_request: 100,
+                                                set_width_request: 100,
+                                                connect_clicked[sender] => move |_| {
+                                                    sender.input(PkgMsg::OpenHomepage)
                                                 },
-                                                gtk::Label {
+                                                gtk::Box {
+                                                    set_orientation: gtk::Orientation::Vertical,
                                                     set_halign: gtk::Align::Fill,
-                                                    set_hexpand: true,
-                                                    add_css_class: "caption",
-                                                    add_css_class: "dim-label",
-                                                    set_ellipsize: pango::EllipsizeMode::End,
-                                                    set_lines: 2,
-                                                    set_wrap: true,
-                                                    set_max_width_chars: 0,
-                                                    set_justify: gtk::Justification::Center,
-                                                    #[watch]
-                                                    set_label: {
-                                                        let mut s = String::new();
-                                                        for p in model.platforms.iter() {
-                                                            if model.platforms.iter().len() == 1 {
-                                                                s.push_str(p);
-                                                            } else if model.platforms.iter().len() == 2 && model.platforms.get(0) == Some(p) {
-                                                                let _ = write!(s, "{} ", p);
-                                                            } else if Some(p) == model.platforms.iter().last() {
-                                                                let _ = write!(s, "and {}", p);
-                                                            } else {
-                                                                let _ = write!(s, "{}, ", p);
-                                                            }
-                                                        }
-                                                        if model.platforms.is_empty() {
-                                                            s.push_str("Unknown");
+                                                    set_valign: gtk::Align::Center,
+                                                    set_spacing: 10,
+                                                    set_margin_all: 15,
+                                                    gtk::Image {
+                                                        add_css_class: "accent",
+                                                        set_halign: gtk::Align::Center,
+                                                        #[track(model.changed(PkgModel::favicons()) || model.changed(PkgModel::pkg()))]
+                                                        set_from_gicon: &linkicon(&model.favicons, model.homepage.as_deref(), "user-home-symbolic"),
+                                                        set_pixel_size: 24,
+                                                    },
+                                                    gtk::Box {
+                                                        set_orientation: gtk::Orientation::Vertical,
+                                                        set_halign: gtk::Align::Fill,
+                                                        set_valign: gtk::Align::Center,
+                                                        set_hexpand: true,
+                                                        set_spacing: 5,
+                                                        gtk::Label {
+                                                            set_halign: gtk::Align::Center,
+                                                            set_valign: gtk::Align::Center,
+                                                            add_css_class: "heading",
+                                                            set_label: &gettext("Homepage")
+                                                        },
+                                                        gtk::Label {
+                                                            set_halign: gtk::Align::Fill,
+                                                            set_valign: gtk::Align::Center,
+                                                            add_css_class: "caption",
+                                                            add_css_class: "dim-label",
+                                                            set_ellipsize: pango::EllipsizeMode::End,
+                                                            set_lines: 2,
+                                                            set_wrap: true,
+                                                            set_max_width_chars: 0,
+                                                            set_justify: gtk::Justification::Center,
+                                                            #[watch]
+                                                            set_label: &model.homepage.as_deref().map(|x| favicon::domain(x).unwrap_or_else(|| x.to_string())).unwrap_or_default(),
+                                                            #[watch]
+                                                            set_tooltip_text: model.homepage.as_deref(),
+                                                            #[watch]
+                                                            set_visible: model.homepage.is_some(),
                                                         }
-                                                        &s.to_string()
+                                                    }
+
+                                                }
+                                            },
+                                            gtk::Button {
+                                                set_hexpand: true,
+                                                add_css_class: "card",
+                                                set_height_request: 100,
+                                                set_width_request: 100,
+                                                gtk::Box {
+                                                    set_orientation: gtk::Orientation::Vertical,
+                                                    set_halign: gtk::Align::Fill,
+                                                    set_valign: gtk::Align::Center,
+                                                    set_spacing: 10,
+                                                    set_margin_all: 15,
+                                                    gtk::Image {
+                                                        #[watch]
+                                                        set_css_classes: &[ if model.licenses.iter().any(|x| x.free == Some(false)) { "error" } else if model.licenses.iter().all(|x| x.free == Some(true)) { "success" } else { "warning" } ],
+                                                        set_halign: gtk::Align::Center,
+                                                        #[watch]
+                                                        set_icon_name : if model.licenses.iter().any(|x| x.free == Some(false)) { Some("dialog-warning-symbolic") } else if model.licenses.iter().all(|x| x.free == Some(true)) { Some("emblem-default-symbolic") } else { Some("dialog-question-symbolic") },
+                                                        set_pixel_size: 24,
                                                     },
-                                                    #[watch]
-                                                    set_visible: !model.platforms.is_empty()
+                                                    gtk::Box {
+                                                        set_orientation: gtk::Orientation::Vertical,
+                                                        set_halign: gtk::Align::Fill,
+                                                        set_valign: gtk::Align::Center,
+                                                        set_spacing: 5,
+                                                        gtk::Label {
+                                                            set_halign: gtk::Align::Center,
+                                                            add_css_class: "heading",
+                                                            #[watch]
+                                                            set_label: &ngettext("License", "Licenses", model.licenses.len() as u32)
+                                                        },
+                                                        gtk::Label {
+                                                            set_halign: gtk::Align::Fill,
+                                                            set_hexpand: true,
+                                                            add_css_class: "caption",
+                                                            add_css_class: "dim-label",
+                                                            set_ellipsize: pango::EllipsizeMode::End,
+                                                            set_lines: 2,
+                                                            set_wrap: true,
+                                                            set_max_width_chars: 0,
+                                                            set_justify: gtk::Justification::Center,
+                                                            #[watch]
+                                                            set_label: {
+                                                                let mut s = String::new();
+                                                                for license in model.licenses.iter() {
+                                                                    if model.licenses.iter().len() == 1 {
+                                                                        if let Some(id) = &license.spdxid {
+                                                                            s.push_str(id)
+                                                                        } else {
+                                                                            s.push_str(&license.fullname)
+                                                                        }
+                                                                    } else if model.licenses.iter().len() == 2 && model.licenses.get(0) == Some(license) {
+                                                                        if let Some(id) = &license.spdxid {
+                                                                            let _ = write!(s, "{} ", id);
+                                                                        } else {
+                                                                            let _ = write!(s, "{} ", license.fullname);
+                                                                        }
+                                                                    } else if Some(license) == model.licenses.iter().last() {
+                                                                        if let Some(id) = &license.spdxid {
+                                                                            let _ = write!(s, "and {}", id);
+                                                                        } else {
+                                                                            let _ = write!(s, "and {}", license.fullname);
+                                                                        }
+                                                                    } else if let Some(id) = &license.spdxid {
+                                                                        let _ = write!(s, "{}, ", id);
+                                                                    } else {
+                                                                        let _ = write!(s, "{}, ", license.fullname);
+                                                                    }
+                                                                }
+                                                                if model.licenses.is_empty() {
+                                                                    s.push_str(&gettext("Unknown"));
+                                                                }
+                                                                &s.to_string()
+                                                            },
+                                                            #[watch]
+                                                            set_visible: !model.licenses.is_empty()
+                                                        },
+                                                        gtk::Label {
+                                                            set_halign: gtk::Align::Center,
+                                                            add_css_class: "caption",
+                                                            add_css_class: "warning",
+                                                            set_label: &gettext("Unfree"),
+                                                            #[watch]
+                                                            set_visible: model.licenses.iter().any(|x| x.free == Some(false)),
+                                                        }
+                                                    }
                                                 }
-                                            }
+                                            },
                                         }
                                     },
-                                    gtk::Button {
+                                    append = &gtk::FlowBoxChild {
                                         set_hexpand: true,
-                                        add_css_class: "card",
-                                        set_height_request: 100,
-                                        set_width_request: 100,
                                         gtk::Box {
-                                            set_orientation: gtk::Orientation::Vertical,
-                                            set_halign: gtk::Align::Fill,
-                                            set_valign: gtk::Align::Center,
                                             set_spacing: 10,
-                                            set_margin_all: 15,
-                                            gtk::Image {
-                                                add_css_class: "circular",
-                                                #[watch]
-                                                set_css_classes: &[ if model.maintainers.is_empty() { "error" } else { "accent" } ],
-                                                set_halign: gtk::Align::Center,
-                                                set_icon_name: Some("system-users-symbolic"),
-                                                set_pixel_size: 24,
+                                            set_hexpand: true,
+                                            set_homogeneous: true,
+                                            gtk::Button {
+                                                set_hexpand: true,
+                                                add_css_class: "card",
+                                                set_height_request: 100,
+                                                set_width_request: 100,
+                                                gtk::Box {
+                                                    set_orientation: gtk::Orientation::Vertical,
+                                                    set_valign: gtk::Align::Center,
+                                                    set_spacing: 10,
+                                                    set_margin_all: 15,
+                                                    gtk::Image {
+                                                        add_css_class: "success",
+                                                        set_icon_name: Some("video-display-symbolic"),
+                                                        set_pixel_size: 24,
+                                                    },
+                                                    gtk::Box {
+                                                        set_orientation: gtk::Orientation::Vertical,
+                                                        set_valign: gtk::Align::Center,
+                                                        set_spacing: 5,
+                                                        gtk::Label {
+                                                            set_halign: gtk::Align::Center,
+                                                            add_css_class: "heading",
+                                                            set_label: &gettext("Platforms")
+                                                        },
+                                                        gtk::Label {
+                                                            set_halign: gtk::Align::Fill,
+                                                            set_hexpand: true,
+                                                            add_css_class: "caption",
+                                                            add_css_class: "dim-label",
+                                                            set_ellipsize: pango::EllipsizeMode::End,
+                                                            set_lines: 2,
+                                                            set_wrap: true,
+                                                            set_max_width_chars: 0,
+                                                            set_justify: gtk::Justification::Center,
+                                                            #[watch]
+                                                            set_label: {
+                                                                let mut s = String::new();
+                                                                for p in model.platforms.iter() {
+                                                                    if model.platforms.iter().len() == 1 {
+                                                                        s.push_str(p);
+                                                                    } else if model.platforms.iter().len() == 2 && model.platforms.get(0) == Some(p) {
+                                                                        let _ = write!(s, "{} ", p);
+                                                                    } else if Some(p) == model.platforms.iter().last() {
+                                                                        let _ = write!(s, "and {}", p);
+                                                                    } else {
+                                                                        let _ = write!(s, "{}, ", p);
+                                                                    }
+                                                                }
+                                                                if model.platforms.is_empty() {
+                                                                    s.push_str(&gettext("Unknown"));
+                                                                }
+                                                                &s.to_string()
+                                                            },
+                                                            #[watch]
+                                                            set_visible: !model.platforms.is_empty()
+                                                        }
+                                                    }
+                                                }
                                             },
-                                            gtk::Box {
-                                                set_orientation: gtk::Orientation::Vertical,
-                                                set_valign: gtk::Align::Center,
-                                                set_spacing: 5,
-                                                gtk::Label {
-                                                    set_halign: gtk::Align::Center,
-                                                    add_css_class: "heading",
-                                                    #[watch]
-                                                    set_label: if model.maintainers.len() > 1 { "Maintainers" } else { "Maintainer" }
-                                                },
-                                                gtk::Label {
+                                            gtk::Button {
+                                                set_hexpand: true,
+                                                add_css_class: "card",
+                                                set_height_request: 100,
+                                                set_width_request: 100,
+                                                gtk::Box {
+                                                    set_orientation: gtk::Orientation::Vertical,
                                                     set_halign: gtk::Align::Fill,
-                                                    set_hexpand: true,
-                                                    add_css_class: "caption",
-                                                    add_css_class: "dim-label",
-                                                    set_ellipsize: pango::EllipsizeMode::End,
-                                                    set_lines: 2,
-                                                    set_wrap: true,
-                                                    set_max_width_chars: 0,
-                                                    set_justify: gtk::Justification::Center,
-                                                    #[watch]
-                                                    set_label: {
-                                                        let mut s = String::new();
-                                                        let maintainerlist = model.maintainers.iter().filter(|m| m.name.is_some() || m.github.is_some()).collect::<Vec<_>>();
-                                                        for p in &maintainerlist {
-                                                            if maintainerlist.len() == 1 {
-                                                                if let Some(n) = &p.name {
-                                                                    s.push_str(n);
-                                                                } else if let Some(g) = &p.github {
-                                                                    s.push_str(g);
-                                                                }
-                                                            } else if maintainerlist.len() == 2 && model.maintainers.get(0) == Some(p) {
-                                                                if let Some(n) = &p.name {
-                                                                    let _ = write!(s, "{} ", n.as_str());
-                                                                } else if let Some(g) = &p.github {
-                                                                    s.push_str(g);
+                                                    set_valign: gtk::Align::Center,
+                                                    set_spacing: 10,
+                                                    set_margin_all: 15,
+                                                    gtk::Image {
+                                                        add_css_class: "circular",
+                                                        #[watch]
+                                                        set_css_classes: &[ if model.maintainers.is_empty() { "error" } else { "accent" } ],
+                                                        set_halign: gtk::Align::Center,
+                                                        set_icon_name: Some("system-users-symbolic"),
+                                                        set_pixel_size: 24,
+                                                    },
+                                                    gtk::Box {
+                                                        set_orientation: gtk::Orientation::Vertical,
+                                                        set_valign: gtk::Align::Center,
+                                                        set_spacing: 5,
+                                                        gtk::Label {
+                                                            set_halign: gtk::Align::Center,
+                                                            add_css_class: "heading",
+                                                            #[watch]
+                                                            set_label: &ngettext("Maintainer", "Maintainers", model.maintainers.len() as u32)
+                                                        },
+                                                        gtk::Label {
+                                                            set_halign: gtk::Align::Fill,
+                                                            set_hexpand: true,
+                                                            add_css_class: "caption",
+                                                            add_css_class: "dim-label",
+                                                            set_ellipsize: pango::EllipsizeMode::End,
+                                                            set_lines: 2,
+                                                            set_wrap: true,
+                                                            set_max_width_chars: 0,
+                                                            set_justify: gtk::Justification::Center,
+                                                            #[watch]
+                                                            set_label: {
+                                                                let mut s = String::new();
+                                                                let maintainerlist = model.maintainers.iter().filter(|m| m.name.is_some() || m.github.is_some()).collect::<Vec<_>>();
+                                                                for p in &maintainerlist {
+                                                                    if maintainerlist.len() == 1 {
+                                                                        if let Some(n) = &p.name {
+                                                                            s.push_str(n);
+                                                                        } else if let Some(g) = &p.github {
+                                                                            s.push_str(g);
+                                                                        }
+                                                                    } else if maintainerlist.len() == 2 && model.maintainers.get(0) == Some(p) {
+                                                                        if let Some(n) = &p.name {
+                                                                            let _ = write!(s, "{} ", n.as_str());
+                                                                        } else if let Some(g) = &p.github {
+                                                                            s.push_str(g);
+                                                                        }
+                                                                    } else if Some(p) == maintainerlist.last() {
+                                                                        if let Some(n) = &p.name {
+                                                                            let _ = write!(s, "and {}", n.as_str());
+                                                                        } else if let Some(g) = &p.github {
+                                                                            let _ = write!(s, "and {}", g.as_str());
+                                                                        }
+                                                                    } else if let Some(n) = &p.name {
+                                                                        let _ = write!(s, "{}, ", n.as_str());
+                                                                    } else if let Some(g) = &p.github {
+                                                                        let _ = write!(s, "{}, ", g.as_str());
+                                                                    }
                                                                 }
-                                                            } else if Some(p) == maintainerlist.last() {
-                                                                if let Some(n) = &p.name {
-                                                                    let _ = write!(s, "and {}", n.as_str());
-                                                                } else if let Some(g) = &p.github {
-                                                                    let _ = write!(s, "and {}", g.as_str());
+                                                                if model.maintainers.is_empty() {
+                                                                    s.push_str(&gettext("Unknown"));
                                                                 }
-                                                            } else if let Some(n) = &p.name {
-                                                                let _ = write!(s, "{}, ", n.as_str());
-                                                            } else if let Some(g) = &p.github {
-                                                                let _ = write!(s, "{}, ", g.as_str());
+                                                                &s.to_string()
                                                             }
                                                         }
-                                                        if model.maintainers.is_empty() {
-                                                            s.push_str("Unknown");
-                                                        }
-                                                        &s.to_string()
                                                     }
                                                 }
-                                            }
+                                            },
                                         }
                                     },
                                 }
                             },
-                        }
+                            adw::Clamp {
+                                set_halign: gtk::Align::Fill,
+                                set_valign: gtk::Align::Start,
+                                set_maximum_size: 1000,
+                                gtk::ListBox {
+                                    add_css_class: "boxed-list",
+                                    set_selection_mode: gtk::SelectionMode::None,
+                                    set_margin_start: 15,
+                                    set_margin_end: 15,
+                                    set_margin_bottom: 15,
+                                    adw::EntryRow {
+                                        set_title: &gettext("Private note"),
+                                        set_tooltip_text: Some(&gettext("Only kept on this computer, e.g. why the package was installed")),
+                                        set_show_apply_button: true,
+                                        add_prefix = &gtk::Image {
+                                            set_icon_name: Some("document-edit-symbolic"),
+                                        },
+                                        connect_apply[sender] => move |x| {
+                                            sender.input(PkgMsg::SetNote(x.text().to_string()));
+                                        } @notehandler,
+                                        #[track(model.changed(PkgModel::pkg()) || model.changed(PkgModel::note()))]
+                                        #[block_signal(notehandler)]
+                                        set_text: &model.note,
+                                    }
+                                }
+                            },
+                            adw::Clamp {
+                                set_halign: gtk::Align::Fill,
+                                set_valign: gtk::Align::Start,
+                                set_maximum_size: 1000,
+                                #[watch]
+                                set_visible: model.bugtracker.is_some(),
+                                gtk::ListBox {
+                                    add_css_class: "boxed-list",
+                                    set_selection_mode: gtk::SelectionMode::None,
+                                    set_margin_start: 15,
+                                    set_margin_end: 15,
+                                    set_margin_bottom: 15,
+                                    adw::ActionRow {
+                                        set_title: &gettext("Report an Issue"),
+                                        set_activatable: true,
+                                        #[watch]
+                                        set_subtitle: &model.bugtracker.as_deref().and_then(favicon::domain).unwrap_or_default(),
+                                        #[watch]
+                                        set_tooltip_text: model.bugtracker.as_deref(),
+                                        add_prefix = &gtk::Image {
+                                            #[track(model.changed(PkgModel::favicons()) || model.changed(PkgModel::pkg()))]
+                                            set_from_gicon: &linkicon(&model.favicons, model.bugtracker.as_deref(), "dialog-warning-symbolic"),
+                                            set_pixel_size: 16,
+                                        },
+                                        add_suffix = &gtk::Image {
+                                            set_icon_name: Some("web-browser-symbolic"),
+                                        },
+                                        connect_activated[sender] => move |_| {
+                                            sender.input(PkgMsg::OpenBugtracker);
+                                        }
+                                    }
+                                }
+                            },
+                            adw::Clamp {
+                                set_halign: gtk::Align::Fill,
+                                set_valign: gtk::Align::Start,
+                                set_maximum_size: 1000,
+                                #[watch]
+                                set_visible: model.position.is_some(),
+                                gtk::ListBox {
+                                    add_css_class: "boxed-list",
+                                    set_selection_mode: gtk::SelectionMode::None,
+                                    set_margin_start: 15,
+                                    set_margin_end: 15,
+                                    set_margin_bottom: 15,
+                                    adw::ExpanderRow {
+                                        set_title: &gettext("Nix expression"),
+                                        #[watch]
+                                        set_subtitle: &gtk::glib::markup_escape_text(model.position.as_ref().map(|x| x.path.as_str()).unwrap_or_default()),
+                                        #[track(model.changed(PkgModel::pkg()))]
+                                        set_expanded: false,
+                                        connect_expanded_notify[sender] => move |x| {
+                                            if x.is_expanded() {
+                                                sender.input(PkgMsg::LoadExpression);
+                                            }
+                                        },
+                                        add_suffix = &gtk::Button {
+                                            add_css_class: "flat",
+                                            set_valign: gtk::Align::Center,
+                                            set_icon_name: "web-browser-symbolic",
+                                            set_tooltip_text: Some(&gettext("Open on GitHub")),
+                                            connect_clicked[sender] => move |_| {
+                                                sender.input(PkgMsg::OpenExpression);
+                                            }
+                                        },
+                                        add_row = &gtk::Stack {
+                                            add_named[Some("loading")] = &gtk::Spinner {
+                                                set_margin_all: 20,
+                                                #[watch]
+                                                set_spinning: model.expression == NixExpression::Loading,
+                                            },
+                                            add_named[Some("failed")] = &gtk::Label {
+                                                set_margin_all: 20,
+                                                add_css_class: "dim-label",
+                                                set_label: &gettext("Could not download the nix expression"),
+                                            },
+                                            add_named[Some("source")] = &gtk::ScrolledWindow {
+                                                set_height_request: 400,
+                                                set_hscrollbar_policy: gtk::PolicyType::Automatic,
+                                                gtk::TextView {
+                                                    set_editable: false,
+                                                    set_cursor_visible: false,
+                                                    set_monospace: true,
+                                                    set_left_margin: 10,
+                                                    set_right_margin: 10,
+                                                    set_top_margin: 10,
+                                                    set_bottom_margin: 10,
+                                                    #[track(model.changed(PkgModel::expression()))]
+                                                    set_buffer: Some(&gtk::TextBuffer::builder().text(match &model.expression {
+                                                        NixExpression::Loaded(x) => x.as_str(),
+                                                        _ => "",
+                                                    }).build()),
+                                                }
+                                            },
+                                            #[watch]
+                                            set_visible_child_name: match model.expression {
+                                                NixExpression::Loaded(_) => "source",
+                                                NixExpression::Failed => "failed",
+                                                _ => "loading",
+                                            },
+                                        },
+                                    }
+                                }
+                            },
+                            adw::Clamp {
+                                set_halign: gtk::Align::Fill,
+                                set_valign: gtk::Align::Start,
+                                set_maximum_size: 1000,
+                                gtk::ListBox {
+                                    add_css_class: "boxed-list",
+                                    set_selection_mode: gtk::SelectionMode::None,
+                                    set_margin_start: 15,
+                                    set_margin_end: 15,
+                                    set_margin_bottom: 15,
+                                    adw::ExpanderRow {
+                                        set_title: &gettext("Dependencies"),
+                                        #[watch]
+                                        set_subtitle: &match &model.dependencies {
+                                            DependencyTree::Loaded(x) => ngettextf(
+                                                "{} store path, {} unpacked",
+                                                "{} store paths, {} unpacked",
+                                                x.paths() as u32,
+                                                &[&x.paths().to_string(), &gtk::glib::format_size(x.closuresize())],
+                                            ),
+                                            _ => gettext("Everything installing the package pulls in"),
+                                        },
+                                        #[track(model.changed(PkgModel::pkg()))]
+                                        set_expanded: false,
+                                        connect_expanded_notify[sender] => move |x| {
+                                            if x.is_expanded() {
+                                                sender.input(PkgMsg::LoadDependencies);
+                                            }
+                                        },
+                                        add_row = &gtk::Stack {
+                                            add_named[Some("loading")] = &gtk::Spinner {
+                                                set_margin_all: 20,
+                                                #[watch]
+                                                set_spinning: model.dependencies == DependencyTree::Loading,
+                                            },
+                                            add_named[Some("failed")] = &gtk::Label {
+                                                set_margin_all: 20,
+                                                add_css_class: "dim-label",
+                                                set_label: &gettext("Could not find the dependencies"),
+                                            },
+                                            add_named[Some("tree")] = &gtk::ScrolledWindow {
+                                                set_height_request: 400,
+                                                set_hscrollbar_policy: gtk::PolicyType::Never,
+                                                #[name(dependencylist)]
+                                                gtk::ListBox {
+                                                    set_selection_mode: gtk::SelectionMode::None,
+                                                }
+                                            },
+                                            #[watch]
+                                            set_visible_child_name: match model.dependencies {
+                                                DependencyTree::Loaded(_) => "tree",
+                                                DependencyTree::Failed => "failed",
+                                                _ => "loading",
+                                            },
+                                        },
+                                    }
+                                }
+                            },
+                            adw::Clamp {
+                                set_halign: gtk::Align::Fill,
+                                set_valign: gtk::Align::Start,
+                                set_maximum_size: 1000,
+                                gtk::ListBox {
+                                    add_css_class: "boxed-list",
+                                    set_selection_mode: gtk::SelectionMode::None,
+                                    set_margin_start: 15,
+                                    set_margin_end: 15,
+                                    set_margin_bottom: 15,
+                                    adw::ExpanderRow {
+                                        set_title: &gettext("Advanced options"),
+                                        set_subtitle: &gettext("Extra arguments for the nix command"),
+                                        add_row = &adw::EntryRow {
+                                            set_title: &gettext("Extra arguments"),
+                                            #[track(model.changed(PkgModel::pkg()))]
+                                            #[block_signal(extrahandler)]
+                                            set_text: &model.extraargs,
+                                            #[watch]
+                                            set_class_active: ("error", model.argserror.is_some()),
+                                            #[watch]
+                                            set_tooltip_text: model.argserror.as_deref(),
+                                            connect_changed[sender] => move |x| {
+                                                sender.input(PkgMsg::SetExtraArgs(x.text().to_string()));
+                                            } @extrahandler,
+                                        },
+                                        add_row = &adw::ActionRow {
+                                            set_title: &gettext("Impure evaluation"),
+                                            set_subtitle: &gettext("Allow access to the environment, e.g. NIXPKGS_ALLOW_UNFREE"),
+                                            add_suffix = &gtk::Switch {
+                                                set_valign: gtk::Align::Center,
+                                                connect_state_set[sender] => move |_, b| {
+                                                    sender.input(PkgMsg::SetImpure(b));
+                                                    gtk::glib::Propagation::Proceed
+                                                } @impurehandler,
+                                                #[track(model.changed(PkgModel::pkg()))]
+                                                #[block_signal(impurehandler)]
+                                                set_state: model.impure,
+                                            }
+                                        },
+                                        add_row = &adw::ActionRow {
+                                            set_title: &gettext("Command"),
+                                            set_activatable: false,
+                                            #[watch]
+                                            set_subtitle: &gtk::glib::markup_escape_text(&model.cmdpreview()),
+                                            add_css_class: "monospace",
+                                        },
+                                    }
+                                }
+                            },
+                        },
+                        add_titled[Some("versions"), &gettext("Versions")] = &adw::Clamp {
+                            set_halign: gtk::Align::Fill,
+                            set_valign: gtk::Align::Start,
+                            set_maximum_size: 1000,
+                            gtk::ListBox {
+                                add_css_class: "boxed-list",
+                                set_selection_mode: gtk::SelectionMode::None,
+                                set_margin_start: 15,
+                                set_margin_end: 15,
+                                set_margin_bottom: 15,
+                                adw::ActionRow {
+                                    set_title: "nixpkgs-unstable",
+                                    set_activatable: false,
+                                    #[watch]
+                                    set_subtitle: &model.versions.unstable.clone().unwrap_or_else(|| gettext("Not available")),
+                                },
+                                adw::ActionRow {
+                                    set_activatable: false,
+                                    #[watch]
+                                    set_visible: model.versions.stable.is_some(),
+                                    #[watch]
+                                    set_title: model.versions.stable.as_ref().map(|(c, _)| c.as_str()).unwrap_or_default(),
+                                    #[watch]
+                                    set_subtitle: model.versions.stable.as_ref().map(|(_, v)| v.as_str()).unwrap_or_default(),
+                                },
+                                adw::ActionRow {
+                                    set_title: &gettext("Installed in user profile"),
+                                    set_activatable: false,
+                                    #[watch]
+                                    set_visible: model.versions.user.is_some(),
+                                    #[watch]
+                                    set_subtitle: model.versions.user.as_deref().unwrap_or_default(),
+                                },
+                                adw::ActionRow {
+                                    set_title: &gettext("Installed in system configuration"),
+                                    set_activatable: false,
+                                    #[watch]
+                                    set_visible: model.versions.system.is_some(),
+                                    #[watch]
+                                    set_subtitle: model.versions.system.as_deref().unwrap_or_default(),
+                                },
+                                adw::ActionRow {
+                                    set_title: &gettext("Previous version"),
+                                    set_activatable: false,
+                                    #[watch]
+                                    set_visible: model.previousversion.is_some(),
+                                    #[watch]
+                                    set_subtitle: model.previousversion.as_ref().map(|(v, _)| v.as_str()).unwrap_or_default(),
+                                },
+                                adw::ActionRow {
+                                    set_title: &gettext("Attribute"),
+                                    set_activatable: false,
+                                    #[watch]
+                                    set_subtitle: &model.pkg,
+                                    add_css_class: "monospace",
+                                },
+                            }
+                        },
+                        #[track(model.changed(PkgModel::pkg()))]
+                        set_visible_child_name: "overview",
                     },
                     gtk::Separator {
                         set_vexpand: true,
@@ -992,16 +1681,32 @@ impl Component for PkgModel {
 
     menu! {
         installtype: {
-            "User (nix-env)" => NixEnvAction,
-            "System (configuration.nix)" => NixSystemAction,
+            &gettext("User (nix-env)") => NixEnvAction,
+            &gettext("System (configuration.nix)") => NixSystemAction,
         },
         installprofiletype: {
-            "User (nix-profile)" => NixProfileAction,
-            "System (configuration.nix)" => NixSystemAction,
+            &gettext("User (nix-profile)") => NixProfileAction,
+            &gettext("System (configuration.nix)") => NixSystemAction,
         },
         runaction: {
-            "Run without installing" => LaunchAction,
-            "Open interactive shell" => TermShellAction,
+            &gettext("Run without installing") => LaunchAction,
+            &gettext("Open interactive shell") => TermShellAction,
+        }
+    }
+
+    fn post_view() {
+        if model.changed(PkgModel::dependencies()) {
+            filldependencies(&dependencylist, &model.dependencies);
+        }
+        // Keep focus on the install buttons when the one that had it is replaced
+        if model.refocus && pkg_window.is_mapped() {
+            let lost = pkg_window.root().and_then(|x| x.focus()).map(|x| !x.is_mapped()).unwrap_or(true);
+            if lost {
+                match model.installtype {
+                    InstallType::User => userinstallstack.child_focus(gtk::DirectionType::TabForward),
+                    InstallType::System => systeminstallstack.child_focus(gtk::DirectionType::TabForward),
+                };
+            }
         }
     }
 
@@ -1013,8 +1718,14 @@ impl Component for PkgModel {
         let installworker = InstallAsyncHandler::builder()
             .detach_worker(InstallAsyncHandlerInit { syspkgs: initparams.syspkgs.clone(), userpkgs: initparams.userpkgs.clone() })
             .forward(sender.input_sender(), identity);
+        let systemworker = InstallAsyncHandler::builder()
+            .detach_worker(InstallAsyncHandlerInit { syspkgs: initparams.syspkgs.clone(), userpkgs: initparams.userpkgs.clone() })
+            .forward(sender.input_sender(), identity);
         let config = initparams.config;
-        installworker.emit(InstallAsyncHandlerMsg::SetConfig(config.clone()));
+        for worker in [&installworker, &systemworker] {
+            worker.emit(InstallAsyncHandlerMsg::SetConfig(config.clone()));
+            worker.emit(InstallAsyncHandlerMsg::SetNscConfig(initparams.nscconfig.clone()));
+        }
         let model = PkgModel {
             config,
             name: String::default(),
@@ -1025,10 +1736,16 @@ impl Component for PkgModel {
             version: None,
             icon: None,
             homepage: None,
+            bugtracker: None,
+            note: String::new(),
+            favicons: HashMap::new(),
+            refocus: false,
             licenses: vec![],
             screenshots: FactoryVecDeque::builder().launch(adw::Carousel::new()).detach(),
             installworker,
+            systemworker,
             platforms: vec![],
+            supported: true,
             carpage: CarouselPage::Single,
             installtype: InstallType::User,
             maintainers: vec![],
@@ -1036,10 +1753,31 @@ impl Component for PkgModel {
             installedsystempkgs: HashSet::new(),
             syspkgtype: initparams.syspkgs,
             userpkgtype: initparams.userpkgs,
-            workqueue: HashSet::new(),
+            workqueue: WorkQueue::default(),
+            previousversion: None,
+            impure: initparams.nscconfig.impure,
+            nscconfig: initparams.nscconfig,
+            extraargs: String::new(),
+            argserror: None,
             launchable: None,
+            developer: None,
+            versions: PkgVersions::default(),
+            position: None,
+            systemnote: None,
+            displaynote: None,
+            warnings: vec![],
+            gpunote: None,
+            expression: NixExpression::Unloaded,
+            dependencies: DependencyTree::Unloaded,
             visible: false,
             online: initparams.online,
+            policy: initparams.policy,
+            readonly: initparams.readonly,
+            sharesnapshot: HashMap::new(),
+            downloads: HashMap::new(),
+            sizing: false,
+            lockdialog: None,
+            host: None,
             tracker: 0,
         };
 
@@ -1050,6 +1788,9 @@ impl Component for PkgModel {
             border-right-width: 0;
             border-top-width: 1px;
             border-bottom-width: 1px;
+        }
+        .sensitive {
+            filter: blur(40px);
         }",
         );
         let widgets = view_output!();
@@ -1113,17 +1854,49 @@ impl Component for PkgModel {
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
         self.reset();
+        self.refocus = false;
+        if self.argserror.is_some()
+            && matches!(
+                msg,
+                PkgMsg::InstallUser
+                    | PkgMsg::QueueInstallUser(_)
+                    | PkgMsg::InstallSystem
+                    | PkgMsg::RemoveUser
+                    | PkgMsg::RemoveSystem
+                    | PkgMsg::RevertUser
+            )
+        {
+            warn!("Not running {:?}, the extra arguments don't parse", msg);
+            return;
+        }
         match msg {
             PkgMsg::UpdateConfig(config) => {
                 self.config = config.clone();
-                self.installworker.emit(InstallAsyncHandlerMsg::SetConfig(config));
+                for worker in self.workers() {
+                    worker.emit(InstallAsyncHandlerMsg::SetConfig(config.clone()));
+                }
+            }
+            PkgMsg::UpdateNscConfig(nscconfig) => {
+                self.impure = nscconfig.impure;
+                self.nscconfig = nscconfig.clone();
+                for worker in self.workers() {
+                    worker.emit(InstallAsyncHandlerMsg::SetNscConfig(nscconfig.clone()));
+                }
             }
             PkgMsg::UpdatePkgTypes(syspkgs, userpkgs) => {
                 self.syspkgtype = syspkgs.clone();
                 self.userpkgtype = userpkgs.clone();
-                self.installworker.emit(InstallAsyncHandlerMsg::SetPkgTypes(syspkgs, userpkgs));
+                for worker in self.workers() {
+                    worker.emit(InstallAsyncHandlerMsg::SetPkgTypes(syspkgs.clone(), userpkgs.clone()));
+                }
+            }
+            PkgMsg::SetHost(host) => {
+                self.host = host.clone();
+                for worker in self.workers() {
+                    worker.emit(InstallAsyncHandlerMsg::SetHost(host.clone()));
+                }
             }
             PkgMsg::Open(pkgmodel) => {
 
@@ -1131,6 +1904,10 @@ impl Component for PkgModel {
                 self.summary = None;
                 self.description = None;
                 self.icon = None;
+                self.previousversion = None;
+                self.extraargs = String::new();
+                self.set_argserror(None);
+                self.impure = self.nscconfig.impure;
                 let mut scrn_guard = self.screenshots.guard();
                 scrn_guard.clear();
                 scrn_guard.drop();
@@ -1140,10 +1917,21 @@ impl Component for PkgModel {
                 self.set_name(pkgmodel.name);
                 self.set_icon(pkgmodel.icon);
                 self.set_version(pkgmodel.version);
+                self.set_versions(pkgmodel.versions);
+                self.set_position(pkgmodel.position);
+                self.set_expression(NixExpression::Unloaded);
+                self.set_dependencies(DependencyTree::Unloaded);
                 self.set_platforms(pkgmodel.platforms);
+                self.set_supported(pkgmodel.supported);
                 self.set_maintainers(pkgmodel.maintainers);
                 self.set_licenses(pkgmodel.licenses);
                 self.set_pname(pkgmodel.pname);
+                self.set_systemnote(hints::systemnote(&self.pname));
+                self.set_displaynote(hints::displaynote(&self.pname));
+                self.set_gpunote(hints::gpunote(&self.pname, &self.pkg));
+                self.set_warnings(vec![]);
+                // A size check still running for the previous package is dropped when it returns
+                self.set_sizing(false);
                 self.set_installeduserpkgs(pkgmodel.installeduserpkgs);
                 self.set_installedsystempkgs(pkgmodel.installedsystempkgs);
 
@@ -1153,6 +1941,7 @@ impl Component for PkgModel {
                     self.set_installtype(InstallType::User)
                 }
 
+                self.developer = pkgmodel.developer;
                 self.launchable = if let Some(l) = pkgmodel.launchable {
                     Some(Launch::GtkApp(l))
                 } else if self.installeduserpkgs.contains(match self.userpkgtype { UserPkgs::Env => &self.pname, UserPkgs::Profile => &self.pkg }) {
@@ -1210,6 +1999,36 @@ impl Component for PkgModel {
                 }
 
                 self.homepage = pkgmodel.homepage;
+                self.bugtracker = pkgmodel.bugtracker;
+                self.note = state::getnscstate().notes.get(&self.pkg).cloned().unwrap_or_default();
+                if self.online {
+                    for url in self.homepage.iter().chain(self.bugtracker.iter()) {
+                        let Some(domain) = favicon::domain(url) else {
+                            continue;
+                        };
+                        if self.favicons.contains_key(&domain) {
+                            continue;
+                        }
+                        let url = url.to_string();
+                        let pkg = self.pkg.to_string();
+                        sender.oneshot_command(async move {
+                            match favicon::fetchfavicon(&url).await {
+                                Ok(path) => PkgAsyncMsg::SetFavicon(pkg, domain, path),
+                                Err(e) => {
+                                    debug!("No favicon for {}: {}", url, e);
+                                    PkgAsyncMsg::SetFavicon(pkg, domain, String::new())
+                                }
+                            }
+                        });
+                    }
+                }
+
+                if self.installeduserpkgs.contains(match self.userpkgtype { UserPkgs::Env => &self.pname, UserPkgs::Profile => &self.pkg }) {
+                    self.getpreviousversion(&sender);
+                }
+                if self.online {
+                    self.cachedownloadsize();
+                }
 
                 if pkgmodel.screenshots.len() <= 1 {
                     self.carpage = CarouselPage::Single;
@@ -1220,108 +2039,36 @@ impl Component for PkgModel {
                 {
                     let mut scrn_guard = self.screenshots.guard();
                     scrn_guard.clear();
-                    for _i in 0..pkgmodel.screenshots.len() {
-                        scrn_guard.push_back(());
+                    let sensitivity = match (pkgmodel.mature, self.policy.nomature) {
+                        (false, _) => Sensitivity::None,
+                        (true, false) => Sensitivity::Blurred,
+                        (true, true) => Sensitivity::Locked,
+                    };
+                    for screenshot in &pkgmodel.screenshots {
+                        scrn_guard.push_back((screenshot.caption.clone(), sensitivity));
                     }
                 }
 
-                for (i, url) in pkgmodel.screenshots.into_iter().enumerate() {
-                    if let Ok(home) = env::var("HOME") {
-                        let cachedir = format!("{}/.cache/nix-software-center", home);
-                        let sha = digest(url.to_string());
-                        let scrnpath = format!("{}/screenshots/{}", cachedir, sha);
-                        let pkg = self.pkg.clone();
-
-                        sender.command(move |out, shutdown| {
-                            let url = url.clone();
-                            let home = home.clone();
-                            let scrnpath = scrnpath.clone();
-                            let pkg = pkg.clone();
-                            shutdown
-                                .register(async move {
-                                    tokio::time::sleep(Duration::from_millis(5)).await;
-                                    if Path::new(&format!("{}.png", scrnpath)).exists() {
-                                        out.send(PkgAsyncMsg::LoadScreenshot(pkg, i, format!("{}.png", scrnpath)));
-                                    } else {
-                                        match reqwest::get(&url).await {
-                                            Ok(response) => {
-                                                if response.status().is_success() {
-                                                    if !Path::new(&format!(
-                                                        "{}/.cache/nix-software-center/screenshots",
-                                                        home
-                                                    ))
-                                                    .exists()
-                                                    {
-                                                        match fs::create_dir_all(format!(
-                                                            "{}/.cache/nix-software-center/screenshots",
-                                                            home
-                                                        )) {
-                                                            Ok(_) => {}
-                                                            Err(_) => {
-                                                                out.send(PkgAsyncMsg::SetError(pkg, i));
-                                                                return;
-                                                            }
-                                                        }
-                                                    }
-                                                    if let Ok(mut file) = File::create(&scrnpath) {
-                                                        if let Ok(b) = response.bytes().await {
-                                                            let mut content =  Cursor::new(b);
-                                                            if std::io::copy(&mut content, &mut file).is_ok() {
-                                                                fn openimg(scrnpath: &str) -> Result<(), Box<dyn Error>> {
-                                                                    let img = if let Ok(x) = image::load(BufReader::new(File::open(scrnpath)?), image::ImageFormat::Png) {
-                                                                        x
-                                                                    } else if let Ok(x) = image::load(BufReader::new(File::open(scrnpath)?), image::ImageFormat::Jpeg) {
-                                                                        x
-                                                                    } else if let Ok(x) = image::load(BufReader::new(File::open(scrnpath)?), image::ImageFormat::WebP) {
-                                                                        x
-                                                                    } else {
-                                                                        let imgdata = BufReader::new(File::open(scrnpath)?);
-                                                                        let format = image::guess_format(imgdata.buffer())?;
-                                                                        image::load(imgdata, format)?
-                                                                    };
-                                                                    let scaled = img.resize(640, 360, FilterType::Lanczos3);
-                                                                    let mut output = File::create(&format!("{}.png", scrnpath))?;
-                                                                    scaled.write_to(&mut output, ImageFormat::Png)?;
-                                                                    if let Err(e) = fs::remove_file(&scrnpath) {
-                                                                        warn!("{}", e);
-                                                                    }
-                                                                    Ok(())
-                                                                }
-    
-                                                                match openimg(&scrnpath) {
-                                                                    Ok(_) => {
-                                                                        out.send(PkgAsyncMsg::LoadScreenshot(
-                                                                            pkg, i, format!("{}.png", scrnpath),
-                                                                        ));
-                                                                    }
-                                                                    Err(_) => {
-                                                                        if let Err(e) = fs::remove_file(&scrnpath) {
-                                                                            warn!("{}", e);
-                                                                        }
-                                                                        out.send(PkgAsyncMsg::SetError(pkg, i));
-                                                                    }
-                                                                }
-                                                            }
-                                                        }
-                                                    } else {
-                                                        out.send(PkgAsyncMsg::SetError(pkg, i));
-                                                        warn!("Error: {}", response.status());    
-                                                    }
-                                                } else {
-                                                    out.send(PkgAsyncMsg::SetError(pkg, i));
-                                                    warn!("Error: {}", response.status());
-                                                }
-                                            }
-                                            Err(e) => {
-                                                out.send(PkgAsyncMsg::SetError(pkg, i));
-                                                warn!("Error: {}", e);
-                                            }
-                                        }
+                for (i, url) in pkgmodel.screenshots.into_iter().map(|x| x.url).enumerate() {
+                    let pkg = self.pkg.clone();
+                    sender.command(move |out, shutdown| {
+                        let url = url.clone();
+                        let pkg = pkg.clone();
+                        shutdown
+                            .register(async move {
+                                tokio::time::sleep(Duration::from_millis(5)).await;
+                                match screenshot::fetchscreenshot(&url).await {
+                                    Ok(path) => {
+                                        out.send(PkgAsyncMsg::LoadScreenshot(pkg, i, path));
                                     }
-                                })
-                                .drop_on_shutdown()
-                        })
-                    }
+                                    Err(e) => {
+                                        out.send(PkgAsyncMsg::SetError(pkg, i));
+                                        warn!("Error: {}", e);
+                                    }
+                                }
+                            })
+                            .drop_on_shutdown()
+                    })
                 }
             }
             PkgMsg::LoadScreenshot(pkg, i, u) => {
@@ -1349,6 +2096,56 @@ impl Component for PkgModel {
             PkgMsg::SetCarouselPage(page) => {
                 self.carpage = page;
             }
+            PkgMsg::InstallShortcut => {
+                if self.readonly {
+                    sender.input(PkgMsg::CopyInstallCommand);
+                } else if self.online
+                    && self.supported
+                    && self.blockedbypolicy().is_none()
+                    && !self.installed()
+                    && !self.workqueue.isbusy(&self.pkg, lane(&self.installtype))
+                {
+                    sender.input(match self.installtype {
+                        InstallType::User => PkgMsg::InstallUser,
+                        InstallType::System => PkgMsg::InstallSystem,
+                    });
+                }
+            }
+            PkgMsg::RemoveShortcut => {
+                if !self.readonly
+                    && !self.policy.noremove
+                    && self.installed()
+                    && !self.workqueue.isbusy(&self.pkg, lane(&self.installtype))
+                {
+                    sender.input(match self.installtype {
+                        InstallType::User => PkgMsg::RemoveUser,
+                        InstallType::System => PkgMsg::RemoveSystem,
+                    });
+                }
+            }
+            PkgMsg::SetNote(note) => {
+                let note = note.trim().to_string();
+                let pkg = self.pkg.to_string();
+                if let Err(e) = state::editnscstate(|x| {
+                    if note.is_empty() {
+                        x.notes.remove(&pkg);
+                    } else {
+                        x.notes.insert(pkg.to_string(), note.to_string());
+                    }
+                }) {
+                    warn!("Failed to save note: {}", e);
+                    return;
+                }
+                sender.output(AppMsg::SetNote(pkg, Some(note.to_string()).filter(|x| !x.is_empty())));
+                self.note = note;
+            }
+            PkgMsg::OpenBugtracker => {
+                if let Some(u) = &self.bugtracker {
+                    if let Err(e) = gio::AppInfo::launch_default_for_uri(u, gio::AppLaunchContext::NONE) {
+                        warn!("error: {}", e);
+                    }
+                }
+            }
             PkgMsg::OpenHomepage => {
                 if let Some(u) = &self.homepage {
                     if let Err(e) =
@@ -1358,45 +2155,144 @@ impl Component for PkgModel {
                     }
                 }
             }
+            PkgMsg::CopyInstallCommand => {
+                root.clipboard().set_text(&self.cmdpreview());
+                announce(&gettext("Install command copied"), false);
+            }
+            PkgMsg::OpenGpuVariant => {
+                if let Some(variant) = self.gpunote.as_ref().and_then(|x| x.variant.clone()) {
+                    sender.output(AppMsg::OpenPkg(variant));
+                }
+            }
+            PkgMsg::CopySystemNote => {
+                if let Some(note) = &self.systemnote {
+                    root.clipboard().set_text(note.snippet);
+                    announce(&gettext("Configuration copied"), false);
+                }
+            }
+            PkgMsg::LoadExpression => {
+                if let (Some(position), NixExpression::Unloaded) = (self.position.clone(), &self.expression) {
+                    self.set_expression(NixExpression::Loading);
+                    let pkg = self.pkg.to_string();
+                    sender.oneshot_command(async move {
+                        let expression = match expression::fetchexpression(&position).await {
+                            Ok(x) => NixExpression::Loaded(x),
+                            Err(e) => {
+                                warn!("Failed to fetch {}: {}", position.path, e);
+                                NixExpression::Failed
+                            }
+                        };
+                        PkgAsyncMsg::SetExpression(pkg, expression)
+                    });
+                }
+            }
+            PkgMsg::LoadDependencies => {
+                if self.dependencies == DependencyTree::Unloaded && self.readonly {
+                    self.set_dependencies(DependencyTree::Failed);
+                } else if self.dependencies == DependencyTree::Unloaded {
+                    self.set_dependencies(DependencyTree::Loading);
+                    let pkg = self.pkg.to_string();
+                    let nscconfig = self.nscconfig.clone();
+                    sender.oneshot_command(async move {
+                        let dependencies = match deps::fetchdependencies(&pkg, &nscconfig).await {
+                            Ok(x) => DependencyTree::Loaded(Arc::new(x)),
+                            Err(e) => {
+                                warn!("Failed to get dependencies of {}: {}", pkg, e);
+                                DependencyTree::Failed
+                            }
+                        };
+                        PkgAsyncMsg::SetDependencies(pkg, dependencies)
+                    });
+                }
+            }
+            PkgMsg::OpenExpression => {
+                if let Some(position) = &self.position {
+                    if let Err(e) =
+                        gio::AppInfo::launch_default_for_uri(&position.weburl(), gio::AppLaunchContext::NONE)
+                    {
+                        warn!("error: {}", e);
+                    }
+                }
+            }
             PkgMsg::Close => {
                 self.set_visible(false);
                 sender.output(AppMsg::FrontPage);
             }
             PkgMsg::InstallUser => {
+                if let Some(reason) = self.blockedbypolicy() {
+                    warn!("Not installing {}: {}", self.pkg, reason);
+                    return;
+                }
                 let online = util::checkonline();
                 if !online {
                     sender.output(AppMsg::CheckNetwork);
                     self.online = false;
                     return;
                 }
-                let w = WorkPkg {
-                    pkg: self.pkg.to_string(),
-                    pname: self.pname.to_string(),
-                    pkgtype: InstallType::User,
-                    action: PkgAction::Install,
-                    block: false,
-                    notify: None,
-                };
-                self.workqueue.insert(w.clone());
-                if self.workqueue.len() == 1 {
-                    self.installworker.emit(InstallAsyncHandlerMsg::Process(w));
+                if let Some((interpreter, library)) = packages::interpreterlibrary(&self.pkg) {
+                    showwithpackages(root, &sender, &interpreter, &library);
+                    return;
+                }
+                // Asked before rather than after nixpkgs refuses it
+                if self.licenses.iter().any(|x| x.free == Some(false)) && !nixpkgsconfig::allowsunfree(&self.nscconfig, &self.pname) {
+                    sender.input(PkgMsg::RequestAllow(self.userwork(PkgAction::Install), AllowKind::Unfree, self.pname.to_string()));
+                    return;
                 }
+                if self.host.is_some() {
+                    sender.input(PkgMsg::QueueInstallUser(PkgAction::Install));
+                    return;
+                }
+                self.set_sizing(true);
+                let pkg = self.pkg.to_string();
+                let pname = self.pname.to_string();
+                let nscconfig = self.nscconfig.clone();
+                sender.oneshot_command(async move {
+                    match closuresize(&pkg, &pname, &nscconfig).await {
+                        Ok((size, warnings)) => PkgAsyncMsg::SetClosureSize(pkg, Some(size), warnings),
+                        Err(e) => {
+                            warn!("Failed to get closure size of {}: {}", pkg, e);
+                            PkgAsyncMsg::SetClosureSize(pkg, None, vec![])
+                        }
+                    }
+                });
+            }
+            PkgMsg::QueueInstallUser(action) => {
+                sender.output(AppMsg::QueueWork(self.userwork(action)));
             }
             PkgMsg::RemoveUser => {
+                if self.policy.noremove {
+                    return;
+                }
                 let w = WorkPkg {
                     pkg: self.pkg.to_string(),
                     pname: self.pname.to_string(),
                     pkgtype: InstallType::User,
                     action: PkgAction::Remove,
                     block: false,
-                    notify: None,
+                    extraargs: self.parsedargs(),
+                    impure: Some(self.impure),
                 };
-                self.workqueue.insert(w.clone());
-                if self.workqueue.len() == 1 {
-                    self.installworker.emit(InstallAsyncHandlerMsg::Process(w));
+                sender.output(AppMsg::QueueWork(w));
+            }
+            PkgMsg::RevertUser => {
+                if let Some((_, path)) = &self.previousversion {
+                    let w = WorkPkg {
+                        pkg: self.pkg.to_string(),
+                        pname: self.pname.to_string(),
+                        pkgtype: InstallType::User,
+                        action: PkgAction::Revert(path.to_string()),
+                        block: false,
+                        extraargs: self.parsedargs(),
+                        impure: Some(self.impure),
+                    };
+                    sender.output(AppMsg::QueueWork(w));
                 }
             }
             PkgMsg::InstallSystem => {
+                if let Some(reason) = self.blockedbypolicy() {
+                    warn!("Not installing {}: {}", self.pkg, reason);
+                    return;
+                }
                 let online = util::checkonline();
                 if !online {
                     sender.output(AppMsg::CheckNetwork);
@@ -1409,39 +2305,47 @@ impl Component for PkgModel {
                     pkgtype: InstallType::System,
                     action: PkgAction::Install,
                     block: false,
-                    notify: None,
+                    extraargs: self.parsedargs(),
+                    impure: Some(self.impure),
                 };
-                self.workqueue.insert(w.clone());
-                if self.workqueue.len() == 1 {
-                    self.installworker.emit(InstallAsyncHandlerMsg::Process(w));
-                }
+                sender.output(AppMsg::QueueWork(w));
             }
             PkgMsg::RemoveSystem => {
+                if self.policy.noremove {
+                    return;
+                }
                 let w = WorkPkg {
                     pkg: self.pkg.to_string(),
                     pname: self.pname.to_string(),
                     pkgtype: InstallType::System,
                     action: PkgAction::Remove,
                     block: false,
-                    notify: None,
+                    extraargs: self.parsedargs(),
+                    impure: Some(self.impure),
                 };
-                self.workqueue.insert(w.clone());
-                if self.workqueue.len() == 1 {
-                    self.installworker.emit(InstallAsyncHandlerMsg::Process(w));
-                }
+                sender.output(AppMsg::QueueWork(w));
             }
             PkgMsg::FinishedProcess(work) => {
                 let _ = nix_data::utils::refreshicons();
-                self.workqueue.remove(&work);
-                trace!("WORK QUEUE: {}", self.workqueue.len());
+                announce(&format!("{} {}", work.pname, match work.action {
+                    PkgAction::Install | PkgAction::InstallEnv(_) => "installed",
+                    PkgAction::Remove => "removed",
+                    PkgAction::Revert(_) => "reverted",
+                }), false);
                 match work.pkgtype {
                     InstallType::User => {
                         match work.action {
                             PkgAction::Install => {
-                                match self.userpkgtype {
-                                    UserPkgs::Env => self.installeduserpkgs.insert(work.pname.to_string()),
-                                    UserPkgs::Profile => self.installeduserpkgs.insert(work.pkg.to_string()),
+                                let key = match self.userpkgtype {
+                                    UserPkgs::Env => work.pname.to_string(),
+                                    UserPkgs::Profile => work.pkg.to_string(),
                                 };
+                                self.installeduserpkgs.insert(key.to_string());
+                                if let Err(e) = state::editnscstate(|x| {
+                                    x.managed.get_or_insert_with(HashSet::new).insert(key);
+                                }) {
+                                    warn!("Failed to save managed packages: {}", e);
+                                }
                                 if self.launchable.is_none() {
                                     if let Ok(o) = Command::new("command").arg("-v").arg(&self.pname).output() {
                                         if o.status.success() {
@@ -1451,11 +2355,26 @@ impl Component for PkgModel {
                                 }
                             }
                             PkgAction::Remove => {
-                                match self.userpkgtype {
-                                    UserPkgs::Env => self.installeduserpkgs.remove(&work.pname),
-                                    UserPkgs::Profile => self.installeduserpkgs.remove(&work.pkg),
+                                let key = match self.userpkgtype {
+                                    UserPkgs::Env => &work.pname,
+                                    UserPkgs::Profile => &work.pkg,
                                 };
+                                self.installeduserpkgs.remove(key);
+                                if let Err(e) = state::editnscstate(|x| {
+                                    if let Some(managed) = &mut x.managed {
+                                        managed.remove(key);
+                                    }
+                                }) {
+                                    warn!("Failed to save managed packages: {}", e);
+                                }
+                            }
+                            PkgAction::Revert(_) => {
+                                if work.pkg == self.pkg {
+                                    self.getpreviousversion(&sender);
+                                }
                             }
+                            // The environment is not an install of the library itself
+                            PkgAction::InstallEnv(_) => {}
                         }
                     }
                     InstallType::System => {
@@ -1473,70 +2392,195 @@ impl Component for PkgModel {
                             PkgAction::Remove => {
                                 self.installedsystempkgs.remove(&work.pkg);
                             }
+                            PkgAction::InstallEnv(_) | PkgAction::Revert(_) => {}
                         }
                     }
                 }
-                sender.output(AppMsg::UpdateInstalledPkgs);
-                if let Some(n) = &work.notify {
-                    match n {
-                        NotifyPage::Installed => {
-                            sender.output(AppMsg::RemoveInstalledBusy(work));
-                        }
-                    }
-                }
-                
-                if !self.workqueue.is_empty() {
-                    if let Some(w) = self.workqueue.clone().iter().next() {
-                        self.installworker.emit(InstallAsyncHandlerMsg::Process(w.clone()));
-                    }
+                let downloads = self.downloads.remove(&work.pkgtype).unwrap_or_default();
+                if let (PkgAction::Install, Some(before)) = (&work.action, self.sharesnapshot.remove(&work.pkgtype)) {
+                    let system = work.pkgtype == InstallType::System;
+                    let pname = work.pname.to_string();
+                    sender.oneshot_command(async move {
+                        let added = relm4::spawn_blocking(move || {
+                            integration::sharedir(system)
+                                .map(|share| integration::added(&share, &before))
+                                .unwrap_or_default()
+                        })
+                        .await
+                        .unwrap_or_default();
+                        PkgAsyncMsg::SetIntegration(pname, added, downloads)
+                    });
                 }
+                sender.output(AppMsg::UpdateInstalledPkgs);
+                sender.output(AppMsg::WorkFinished(work, true));
             }
             PkgMsg::FailedProcess(work) => {
-                self.workqueue.remove(&work);
-                if let Some(n) = &work.notify {
-                    match n {
-                        NotifyPage::Installed => {
-                            sender.output(AppMsg::RemoveInstalledBusy(work));
-                        }
-                    }
+                let msg = match work.action {
+                    PkgAction::Install | PkgAction::InstallEnv(_) => gettextf("Failed to install {}", &[&work.pname]),
+                    PkgAction::Remove => gettextf("Failed to remove {}", &[&work.pname]),
+                    PkgAction::Revert(_) => gettextf("Failed to revert {}", &[&work.pname]),
+                };
+                announce(&msg, true);
+                crash::failure("install", &format!("{:?} {}", work.action, work.pname));
+                sender.output(AppMsg::WorkFinished(work, false));
+            }
+            PkgMsg::RequestAllow(work, kind, name) => {
+                if kind == AllowKind::Unfree && self.policy.nounfree {
+                    announce(&gettextf("{} is unfree, which is not allowed on this computer", &[&work.pname]), true);
+                    USER_REBUILD_BROKER.send(RebuildMsg::Show);
+                    USER_REBUILD_BROKER.send(RebuildMsg::FinishError(Some(gettextf(
+                        "{} has an unfree license, which is not allowed on this computer.",
+                        &[&name],
+                    ))));
+                    return;
                 }
-                if !self.workqueue.is_empty() {
-                    if let Some(w) = self.workqueue.clone().iter().next() {
-                        self.installworker.emit(InstallAsyncHandlerMsg::Process(w.clone()));
+                let (heading, body) = match kind {
+                    AllowKind::Unfree => (
+                        gettext("Allow Unfree Package?"),
+                        gettextf("{} has an unfree license, so nixpkgs won't install it unless it is allowed.", &[&name]),
+                    ),
+                    AllowKind::Insecure => (
+                        gettext("Allow Insecure Package?"),
+                        gettextf("{} is marked as insecure in nixpkgs because of known vulnerabilities. Only allow it if you understand the risks.", &[&name]),
+                    ),
+                };
+                let detail = match nixpkgsconfig::configpath() {
+                    Ok(path) if nixpkgsconfig::managed() => gettextf("It will be added to {}.", &[&path]),
+                    _ => gettext("Your nixpkgs config.nix is managed by hand, so it will be allowed through environment variables when installing and updating."),
+                };
+                let dialog = adw::MessageDialog::new(
+                    root.root().and_downcast::<gtk::Window>().as_ref(),
+                    Some(&heading),
+                    Some(&gettextf("{} {} This can be revoked in preferences.", &[&body, &detail])),
+                );
+                dialog.add_response("cancel", &gettext("Cancel"));
+                dialog.add_response("allow", &gettext("Allow and Install"));
+                dialog.set_default_response(Some("cancel"));
+                dialog.set_close_response("cancel");
+                dialog.set_response_appearance(
+                    "allow",
+                    match kind {
+                        AllowKind::Unfree => adw::ResponseAppearance::Suggested,
+                        AllowKind::Insecure => adw::ResponseAppearance::Destructive,
+                    },
+                );
+                let sender = sender.clone();
+                dialog.connect_response(None, move |_, resp| {
+                    if resp == "allow" {
+                        sender.input(PkgMsg::Allow(work.clone(), kind.clone(), name.clone()));
                     }
+                });
+                dialog.present();
+            }
+            PkgMsg::Allow(work, kind, name) => {
+                let list = match kind {
+                    AllowKind::Unfree => &mut self.nscconfig.allowunfree,
+                    AllowKind::Insecure => &mut self.nscconfig.allowinsecure,
+                };
+                if !list.contains(&name) {
+                    list.push(name);
+                }
+                // Written here as well so that it is in place before the install is retried
+                if let Err(e) = nixpkgsconfig::writeconfig(&self.nscconfig) {
+                    warn!("Failed to write nixpkgs config: {}", e);
                 }
+                for worker in self.workers() {
+                    worker.emit(InstallAsyncHandlerMsg::SetNscConfig(self.nscconfig.clone()));
+                }
+                sender.output(AppMsg::UpdateNscConfig(self.nscconfig.clone()));
+                sender.output(AppMsg::QueueWork(work));
             }
             PkgMsg::Cancel => {
-                // If running, cancel the current process
-                if let Some(h) = self.workqueue.iter().next() {
-                    if h.pkg == self.pkg {
-                        self.installworker.
-                        emit(InstallAsyncHandlerMsg::CancelProcess);
-                        return
-                    }
+                // If running, stop the process first, the queue is updated once it has stopped
+                if let Some(work) = self.workqueue.running().find(|x| x.pkg == self.pkg) {
+                    self.worker(&work.pkgtype).emit(InstallAsyncHandlerMsg::CancelProcess);
+                } else {
+                    sender.output(AppMsg::CancelWork(self.pkg.to_string()));
                 }
-
-                // If not running, remove from queue
-                for w in self.workqueue.clone() {
-                    if w.pkg == self.pkg {
-                        self.workqueue.remove(&w);
+            }
+            PkgMsg::CancelFinished(work) => {
+                if let Some(dialog) = self.lockdialog.take() {
+                    dialog.close();
+                }
+                sender.output(AppMsg::CancelWork(work.pkg.to_string()));
+            }
+            PkgMsg::ProfileLocked(work) => {
+                let body = match work.action {
+                    PkgAction::Remove => gettextf("Another program, such as nix in a terminal, is changing your packages. {} will be removed once it has finished.", &[&work.pname]),
+                    PkgAction::Revert(_) => gettextf("Another program, such as nix in a terminal, is changing your packages. {} will be reverted once it has finished.", &[&work.pname]),
+                    _ => gettextf("Another program, such as nix in a terminal, is changing your packages. {} will be installed once it has finished.", &[&work.pname]),
+                };
+                let dialog = adw::MessageDialog::new(
+                    root.root().and_downcast::<gtk::Window>().as_ref(),
+                    Some(&gettext("Another Nix Operation Is in Progress")),
+                    Some(&body),
+                );
+                dialog.add_response("cancel", &gettext("Cancel"));
+                dialog.add_response("wait", &gettext("Wait"));
+                dialog.set_response_appearance("wait", adw::ResponseAppearance::Suggested);
+                dialog.set_default_response(Some("wait"));
+                dialog.set_close_response("wait");
+                let worker = self.worker(&work.pkgtype).sender().clone();
+                dialog.connect_response(None, move |_, resp| {
+                    if resp == "cancel" {
+                        worker.emit(InstallAsyncHandlerMsg::CancelProcess);
                     }
+                });
+                dialog.present();
+                if let Some(old) = self.lockdialog.replace(dialog) {
+                    old.close();
                 }
             }
-            PkgMsg::CancelFinished => {
-                // If running, cancel the current process
-                if let Some(h) = self.workqueue.clone().iter().next() {
-                    if h.pkg == self.pkg {
-                        self.workqueue.remove(h);
-                        return
+            PkgMsg::SetWarnings(pkg, warnings) => {
+                if pkg == self.pkg {
+                    for warning in warnings {
+                        if !self.warnings.contains(&warning) {
+                            self.warnings.push(warning);
+                        }
                     }
+                    self.update_warnings(|_| ());
                 }
-
-                // If not running, remove from queue
-                for w in self.workqueue.clone() {
-                    if w.pkg == self.pkg {
-                        self.workqueue.remove(&w);
+            }
+            PkgMsg::DismissWarnings => {
+                self.set_warnings(vec![]);
+            }
+            PkgMsg::SetDownloads(pkgtype, downloads) => {
+                self.downloads.insert(pkgtype, downloads);
+            }
+            PkgMsg::HideLaunchers(ids) => {
+                sender.oneshot_command(async move {
+                    let hidden = relm4::spawn_blocking(move || {
+                        ids.into_iter()
+                            .filter(|id| match integration::hidelauncher(id) {
+                                Ok(()) => {
+                                    info!("Hid launcher {}", id);
+                                    true
+                                }
+                                Err(e) => {
+                                    warn!("Failed to hide launcher {}: {}", id, e);
+                                    false
+                                }
+                            })
+                            .collect()
+                    })
+                    .await
+                    .unwrap_or_default();
+                    PkgAsyncMsg::LaunchersHidden(hidden)
+                });
+            }
+            PkgMsg::UnhideLaunchers(ids) => {
+                relm4::spawn_blocking(move || {
+                    for id in ids {
+                        match integration::unhidelauncher(&id) {
+                            Ok(()) => info!("Showed launcher {} again", id),
+                            Err(e) => warn!("Failed to show launcher {} again: {}", id, e),
+                        }
                     }
+                });
+            }
+            PkgMsg::ProfileUnlocked(_) => {
+                if let Some(dialog) = self.lockdialog.take() {
+                    dialog.close();
                 }
             }
             PkgMsg::Launch => {
@@ -1616,11 +2660,19 @@ impl Component for PkgModel {
             PkgMsg::SetInstallType(t) => {
                 self.set_installtype(t);
             }
-            PkgMsg::AddToQueue(work) => {
-                self.workqueue.insert(work.clone());
-                if self.workqueue.len() == 1 {
-                    self.installworker.emit(InstallAsyncHandlerMsg::Process(work));
-                }
+            PkgMsg::SetExtraArgs(args) => {
+                self.set_argserror(cmd::extraargs(&args).err().map(|e| e.to_string()));
+                self.extraargs = args;
+            }
+            PkgMsg::SetImpure(impure) => {
+                self.impure = impure;
+            }
+            PkgMsg::Process(work) => {
+                self.startwork(work, &sender);
+            }
+            PkgMsg::SetQueue(workqueue) => {
+                self.workqueue = workqueue;
+                self.refocus = true;
             }
             PkgMsg::UpdateOnline(online) => {
                 self.set_online(online);
@@ -1628,7 +2680,7 @@ impl Component for PkgModel {
         }
     }
 
-    fn update_cmd(&mut self, msg: Self::CommandOutput, sender: ComponentSender<Self>, _root: &Self::Root) {
+    fn update_cmd(&mut self, msg: Self::CommandOutput, sender: ComponentSender<Self>, root: &Self::Root) {
         match msg {
             PkgAsyncMsg::LoadScreenshot(pkg, i, u) => {
                 sender.input(PkgMsg::LoadScreenshot(pkg, i, u));
@@ -1636,10 +2688,227 @@ impl Component for PkgModel {
             PkgAsyncMsg::SetError(pkg, i) => {
                 sender.input(PkgMsg::SetError(pkg, i));
             }
+            PkgAsyncMsg::SetFavicon(pkg, domain, path) => {
+                // Empty paths are kept too, so sites without a favicon aren't asked again
+                if pkg == self.pkg || path.is_empty() {
+                    self.update_favicons(|x| {
+                        x.insert(domain, path);
+                    });
+                }
+            }
+            PkgAsyncMsg::SetPreviousVersion(pkg, previous) => {
+                if pkg == self.pkg {
+                    self.set_previousversion(previous);
+                }
+            }
+            PkgAsyncMsg::SetExpression(pkg, expression) => {
+                if pkg == self.pkg {
+                    self.set_expression(expression);
+                }
+            }
+            PkgAsyncMsg::SetDependencies(pkg, dependencies) => {
+                if pkg == self.pkg {
+                    self.set_dependencies(dependencies);
+                }
+            }
+            PkgAsyncMsg::SetClosureSize(pkg, size, warnings) => {
+                if pkg != self.pkg || !self.sizing {
+                    return;
+                }
+                self.set_sizing(false);
+                if !warnings.is_empty() {
+                    self.set_warnings(warnings);
+                }
+                match size {
+                    Some(size) if size.unpacked >= LARGEINSTALL || size.builds > 0 => {
+                        showclosuresize(root, &sender, &self.name, &size);
+                    }
+                    _ => sender.input(PkgMsg::QueueInstallUser(PkgAction::Install)),
+                }
+            }
+            PkgAsyncMsg::StartWork(work, snapshot) => {
+                match snapshot {
+                    Some(x) => {
+                        self.sharesnapshot.insert(work.pkgtype.clone(), x);
+                    }
+                    None => {
+                        self.sharesnapshot.remove(&work.pkgtype);
+                    }
+                }
+                self.worker(&work.pkgtype).emit(InstallAsyncHandlerMsg::Process(work));
+            }
+            PkgAsyncMsg::SetIntegration(pname, added, downloads) => {
+                // Worth interrupting for when extra caches were used, so it is clear they work
+                if !added.is_empty() || downloads.iter().any(|x| x.host != DEFAULTSUBSTITUTER) {
+                    showintegration(root, &sender, &pname, &added, &downloads);
+                }
+            }
+            PkgAsyncMsg::LaunchersHidden(ids) => {
+                if !ids.is_empty() {
+                    showhidden(root, &sender, ids);
+                }
+            }
+        }
+    }
+}
+
+impl PkgModel {
+    fn startwork(&mut self, work: WorkPkg, sender: &ComponentSender<Self>) {
+        let msg = match work.action {
+            PkgAction::Install | PkgAction::InstallEnv(_) => gettextf("Installing {}", &[&work.pname]),
+            PkgAction::Remove => gettextf("Removing {}", &[&work.pname]),
+            PkgAction::Revert(_) => gettextf("Reverting {}", &[&work.pname]),
+        };
+        announce(&msg, false);
+        match work.action {
+            PkgAction::Install | PkgAction::InstallEnv(_) => {
+                // Walking the share directory can take a moment, the work starts once it is done
+                let system = work.pkgtype == InstallType::System;
+                sender.oneshot_command(async move {
+                    let snapshot = relm4::spawn_blocking(move || integration::sharedir(system).map(|x| integration::snapshot(&x)))
+                        .await
+                        .ok()
+                        .flatten();
+                    PkgAsyncMsg::StartWork(work, snapshot)
+                });
+            }
+            _ => {
+                self.sharesnapshot.remove(&work.pkgtype);
+                self.worker(&work.pkgtype).emit(InstallAsyncHandlerMsg::Process(work));
+            }
+        }
+    }
+
+    fn userwork(&self, action: PkgAction) -> WorkPkg {
+        WorkPkg {
+            pkg: self.pkg.to_string(),
+            pname: self.pname.to_string(),
+            pkgtype: InstallType::User,
+            action,
+            block: false,
+            extraargs: self.parsedargs(),
+            impure: Some(self.impure),
+        }
+    }
+
+    fn worker(&self, pkgtype: &InstallType) -> &WorkerController<InstallAsyncHandler> {
+        match pkgtype {
+            InstallType::User => &self.installworker,
+            InstallType::System => &self.systemworker,
+        }
+    }
+
+    fn workers(&self) -> [&WorkerController<InstallAsyncHandler>; 2] {
+        [&self.installworker, &self.systemworker]
+    }
+
+    /// Whether the package is installed with the current install type.
+    fn installed(&self) -> bool {
+        match self.installtype {
+            InstallType::User => self.installeduserpkgs.contains(match self.userpkgtype {
+                UserPkgs::Env => &self.pname,
+                UserPkgs::Profile => &self.pkg,
+            }),
+            InstallType::System => self.installedsystempkgs.contains(&self.pkg),
+        }
+    }
+
+    /// Why the administrator's policy doesn't allow installing this package.
+    fn blockedbypolicy(&self) -> Option<String> {
+        let unfree = self.licenses.iter().any(|x| x.free == Some(false));
+        self.policy.blocksinstall(&self.pkg, unfree).map(|x| match x {
+            PolicyBlock::NotAllowed => gettext("Not in the software approved for this computer"),
+            PolicyBlock::Unfree => gettext("Unfree software is not allowed on this computer"),
+        })
+    }
+
+    /// The extra arguments as passed to nix, none while they don't parse.
+    fn parsedargs(&self) -> Vec<String> {
+        cmd::extraargs(&self.extraargs).unwrap_or_default()
+    }
+
+    fn cmdpreview(&self) -> String {
+        let extraargs = self.parsedargs();
+        let mut nscconfig = self.nscconfig.clone();
+        nscconfig.impure = self.impure;
+        match self.installtype {
+            InstallType::User => {
+                let installed = self.installeduserpkgs.contains(match self.userpkgtype {
+                    UserPkgs::Env => &self.pname,
+                    UserPkgs::Profile => &self.pkg,
+                });
+                let action = if installed { PkgAction::Remove } else { PkgAction::Install };
+                let (program, args) = usercmd(&self.pkg, &self.pname, &action, &self.userpkgtype, &nscconfig, &extraargs);
+                cmd::cmdline(program, &args, &nscconfig)
+            }
+            InstallType::System => {
+                let args = sysargs(&self.config, &nscconfig, &extraargs);
+                cmd::cmdline("nixos-rebuild", &args, &nscconfig)
+            }
+        }
+    }
+
+    /// Looks up the download size in the background so tiles and search can show it later.
+    fn cachedownloadsize(&self) {
+        // Evaluating the package needs nix
+        if self.readonly {
+            return;
         }
+        let Some(version) = self.version.clone() else {
+            return;
+        };
+        let pkg = self.pkg.to_string();
+        let nscconfig = self.nscconfig.clone();
+        relm4::spawn(async move {
+            if let Err(e) = narinfo::fetchsize(&pkg, &version, &nscconfig).await {
+                debug!("Failed to get download size of {}: {}", pkg, e);
+            }
+        });
+    }
+
+    fn getpreviousversion(&self, sender: &ComponentSender<Self>) {
+        let pkg = self.pkg.to_string();
+        let pname = self.pname.to_string();
+        let userpkgtype = self.userpkgtype.clone();
+        sender.oneshot_command(async move {
+            let key = match userpkgtype {
+                UserPkgs::Env => &pname,
+                UserPkgs::Profile => &pkg,
+            };
+            let previous = match profile::previousversion(key, &pname, &userpkgtype).await {
+                Ok(p) => p,
+                Err(e) => {
+                    warn!("Failed to find previous version of {}: {}", pkg, e);
+                    None
+                }
+            };
+            PkgAsyncMsg::SetPreviousVersion(pkg, previous)
+        });
     }
 }
 
+/// The cached favicon of a link, or a themed fallback icon
+fn linkicon(favicons: &HashMap<String, String>, url: Option<&str>, fallback: &str) -> gio::Icon {
+    url.and_then(favicon::domain)
+        .and_then(|x| favicons.get(&x))
+        .filter(|x| !x.is_empty())
+        .map(|x| gio::FileIcon::new(&gio::File::for_path(x)).upcast())
+        .unwrap_or_else(|| gio::ThemedIcon::new(fallback).upcast())
+}
+
+/// Runs a page shortcut, but only while the page is shown
+fn shortcutaction(sender: &ComponentSender<PkgModel>, msg: fn() -> PkgMsg) -> gtk::CallbackAction {
+    let sender = sender.clone();
+    gtk::CallbackAction::new(move |widget, _| {
+        if widget.is_mapped() {
+            sender.input(msg());
+            gtk::glib::Propagation::Stop
+        } else {
+            gtk::glib::Propagation::Proceed
+        }
+    })
+}
+
 fn launchterm(cmd: &str) {
     let _ = Command::new("kgx").arg("-e").arg(&cmd).spawn();
 }
@@ -1652,3 +2921,237 @@ relm4::new_stateless_action!(NixSystemAction, ModeActionGroup, "system");
 relm4::new_action_group!(RunActionGroup, "run");
 relm4::new_stateless_action!(LaunchAction, RunActionGroup, "launch");
 relm4::new_stateless_action!(TermShellAction, RunActionGroup, "term");
+
+/// Tells the user where to find what was just installed and which caches it came from.
+fn showintegration(
+    root: &gtk::Box,
+    sender: &ComponentSender<PkgModel>,
+    pname: &str,
+    added: &DesktopIntegration,
+    downloads: &[SubstituterUse],
+) {
+    let mut body = vec![];
+    for x in downloads {
+        body.push(gettextf("Downloaded from {} ({}).", &[&x.host, &gtk::glib::format_size(x.bytes)]));
+    }
+    if !added.applications.is_empty() {
+        body.push(gettextf("Added to the app menu: {}.", &[&added.applications.join(", ")]));
+    }
+    match added.mimetypes.len() {
+        0 => {}
+        1..=3 => body.push(gettextf("Can open {}.", &[&added.mimetypes.join(", ")])),
+        n => body.push(ngettextf(
+            "Can open {} and {} more file type.",
+            "Can open {} and {} more file types.",
+            (n - 3) as u32,
+            &[&added.mimetypes[..3].join(", "), &(n - 3).to_string()],
+        )),
+    }
+    if added.icons > 0 {
+        body.push(ngettextf(
+            "{} icon added.",
+            "{} icons added.",
+            added.icons as u32,
+            &[&added.icons.to_string()],
+        ));
+    }
+    if added.needsrestart {
+        body.push(gettext("Log out and back in for it to appear in the launcher."));
+    }
+    for conflict in &added.conflicts {
+        body.push(if conflict.masks {
+            gettextf("{} replaces a launcher that was installed already.", &[&conflict.name])
+        } else {
+            gettextf("{} now appears twice in the app menu.", &[&conflict.name])
+        });
+    }
+    let dialog = adw::MessageDialog::new(
+        root.root().and_downcast::<gtk::Window>().as_ref(),
+        Some(&gettextf("{} Installed", &[pname])),
+        Some(&body.join("\n")),
+    );
+    // Launchers with the same ID can't be hidden one at a time
+    let duplicates: Vec<_> = added.conflicts.iter().filter(|x| !x.masks).cloned().collect();
+    if duplicates.is_empty() {
+        dialog.add_response("close", &gettext("Close"));
+    } else {
+        dialog.add_response("close", &gettext("Keep Both"));
+        dialog.add_response("hideexisting", &gettext("Hide Old Launcher"));
+        dialog.add_response("hideadded", &gettext("Hide New Launcher"));
+        let sender = sender.clone();
+        dialog.connect_response(None, move |_, resp| {
+            let ids = match resp {
+                "hideexisting" => duplicates.iter().map(|x| x.existing.clone()).collect(),
+                "hideadded" => duplicates.iter().map(|x| x.added.clone()).collect(),
+                _ => return,
+            };
+            sender.input(PkgMsg::HideLaunchers(ids));
+        });
+    }
+    dialog.present();
+}
+
+/// Confirms launchers were hidden, with a way to show them again.
+fn showhidden(root: &gtk::Box, sender: &ComponentSender<PkgModel>, ids: Vec<String>) {
+    let dialog = adw::MessageDialog::new(
+        root.root().and_downcast::<gtk::Window>().as_ref(),
+        Some(&ngettext("Launcher Hidden", "Launchers Hidden", ids.len() as u32)),
+        Some(&gettext("Hidden launchers can be shown again from Preferences.")),
+    );
+    dialog.add_response("close", &gettext("Close"));
+    dialog.add_response("unhide", &gettext("Show Again"));
+    dialog.set_close_response("close");
+    let sender = sender.clone();
+    dialog.connect_response(None, move |_, resp| {
+        if resp == "unhide" {
+            sender.input(PkgMsg::UnhideLaunchers(ids.clone()));
+        }
+    });
+    dialog.present();
+}
+
+fn filldependencies(list: &gtk::ListBox, dependencies: &DependencyTree) {
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
+    }
+    if let DependencyTree::Loaded(deps) = dependencies {
+        let row = dependencyrow(deps, &deps.root);
+        if let Some(row) = row.downcast_ref::<adw::ExpanderRow>() {
+            row.set_expanded(true);
+        }
+        list.append(&row);
+    }
+}
+
+/// A row for `path` whose references are only added once it is expanded, as closures can have thousands of paths.
+fn dependencyrow(deps: &Arc<Dependencies>, path: &str) -> gtk::Widget {
+    let size = deps.narsize.get(path).map(|x| gtk::glib::format_size(*x).to_string()).unwrap_or_default();
+    let title = gtk::glib::markup_escape_text(deps::pathname(path));
+    let children = deps.children(path);
+    if children.is_empty() {
+        return adw::ActionRow::builder().title(title.as_str()).subtitle(&size).build().upcast();
+    }
+    let row = adw::ExpanderRow::builder()
+        .title(title.as_str())
+        .subtitle(&format!("{}, {} direct dependencies", size, children.len()))
+        .build();
+    let filled = Cell::new(false);
+    let deps = deps.clone();
+    let path = path.to_string();
+    row.connect_expanded_notify(move |row| {
+        if row.is_expanded() && !filled.replace(true) {
+            for child in deps.children(&path) {
+                row.add_row(&dependencyrow(&deps, child));
+            }
+        }
+    });
+    row.upcast()
+}
+
+const DEFAULTSUBSTITUTER: &str = "cache.nixos.org";
+
+/// Installs that unpack to more than this ask before going ahead.
+const LARGEINSTALL: u64 = 1024 * 1024 * 1024;
+
+/// Large downloads and local builds can take a long while, so show what an install involves first.
+fn showclosuresize(root: &gtk::Box, sender: &ComponentSender<PkgModel>, name: &str, size: &ClosureSize) {
+    let mut body = vec![];
+    if size.paths > 0 {
+        body.push(ngettextf(
+            "{} store path will be downloaded: {} to download, {} on disk once unpacked.",
+            "{} store paths will be downloaded: {} to download, {} on disk once unpacked.",
+            size.paths as u32,
+            &[
+                &size.paths.to_string(),
+                &gtk::glib::format_size(size.download),
+                &gtk::glib::format_size(size.unpacked),
+            ],
+        ));
+    }
+    if size.builds > 0 {
+        body.push(ngettextf(
+            "{} package is not in the binary cache and will be built on this computer, which can take a long time.",
+            "{} packages are not in the binary cache and will be built on this computer, which can take a long time.",
+            size.builds as u32,
+            &[&size.builds.to_string()],
+        ));
+    }
+    let dialog = adw::MessageDialog::new(
+        root.root().and_downcast::<gtk::Window>().as_ref(),
+        Some(&gettextf("Install {}?", &[name])),
+        Some(&body.join("\n\n")),
+    );
+    dialog.add_response("cancel", &gettext("Cancel"));
+    dialog.add_response("install", &gettext("Install"));
+    dialog.set_response_appearance("install", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("install"));
+    dialog.set_close_response("cancel");
+    let sender = sender.clone();
+    dialog.connect_response(None, move |_, resp| {
+        if resp == "install" {
+            sender.input(PkgMsg::QueueInstallUser(PkgAction::Install));
+        }
+    });
+    dialog.present();
+}
+
+/// Libraries installed on their own aren't found by their interpreter, so offer to install
+/// the interpreter bundled with them instead.
+fn showwithpackages(root: &gtk::Box, sender: &ComponentSender<PkgModel>, interpreter: &str, library: &str) {
+    let dialog = adw::MessageDialog::new(
+        root.root().and_downcast::<gtk::Window>().as_ref(),
+        Some(&gettextf("Install {} With {}?", &[interpreter, library])),
+        Some(&gettextf(
+            "{} is a library, and {} won't find it when it is installed on its own. Install {} bundled with it instead, adding any other libraries you need.",
+            &[library, interpreter, interpreter],
+        )),
+    );
+    let entry = gtk::Entry::builder()
+        .placeholder_text(gettext("Other libraries, separated by spaces"))
+        .build();
+    let preview = gtk::Label::builder()
+        .css_classes(["monospace", "dim-label"])
+        .wrap(true)
+        .wrap_mode(pango::WrapMode::WordChar)
+        .selectable(true)
+        .xalign(0.0)
+        .build();
+    let libraries = {
+        let entry = entry.clone();
+        let library = library.to_string();
+        move || {
+            std::iter::once(library.clone())
+                .chain(entry.text().split_whitespace().map(String::from))
+                .collect::<Vec<_>>()
+        }
+    };
+    preview.set_label(&packages::withpackagesexpr(interpreter, &libraries()));
+    {
+        let preview = preview.clone();
+        let interpreter = interpreter.to_string();
+        let libraries = libraries.clone();
+        entry.connect_changed(move |_| {
+            preview.set_label(&packages::withpackagesexpr(&interpreter, &libraries()));
+        });
+    }
+    let extra = gtk::Box::new(gtk::Orientation::Vertical, 10);
+    extra.append(&entry);
+    extra.append(&preview);
+    dialog.set_extra_child(Some(&extra));
+    dialog.add_response("cancel", &gettext("Cancel"));
+    dialog.add_response("library", &gettext("Library Only"));
+    dialog.add_response("env", &gettextf("Install {}", &[interpreter]));
+    dialog.set_response_appearance("env", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("env"));
+    let sender = sender.clone();
+    let interpreter = interpreter.to_string();
+    dialog.connect_response(None, move |_, resp| match resp {
+        "env" => sender.input(PkgMsg::QueueInstallUser(PkgAction::InstallEnv(packages::withpackagesexpr(
+            &interpreter,
+            &libraries(),
+        )))),
+        "library" => sender.input(PkgMsg::QueueInstallUser(PkgAction::Install)),
+        _ => {}
+    });
+    dialog.present();
+}
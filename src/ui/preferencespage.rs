@@ -1,9 +1,21 @@
+use crate::i18n::{gettext, gettextf, ngettextf};
+use std::convert::identity;
 use std::path::{PathBuf, Path};
 use super::window::AppMsg;
+use crate::parse::config::NscConfig;
+use crate::parse::crash::{self, CrashKind, CrashReport};
+use crate::parse::http;
+use crate::parse::integration;
+use crate::parse::metadata;
+use crate::parse::nixpkgsconfig::AllowKind;
+use crate::parse::profile;
+use crate::parse::remote;
+use crate::parse::state::{self, OperationRecord};
 use adw::prelude::*;
+use log::*;
 use nix_data::config::configfile::NixDataConfig;
 use gtk::glib;
-use relm4::*;
+use relm4::{factory::FactoryVecDeque, *};
 use relm4_components::open_dialog::*;
 
 #[tracker::track]
@@ -12,21 +24,57 @@ pub struct PreferencesPageModel {
     configpath: Option<PathBuf>,
     flake: Option<PathBuf>,
     flakearg: Option<String>,
+    nscconfig: NscConfig,
     #[tracker::no_eq]
     open_dialog: Controller<OpenDialog>,
     #[tracker::no_eq]
     flake_file_dialog: Controller<OpenDialog>,
+    #[tracker::no_eq]
+    bundle_dialog: Controller<OpenDialog>,
+    #[tracker::no_eq]
+    allowed: FactoryVecDeque<AllowedItem>,
+    #[tracker::no_eq]
+    hosts: FactoryVecDeque<HostItem>,
+    #[tracker::no_eq]
+    crashes: FactoryVecDeque<CrashItem>,
+    #[tracker::no_eq]
+    hiddenlaunchers: FactoryVecDeque<HiddenLauncherItem>,
+    /// Loading the previous metadata instead of the latest
+    pinnedmetadata: bool,
+    haspreviousmetadata: bool,
+    /// Saved statistics of finished operations
+    operations: Vec<OperationRecord>,
 }
 
 #[derive(Debug)]
 pub enum PreferencesPageMsg {
-    Show(NixDataConfig),
+    Show(NixDataConfig, NscConfig),
     Open,
     OpenFlake,
     SetConfigPath(Option<PathBuf>),
     SetFlakePath(Option<PathBuf>),
     SetFlakeArg(Option<String>),
     ModifyFlake,
+    SetMaxJobs(Option<u32>),
+    SetCores(Option<u32>),
+    SetMaxDownloads(Option<u32>),
+    SetLowPriority(bool),
+    SetImpure(bool),
+    SetIgnorePowerSaving(bool),
+    OpenBundle,
+    SetBundle(Option<PathBuf>),
+    SetPopularity(bool),
+    SetPopularityUrl(Option<String>),
+    SetPopularityData(Option<String>),
+    SetCrashReports(bool),
+    SetCrashReportsUrl(Option<String>),
+    RemoveCrashReport(PathBuf),
+    RemoveAllowed(AllowKind, String),
+    SetHiddenLaunchers(Vec<String>),
+    UnhideLauncher(String),
+    AddHost(String),
+    RemoveHost(String),
+    ToggleMetadata,
     Ignore,
 }
 
@@ -48,7 +96,7 @@ impl SimpleComponent for PreferencesPageModel {
                     // set_title: "Preferences",
                     set_visible: Path::new("/etc/NIXOS").exists(),
                     add = &adw::ActionRow {
-                        set_title: "Configuration file",
+                        set_title: &gettext("Configuration file"),
                         add_suffix = &gtk::Box {
                             set_orientation: gtk::Orientation::Horizontal,
                             set_halign: gtk::Align::End,
@@ -86,7 +134,7 @@ impl SimpleComponent for PreferencesPageModel {
                         }
                     },
                     add = &adw::ActionRow {
-                        set_title: "Use nix flakes",
+                        set_title: &gettext("Use nix flakes"),
                         add_suffix = &gtk::Switch {
                             set_valign: gtk::Align::Center,
                             connect_state_set[sender] => move |_, b| {
@@ -104,7 +152,7 @@ impl SimpleComponent for PreferencesPageModel {
                         }
                     },
                     add = &adw::ActionRow {
-                        set_title: "Flake file",
+                        set_title: &gettext("Flake file"),
                         #[watch]
                         set_visible: model.flake.is_some(),
                         add_suffix = &gtk::Box {
@@ -151,7 +199,7 @@ impl SimpleComponent for PreferencesPageModel {
                     add = &adw::EntryRow {
                         #[watch]
                         set_visible: model.flake.is_some(),
-                        set_title: "Flake arguments (--flake path/to/flake.nix#<THIS ENTRY>)",
+                        set_title: &gettext("Flake arguments (--flake path/to/flake.nix#<THIS ENTRY>)"),
                         set_use_markup: false,
                         set_use_markup: false,
                         connect_changed[sender] => move |x| {
@@ -168,6 +216,334 @@ impl SimpleComponent for PreferencesPageModel {
                         set_text: model.flakearg.as_ref().unwrap_or(&String::new())
                     }
 
+                },
+                add = &adw::PreferencesGroup {
+                    set_title: &gettext("Advanced"),
+                    add = &adw::ActionRow {
+                        set_title: &gettext("Managed profile"),
+                        set_subtitle: &profile::userprofile().unwrap_or_default(),
+                        set_tooltip_text: Some(&gettext("Set NIX_PROFILE to manage a different profile")),
+                    },
+                    add = &adw::ActionRow {
+                        set_title: &gettext("Maximum parallel builds"),
+                        set_subtitle: &gettext("0 uses the nix default"),
+                        add_suffix = &gtk::SpinButton::with_range(0.0, 128.0, 1.0) {
+                            set_valign: gtk::Align::Center,
+                            connect_value_changed[sender] => move |x| {
+                                let v = x.value_as_int();
+                                sender.input(PreferencesPageMsg::SetMaxJobs(if v > 0 { Some(v as u32) } else { None }));
+                            } @maxjobschanged,
+                            #[track(model.changed(PreferencesPageModel::nscconfig()))]
+                            #[block_signal(maxjobschanged)]
+                            set_value: model.nscconfig.maxjobs.unwrap_or(0) as f64,
+                        }
+                    },
+                    add = &adw::ActionRow {
+                        set_title: &gettext("Cores per build"),
+                        set_subtitle: &gettext("0 uses all available cores"),
+                        add_suffix = &gtk::SpinButton::with_range(0.0, 256.0, 1.0) {
+                            set_valign: gtk::Align::Center,
+                            connect_value_changed[sender] => move |x| {
+                                let v = x.value_as_int();
+                                sender.input(PreferencesPageMsg::SetCores(if v > 0 { Some(v as u32) } else { None }));
+                            } @coreschanged,
+                            #[track(model.changed(PreferencesPageModel::nscconfig()))]
+                            #[block_signal(coreschanged)]
+                            set_value: model.nscconfig.cores.unwrap_or(0) as f64,
+                        }
+                    },
+                    add = &adw::ActionRow {
+                        set_title: &gettext("Parallel screenshot downloads"),
+                        set_subtitle: &gettext("Lower this on slow connections"),
+                        add_suffix = &gtk::SpinButton::with_range(1.0, 16.0, 1.0) {
+                            set_valign: gtk::Align::Center,
+                            connect_value_changed[sender] => move |x| {
+                                let v = x.value_as_int() as u32;
+                                sender.input(PreferencesPageMsg::SetMaxDownloads(if v != http::DEFAULT_MAXDOWNLOADS { Some(v) } else { None }));
+                            } @maxdownloadschanged,
+                            #[track(model.changed(PreferencesPageModel::nscconfig()))]
+                            #[block_signal(maxdownloadschanged)]
+                            set_value: model.nscconfig.maxdownloads.unwrap_or(http::DEFAULT_MAXDOWNLOADS) as f64,
+                        }
+                    },
+                    add = &adw::ActionRow {
+                        set_title: &gettext("Low priority"),
+                        set_subtitle: &gettext("Run nix under nice and ionice to keep the system responsive"),
+                        add_suffix = &gtk::Switch {
+                            set_valign: gtk::Align::Center,
+                            connect_state_set[sender] => move |_, b| {
+                                sender.input(PreferencesPageMsg::SetLowPriority(b));
+                                glib::Propagation::Proceed
+                            } @lowprioritychanged,
+                            #[track(model.changed(PreferencesPageModel::nscconfig()))]
+                            #[block_signal(lowprioritychanged)]
+                            set_state: model.nscconfig.lowpriority,
+                        }
+                    },
+                    add = &adw::ActionRow {
+                        set_title: &gettext("Refresh while saving power"),
+                        set_subtitle: &gettext("Check for new package data and updates in power saver mode or below 20% battery"),
+                        add_suffix = &gtk::Switch {
+                            set_valign: gtk::Align::Center,
+                            connect_state_set[sender] => move |_, b| {
+                                sender.input(PreferencesPageMsg::SetIgnorePowerSaving(b));
+                                glib::Propagation::Proceed
+                            } @ignorepowersavingchanged,
+                            #[track(model.changed(PreferencesPageModel::nscconfig()))]
+                            #[block_signal(ignorepowersavingchanged)]
+                            set_state: model.nscconfig.ignorepowersaving,
+                        }
+                    },
+                    add = &adw::ActionRow {
+                        set_title: &gettext("Impure evaluation"),
+                        set_subtitle: &gettext("Pass --impure so that environment variables like NIXPKGS_ALLOW_UNFREE are respected"),
+                        add_suffix = &gtk::Switch {
+                            set_valign: gtk::Align::Center,
+                            connect_state_set[sender] => move |_, b| {
+                                sender.input(PreferencesPageMsg::SetImpure(b));
+                                glib::Propagation::Proceed
+                            } @impurechanged,
+                            #[track(model.changed(PreferencesPageModel::nscconfig()))]
+                            #[block_signal(impurechanged)]
+                            set_state: model.nscconfig.impure,
+                        }
+                    },
+                    add = &adw::ActionRow {
+                        set_title: &gettext("Offline metadata"),
+                        set_subtitle: &gettext("Package database to use when it can't be downloaded"),
+                        add_suffix = &gtk::Box {
+                            set_orientation: gtk::Orientation::Horizontal,
+                            set_halign: gtk::Align::End,
+                            set_valign: gtk::Align::Center,
+                            set_spacing: 10,
+                            gtk::Button {
+                                gtk::Box {
+                                    set_orientation: gtk::Orientation::Horizontal,
+                                    set_spacing: 5,
+                                    gtk::Image {
+                                        set_icon_name: Some("document-open-symbolic"),
+                                    },
+                                    gtk::Label {
+                                        #[watch]
+                                        set_label: model.nscconfig.bundle.as_ref().and_then(|x| Path::new(x).file_name()).and_then(|x| x.to_str()).unwrap_or("(None)"),
+                                    }
+                                },
+                                connect_clicked[sender] => move |_| {
+                                    sender.input(PreferencesPageMsg::OpenBundle);
+                                }
+                            },
+                            gtk::Button {
+                                set_icon_name: "user-trash-symbolic",
+                                connect_clicked[sender] => move |_| {
+                                    sender.input(PreferencesPageMsg::SetBundle(None));
+                                }
+                            }
+                        }
+                    },
+                    add = &adw::ActionRow {
+                        #[watch]
+                        set_visible: model.pinnedmetadata || model.haspreviousmetadata,
+                        set_title: &gettext("Previous metadata"),
+                        #[watch]
+                        set_subtitle: &if model.pinnedmetadata {
+                            gettext("The package data from before the last update is in use, and updates to it are paused")
+                        } else {
+                            gettext("Go back to the package data from before the last update if the latest is broken")
+                        },
+                        add_suffix = &gtk::Button {
+                            set_valign: gtk::Align::Center,
+                            #[watch]
+                            set_label: &if model.pinnedmetadata { gettext("Use Latest") } else { gettext("Revert") },
+                            connect_clicked[sender] => move |_| {
+                                sender.input(PreferencesPageMsg::ToggleMetadata);
+                            }
+                        }
+                    },
+                },
+                add = &adw::PreferencesGroup {
+                    set_title: &gettext("Privacy"),
+                    add = &adw::ActionRow {
+                        set_title: &gettext("Share popularity statistics"),
+                        set_subtitle: &gettext("Once a week, anonymously send the names of installed packages to help rank popular apps"),
+                        add_suffix = &gtk::Switch {
+                            set_valign: gtk::Align::Center,
+                            connect_state_set[sender] => move |_, b| {
+                                sender.input(PreferencesPageMsg::SetPopularity(b));
+                                glib::Propagation::Proceed
+                            } @popularitychanged,
+                            #[track(model.changed(PreferencesPageModel::nscconfig()))]
+                            #[block_signal(popularitychanged)]
+                            set_state: model.nscconfig.popularity,
+                        }
+                    },
+                    add = &adw::EntryRow {
+                        #[watch]
+                        set_visible: model.nscconfig.popularity,
+                        set_title: &gettext("Statistics server"),
+                        set_show_apply_button: true,
+                        connect_apply[sender] => move |x| {
+                            sender.input(PreferencesPageMsg::SetPopularityUrl({
+                                let text = x.text().trim().to_string();
+                                if text.is_empty() {
+                                    None
+                                } else {
+                                    Some(text)
+                                }}));
+                        } @popularityurlentry,
+                        #[track(model.changed(PreferencesPageModel::nscconfig()))]
+                        #[block_signal(popularityurlentry)]
+                        set_text: model.nscconfig.popularityurl.as_deref().unwrap_or_default(),
+                    },
+                    add = &adw::EntryRow {
+                        set_title: &gettext("Popularity dataset (used for ranking and trending apps)"),
+                        set_show_apply_button: true,
+                        connect_apply[sender] => move |x| {
+                            sender.input(PreferencesPageMsg::SetPopularityData({
+                                let text = x.text().trim().to_string();
+                                if text.is_empty() {
+                                    None
+                                } else {
+                                    Some(text)
+                                }}));
+                        } @popularitydataentry,
+                        #[track(model.changed(PreferencesPageModel::nscconfig()))]
+                        #[block_signal(popularitydataentry)]
+                        set_text: model.nscconfig.popularitydata.as_deref().unwrap_or_default(),
+                    },
+                },
+                add = &adw::PreferencesGroup {
+                    set_title: &gettext("Remote Hosts"),
+                    set_description: Some(&gettext("Manage the user profile on other machines over SSH. They need nix and key based login.")),
+                    add = &adw::EntryRow {
+                        set_title: &gettext("Add host"),
+                        set_show_apply_button: true,
+                        connect_apply[sender] => move |x| {
+                            let text = x.text().trim().to_string();
+                            if !text.is_empty() {
+                                sender.input(PreferencesPageMsg::AddHost(text));
+                            }
+                            x.set_text("");
+                        }
+                    },
+                    #[local_ref]
+                    hostlist -> gtk::ListBox {
+                        #[watch]
+                        set_visible: !model.hosts.is_empty(),
+                        set_margin_top: 12,
+                        add_css_class: "boxed-list",
+                        set_selection_mode: gtk::SelectionMode::None,
+                    }
+                },
+                add = &adw::PreferencesGroup {
+                    set_title: &gettext("Crash Reports"),
+                    set_description: Some(&gettext("Reports of crashes and operations that keep failing are kept on this computer")),
+                    add = &adw::ActionRow {
+                        set_title: &gettext("Send crash reports"),
+                        set_subtitle: &gettext("Send new reports to the crash report server to help fix them"),
+                        add_suffix = &gtk::Switch {
+                            set_valign: gtk::Align::Center,
+                            connect_state_set[sender] => move |_, b| {
+                                sender.input(PreferencesPageMsg::SetCrashReports(b));
+                                glib::Propagation::Proceed
+                            } @crashreportschanged,
+                            #[track(model.changed(PreferencesPageModel::nscconfig()))]
+                            #[block_signal(crashreportschanged)]
+                            set_state: model.nscconfig.crashreports,
+                        }
+                    },
+                    add = &adw::EntryRow {
+                        #[watch]
+                        set_visible: model.nscconfig.crashreports,
+                        set_title: &gettext("Crash report server"),
+                        set_show_apply_button: true,
+                        connect_apply[sender] => move |x| {
+                            sender.input(PreferencesPageMsg::SetCrashReportsUrl({
+                                let text = x.text().trim().to_string();
+                                if text.is_empty() {
+                                    None
+                                } else {
+                                    Some(text)
+                                }}));
+                        } @crashreportsurlentry,
+                        #[track(model.changed(PreferencesPageModel::nscconfig()))]
+                        #[block_signal(crashreportsurlentry)]
+                        set_text: model.nscconfig.crashreportsurl.as_deref().unwrap_or_default(),
+                    },
+                    #[local_ref]
+                    crashlist -> gtk::ListBox {
+                        #[watch]
+                        set_visible: !model.crashes.is_empty(),
+                        set_margin_top: 12,
+                        add_css_class: "boxed-list",
+                        set_selection_mode: gtk::SelectionMode::None,
+                    }
+                },
+                add = &adw::PreferencesGroup {
+                    set_title: &gettext("Allowed Packages"),
+                    set_description: Some(&gettext("Unfree and insecure packages you chose to install")),
+                    #[watch]
+                    set_visible: !model.allowed.is_empty(),
+                    #[local_ref]
+                    allowedlist -> gtk::ListBox {
+                        add_css_class: "boxed-list",
+                        set_selection_mode: gtk::SelectionMode::None,
+                    }
+                },
+                add = &adw::PreferencesGroup {
+                    set_title: &gettext("Statistics"),
+                    set_description: Some(&gettext("Installs, removals and updates of user packages")),
+                    #[watch]
+                    set_visible: !model.operations.is_empty(),
+                    add = &adw::ActionRow {
+                        set_title: &gettext("Operations"),
+                        add_suffix = &gtk::Label {
+                            #[watch]
+                            set_label: &{
+                                let failed = model.operations.iter().filter(|x| !x.success).count();
+                                if failed == 0 {
+                                    model.operations.len().to_string()
+                                } else {
+                                    gettextf("{} ({} failed)", &[&model.operations.len().to_string(), &failed.to_string()])
+                                }
+                            },
+                        }
+                    },
+                    add = &adw::ActionRow {
+                        set_title: &gettext("Time spent"),
+                        add_suffix = &gtk::Label {
+                            #[watch]
+                            set_label: &formatduration(state::operationtotals(&model.operations).duration),
+                        }
+                    },
+                    add = &adw::ActionRow {
+                        set_title: &gettext("Downloaded"),
+                        add_suffix = &gtk::Label {
+                            #[watch]
+                            set_label: &glib::format_size(state::operationtotals(&model.operations).downloaded),
+                        }
+                    },
+                    add = &adw::ActionRow {
+                        set_title: &gettext("Store paths"),
+                        set_subtitle: &gettext("Fetched from binary caches or built on this computer"),
+                        add_suffix = &gtk::Label {
+                            #[watch]
+                            set_label: &{
+                                let totals = state::operationtotals(&model.operations);
+                                gettextf("{} fetched, {} built", &[&totals.substituted.to_string(), &totals.built.to_string()])
+                            },
+                        }
+                    },
+                },
+                add = &adw::PreferencesGroup {
+                    set_title: &gettext("Hidden Launchers"),
+                    set_description: Some(&gettext("Launchers hidden from the app menu because another one had the same name")),
+                    #[watch]
+                    set_visible: !model.hiddenlaunchers.is_empty(),
+                    #[local_ref]
+                    hiddenlist -> gtk::ListBox {
+                        add_css_class: "boxed-list",
+                        set_selection_mode: gtk::SelectionMode::None,
+                    }
                 }
             }
         }
@@ -192,15 +568,44 @@ impl SimpleComponent for PreferencesPageModel {
                 OpenDialogResponse::Accept(path) => PreferencesPageMsg::SetFlakePath(Some(path)),
                 OpenDialogResponse::Cancel => PreferencesPageMsg::Ignore,
             });
+        let bundle_dialog = OpenDialog::builder()
+            .transient_for_native(&root)
+            .launch(OpenDialogSettings::default())
+            .forward(sender.input_sender(), |response| match response {
+                OpenDialogResponse::Accept(path) => PreferencesPageMsg::SetBundle(Some(path)),
+                OpenDialogResponse::Cancel => PreferencesPageMsg::Ignore,
+            });
         let model = PreferencesPageModel {
             configpath: None,
             flake: None,
             flakearg: None,
+            nscconfig: NscConfig::default(),
             open_dialog,
             flake_file_dialog,
+            bundle_dialog,
+            allowed: FactoryVecDeque::builder()
+                .launch(gtk::ListBox::new())
+                .forward(sender.input_sender(), identity),
+            hosts: FactoryVecDeque::builder()
+                .launch(gtk::ListBox::new())
+                .forward(sender.input_sender(), identity),
+            crashes: FactoryVecDeque::builder()
+                .launch(gtk::ListBox::new())
+                .forward(sender.input_sender(), identity),
+            hiddenlaunchers: FactoryVecDeque::builder()
+                .launch(gtk::ListBox::new())
+                .forward(sender.input_sender(), identity),
+            pinnedmetadata: false,
+            haspreviousmetadata: false,
+            operations: vec![],
             tracker: 0,
         };
 
+        let allowedlist = model.allowed.widget();
+        let hostlist = model.hosts.widget();
+        let crashlist = model.crashes.widget();
+        let hiddenlist = model.hiddenlaunchers.widget();
+
         let widgets = view_output!();
 
         ComponentParts { model, widgets }
@@ -209,13 +614,27 @@ impl SimpleComponent for PreferencesPageModel {
     fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
         self.reset();
         match msg {
-            PreferencesPageMsg::Show(config) => {
+            PreferencesPageMsg::Show(config, nscconfig) => {
                 self.configpath = config.systemconfig.as_ref().map(PathBuf::from);
                 self.set_flake(config.flake.as_ref().map(PathBuf::from));
                 self.set_flakearg(config.flakearg);
+                self.set_nscconfig(nscconfig);
+                self.setallowed();
+                self.sethosts();
+                self.setcrashes();
+                loadhiddenlaunchers(&sender, None);
+                let nscstate = state::getnscstate();
+                self.pinnedmetadata = nscstate.pinnedmetadata;
+                self.operations = nscstate.operations;
+                self.haspreviousmetadata = metadata::previous().is_some();
+            }
+            PreferencesPageMsg::ToggleMetadata => {
+                self.pinnedmetadata = !self.pinnedmetadata;
+                sender.output(AppMsg::PinMetadata(self.pinnedmetadata));
             }
             PreferencesPageMsg::Open => self.open_dialog.emit(OpenDialogMsg::Open),
             PreferencesPageMsg::OpenFlake => self.flake_file_dialog.emit(OpenDialogMsg::Open),
+            PreferencesPageMsg::OpenBundle => self.bundle_dialog.emit(OpenDialogMsg::Open),
             PreferencesPageMsg::SetConfigPath(path) => {
                 self.configpath = path.clone();
                 sender.output(AppMsg::UpdateSysconfig(path.map(|x| x.to_string_lossy().to_string())));
@@ -231,7 +650,342 @@ impl SimpleComponent for PreferencesPageModel {
             PreferencesPageMsg::ModifyFlake => {
                 sender.output(AppMsg::UpdateFlake(self.flake.as_ref().map(|x| x.to_string_lossy().to_string()), self.flakearg.clone()));
             }
+            PreferencesPageMsg::SetMaxJobs(jobs) => {
+                self.nscconfig.maxjobs = jobs;
+                sender.output(AppMsg::UpdateNscConfig(self.nscconfig.clone()));
+            }
+            PreferencesPageMsg::SetCores(cores) => {
+                self.nscconfig.cores = cores;
+                sender.output(AppMsg::UpdateNscConfig(self.nscconfig.clone()));
+            }
+            PreferencesPageMsg::SetMaxDownloads(max) => {
+                self.nscconfig.maxdownloads = max;
+                sender.output(AppMsg::UpdateNscConfig(self.nscconfig.clone()));
+            }
+            PreferencesPageMsg::SetLowPriority(lowpriority) => {
+                self.nscconfig.lowpriority = lowpriority;
+                sender.output(AppMsg::UpdateNscConfig(self.nscconfig.clone()));
+            }
+            PreferencesPageMsg::SetImpure(impure) => {
+                self.nscconfig.impure = impure;
+                sender.output(AppMsg::UpdateNscConfig(self.nscconfig.clone()));
+            }
+            PreferencesPageMsg::SetIgnorePowerSaving(ignore) => {
+                self.nscconfig.ignorepowersaving = ignore;
+                sender.output(AppMsg::UpdateNscConfig(self.nscconfig.clone()));
+            }
+            PreferencesPageMsg::SetBundle(path) => {
+                self.nscconfig.bundle = path.map(|x| x.to_string_lossy().to_string());
+                sender.output(AppMsg::UpdateNscConfig(self.nscconfig.clone()));
+            }
+            PreferencesPageMsg::SetPopularity(popularity) => {
+                self.nscconfig.popularity = popularity;
+                sender.output(AppMsg::UpdateNscConfig(self.nscconfig.clone()));
+            }
+            PreferencesPageMsg::SetPopularityUrl(url) => {
+                self.nscconfig.popularityurl = url;
+                sender.output(AppMsg::UpdateNscConfig(self.nscconfig.clone()));
+            }
+            PreferencesPageMsg::SetPopularityData(url) => {
+                self.nscconfig.popularitydata = url;
+                sender.output(AppMsg::UpdateNscConfig(self.nscconfig.clone()));
+            }
+            PreferencesPageMsg::SetCrashReports(crashreports) => {
+                self.nscconfig.crashreports = crashreports;
+                sender.output(AppMsg::UpdateNscConfig(self.nscconfig.clone()));
+            }
+            PreferencesPageMsg::SetCrashReportsUrl(url) => {
+                self.nscconfig.crashreportsurl = url;
+                sender.output(AppMsg::UpdateNscConfig(self.nscconfig.clone()));
+            }
+            PreferencesPageMsg::RemoveCrashReport(path) => {
+                if let Err(e) = crash::remove(&path) {
+                    warn!("Failed to remove {}: {}", path.display(), e);
+                }
+                self.setcrashes();
+            }
+            PreferencesPageMsg::RemoveAllowed(kind, name) => {
+                match kind {
+                    AllowKind::Unfree => self.nscconfig.allowunfree.retain(|x| x != &name),
+                    AllowKind::Insecure => self.nscconfig.allowinsecure.retain(|x| x != &name),
+                }
+                self.setallowed();
+                sender.output(AppMsg::UpdateNscConfig(self.nscconfig.clone()));
+            }
+            PreferencesPageMsg::SetHiddenLaunchers(ids) => {
+                let mut hidden = self.hiddenlaunchers.guard();
+                hidden.clear();
+                for id in ids {
+                    hidden.push_back(HiddenLauncherItem { id });
+                }
+            }
+            PreferencesPageMsg::UnhideLauncher(id) => {
+                loadhiddenlaunchers(&sender, Some(id));
+            }
+            PreferencesPageMsg::AddHost(host) => {
+                if !self.nscconfig.remotehosts.contains(&host) {
+                    self.nscconfig.remotehosts.push(host);
+                    self.sethosts();
+                    sender.output(AppMsg::UpdateNscConfig(self.nscconfig.clone()));
+                }
+            }
+            PreferencesPageMsg::RemoveHost(host) => {
+                self.nscconfig.remotehosts.retain(|x| x != &host);
+                self.sethosts();
+                sender.output(AppMsg::UpdateNscConfig(self.nscconfig.clone()));
+            }
             _ => {}
         }
     }
 }
+
+/// Lists the hidden launchers off the main thread, after showing `unhide` again if given.
+/// `secs` as hours and minutes, or seconds when shorter than a minute.
+fn formatduration(secs: u64) -> String {
+    let (hours, minutes) = (secs / 3600, secs % 3600 / 60);
+    if hours > 0 {
+        gettextf("{} h {} min", &[&hours.to_string(), &minutes.to_string()])
+    } else if minutes > 0 {
+        gettextf("{} min", &[&minutes.to_string()])
+    } else {
+        ngettextf("{} second", "{} seconds", secs as u32, &[&secs.to_string()])
+    }
+}
+
+fn loadhiddenlaunchers(sender: &ComponentSender<PreferencesPageModel>, unhide: Option<String>) {
+    let sender = sender.clone();
+    relm4::spawn_blocking(move || {
+        if let Some(id) = unhide {
+            match integration::unhidelauncher(&id) {
+                Ok(()) => info!("Showed launcher {} again", id),
+                Err(e) => warn!("Failed to show launcher {} again: {}", id, e),
+            }
+        }
+        sender.input(PreferencesPageMsg::SetHiddenLaunchers(integration::hiddenlaunchers()));
+    });
+}
+
+impl PreferencesPageModel {
+    /// Added hosts, followed by the ones from the SSH config that could be added.
+    fn sethosts(&mut self) {
+        let mut hosts = self.hosts.guard();
+        hosts.clear();
+        for name in &self.nscconfig.remotehosts {
+            hosts.push_back(HostItem {
+                name: name.to_string(),
+                added: true,
+            });
+        }
+        for name in remote::sshhosts() {
+            if !self.nscconfig.remotehosts.contains(&name) {
+                hosts.push_back(HostItem { name, added: false });
+            }
+        }
+    }
+
+    fn setcrashes(&mut self) {
+        let mut crashes = self.crashes.guard();
+        crashes.clear();
+        for (path, report) in crash::reports() {
+            crashes.push_back(CrashItem { path, report });
+        }
+    }
+
+    fn setallowed(&mut self) {
+        let mut allowed = self.allowed.guard();
+        allowed.clear();
+        for name in &self.nscconfig.allowunfree {
+            allowed.push_back(AllowedItem {
+                kind: AllowKind::Unfree,
+                name: name.to_string(),
+            });
+        }
+        for name in &self.nscconfig.allowinsecure {
+            allowed.push_back(AllowedItem {
+                kind: AllowKind::Insecure,
+                name: name.to_string(),
+            });
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct AllowedItem {
+    pub kind: AllowKind,
+    pub name: String,
+}
+
+#[relm4::factory(pub)]
+impl FactoryComponent for AllowedItem {
+    type CommandOutput = ();
+    type Init = AllowedItem;
+    type Input = ();
+    type Output = PreferencesPageMsg;
+    type ParentWidget = gtk::ListBox;
+
+    view! {
+        adw::ActionRow {
+            set_title: &self.name,
+            set_subtitle: &match self.kind {
+                AllowKind::Unfree => gettext("Unfree license"),
+                AllowKind::Insecure => gettext("Marked as insecure"),
+            },
+            add_suffix = &gtk::Button {
+                add_css_class: "flat",
+                set_valign: gtk::Align::Center,
+                set_icon_name: "user-trash-symbolic",
+                set_tooltip_text: Some(&gettext("Revoke")),
+                connect_clicked[sender, kind = self.kind.clone(), name = self.name.clone()] => move |_| {
+                    let _ = sender.output(PreferencesPageMsg::RemoveAllowed(kind.clone(), name.clone()));
+                }
+            }
+        }
+    }
+
+    fn init_model(init: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+        init
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct HiddenLauncherItem {
+    /// Desktop ID, e.g. `org.gnome.Maps.desktop`
+    pub id: String,
+}
+
+#[relm4::factory(pub)]
+impl FactoryComponent for HiddenLauncherItem {
+    type CommandOutput = ();
+    type Init = HiddenLauncherItem;
+    type Input = ();
+    type Output = PreferencesPageMsg;
+    type ParentWidget = gtk::ListBox;
+
+    view! {
+        adw::ActionRow {
+            set_title: &self.id,
+            add_suffix = &gtk::Button {
+                add_css_class: "flat",
+                set_valign: gtk::Align::Center,
+                set_icon_name: "view-reveal-symbolic",
+                set_tooltip_text: Some(&gettext("Show in app menu")),
+                connect_clicked[sender, id = self.id.clone()] => move |_| {
+                    let _ = sender.output(PreferencesPageMsg::UnhideLauncher(id.clone()));
+                }
+            }
+        }
+    }
+
+    fn init_model(init: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+        init
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct HostItem {
+    pub name: String,
+    /// Otherwise only found in the SSH config
+    pub added: bool,
+}
+
+#[relm4::factory(pub)]
+impl FactoryComponent for HostItem {
+    type CommandOutput = ();
+    type Init = HostItem;
+    type Input = ();
+    type Output = PreferencesPageMsg;
+    type ParentWidget = gtk::ListBox;
+
+    view! {
+        adw::ActionRow {
+            set_title: &self.name,
+            set_subtitle: &if self.added { String::new() } else { gettext("From SSH config") },
+            add_suffix = &gtk::Button {
+                add_css_class: "flat",
+                set_valign: gtk::Align::Center,
+                set_icon_name: if self.added { "user-trash-symbolic" } else { "list-add-symbolic" },
+                set_tooltip_text: Some(if self.added { "Remove" } else { "Add" }),
+                connect_clicked[sender, added = self.added, name = self.name.clone()] => move |_| {
+                    let _ = sender.output(if added {
+                        PreferencesPageMsg::RemoveHost(name.clone())
+                    } else {
+                        PreferencesPageMsg::AddHost(name.clone())
+                    });
+                }
+            }
+        }
+    }
+
+    fn init_model(init: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+        init
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CrashItem {
+    pub path: PathBuf,
+    pub report: CrashReport,
+}
+
+#[relm4::factory(pub)]
+impl FactoryComponent for CrashItem {
+    type CommandOutput = ();
+    type Init = CrashItem;
+    type Input = ();
+    type Output = PreferencesPageMsg;
+    type ParentWidget = gtk::ListBox;
+
+    view! {
+        adw::ExpanderRow {
+            set_title: &match self.report.kind {
+                CrashKind::Panic => gettextf("Crash in {}", &[&self.report.component]),
+                CrashKind::Failure => gettextf("{} keeps failing", &[&self.report.component]),
+            },
+            set_subtitle: &glib::DateTime::from_unix_local(self.report.time as i64)
+                .and_then(|x| x.format("%c"))
+                .map(|x| x.to_string())
+                .unwrap_or_default(),
+            add_action = &gtk::Box {
+                set_valign: gtk::Align::Center,
+                set_spacing: 5,
+                gtk::Button {
+                    add_css_class: "flat",
+                    set_icon_name: "edit-copy-symbolic",
+                    set_tooltip_text: Some(&gettext("Copy report")),
+                    connect_clicked[text = self.text()] => move |x| {
+                        x.clipboard().set_text(&text);
+                    }
+                },
+                gtk::Button {
+                    add_css_class: "flat",
+                    set_icon_name: "user-trash-symbolic",
+                    set_tooltip_text: Some(&gettext("Delete report")),
+                    connect_clicked[sender, path = self.path.clone()] => move |_| {
+                        let _ = sender.output(PreferencesPageMsg::RemoveCrashReport(path.clone()));
+                    }
+                },
+            },
+            add_row = &gtk::Label {
+                set_margin_all: 12,
+                set_halign: gtk::Align::Start,
+                set_xalign: 0.0,
+                set_wrap: true,
+                set_wrap_mode: gtk::pango::WrapMode::WordChar,
+                set_selectable: true,
+                add_css_class: "monospace",
+                add_css_class: "caption",
+                set_label: &self.text(),
+            },
+        }
+    }
+
+    fn init_model(init: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+        init
+    }
+}
+
+impl CrashItem {
+    fn text(&self) -> String {
+        serde_json::to_string_pretty(&self.report).unwrap_or_default()
+    }
+}
@@ -1,4 +1,5 @@
-use super::window::AppMsg;
+use crate::i18n::gettext;
+use super::{announce::announce, window::AppMsg};
 use adw::prelude::*;
 use log::{info, trace};
 use relm4::*;
@@ -13,6 +14,10 @@ pub struct RebuildModel {
     path: String,
     flake: Option<String>,
     scheme: Option<sourceview5::StyleScheme>,
+    /// Whether the running command can be stopped
+    cancellable: bool,
+    /// Last progress step read out to screen readers
+    milestone: Option<String>,
 }
 
 #[derive(Debug)]
@@ -23,6 +28,8 @@ pub enum RebuildMsg {
     UpdateText(String),
     Close,
     SetScheme(String),
+    SetCancellable(bool),
+    Cancel,
     Quit,
 }
 
@@ -70,7 +77,7 @@ impl SimpleComponent for RebuildModel {
                             set_height_request: 60,
                         },
                         gtk::Label {
-                            set_label: "Building...",
+                            set_label: &gettext("Building..."),
                             add_css_class: "title-1",
                         },
                     },
@@ -84,11 +91,11 @@ impl SimpleComponent for RebuildModel {
                             set_pixel_size: 128,
                         },
                         gtk::Label {
-                            set_label: "Done!",
+                            set_label: &gettext("Done!"),
                             add_css_class: "title-1",
                         },
                         gtk::Label {
-                            set_label: "Rebuild successful!",
+                            set_label: &gettext("Rebuild successful!"),
                             add_css_class: "dim-label",
                         }
                     },
@@ -102,11 +109,11 @@ impl SimpleComponent for RebuildModel {
                             set_pixel_size: 128,
                         },
                         gtk::Label {
-                            set_label: "Error!",
+                            set_label: &gettext("Error!"),
                             add_css_class: "title-1",
                         },
                         gtk::Label {
-                            set_label: "Rebuild failed! See below for error message.",
+                            set_label: &gettext("Rebuild failed! See below for error message."),
                             add_css_class: "dim-label",
                         }
                     }
@@ -142,10 +149,32 @@ impl SimpleComponent for RebuildModel {
                     add_css_class: "dialog-action-area",
                     set_orientation: gtk::Orientation::Horizontal,
                     set_homogeneous: true,
-                    #[track(model.changed(RebuildModel::status()))]
-                    set_visible: model.status != RebuildStatus::Building,
+                    #[track(model.changed(RebuildModel::status()) || model.changed(RebuildModel::cancellable()))]
+                    set_visible: model.status != RebuildStatus::Building || model.cancellable,
                     gtk::Button {
-                        set_label: "Close",
+                        set_label: &gettext("Cancel"),
+                        #[track(model.changed(RebuildModel::status()) || model.changed(RebuildModel::cancellable()))]
+                        set_visible: model.status == RebuildStatus::Building && model.cancellable,
+                        connect_clicked[sender, dialog] => move |_| {
+                            let confirm = adw::MessageDialog::new(
+                                Some(&dialog),
+                                Some(&gettext("Cancel update?")),
+                                Some(&gettext("Packages that have already been updated will stay updated")),
+                            );
+                            confirm.add_response("continue", &gettext("Keep Updating"));
+                            confirm.add_response("cancel", &gettext("Cancel Update"));
+                            confirm.set_response_appearance("cancel", adw::ResponseAppearance::Destructive);
+                            let sender = sender.clone();
+                            confirm.connect_response(None, move |_, resp| {
+                                if resp == "cancel" {
+                                    sender.input(RebuildMsg::Cancel);
+                                }
+                            });
+                            confirm.present();
+                        }
+                    },
+                    gtk::Button {
+                        set_label: &gettext("Close"),
                         #[track(model.changed(RebuildModel::status()))]
                         set_visible: model.status != RebuildStatus::Building,
                         connect_clicked[sender] => move |_| {
@@ -193,6 +222,8 @@ impl SimpleComponent for RebuildModel {
             path: String::new(),
             flake: None,
             scheme: None,
+            cancellable: false,
+            milestone: None,
             tracker: 0,
         };
 
@@ -208,9 +239,18 @@ impl SimpleComponent for RebuildModel {
                 self.update_hidden(|x| *x = false);
                 self.update_text(|x| x.clear());
                 self.set_status(RebuildStatus::Building);
+                self.set_cancellable(false);
+                self.milestone = None;
+                announce(&gettext("Applying changes"), false);
             }
             RebuildMsg::UpdateText(s) => {
                 info!("RebuildMsg::UpdateText({})", s);
+                if let Some(milestone) = milestone(&s) {
+                    if self.milestone.as_ref() != Some(&milestone) {
+                        announce(&milestone, false);
+                        self.milestone = Some(milestone);
+                    }
+                }
                 let newtext = if self.text.is_empty() {
                     s
                 } else {
@@ -221,8 +261,10 @@ impl SimpleComponent for RebuildModel {
             }
             RebuildMsg::FinishSuccess => {
                 self.set_status(RebuildStatus::Success);
+                announce(&gettext("Changes applied"), true);
             }
             RebuildMsg::FinishError(msg) => {
+                announce(&gettext("Failed to apply changes"), true);
                 if let Some(s) = msg {
                     self.set_text(s)
                 }
@@ -236,9 +278,33 @@ impl SimpleComponent for RebuildModel {
             RebuildMsg::SetScheme(scheme) => {
                 self.set_scheme(sourceview5::StyleSchemeManager::default().scheme(&scheme));
             }
+            RebuildMsg::SetCancellable(cancellable) => {
+                self.set_cancellable(cancellable);
+            }
+            RebuildMsg::Cancel => {
+                info!("RebuildMsg::Cancel");
+                self.set_cancellable(false);
+                sender.output(AppMsg::CancelUpdate);
+            }
             RebuildMsg::Quit => {
                 sender.output(AppMsg::Close);
             }
         }
     }
 }
+
+/// Maps a line of nix output to the step of the operation it starts.
+fn milestone(line: &str) -> Option<String> {
+    let line = line.trim_start();
+    if line.starts_with("evaluating") || line.starts_with("building the system configuration") {
+        Some(gettext("Evaluating"))
+    } else if line.starts_with("copying path") || line.contains("will be fetched") {
+        Some(gettext("Downloading packages"))
+    } else if line.starts_with("building '") || line.contains("will be built") {
+        Some(gettext("Building packages"))
+    } else if line.starts_with("activating the configuration") || line.starts_with("updating GRUB") || line.starts_with("setting up /etc") {
+        Some(gettext("Activating"))
+    } else {
+        None
+    }
+}
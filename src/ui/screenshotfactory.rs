@@ -1,12 +1,31 @@
+use crate::i18n::gettext;
 use relm4::adw::prelude::*;
 use relm4::{factory::*, *};
 
 use super::pkgpage::PkgMsg;
 
+/// How a screenshot of an app rated for mature content is shown.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sensitivity {
+    #[default]
+    None,
+    /// Blurred until clicked
+    Blurred,
+    /// Blurred for good, the policy doesn't allow mature content
+    Locked,
+}
+
 #[derive(Default, Debug, PartialEq, Eq)]
 pub struct ScreenshotItem {
     pub path: Option<String>,
     pub error: bool,
+    pub caption: Option<String>,
+    pub sensitivity: Sensitivity,
+}
+
+#[derive(Debug)]
+pub enum ScreenshotItemInput {
+    Reveal,
 }
 
 #[derive(Debug)]
@@ -15,26 +34,54 @@ pub enum ScreenshotItemMsg {}
 #[relm4::factory(pub)]
 impl FactoryComponent for ScreenshotItem {
     type CommandOutput = ();
-    type Init = ();
-    type Input = ();
+    type Init = (Option<String>, Sensitivity);
+    type Input = ScreenshotItemInput;
     type Output = ScreenshotItemMsg;
     type ParentWidget = adw::Carousel;
 
     view! {
         gtk::Box {
+            set_orientation: gtk::Orientation::Vertical,
             set_margin_all: 15,
+            set_spacing: 10,
             set_halign: gtk::Align::Center,
             set_valign: gtk::Align::Fill,
             set_vexpand: true,
-            gtk::Picture {
+            gtk::Overlay {
                 #[watch]
                 set_visible: self.path.is_some() && !self.error,
-                #[watch]
-                set_filename: self.path.as_ref(),
-                set_halign: gtk::Align::Center,
-                set_valign: gtk::Align::Center,
                 set_hexpand: true,
                 set_vexpand: true,
+                gtk::Picture {
+                    #[watch]
+                    set_filename: self.path.as_ref(),
+                    #[watch]
+                    set_class_active: ("sensitive", self.sensitivity != Sensitivity::None),
+                    set_halign: gtk::Align::Center,
+                    set_valign: gtk::Align::Center,
+                    set_hexpand: true,
+                    set_vexpand: true,
+                },
+                add_overlay = &gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_halign: gtk::Align::Center,
+                    set_valign: gtk::Align::Center,
+                    set_spacing: 10,
+                    #[watch]
+                    set_visible: self.sensitivity != Sensitivity::None,
+                    gtk::Label {
+                        add_css_class: "heading",
+                        set_label: &gettext("May show mature content"),
+                    },
+                    gtk::Button {
+                        add_css_class: "pill",
+                        add_css_class: "osd",
+                        set_label: &gettext("Show Screenshot"),
+                        #[watch]
+                        set_visible: self.sensitivity == Sensitivity::Blurred,
+                        connect_clicked => ScreenshotItemInput::Reveal,
+                    },
+                },
             },
             gtk::Spinner {
                 set_halign: gtk::Align::Center,
@@ -54,18 +101,37 @@ impl FactoryComponent for ScreenshotItem {
                 set_icon_name: Some("dialog-error-symbolic"),
                 #[watch]
                 set_visible: self.error,
+            },
+            gtk::Label {
+                add_css_class: "dim-label",
+                set_wrap: true,
+                set_justify: gtk::Justification::Center,
+                set_label: self.caption.as_deref().unwrap_or_default(),
+                set_visible: self.caption.is_some(),
             }
         }
     }
 
     fn init_model(
-        _parent: Self::Init,
+        (caption, sensitivity): Self::Init,
         _index: &DynamicIndex,
         _sender: FactorySender<Self>,
     ) -> Self {
         Self {
             path: None,
             error: false,
+            caption,
+            sensitivity,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Input, _sender: FactorySender<Self>) {
+        match msg {
+            ScreenshotItemInput::Reveal => {
+                if self.sensitivity == Sensitivity::Blurred {
+                    self.sensitivity = Sensitivity::None;
+                }
+            }
         }
     }
 }
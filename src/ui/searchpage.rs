@@ -1,7 +1,12 @@
-use std::{path::Path, collections::HashSet};
+use crate::i18n::{gettext, gettextf, ngettextf};
+use std::{path::Path, collections::{HashMap, HashSet}};
 use crate::APPINFO;
+use crate::parse::{packages, state};
+pub use crate::parse::packages::{LicenseFilter, SearchFilters};
 
+use super::pkgtile::tilemeta;
 use super::window::*;
+use super::categories;
 use adw::prelude::*;
 use relm4::{factory::*, *, gtk::pango};
 use log::*;
@@ -11,14 +16,39 @@ use log::*;
 pub struct SearchPageModel {
     #[tracker::no_eq]
     searchitems: FactoryVecDeque<SearchItemModel>,
+    /// Packages with a program named like the search
+    #[tracker::no_eq]
+    provideritems: FactoryVecDeque<SearchItemModel>,
+    command: String,
     searchitemtracker: u8,
+    filters: SearchFilters,
 }
 
+/// Sections of apps the results can be narrowed to, as named by `packages::appcategory`.
+const APPCATEGORIES: [&str; 10] = [
+    "Audio & Video",
+    "Development",
+    "Education",
+    "Games",
+    "Graphics",
+    "Networking",
+    "Productivity",
+    "Science",
+    "System",
+    "Utilities",
+];
+
 #[derive(Debug)]
 pub enum SearchPageMsg {
-    Search(Vec<SearchItem>),
+    /// Results and the packages providing the searched command
+    Search(Vec<SearchItem>, String, Vec<SearchItem>),
+    SetLicense(LicenseFilter),
+    SetCategory(Option<String>),
+    SetGui(bool),
+    SetInstalled(bool),
     UpdateInstalled(HashSet<String>, HashSet<String>),
-    OpenRow(gtk::ListBoxRow)
+    OpenRow(gtk::ListBoxRow),
+    Open(String),
 }
 
 #[relm4::component(pub)]
@@ -28,20 +58,157 @@ impl SimpleComponent for SearchPageModel {
     type Output = AppMsg;
 
     view! {
-        gtk::ScrolledWindow {
-            set_hscrollbar_policy: gtk::PolicyType::Never,
-            #[track(model.changed(SearchPageModel::searchitemtracker()))]
-            set_vadjustment: gtk::Adjustment::NONE,
-            adw::Clamp {
-                gtk::Stack {
-                    set_margin_all: 20,
-                    #[local_ref]
-                    searchlist -> gtk::ListBox {
-                        set_valign: gtk::Align::Start,
-                        add_css_class: "boxed-list",
-                        set_selection_mode: gtk::SelectionMode::None,
-                        connect_row_activated[sender] => move |_, row| {
-                            sender.input(SearchPageMsg::OpenRow(row.clone()));
+        adw::BreakpointBin {
+            set_width_request: 360,
+            set_height_request: 200,
+            #[name(splitview)]
+            #[wrap(Some)]
+            set_child = &adw::OverlaySplitView {
+                set_min_sidebar_width: 220.0,
+                set_max_sidebar_width: 320.0,
+                #[wrap(Some)]
+                set_sidebar = &gtk::ScrolledWindow {
+                    set_hscrollbar_policy: gtk::PolicyType::Never,
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_spacing: 10,
+                        set_margin_all: 20,
+                        gtk::Label {
+                            set_halign: gtk::Align::Start,
+                            add_css_class: "heading",
+                            set_label: &gettext("Filters"),
+                        },
+                        gtk::ListBox {
+                            add_css_class: "boxed-list",
+                            set_selection_mode: gtk::SelectionMode::None,
+                            adw::ComboRow {
+                                set_title: &gettext("License"),
+                                set_model: Some(&gtk::StringList::new(&[&gettext("Any"), &gettext("Free"), &gettext("Unfree")])),
+                                #[watch]
+                                set_selected: match model.filters.license {
+                                    LicenseFilter::Any => 0,
+                                    LicenseFilter::Free => 1,
+                                    LicenseFilter::Unfree => 2,
+                                },
+                                connect_selected_notify[sender] => move |x| {
+                                    let license = match x.selected() {
+                                        1 => LicenseFilter::Free,
+                                        2 => LicenseFilter::Unfree,
+                                        _ => LicenseFilter::Any,
+                                    };
+                                    sender.input(SearchPageMsg::SetLicense(license));
+                                }
+                            },
+                            adw::ComboRow {
+                                set_title: &gettext("Category"),
+                                set_model: Some(&gtk::StringList::new(
+                                    &[gettext("Any")]
+                                        .into_iter()
+                                        .chain(APPCATEGORIES.iter().map(|x| categories::sectionname(x)))
+                                        .collect::<Vec<_>>()
+                                        .iter()
+                                        .map(|x| x.as_str())
+                                        .collect::<Vec<_>>(),
+                                )),
+                                #[watch]
+                                set_selected: model
+                                    .filters
+                                    .category
+                                    .as_ref()
+                                    .and_then(|c| APPCATEGORIES.iter().position(|x| x == c))
+                                    .map(|i| i as u32 + 1)
+                                    .unwrap_or(0),
+                                connect_selected_notify[sender] => move |x| {
+                                    let category = (x.selected() as usize)
+                                        .checked_sub(1)
+                                        .and_then(|i| APPCATEGORIES.get(i))
+                                        .map(|x| x.to_string());
+                                    sender.input(SearchPageMsg::SetCategory(category));
+                                }
+                            },
+                            adw::ActionRow {
+                                set_title: &gettext("Apps only"),
+                                set_subtitle: &gettext("Packages with a graphical interface"),
+                                set_activatable_widget: Some(&guiswitch),
+                                #[name(guiswitch)]
+                                add_suffix = &gtk::Switch {
+                                    set_valign: gtk::Align::Center,
+                                    #[watch]
+                                    set_active: model.filters.gui,
+                                    connect_active_notify[sender] => move |x| {
+                                        sender.input(SearchPageMsg::SetGui(x.is_active()));
+                                    }
+                                }
+                            },
+                            adw::ActionRow {
+                                set_title: &gettext("Installed only"),
+                                set_activatable_widget: Some(&installedswitch),
+                                #[name(installedswitch)]
+                                add_suffix = &gtk::Switch {
+                                    set_valign: gtk::Align::Center,
+                                    #[watch]
+                                    set_active: model.filters.installed,
+                                    connect_active_notify[sender] => move |x| {
+                                        sender.input(SearchPageMsg::SetInstalled(x.is_active()));
+                                    }
+                                }
+                            }
+                        },
+                    }
+                },
+                #[wrap(Some)]
+                set_content = &gtk::ScrolledWindow {
+                    set_hscrollbar_policy: gtk::PolicyType::Never,
+                    set_hexpand: true,
+                    #[track(model.changed(SearchPageModel::searchitemtracker()))]
+                    set_vadjustment: gtk::Adjustment::NONE,
+                    adw::Clamp {
+                        gtk::Box {
+                            set_orientation: gtk::Orientation::Vertical,
+                            set_valign: gtk::Align::Start,
+                            set_spacing: 10,
+                            set_margin_all: 20,
+                            // Only shown once the sidebar collapses into an overlay
+                            #[name(filterbutton)]
+                            gtk::ToggleButton {
+                                set_halign: gtk::Align::Start,
+                                set_icon_name: "sidebar-show-symbolic",
+                                set_tooltip_text: Some(&gettext("Filters")),
+                            },
+                            gtk::Label {
+                                set_halign: gtk::Align::Start,
+                                add_css_class: "heading",
+                                #[watch]
+                                set_label: &gettextf("Provides the command “{}”", &[&model.command]),
+                                #[watch]
+                                set_visible: !model.provideritems.is_empty(),
+                            },
+                            #[local_ref]
+                            providerlist -> gtk::ListBox {
+                                add_css_class: "boxed-list",
+                                set_selection_mode: gtk::SelectionMode::None,
+                                #[watch]
+                                set_visible: !model.provideritems.is_empty(),
+                                connect_row_activated[sender] => move |_, row| {
+                                    sender.input(SearchPageMsg::OpenRow(row.clone()));
+                                }
+                            },
+                            gtk::Label {
+                                set_halign: gtk::Align::Start,
+                                set_margin_top: 10,
+                                add_css_class: "heading",
+                                set_label: &gettext("Packages"),
+                                #[watch]
+                                set_visible: !model.provideritems.is_empty(),
+                            },
+                            #[local_ref]
+                            searchlist -> gtk::ListBox {
+                                add_css_class: "boxed-list",
+                                set_selection_mode: gtk::SelectionMode::None,
+                                connect_row_activated[sender] => move |_, row| {
+                                    sender.input(SearchPageMsg::OpenRow(row.clone()));
+                                }
+                            }
                         }
                     }
                 }
@@ -55,49 +222,114 @@ impl SimpleComponent for SearchPageModel {
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         let model = SearchPageModel {
-            searchitems: FactoryVecDeque::builder().launch(gtk::ListBox::new()).detach(),
+            searchitems: FactoryVecDeque::builder().launch(gtk::ListBox::new()).forward(sender.input_sender(), |output| match output {
+                SearchItemMsg::Open(pkg) => SearchPageMsg::Open(pkg),
+            }),
+            provideritems: FactoryVecDeque::builder().launch(gtk::ListBox::new()).forward(sender.input_sender(), |output| match output {
+                SearchItemMsg::Open(pkg) => SearchPageMsg::Open(pkg),
+            }),
+            command: String::new(),
             searchitemtracker: 0,
+            filters: state::getnscstate().ui.searchfilters,
             tracker: 0,
         };
 
         let searchlist = model.searchitems.widget();
+        let providerlist = model.provideritems.widget();
 
         let widgets = view_output!();
 
+        // The filters move into an overlay when there is no room left for the results
+        let narrow = adw::Breakpoint::new(adw::BreakpointCondition::new_length(
+            adw::BreakpointConditionLengthType::MaxWidth,
+            640.0,
+            adw::LengthUnit::Sp,
+        ));
+        narrow.add_setter(&widgets.splitview, "collapsed", Some(&true.to_value()));
+        root.add_breakpoint(narrow);
+        widgets
+            .splitview
+            .bind_property("collapsed", &widgets.filterbutton, "visible")
+            .sync_create()
+            .build();
+        widgets
+            .splitview
+            .bind_property("show-sidebar", &widgets.filterbutton, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
         ComponentParts { model, widgets }
     }
 
     fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
         self.reset();
         match msg {
-            SearchPageMsg::Search(items) => {
+            SearchPageMsg::Search(items, command, providers) => {
+                // The best ranked variant of a program stands for the others
+                let mut groups: Vec<SearchItem> = vec![];
+                let mut bases: HashMap<String, usize> = HashMap::new();
+                for item in items {
+                    let base = packages::variantbase(&item.pkg);
+                    if let Some(i) = bases.get(&base) {
+                        groups[*i].variants.push(item.pkg);
+                    } else {
+                        bases.insert(base, groups.len());
+                        groups.push(item);
+                    }
+                }
                 let mut searchitem_guard = self.searchitems.guard();
                 searchitem_guard.clear();
-                for item in items {
+                for item in groups {
                     searchitem_guard.push_back(item);
                 }
                 searchitem_guard.drop();
+                let mut provider_guard = self.provideritems.guard();
+                provider_guard.clear();
+                for item in providers {
+                    provider_guard.push_back(item);
+                }
+                provider_guard.drop();
+                self.set_command(command);
                 self.update_searchitemtracker(|_| ());
             }
+            SearchPageMsg::SetLicense(license) => {
+                self.setfilters(SearchFilters { license, ..self.filters.clone() }, &sender);
+            }
+            SearchPageMsg::SetCategory(category) => {
+                self.setfilters(SearchFilters { category, ..self.filters.clone() }, &sender);
+            }
+            SearchPageMsg::SetGui(gui) => {
+                self.setfilters(SearchFilters { gui, ..self.filters.clone() }, &sender);
+            }
+            SearchPageMsg::SetInstalled(installed) => {
+                self.setfilters(SearchFilters { installed, ..self.filters.clone() }, &sender);
+            }
             SearchPageMsg::OpenRow(row) => {
-                let searchitem_guard = self.searchitems.guard();
-                for (i, child) in searchitem_guard.widget().iter_children().enumerate() {
-                    if child == row {
-                        if let Some(item) = searchitem_guard.get(i) {
-                            let pkg = &item.get_item().pkg;
-                            sender.output(AppMsg::OpenPkg(pkg.to_string()));
+                for items in [&self.provideritems, &self.searchitems] {
+                    for (i, child) in items.widget().iter_children().enumerate() {
+                        if child == row {
+                            if let Some(item) = items.get(i) {
+                                let pkg = &item.get_item().pkg;
+                                sender.output(AppMsg::OpenPkg(pkg.to_string()));
+                            }
                         }
                     }
                 }
             }
+            SearchPageMsg::Open(pkg) => {
+                sender.output(AppMsg::OpenPkg(pkg));
+            }
             SearchPageMsg::UpdateInstalled(installeduserpkgs, installedsystempkgs) => {
-                let mut searchitem_guard = self.searchitems.guard();
-                for i in 0..searchitem_guard.len() {
-                    if let Some(item) = searchitem_guard.get_mut(i) {
-                        let mut pkgitem = item.get_mut_item();
-                        pkgitem.installeduser = installeduserpkgs.contains(&pkgitem.pname.to_string());
-                        pkgitem.installedsystem = installedsystempkgs.contains(&pkgitem.pkg.to_string());
+                for items in [&mut self.searchitems, &mut self.provideritems] {
+                    let mut searchitem_guard = items.guard();
+                    for i in 0..searchitem_guard.len() {
+                        if let Some(item) = searchitem_guard.get_mut(i) {
+                            let mut pkgitem = item.get_mut_item();
+                            pkgitem.installeduser = installeduserpkgs.contains(&pkgitem.pname.to_string());
+                            pkgitem.installedsystem = installedsystempkgs.contains(&pkgitem.pkg.to_string());
 
+                        }
                     }
                 }
             }
@@ -105,7 +337,17 @@ impl SimpleComponent for SearchPageModel {
     }
 }
 
-#[derive(Default, Debug, PartialEq, Eq)]
+impl SearchPageModel {
+    /// The window runs the search again with the new filters.
+    fn setfilters(&mut self, filters: SearchFilters, sender: &ComponentSender<Self>) {
+        if filters != self.filters {
+            self.set_filters(filters.clone());
+            sender.output(AppMsg::SetSearchFilters(filters));
+        }
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Eq, Clone)]
 pub struct SearchItem {
     pub name: String,
     pub pkg: String,
@@ -114,6 +356,13 @@ pub struct SearchItem {
     pub icon: Option<String>,
     pub installeduser: bool,
     pub installedsystem: bool,
+    pub supported: bool,
+    pub unfree: bool,
+    pub license: Option<String>,
+    pub size: Option<u64>,
+    pub version: String,
+    /// Attributes of other builds of the same program
+    pub variants: Vec<String>,
 }
 
 #[tracker::track]
@@ -123,7 +372,9 @@ pub struct SearchItemModel {
 }
 
 #[derive(Debug)]
-pub enum SearchItemMsg {}
+pub enum SearchItemMsg {
+    Open(String),
+}
 
 #[relm4::factory(pub)]
 impl FactoryComponent for SearchItemModel {
@@ -220,6 +471,27 @@ impl FactoryComponent for SearchItemModel {
                             set_wrap: true,
                             set_max_width_chars: 0,
                         },
+                        gtk::Label {
+                            set_halign: gtk::Align::Start,
+                            add_css_class: "warning",
+                            add_css_class: "caption",
+                            set_label: &gettext("Unfree"),
+                            set_visible: self.item.unfree,
+                        },
+                        gtk::Label {
+                            set_halign: gtk::Align::Start,
+                            add_css_class: "dim-label",
+                            add_css_class: "caption",
+                            set_label: &tilemeta(self.item.license.as_deref(), self.item.size),
+                            set_visible: self.item.license.is_some() || self.item.size.is_some(),
+                        },
+                        gtk::Label {
+                            set_halign: gtk::Align::Start,
+                            add_css_class: "warning",
+                            add_css_class: "caption",
+                            set_label: &gettext("Not available for this system"),
+                            set_visible: !self.item.supported,
+                        },
                         gtk::Label {
                             set_halign: gtk::Align::Start,
                             set_label: self.item.summary.as_deref().unwrap_or(""),
@@ -229,7 +501,50 @@ impl FactoryComponent for SearchItemModel {
                             set_wrap: true,
                             set_max_width_chars: 0,
                         },
+                        gtk::Expander {
+                            set_visible: !self.item.variants.is_empty(),
+                            #[wrap(Some)]
+                            set_label_widget = &gtk::Label {
+                                add_css_class: "caption",
+                                set_label: &ngettextf(
+                                    "Other variant ({})",
+                                    "Other variants ({})",
+                                    self.item.variants.len() as u32,
+                                    &[&self.item.variants.len().to_string()],
+                                ),
+                            },
+                            #[wrap(Some)]
+                            set_child = &gtk::FlowBox {
+                                set_selection_mode: gtk::SelectionMode::None,
+                                set_max_children_per_line: 4,
+                                #[iterate]
+                                append: self.item.variants.iter().map(|variant| {
+                                    let button = gtk::Button::builder()
+                                        .label(variant.as_str())
+                                        .css_classes(["flat", "caption"])
+                                        .build();
+                                    let sender = sender.clone();
+                                    let variant = variant.to_string();
+                                    button.connect_clicked(move |_| {
+                                        let _ = sender.output(SearchItemMsg::Open(variant.clone()));
+                                    });
+                                    button
+                                }).collect::<Vec<_>>().iter(),
+                            },
+                        },
                     }
+                },
+                gtk::Label {
+                    set_valign: gtk::Align::Center,
+                    set_xalign: 1.0,
+                    set_width_chars: 10,
+                    set_max_width_chars: 16,
+                    set_ellipsize: pango::EllipsizeMode::Middle,
+                    add_css_class: "dim-label",
+                    add_css_class: "numeric",
+                    set_label: self.item.version.as_str(),
+                    set_tooltip_text: Some(self.item.version.as_str()),
+                    set_visible: !self.item.version.is_empty(),
                 }
             }
         }
@@ -261,6 +576,12 @@ impl FactoryComponent for SearchItemModel {
             icon: parent.icon,
             installeduser: parent.installeduser,
             installedsystem: parent.installedsystem,
+            supported: parent.supported,
+            unfree: parent.unfree,
+            license: parent.license,
+            size: parent.size,
+            version: parent.version,
+            variants: parent.variants,
         };
 
         Self { item, tracker: 0 }
@@ -1,3 +1,4 @@
+use crate::i18n::{gettext, gettextf};
 use std::path::Path;
 
 use gtk::pango;
@@ -7,7 +8,7 @@ use relm4::{*, prelude::*, factory::*};
 use adw::prelude::*;
 use crate::{APPINFO, ui::{window::REBUILD_BROKER, rebuild::RebuildMsg}};
 
-use super::updatepage::{UpdatePageMsg, UpdateType};
+use super::{updatepage::{UpdatePageMsg, UpdateType}, updateworker::PkgChanges};
 
 #[derive(Debug)]
 pub struct UnavailableDialogModel {
@@ -36,8 +37,8 @@ impl SimpleComponent for UnavailableDialogModel {
             set_visible: !model.hidden,
             set_transient_for: Some(&parent_window),
             set_modal: true,
-            set_heading: Some("Some packages are unavailable!"),
-            set_body: "If you continue this update, some packages will be removed",
+            set_heading: Some(&gettext("Some packages are unavailable!")),
+            set_body: &gettext("Choose what to do with each package before continuing the update"),
             #[wrap(Some)]
             set_extra_child = &gtk::Box {
                 set_orientation: gtk::Orientation::Vertical,
@@ -45,7 +46,7 @@ impl SimpleComponent for UnavailableDialogModel {
                 adw::PreferencesGroup {
                     #[watch]
                     set_visible: !model.unavailableuseritems.is_empty(),
-                    set_title: "User Packages",
+                    set_title: &gettext("User Packages"),
                     #[local_ref]
                     unavailableuserlist -> gtk::ListBox {
                         add_css_class: "boxed-list",
@@ -55,7 +56,7 @@ impl SimpleComponent for UnavailableDialogModel {
                 adw::PreferencesGroup {
                     #[watch]
                     set_visible: !model.unavailablesysitems.is_empty(),
-                    set_title: "System Packages",
+                    set_title: &gettext("System Packages"),
                     #[local_ref]
                     unavailablesyslist -> gtk::ListBox {
                         add_css_class: "boxed-list",
@@ -63,8 +64,8 @@ impl SimpleComponent for UnavailableDialogModel {
                     },
                 }
             },
-            add_response: ("cancel", "Cancel"),
-            add_response: ("continue", "Continue"),
+            add_response: ("cancel", &gettext("Cancel")),
+            add_response: ("continue", &gettext("Continue")),
             set_response_appearance: ("continue", adw::ResponseAppearance::Destructive),
             connect_close_request => |_| {
                 glib::Propagation::Stop
@@ -133,13 +134,13 @@ impl SimpleComponent for UnavailableDialogModel {
             UnavailableDialogMsg::Continue => {
                 match self.updatetype {
                     UpdateType::User => {
-                        sender.output(UpdatePageMsg::UpdateAllUserRm(self.unavailableuseritems.iter().map(|x| x.pkg.to_string()).collect()));
+                        sender.output(UpdatePageMsg::UpdateAllUserRm(changes(&self.unavailableuseritems)));
                     }
                     UpdateType::System => {
-                        sender.output(UpdatePageMsg::UpdateSystemRm(self.unavailablesysitems.iter().map(|x| x.pkg.to_string()).collect()));
+                        sender.output(UpdatePageMsg::UpdateSystemRm(changes(&self.unavailablesysitems)));
                     }
                     UpdateType::All => {
-                        sender.output(UpdatePageMsg::UpdateAllRm(self.unavailableuseritems.iter().map(|x| x.pkg.to_string()).collect(), self.unavailablesysitems.iter().map(|x| x.pkg.to_string()).collect()));
+                        sender.output(UpdatePageMsg::UpdateAllRm(changes(&self.unavailableuseritems), changes(&self.unavailablesysitems)));
                     }
                 }
                 sender.input(UnavailableDialogMsg::Close)
@@ -148,6 +149,34 @@ impl SimpleComponent for UnavailableDialogModel {
     }
 }
 
+fn changes(items: &FactoryVecDeque<UnavailableItemModel>) -> PkgChanges {
+    let pkgs = |choices: &[UnavailableChoice]| {
+        items
+            .iter()
+            .filter(|x| choices.contains(&x.choice))
+            .map(|x| x.pkg.to_string())
+            .collect()
+    };
+    PkgChanges {
+        remove: pkgs(&[UnavailableChoice::Remove, UnavailableChoice::Replace]),
+        install: items
+            .iter()
+            .filter(|x| x.choice == UnavailableChoice::Replace)
+            .filter_map(|x| x.replacement.clone())
+            .collect(),
+        skip: pkgs(&[UnavailableChoice::Skip]),
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum UnavailableChoice {
+    #[default]
+    Remove,
+    Replace,
+    Keep,
+    Skip,
+}
+
 #[derive(Default, Debug, PartialEq, Eq)]
 pub struct UnavailableItemModel {
     pub name: String,
@@ -155,17 +184,28 @@ pub struct UnavailableItemModel {
     pub pname: String,
     pub icon: Option<String>,
     pub message: String,
+    /// Attribute the package was renamed to, if it is still available under that name
+    pub replacement: Option<String>,
+    /// Short explanation of why the package is unavailable
+    pub reason: String,
+    pub reasonurl: Option<String>,
+    pub choice: UnavailableChoice,
+    pub system: bool,
+    /// The policy doesn't allow removing packages, so only keeping or skipping is offered
+    pub noremove: bool,
 }
 
 #[derive(Debug)]
-pub enum UnavailableItemMsg {}
+pub enum UnavailableItemMsg {
+    SetChoice(UnavailableChoice),
+}
 
 #[relm4::factory(pub)]
 impl FactoryComponent for UnavailableItemModel {
     type CommandOutput = ();
     type Init = UnavailableItemModel;
-    type Input = ();
-    type Output = UnavailableItemMsg;
+    type Input = UnavailableItemMsg;
+    type Output = ();
     type ParentWidget = adw::gtk::ListBox;
 
     view! {
@@ -239,12 +279,82 @@ impl FactoryComponent for UnavailableItemModel {
                             set_wrap: true,
                             set_max_width_chars: 0,
                         },
+                        gtk::Box {
+                            set_orientation: gtk::Orientation::Horizontal,
+                            set_spacing: 5,
+                            gtk::Label {
+                                set_halign: gtk::Align::Start,
+                                add_css_class: "caption",
+                                add_css_class: "warning",
+                                set_label: self.reason.as_str(),
+                            },
+                            gtk::LinkButton {
+                                add_css_class: "caption",
+                                set_visible: self.reasonurl.is_some(),
+                                set_label: &gettext("Details"),
+                                set_uri: self.reasonurl.as_deref().unwrap_or_default(),
+                            },
+                        },
                     },
                     gtk::Label {
                         set_halign: gtk::Align::Center,
                         set_hexpand: true,
-                        set_label: self.message.as_str(),
+                        #[watch]
+                        set_label: &match self.choice {
+                            UnavailableChoice::Replace => gettextf("Will be replaced with {}", &[self.replacement.as_deref().unwrap_or_default()]),
+                            UnavailableChoice::Keep => gettext("Will be kept, the update may fail"),
+                            UnavailableChoice::Skip => gettext("Will be kept at its current version"),
+                            UnavailableChoice::Remove => self.message.to_string(),
+                        },
                         set_wrap: true,
+                    },
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_valign: gtk::Align::Center,
+                        #[name(removecheck)]
+                        gtk::CheckButton {
+                            set_visible: !self.noremove,
+                            set_label: Some(&gettext("Remove")),
+                            set_active: self.choice == UnavailableChoice::Remove,
+                            connect_toggled[sender] => move |x| {
+                                if x.is_active() {
+                                    sender.input(UnavailableItemMsg::SetChoice(UnavailableChoice::Remove));
+                                }
+                            }
+                        },
+                        gtk::CheckButton {
+                            set_group: Some(&removecheck),
+                            set_visible: self.replacement.is_some() && !self.noremove,
+                            set_label: Some(&gettextf("Replace with {}", &[self.replacement.as_deref().unwrap_or_default()])),
+                            set_active: self.choice == UnavailableChoice::Replace,
+                            connect_toggled[sender] => move |x| {
+                                if x.is_active() {
+                                    sender.input(UnavailableItemMsg::SetChoice(UnavailableChoice::Replace));
+                                }
+                            }
+                        },
+                        gtk::CheckButton {
+                            set_group: Some(&removecheck),
+                            set_label: Some(&gettext("Keep")),
+                            set_active: self.choice == UnavailableChoice::Keep,
+                            connect_toggled[sender] => move |x| {
+                                if x.is_active() {
+                                    sender.input(UnavailableItemMsg::SetChoice(UnavailableChoice::Keep));
+                                }
+                            }
+                        },
+                        // The system is always rebuilt as a whole
+                        gtk::CheckButton {
+                            set_group: Some(&removecheck),
+                            set_visible: !self.system,
+                            set_label: Some(&gettext("Skip update")),
+                            set_active: self.choice == UnavailableChoice::Skip,
+                            connect_toggled[sender] => move |x| {
+                                if x.is_active() {
+                                    sender.input(UnavailableItemMsg::SetChoice(UnavailableChoice::Skip));
+                                }
+                            }
+                        },
                     }
                 }
                 
@@ -257,6 +367,18 @@ impl FactoryComponent for UnavailableItemModel {
         _index: &DynamicIndex,
         _sender: FactorySender<Self>,
     ) -> Self {
-        init
+        let mut item = init;
+        if item.replacement.is_some() {
+            item.choice = UnavailableChoice::Replace;
+        }
+        item
+    }
+
+    fn update(&mut self, msg: Self::Input, _sender: FactorySender<Self>) {
+        match msg {
+            UnavailableItemMsg::SetChoice(choice) => {
+                self.choice = choice;
+            }
+        }
     }
 }
@@ -1,10 +1,11 @@
-use crate::{APPINFO, ui::unavailabledialog::UnavailableDialogModel, parse::util};
+use crate::i18n::{gettext, gettextf, ngettextf};
+use crate::{APPINFO, ui::unavailabledialog::UnavailableDialogModel, parse::{cmd, config::NscConfig, crash, deps, security, state::{self, NscState}, util}};
 
-use super::{pkgpage::InstallType, window::*, updateworker::{UpdateAsyncHandler, UpdateAsyncHandlerMsg, UpdateAsyncHandlerInit}, rebuild::RebuildMsg, unavailabledialog::UnavailableDialogMsg};
+use super::{categories, pkgpage::InstallType, window::*, updateworker::{self, PkgChanges, UpdateAsyncHandler, UpdateAsyncHandlerMsg, UpdateAsyncHandlerInit, UpdatePreview}, rebuild::RebuildMsg, unavailabledialog::UnavailableDialogMsg};
 use adw::prelude::*;
 use nix_data::config::configfile::NixDataConfig;
 use relm4::{factory::*, gtk::pango, *};
-use std::{path::Path, convert::identity, collections::HashMap};
+use std::{path::Path, convert::identity, collections::{BTreeMap, HashMap, HashSet}};
 use log::*;
 
 pub static UNAVAILABLE_BROKER: MessageBroker<UnavailableDialogMsg> = MessageBroker::new();
@@ -16,35 +17,84 @@ pub struct UpdatePageModel {
     updateuserlist: FactoryVecDeque<UpdateItemModel>,
     #[tracker::no_eq]
     updatesystemlist: FactoryVecDeque<UpdateItemModel>,
+    #[tracker::no_eq]
+    updatesecuritylist: FactoryVecDeque<UpdateItemModel>,
+    #[tracker::no_eq]
+    updateusergroups: FactoryVecDeque<UpdateGroupModel>,
+    #[tracker::no_eq]
+    updatesystemgroups: FactoryVecDeque<UpdateGroupModel>,
+    useritems: Vec<UpdateItem>,
+    sysitems: Vec<UpdateItem>,
+    groupupdates: bool,
+    /// Choosing which user packages to update
+    selecting: bool,
+    /// User packages chosen for updating, by the key they're upgraded with
+    selected: HashSet<String>,
+    nscstate: NscState,
     channelupdate: Option<(String, String)>,
     #[tracker::no_eq]
     updateworker: WorkerController<UpdateAsyncHandler>,
     config: NixDataConfig,
+    nscconfig: NscConfig,
+    extraargs: String,
+    /// Why the extra arguments can't be split, nothing is updated until they can
+    argserror: Option<String>,
+    impure: bool,
     systype: SystemPkgs,
     usertype: UserPkgs,
     updatetracker: u8,
     #[tracker::no_eq]
     unavailabledialog: Controller<UnavailableDialogModel>,
     online: bool,
+    /// Running a dry run of updating everything
+    previewing: bool,
+    /// Updates are listed but can't be applied
+    readonly: bool,
+    /// Unavailable packages are kept instead of removed or replaced
+    noremove: bool,
+    #[tracker::no_eq]
+    window: gtk::Window,
 }
 
 #[derive(Debug)]
 pub enum UpdatePageMsg {
     UpdateConfig(NixDataConfig),
+    UpdateNscConfig(NscConfig),
+    SetExtraArgs(String),
+    SetImpure(bool),
     UpdatePkgTypes(SystemPkgs, UserPkgs),
+    SetHost(Option<String>),
     Update(Vec<UpdateItem>, Vec<UpdateItem>),
+    SetSecurity(HashMap<String, String>),
     OpenRow(usize, InstallType),
+    OpenSecurityRow(usize),
+    OpenPkg(String),
+    OpenChangelog(String, String),
+    SkipVersion(UpdateItem),
     UpdateSystem,
-    UpdateSystemRm(Vec<String>),
+    UpdateSystemRm(PkgChanges),
     UpdateAllUser,
-    UpdateAllUserRm(Vec<String>),
-    UpdateUser(String),
+    UpdateAllUserRm(PkgChanges),
+    /// Upgrades a single user package
+    UpdateUser(UpdateItem),
+    UpdateGroup(Vec<String>),
+    SetGroupUpdates(bool),
+    SetSelecting(bool),
+    Select(UpdateItem, bool),
+    SelectAll,
+    UpdateSelected,
     // UpdateChannels,
     // UpdateSystemAndChannels,
     UpdateAll,
-    UpdateAllRm(Vec<String>, Vec<String>),
+    UpdateAllRm(PkgChanges, PkgChanges),
+    Preview,
+    SetPreview(Result<UpdatePreview, String>),
+    /// A package being upgraded, by name
+    SetItemProgress(String, ItemProgress),
     DoneWorking,
     FailedWorking,
+    Cancel,
+    CancelFinished,
     UpdateOnline(bool),
 }
 
@@ -60,7 +110,10 @@ pub struct UpdatePageInit {
     pub systype: SystemPkgs,
     pub usertype: UserPkgs,
     pub config: NixDataConfig,
+    pub nscconfig: NscConfig,
     pub online: bool,
+    pub readonly: bool,
+    pub noremove: bool,
 }
 
 #[relm4::component(pub)]
@@ -79,21 +132,21 @@ impl SimpleComponent for UpdatePageModel {
                 if !model.online {
                     adw::StatusPage {
                         set_icon_name: Some("nsc-network-offline-symbolic"),
-                        set_title: "No internet connection",
-                        set_description: Some("Please connect to the internet to update your system"),
+                        set_title: &gettext("No internet connection"),
+                        set_description: Some(&gettext("Please connect to the internet to update your system")),
                         gtk::Button {
                             add_css_class: "pill",
                             set_halign: gtk::Align::Center,
                             adw::ButtonContent {
                                 set_icon_name: "nsc-refresh-symbolic",
-                                set_label: "Refresh",
+                                set_label: &gettext("Refresh"),
                             },
                             connect_clicked[sender] => move |_| {
                                 sender.output(AppMsg::CheckNetwork);
                             }
                         }
                     }
-                } else if model.channelupdate.is_some() || !model.updateuserlist.is_empty() || !model.updatesystemlist.is_empty() {
+                } else if model.channelupdate.is_some() || !model.updateuserlist.is_empty() || !model.updatesystemlist.is_empty() || !model.updatesecuritylist.is_empty() {
                     gtk::Box {
                         set_orientation: gtk::Orientation::Vertical,
                         set_valign: gtk::Align::Start,
@@ -102,22 +155,115 @@ impl SimpleComponent for UpdatePageModel {
                         gtk::Box {
                             set_orientation: gtk::Orientation::Horizontal,
                             set_hexpand: true,
+                            set_spacing: 10,
                             gtk::Label {
                                 set_halign: gtk::Align::Start,
                                 add_css_class: "title-2",
-                                set_label: "Updates",
+                                set_label: &gettext("Updates"),
+                            },
+                            gtk::ToggleButton {
+                                add_css_class: "flat",
+                                set_halign: gtk::Align::End,
+                                set_valign: gtk::Align::Center,
+                                set_hexpand: true,
+                                set_icon_name: "view-list-symbolic",
+                                set_tooltip_text: Some(&gettext("Group by category")),
+                                set_active: model.groupupdates,
+                                connect_toggled[sender] => move |x| {
+                                    sender.input(UpdatePageMsg::SetGroupUpdates(x.is_active()));
+                                }
+                            },
+                            gtk::Button {
+                                set_halign: gtk::Align::End,
+                                set_valign: gtk::Align::Center,
+                                set_tooltip_text: Some(&gettext("See what updating everything would download and build")),
+                                set_visible: !model.readonly,
+                                #[watch]
+                                set_label: &if model.previewing { gettext("Previewing…") } else { gettext("Preview") },
+                                #[watch]
+                                set_sensitive: !model.previewing,
+                                connect_clicked[sender] => move |_| {
+                                    sender.input(UpdatePageMsg::Preview);
+                                }
                             },
                             gtk::Button {
                                 add_css_class: "suggested-action",
                                 set_halign: gtk::Align::End,
                                 set_valign: gtk::Align::Center,
-                                set_hexpand: true,
-                                set_label: "Update Everything",
+                                set_visible: !model.readonly,
+                                set_label: &gettext("Update Everything"),
                                 connect_clicked[sender] => move |_| {
                                     sender.input(UpdatePageMsg::UpdateAll);
                                 }
                             }
                         },
+                        gtk::ListBox {
+                            add_css_class: "boxed-list",
+                            set_selection_mode: gtk::SelectionMode::None,
+                            adw::ExpanderRow {
+                                set_title: &gettext("Advanced options"),
+                                set_subtitle: &gettext("Extra arguments for the nix commands"),
+                                add_row = &adw::EntryRow {
+                                    set_title: &gettext("Extra arguments"),
+                                    #[watch]
+                                    set_class_active: ("error", model.argserror.is_some()),
+                                    #[watch]
+                                    set_tooltip_text: model.argserror.as_deref(),
+                                    connect_changed[sender] => move |x| {
+                                        sender.input(UpdatePageMsg::SetExtraArgs(x.text().to_string()));
+                                    }
+                                },
+                                add_row = &adw::ActionRow {
+                                    set_title: &gettext("Impure evaluation"),
+                                    set_subtitle: &gettext("Allow access to the environment, e.g. NIXPKGS_ALLOW_UNFREE"),
+                                    add_suffix = &gtk::Switch {
+                                        set_valign: gtk::Align::Center,
+                                        connect_state_set[sender] => move |_, b| {
+                                            sender.input(UpdatePageMsg::SetImpure(b));
+                                            gtk::glib::Propagation::Proceed
+                                        } @impurehandler,
+                                        #[track(model.changed(UpdatePageModel::nscconfig()))]
+                                        #[block_signal(impurehandler)]
+                                        set_state: model.impure,
+                                    }
+                                },
+                                add_row = &adw::ActionRow {
+                                    set_title: &gettext("User packages"),
+                                    set_activatable: false,
+                                    add_css_class: "monospace",
+                                    #[watch]
+                                    set_subtitle: &gtk::glib::markup_escape_text(&model.usercmdpreview()),
+                                },
+                                add_row = &adw::ActionRow {
+                                    set_title: &gettext("System"),
+                                    set_activatable: false,
+                                    add_css_class: "monospace",
+                                    set_visible: model.systype != SystemPkgs::None,
+                                    #[watch]
+                                    set_subtitle: &gtk::glib::markup_escape_text(&model.syscmdpreview()),
+                                },
+                            }
+                        },
+                        gtk::Label {
+                            #[watch]
+                            set_visible: !model.updatesecuritylist.is_empty(),
+                            set_halign: gtk::Align::Start,
+                            add_css_class: "title-4",
+                            set_label: &gettext("Security Updates"),
+                        },
+                        #[local_ref]
+                        updatesecuritylist -> gtk::ListBox {
+                            set_valign: gtk::Align::Start,
+                            add_css_class: "boxed-list",
+                            set_selection_mode: gtk::SelectionMode::None,
+                            connect_row_activated[sender] => move |listbox, row| {
+                                if let Some(i) = listbox.index_of_child(row) {
+                                    sender.input(UpdatePageMsg::OpenSecurityRow(i as usize));
+                                }
+                            },
+                            #[watch]
+                            set_visible: !model.updatesecuritylist.is_empty(),
+                        },
                         gtk::Box {
                             set_orientation: gtk::Orientation::Horizontal,
                             set_hexpand: true,
@@ -126,19 +272,53 @@ impl SimpleComponent for UpdatePageModel {
                             gtk::Label {
                                 set_halign: gtk::Align::Start,
                                 add_css_class: "title-4",
-                                set_label: match model.usertype {
-                                    UserPkgs::Env => "User (nix-env)",
-                                    UserPkgs::Profile => "User (nix profile)",
+                                set_label: &match model.usertype {
+                                    UserPkgs::Env => gettext("User (nix-env)"),
+                                    UserPkgs::Profile => gettext("User (nix profile)"),
                                 }
                             },
-                            gtk::Button {
-                                add_css_class: "suggested-action",
+                            gtk::Box {
                                 set_halign: gtk::Align::End,
-                                set_valign: gtk::Align::Center,
                                 set_hexpand: true,
-                                set_label: "Update All",
-                                connect_clicked[sender] => move |_| {
-                                    sender.input(UpdatePageMsg::UpdateAllUser);
+                                set_spacing: 10,
+                                gtk::ToggleButton {
+                                    add_css_class: "flat",
+                                    set_valign: gtk::Align::Center,
+                                    set_visible: !model.readonly,
+                                    set_icon_name: "selection-mode-symbolic",
+                                    set_tooltip_text: Some(&gettext("Choose which packages to update")),
+                                    connect_toggled[sender] => move |x| {
+                                        sender.input(UpdatePageMsg::SetSelecting(x.is_active()));
+                                    } @selectinghandler,
+                                    #[track(model.changed(UpdatePageModel::selecting()))]
+                                    #[block_signal(selectinghandler)]
+                                    set_active: model.selecting,
+                                },
+                                gtk::Button {
+                                    add_css_class: "flat",
+                                    set_valign: gtk::Align::Center,
+                                    set_label: &gettext("Select All"),
+                                    #[watch]
+                                    set_visible: model.selecting,
+                                    connect_clicked[sender] => move |_| {
+                                        sender.input(UpdatePageMsg::SelectAll);
+                                    }
+                                },
+                                gtk::Button {
+                                    add_css_class: "suggested-action",
+                                    set_valign: gtk::Align::Center,
+                                    set_visible: !model.readonly,
+                                    #[watch]
+                                    set_label: &if model.selecting {
+                                        gettextf("Update Selected ({})", &[&model.selected.len().to_string()])
+                                    } else {
+                                        gettext("Update All")
+                                    },
+                                    #[watch]
+                                    set_sensitive: !model.selecting || !model.selected.is_empty(),
+                                    connect_clicked[sender] => move |_| {
+                                        sender.input(UpdatePageMsg::UpdateSelected);
+                                    }
                                 }
                             }
                         },
@@ -153,7 +333,15 @@ impl SimpleComponent for UpdatePageModel {
                                 }
                             },
                             #[watch]
-                            set_visible: !model.updateuserlist.is_empty(),
+                            set_visible: !model.updateuserlist.is_empty() && !model.groupupdates,
+                        },
+                        #[local_ref]
+                        updateusergroups -> gtk::ListBox {
+                            set_valign: gtk::Align::Start,
+                            add_css_class: "boxed-list",
+                            set_selection_mode: gtk::SelectionMode::None,
+                            #[watch]
+                            set_visible: !model.updateusergroups.is_empty() && model.groupupdates,
                         },
                         gtk::Box {
                             set_orientation: gtk::Orientation::Horizontal,
@@ -163,14 +351,15 @@ impl SimpleComponent for UpdatePageModel {
                             gtk::Label {
                                 set_halign: gtk::Align::Start,
                                 add_css_class: "title-4",
-                                set_label: "System (configuration.nix)",
+                                set_label: &gettext("System (configuration.nix)"),
                             },
                             gtk::Button {
                                 add_css_class: "suggested-action",
                                 set_halign: gtk::Align::End,
                                 set_hexpand: true,
                                 set_valign: gtk::Align::Center,
-                                set_label: "Update",
+                                set_visible: !model.readonly,
+                                set_label: &gettext("Update"),
                                 connect_clicked[sender] => move |_|{
                                     sender.input(UpdatePageMsg::UpdateSystem);
                                 },
@@ -187,7 +376,15 @@ impl SimpleComponent for UpdatePageModel {
                                 }
                             },
                             #[watch]
-                            set_visible: !model.updatesystemlist.is_empty(),
+                            set_visible: !model.updatesystemlist.is_empty() && !model.groupupdates,
+                        },
+                        #[local_ref]
+                        updatesystemgroups -> gtk::ListBox {
+                            set_valign: gtk::Align::Start,
+                            add_css_class: "boxed-list",
+                            set_selection_mode: gtk::SelectionMode::None,
+                            #[watch]
+                            set_visible: !model.updatesystemgroups.is_empty() && model.groupupdates,
                         }
                     }
                 } else {
@@ -205,7 +402,7 @@ impl SimpleComponent for UpdatePageModel {
                         },
                         gtk::Label {
                             add_css_class: "title-1",
-                            set_label: "Everything is up to date!"
+                            set_label: &gettext("Everything is up to date!")
                         }
                     }
                 }
@@ -228,23 +425,59 @@ impl SimpleComponent for UpdatePageModel {
 
         let config = initparams.config;
         updateworker.emit(UpdateAsyncHandlerMsg::UpdateConfig(config.clone()));
+        updateworker.emit(UpdateAsyncHandlerMsg::UpdateNscConfig(initparams.nscconfig.clone()));
 
         let model = UpdatePageModel {
-            updateuserlist: FactoryVecDeque::builder().launch(gtk::ListBox::new()).detach(),
-            updatesystemlist: FactoryVecDeque::builder().launch(gtk::ListBox::new()).detach(),
+            updateuserlist: FactoryVecDeque::builder().launch(gtk::ListBox::new()).forward(sender.input_sender(), |output| match output {
+                UpdateItemMsg::OpenChangelog(pkg, version) => UpdatePageMsg::OpenChangelog(pkg, version),
+                UpdateItemMsg::SkipVersion(item) => UpdatePageMsg::SkipVersion(item),
+                UpdateItemMsg::Update(item) => UpdatePageMsg::UpdateUser(item),
+                UpdateItemMsg::Select(item, selected) => UpdatePageMsg::Select(item, selected),
+            }),
+            updatesystemlist: FactoryVecDeque::builder().launch(gtk::ListBox::new()).forward(sender.input_sender(), |output| match output {
+                UpdateItemMsg::OpenChangelog(pkg, version) => UpdatePageMsg::OpenChangelog(pkg, version),
+                UpdateItemMsg::SkipVersion(item) => UpdatePageMsg::SkipVersion(item),
+                UpdateItemMsg::Update(item) => UpdatePageMsg::UpdateUser(item),
+                UpdateItemMsg::Select(item, selected) => UpdatePageMsg::Select(item, selected),
+            }),
+            updatesecuritylist: FactoryVecDeque::builder().launch(gtk::ListBox::new()).forward(sender.input_sender(), |output| match output {
+                UpdateItemMsg::OpenChangelog(pkg, version) => UpdatePageMsg::OpenChangelog(pkg, version),
+                UpdateItemMsg::SkipVersion(item) => UpdatePageMsg::SkipVersion(item),
+                UpdateItemMsg::Update(item) => UpdatePageMsg::UpdateUser(item),
+                UpdateItemMsg::Select(item, selected) => UpdatePageMsg::Select(item, selected),
+            }),
+            updateusergroups: FactoryVecDeque::builder().launch(gtk::ListBox::new()).forward(sender.input_sender(), identity),
+            updatesystemgroups: FactoryVecDeque::builder().launch(gtk::ListBox::new()).forward(sender.input_sender(), identity),
+            useritems: vec![],
+            sysitems: vec![],
+            groupupdates: state::getnscstate().ui.groupupdates,
+            selecting: false,
+            selected: HashSet::new(),
+            nscstate: state::getnscstate(),
             channelupdate: None,
             updatetracker: 0,
             updateworker,
             config,
+            impure: initparams.nscconfig.impure,
+            nscconfig: initparams.nscconfig,
+            extraargs: String::new(),
+            argserror: None,
             systype: initparams.systype,
             usertype: initparams.usertype,
             unavailabledialog,
             online: initparams.online,
+            previewing: false,
+            readonly: initparams.readonly,
+            noremove: initparams.noremove,
+            window: initparams.window,
             tracker: 0,
         };
 
         let updateuserlist = model.updateuserlist.widget();
         let updatesystemlist = model.updatesystemlist.widget();
+        let updatesecuritylist = model.updatesecuritylist.widget();
+        let updateusergroups = model.updateusergroups.widget();
+        let updatesystemgroups = model.updatesystemgroups.widget();
 
         let widgets = view_output!();
         widgets.mainstack.set_hhomogeneous(false);
@@ -255,31 +488,147 @@ impl SimpleComponent for UpdatePageModel {
 
     fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
         self.reset();
+        let updates = matches!(
+            msg,
+            UpdatePageMsg::UpdateSystem
+                | UpdatePageMsg::UpdateSystemRm(_)
+                | UpdatePageMsg::UpdateAllUser
+                | UpdatePageMsg::UpdateAllUserRm(_)
+                | UpdatePageMsg::UpdateUser(_)
+                | UpdatePageMsg::UpdateGroup(_)
+                | UpdatePageMsg::UpdateSelected
+                | UpdatePageMsg::UpdateAll
+                | UpdatePageMsg::UpdateAllRm(..)
+                | UpdatePageMsg::Preview
+        );
+        if updates && self.readonly {
+            warn!("Not updating, packages can't be changed");
+            return;
+        }
+        if updates && self.argserror.is_some() {
+            warn!("Not updating, the extra arguments don't parse");
+            return;
+        }
         match msg {
             UpdatePageMsg::UpdateConfig(config) => {
                 self.config = config;
                 self.updateworker.emit(UpdateAsyncHandlerMsg::UpdateConfig(self.config.clone()));
             }
+            UpdatePageMsg::UpdateNscConfig(nscconfig) => {
+                self.impure = nscconfig.impure;
+                self.set_nscconfig(nscconfig.clone());
+                self.updateworker.emit(UpdateAsyncHandlerMsg::UpdateNscConfig(nscconfig));
+            }
+            UpdatePageMsg::SetExtraArgs(args) => {
+                match cmd::extraargs(&args) {
+                    Ok(parsed) => {
+                        self.updateworker.emit(UpdateAsyncHandlerMsg::SetExtraArgs(parsed));
+                        self.argserror = None;
+                    }
+                    Err(e) => self.argserror = Some(e.to_string()),
+                }
+                self.extraargs = args;
+            }
+            UpdatePageMsg::SetImpure(impure) => {
+                self.updateworker.emit(UpdateAsyncHandlerMsg::SetImpure(impure));
+                self.impure = impure;
+            }
             UpdatePageMsg::UpdatePkgTypes(systype, usertype) => {
                 self.systype = systype;
                 self.usertype = usertype;
                 self.updateworker.emit(UpdateAsyncHandlerMsg::UpdatePkgTypes(self.systype.clone(), self.usertype.clone()));
             }
-            UpdatePageMsg::Update(updateuserlist, updatesystemlist) => {
+            UpdatePageMsg::SetHost(host) => {
+                self.updateworker.emit(UpdateAsyncHandlerMsg::SetHost(host));
+            }
+            UpdatePageMsg::Update(mut updateuserlist, updatesystemlist) => {
                 info!("UpdatePageMsg::Update");
                 debug!("UPDATEUSERLIST: {:?}", updateuserlist);
                 debug!("UPDATESYSTEMLIST: {:?}", updatesystemlist);
+                // Forget skipped versions once a newer one is available
+                let pending = updateuserlist
+                    .iter()
+                    .filter_map(|x| Some((self.skipkey(x)?, x.verto.clone()?)))
+                    .collect::<HashMap<_, _>>();
+                let skippedupdates = self
+                    .nscstate
+                    .skippedupdates
+                    .iter()
+                    // Packages missing from this list, e.g. on another host, keep their skip
+                    .filter(|(k, v)| pending.get(*k).map(|x| x == *v).unwrap_or(true))
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect::<HashMap<_, _>>();
+                if skippedupdates != self.nscstate.skippedupdates {
+                    self.nscstate.skippedupdates = skippedupdates.clone();
+                    if let Err(e) = state::editnscstate(|x| x.skippedupdates = skippedupdates) {
+                        warn!("Failed to save skipped updates: {}", e);
+                    }
+                }
+                updateuserlist.retain(|x| !self.isskipped(x));
+                self.updateworker.emit(UpdateAsyncHandlerMsg::SetSkipped(self.nscstate.skippedupdates.keys().cloned().collect()));
                 self.update_updatetracker(|_| ());
+                let pkgs = updateuserlist
+                    .iter()
+                    .chain(updatesystemlist.iter())
+                    .filter(|x| !x.pname.is_empty())
+                    .filter_map(|x| x.verfrom.as_ref().map(|v| (x.pname.to_string(), v.to_string())))
+                    .collect::<Vec<_>>();
+                self.updatesecuritylist.guard().clear();
+                self.useritems = updateuserlist.clone();
+                self.sysitems = updatesystemlist.clone();
+                self.regroup();
                 let mut updateuserlist_guard = self.updateuserlist.guard();
                 updateuserlist_guard.clear();
                 for updateuser in updateuserlist {
-                    updateuserlist_guard.push_back(updateuser);
+                    updateuserlist_guard.push_back((updateuser, self.readonly));
                 }
+                updateuserlist_guard.drop();
                 let mut updatesystemlist_guard = self.updatesystemlist.guard();
                 updatesystemlist_guard.clear();
                 for updatesystem in updatesystemlist {
-                    updatesystemlist_guard.push_back(updatesystem);
+                    updatesystemlist_guard.push_back((updatesystem, self.readonly));
+                }
+                updatesystemlist_guard.drop();
+                let listed = self.userrows().filter_map(|x| self.skipkey(&x)).collect::<HashSet<_>>();
+                self.selected.retain(|x| listed.contains(x));
+                self.showselection();
+                if self.online && !pkgs.is_empty() {
+                    let sender = sender.clone();
+                    relm4::spawn(async move {
+                        let vulnerable = security::vulnerablepkgs(pkgs).await;
+                        if !vulnerable.is_empty() {
+                            sender.input(UpdatePageMsg::SetSecurity(vulnerable));
+                        }
+                    });
+                }
+            }
+            UpdatePageMsg::SetSecurity(vulnerable) => {
+                let mut securityitems = vec![];
+                for list in [&mut self.updateuserlist, &mut self.updatesystemlist] {
+                    let mut guard = list.guard();
+                    let mut i = 0;
+                    while i < guard.len() {
+                        if guard.get(i).map(|x| vulnerable.contains_key(&x.item.pname)).unwrap_or(false) {
+                            if let Some(mut m) = guard.remove(i) {
+                                m.item.severity = vulnerable.get(&m.item.pname).cloned();
+                                securityitems.push(m.item);
+                            }
+                        } else {
+                            i += 1;
+                        }
+                    }
                 }
+                self.useritems.retain(|x| !vulnerable.contains_key(&x.pname));
+                self.sysitems.retain(|x| !vulnerable.contains_key(&x.pname));
+                self.regroup();
+                securityitems.sort_by_key(|x| security::severityrank(x.severity.as_deref().unwrap_or_default()));
+                let mut updatesecuritylist_guard = self.updatesecuritylist.guard();
+                updatesecuritylist_guard.clear();
+                for item in securityitems {
+                    updatesecuritylist_guard.push_back((item, self.readonly));
+                }
+                updatesecuritylist_guard.drop();
+                self.showselection();
             }
             UpdatePageMsg::OpenRow(row, pkgtype) => match pkgtype {
                 InstallType::User => {
@@ -299,6 +648,46 @@ impl SimpleComponent for UpdatePageModel {
                     }
                 }
             },
+            UpdatePageMsg::OpenSecurityRow(row) => {
+                let updatesecuritylist_guard = self.updatesecuritylist.guard();
+                if let Some(item) = updatesecuritylist_guard.get(row) {
+                    if let Some(pkg) = &item.item.pkg {
+                        sender.output(AppMsg::OpenPkg(pkg.to_string()));
+                    }
+                }
+            }
+            UpdatePageMsg::OpenPkg(pkg) => {
+                sender.output(AppMsg::OpenPkg(pkg));
+            }
+            UpdatePageMsg::OpenChangelog(pkg, version) => {
+                sender.output(AppMsg::OpenChangelog(pkg, version));
+            }
+            UpdatePageMsg::SkipVersion(item) => {
+                let (Some(key), Some(verto)) = (self.skipkey(&item), item.verto.clone()) else {
+                    return;
+                };
+                info!("Skipping {} {}", key, verto);
+                self.nscstate.skippedupdates.insert(key.to_string(), verto.to_string());
+                if let Err(e) = state::editnscstate(|x| {
+                    x.skippedupdates.insert(key, verto);
+                }) {
+                    warn!("Failed to save skipped updates: {}", e);
+                }
+                let same = |x: &UpdateItem| x.pname == item.pname && x.pkg == item.pkg;
+                for list in [&mut self.updateuserlist, &mut self.updatesecuritylist] {
+                    let mut guard = list.guard();
+                    if let Some(i) = guard.iter().position(|x| same(&x.item)) {
+                        guard.remove(i);
+                    }
+                }
+                self.useritems.retain(|x| !same(x));
+                if let Some(key) = self.skipkey(&item) {
+                    self.selected.remove(&key);
+                }
+                self.regroup();
+                self.showselection();
+                self.updateworker.emit(UpdateAsyncHandlerMsg::SetSkipped(self.nscstate.skippedupdates.keys().cloned().collect()));
+            }
             UpdatePageMsg::UpdateSystem => {
                 let online = util::checkonline();
                 if !online {
@@ -331,11 +720,93 @@ impl SimpleComponent for UpdatePageModel {
             }
             UpdatePageMsg::UpdateSystemRm(pkgs) => {
                 info!("UpdatePageMsg::UpdateSystemRm({:?})", pkgs);
-                self.updateworker.emit(UpdateAsyncHandlerMsg::UpdateSystemRemove(pkgs));
+                self.updateworker.emit(UpdateAsyncHandlerMsg::UpdateSystemRemove(self.allowed(pkgs)));
+            }
+            UpdatePageMsg::UpdateUser(item) => {
+                let Some(target) = self.skipkey(&item) else {
+                    return;
+                };
+                let online = util::checkonline();
+                if !online {
+                    sender.output(AppMsg::CheckNetwork);
+                    self.online = false;
+                    return;
+                }
+                info!("UpdatePageMsg::UpdateUser({})", target);
+                REBUILD_BROKER.send(RebuildMsg::Show);
+                self.updateworker.emit(UpdateAsyncHandlerMsg::UpdateSinglePkg(target));
+                self.setprogress(|x| x.item == item, ItemProgress::Working);
+            }
+            UpdatePageMsg::UpdateGroup(pkgs) => {
+                let online = util::checkonline();
+                if !online {
+                    sender.output(AppMsg::CheckNetwork);
+                    self.online = false;
+                    return;
+                }
+                info!("UpdatePageMsg::UpdateGroup({:?})", pkgs);
+                REBUILD_BROKER.send(RebuildMsg::Show);
+                self.updateworker.emit(UpdateAsyncHandlerMsg::UpdateUserPkgsOnly(pkgs));
+            }
+            UpdatePageMsg::SetGroupUpdates(group) => {
+                if let Err(e) = state::editnscstate(|x| x.ui.groupupdates = group) {
+                    warn!("Failed to save update grouping: {}", e);
+                }
+                self.set_groupupdates(group);
+            }
+            UpdatePageMsg::SetSelecting(selecting) => {
+                self.set_selecting(selecting);
+                self.selected.clear();
+                self.showselection();
+            }
+            UpdatePageMsg::Select(item, selected) => {
+                let Some(key) = self.skipkey(&item) else {
+                    return;
+                };
+                if selected {
+                    self.selected.insert(key);
+                } else {
+                    self.selected.remove(&key);
+                }
+                self.showselection();
+            }
+            UpdatePageMsg::SelectAll => {
+                let keys = self.userrows().filter_map(|x| self.skipkey(&x)).collect::<HashSet<_>>();
+                if keys == self.selected {
+                    self.selected.clear();
+                } else {
+                    self.selected = keys;
+                }
+                self.showselection();
             }
-            UpdatePageMsg::UpdateUser(pkg) => {
-                info!("UPDATE USER PKG: {}", pkg);
-                warn!("unimplemented");
+            UpdatePageMsg::UpdateSelected => {
+                if !self.selecting {
+                    sender.input(UpdatePageMsg::UpdateAllUser);
+                    return;
+                }
+                let online = util::checkonline();
+                if !online {
+                    sender.output(AppMsg::CheckNetwork);
+                    self.online = false;
+                    return;
+                }
+                let targets = self.selected.drain().collect::<Vec<_>>();
+                if targets.is_empty() {
+                    return;
+                }
+                info!("UpdatePageMsg::UpdateSelected({:?})", targets);
+                REBUILD_BROKER.send(RebuildMsg::Show);
+                self.updateworker.emit(UpdateAsyncHandlerMsg::UpdateUserPkgsOnly(targets.clone()));
+                let usertype = self.usertype.clone();
+                self.setprogress(
+                    |x| match usertype {
+                        UserPkgs::Env => targets.contains(&x.item.pname),
+                        UserPkgs::Profile => x.item.pkg.as_ref().map(|x| targets.contains(x)).unwrap_or(false),
+                    },
+                    ItemProgress::Working,
+                );
+                self.set_selecting(false);
+                self.showselection();
             }
             UpdatePageMsg::UpdateAllUser => {
                 let online = util::checkonline();
@@ -364,7 +835,7 @@ impl SimpleComponent for UpdatePageModel {
             }
             UpdatePageMsg::UpdateAllUserRm(pkgs) => {
                 info!("UpdatePageMsg::UpdateAllUserRm({:?})", pkgs);
-                self.updateworker.emit(UpdateAsyncHandlerMsg::UpdateUserPkgsRemove(pkgs));
+                self.updateworker.emit(UpdateAsyncHandlerMsg::UpdateUserPkgsRemove(self.allowed(pkgs)));
             }
             UpdatePageMsg::UpdateAll => {
                 let online = util::checkonline();
@@ -406,16 +877,46 @@ impl SimpleComponent for UpdatePageModel {
             }
             UpdatePageMsg::UpdateAllRm(userpkgs, syspkgs) => {
                 info!("UpdatePageMsg::UpdateAllRm({:?}, {:?})", userpkgs, syspkgs);
-                self.updateworker.emit(UpdateAsyncHandlerMsg::UpdateAllRemove(userpkgs, syspkgs));
+                self.updateworker
+                    .emit(UpdateAsyncHandlerMsg::UpdateAllRemove(self.allowed(userpkgs), self.allowed(syspkgs)));
+            }
+            UpdatePageMsg::Preview => {
+                let online = util::checkonline();
+                if !online {
+                    sender.output(AppMsg::CheckNetwork);
+                    self.online = false;
+                    return;
+                }
+                self.set_previewing(true);
+                self.updateworker.emit(UpdateAsyncHandlerMsg::Preview);
+            }
+            UpdatePageMsg::SetPreview(preview) => {
+                self.set_previewing(false);
+                showpreview(&self.window, &sender, preview);
+            }
+            UpdatePageMsg::SetItemProgress(pname, progress) => {
+                self.setprogress(|x| x.item.pname == pname, progress);
             }
             UpdatePageMsg::DoneWorking => {
+                self.setprogress(|x| x.progress == ItemProgress::Working, ItemProgress::Done);
                 let _ = nix_data::utils::refreshicons();
                 REBUILD_BROKER.send(RebuildMsg::FinishSuccess);
                 sender.output(AppMsg::UpdateInstalledPkgs);
             }
             UpdatePageMsg::FailedWorking => {
+                // The list is reloaded after a successful run only, so checkmarks would stay on failed rows
+                self.setprogress(|x| x.progress != ItemProgress::Idle, ItemProgress::Idle);
+                crash::failure("update", "Updating failed");
                 REBUILD_BROKER.send(RebuildMsg::FinishError(None));
             }
+            UpdatePageMsg::Cancel => {
+                self.updateworker.emit(UpdateAsyncHandlerMsg::Cancel);
+            }
+            UpdatePageMsg::CancelFinished => {
+                REBUILD_BROKER.send(RebuildMsg::Close);
+                // Some packages may have been updated before cancelling
+                sender.output(AppMsg::UpdateInstalledPkgs);
+            }
             UpdatePageMsg::UpdateOnline(online) => {
                 self.set_online(online);
             }
@@ -423,7 +924,222 @@ impl SimpleComponent for UpdatePageModel {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+impl UpdatePageModel {
+    fn opconfig(&self) -> NscConfig {
+        let mut nscconfig = self.nscconfig.clone();
+        nscconfig.impure = self.impure;
+        nscconfig
+    }
+
+    /// Drops the removals and replacements from `changes` when the policy doesn't allow removing packages.
+    fn allowed(&self, changes: PkgChanges) -> PkgChanges {
+        if self.noremove && (!changes.remove.is_empty() || !changes.install.is_empty()) {
+            warn!("Not removing {:?}, removing packages is not allowed", changes.remove);
+            PkgChanges {
+                skip: changes.skip,
+                ..Default::default()
+            }
+        } else {
+            changes
+        }
+    }
+
+    fn regroup(&mut self) {
+        let mut usergroups_guard = self.updateusergroups.guard();
+        usergroups_guard.clear();
+        for group in groupitems(&self.useritems, InstallType::User, &self.usertype, self.readonly) {
+            usergroups_guard.push_back(group);
+        }
+        usergroups_guard.drop();
+        let mut systemgroups_guard = self.updatesystemgroups.guard();
+        systemgroups_guard.clear();
+        for group in groupitems(&self.sysitems, InstallType::System, &self.usertype, self.readonly) {
+            systemgroups_guard.push_back(group);
+        }
+    }
+
+    /// User packages listed for updating, including security updates.
+    fn userrows(&self) -> impl Iterator<Item = UpdateItem> + '_ {
+        self.useritems
+            .iter()
+            .cloned()
+            .chain(self.updatesecuritylist.iter().map(|x| x.item.clone()))
+            .filter(|x| x.pkgtype == InstallType::User)
+    }
+
+    /// Shows the check boxes of the user package rows while selecting, grouped or not.
+    fn showselection(&mut self) {
+        let usertype = self.usertype.clone();
+        let key = |item: &UpdateItem| match usertype {
+            UserPkgs::Env => Some(item.pname.clone()).filter(|x| !x.is_empty()),
+            UserPkgs::Profile => item.pkg.clone(),
+        };
+        let mut groups_guard = self.updateusergroups.guard();
+        let grouplists = groups_guard.iter_mut().map(|x| &mut x.items);
+        for list in [&mut self.updateuserlist, &mut self.updatesecuritylist].into_iter().chain(grouplists) {
+            let mut guard = list.guard();
+            for row in guard.iter_mut() {
+                let rowkey = key(&row.item);
+                row.selecting = self.selecting && row.item.pkgtype == InstallType::User && rowkey.is_some();
+                row.selected = rowkey.map(|x| self.selected.contains(&x)).unwrap_or(false);
+            }
+        }
+    }
+
+    /// Sets the progress of every user package row matching `f`, grouped or not.
+    fn setprogress(&mut self, f: impl Fn(&UpdateItemModel) -> bool, progress: ItemProgress) {
+        let mut groups_guard = self.updateusergroups.guard();
+        let grouplists = groups_guard.iter_mut().map(|x| &mut x.items);
+        for list in [&mut self.updateuserlist, &mut self.updatesecuritylist].into_iter().chain(grouplists) {
+            let mut guard = list.guard();
+            for row in guard.iter_mut() {
+                if row.item.pkgtype == InstallType::User && f(row) {
+                    row.progress = progress;
+                }
+            }
+        }
+    }
+
+    /// Key a user package is upgraded by, by name for nix-env and by attribute for nix profile.
+    fn skipkey(&self, item: &UpdateItem) -> Option<String> {
+        match self.usertype {
+            UserPkgs::Env => Some(item.pname.clone()).filter(|x| !x.is_empty()),
+            UserPkgs::Profile => item.pkg.clone(),
+        }
+    }
+
+    fn isskipped(&self, item: &UpdateItem) -> bool {
+        match (self.skipkey(item), &item.verto) {
+            (Some(key), Some(verto)) => self.nscstate.skippedupdates.get(&key) == Some(verto),
+            _ => false,
+        }
+    }
+
+    fn usercmdpreview(&self) -> String {
+        let nscconfig = self.opconfig();
+        let (program, args) = updateworker::upgradecmd(&self.usertype, &nscconfig, &cmd::extraargs(&self.extraargs).unwrap_or_default(), &[]);
+        cmd::cmdline(program, &args, &nscconfig)
+    }
+
+    fn syscmdpreview(&self) -> String {
+        let nscconfig = self.opconfig();
+        let args = updateworker::sysupdateargs(&self.systype, &self.config, &nscconfig, &cmd::extraargs(&self.extraargs).unwrap_or_default());
+        cmd::cmdline("nixos-rebuild", &args, &nscconfig)
+    }
+}
+
+/// The paths updating everything would fetch and build, asking whether to go ahead.
+fn showpreview(window: &gtk::Window, sender: &ComponentSender<UpdatePageModel>, preview: Result<UpdatePreview, String>) {
+    let preview = match preview {
+        Ok(preview) => preview,
+        Err(e) => {
+            let dialog = adw::MessageDialog::new(Some(window), Some(&gettext("Couldn't Preview Updates")), Some(&e));
+            dialog.add_response("close", &gettext("Close"));
+            dialog.present();
+            return;
+        }
+    };
+    let mut body = vec![];
+    let mut paths = vec![];
+    for (title, size) in [(gettext("System"), preview.system.as_ref()), (gettext("User packages"), Some(&preview.user))] {
+        let Some(size) = size else {
+            continue;
+        };
+        let mut line = if size.paths > 0 {
+            ngettextf(
+                "{}: {} store path to download, {} to download and {} on disk once unpacked.",
+                "{}: {} store paths to download, {} to download and {} on disk once unpacked.",
+                size.paths as u32,
+                &[
+                    &title,
+                    &size.paths.to_string(),
+                    &gtk::glib::format_size(size.download),
+                    &gtk::glib::format_size(size.unpacked),
+                ],
+            )
+        } else {
+            gettextf("{}: nothing to download.", &[&title])
+        };
+        if size.builds > 0 {
+            line.push(' ');
+            line.push_str(&ngettextf(
+                "{} package is not in the binary cache and will be built on this computer.",
+                "{} packages are not in the binary cache and will be built on this computer.",
+                size.builds as u32,
+                &[&size.builds.to_string()],
+            ));
+        }
+        body.push(line);
+        paths.extend(size.fetch.iter().map(|x| deps::pathname(x).to_string()));
+        paths.extend(size.build.iter().map(|x| gettextf("{} (build)", &[deps::pathname(x).trim_end_matches(".drv")])));
+    }
+    let dialog = adw::MessageDialog::new(Some(window), Some(&gettext("Update Everything?")), Some(&body.join("\n\n")));
+    if !paths.is_empty() {
+        paths.sort();
+        paths.dedup();
+        let label = gtk::Label::builder()
+            .label(paths.join("\n"))
+            .css_classes(["monospace", "caption"])
+            .selectable(true)
+            .xalign(0.0)
+            .valign(gtk::Align::Start)
+            .build();
+        let scroll = gtk::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .min_content_height(200)
+            .max_content_height(300)
+            .propagate_natural_height(true)
+            .child(&label)
+            .build();
+        dialog.set_extra_child(Some(&scroll));
+    }
+    dialog.add_response("cancel", &gettext("Cancel"));
+    dialog.add_response("update", &gettext("Update Everything"));
+    dialog.set_response_appearance("update", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("update"));
+    dialog.set_close_response("cancel");
+    let sender = sender.clone();
+    dialog.connect_response(None, move |_, resp| {
+        if resp == "update" {
+            sender.input(UpdatePageMsg::UpdateAll);
+        }
+    });
+    dialog.present();
+}
+
+fn groupitems(items: &[UpdateItem], pkgtype: InstallType, usertype: &UserPkgs, readonly: bool) -> Vec<UpdateGroupInit> {
+    let mut groups: BTreeMap<String, Vec<UpdateItem>> = BTreeMap::new();
+    for item in items {
+        groups
+            .entry(item.category.clone().unwrap_or_else(|| String::from("Other")))
+            .or_default()
+            .push(item.clone());
+    }
+    let mut groups = groups
+        .into_iter()
+        .map(|(category, items)| {
+            // nix-env upgrades by name, nix profile by attribute
+            let targets = items
+                .iter()
+                .filter_map(|x| match usertype {
+                    UserPkgs::Env => Some(x.pname.clone()).filter(|x| !x.is_empty()),
+                    UserPkgs::Profile => x.pkg.clone(),
+                })
+                .collect();
+            UpdateGroupInit {
+                category,
+                pkgtype: pkgtype.clone(),
+                items,
+                targets,
+                readonly,
+            }
+        })
+        .collect::<Vec<_>>();
+    groups.sort_by_key(|x| x.category == "Other");
+    groups
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct UpdateItem {
     pub name: String,
     pub pkg: Option<String>,
@@ -433,20 +1149,42 @@ pub struct UpdateItem {
     pub pkgtype: InstallType,
     pub verfrom: Option<String>,
     pub verto: Option<String>,
+    pub severity: Option<String>,
+    /// Appstream category the package is grouped under
+    pub category: Option<String>,
+}
+
+/// Where a row is while its package is being upgraded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ItemProgress {
+    #[default]
+    Idle,
+    Working,
+    Done,
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct UpdateItemModel {
     item: UpdateItem,
+    progress: ItemProgress,
+    selecting: bool,
+    selected: bool,
+    readonly: bool,
 }
 
 #[derive(Debug)]
-pub enum UpdateItemMsg {}
+pub enum UpdateItemMsg {
+    OpenChangelog(String, String),
+    SkipVersion(UpdateItem),
+    Update(UpdateItem),
+    Select(UpdateItem, bool),
+}
 
 #[relm4::factory(pub)]
 impl FactoryComponent for UpdateItemModel {
     type CommandOutput = ();
-    type Init = UpdateItem;
+    /// The item, and whether it can't be updated
+    type Init = (UpdateItem, bool);
     type Input = ();
     type Output = UpdateItemMsg;
     type ParentWidget = adw::gtk::ListBox;
@@ -461,6 +1199,18 @@ impl FactoryComponent for UpdateItemModel {
                 set_hexpand: true,
                 set_spacing: 10,
                 set_margin_all: 10,
+                gtk::CheckButton {
+                    set_valign: gtk::Align::Center,
+                    set_tooltip_text: Some(&gettext("Include in the update")),
+                    #[watch]
+                    set_visible: self.selecting,
+                    #[watch]
+                    #[block_signal(selecthandler)]
+                    set_active: self.selected,
+                    connect_toggled[sender, item = self.item.clone()] => move |x| {
+                        let _ = sender.output(UpdateItemMsg::Select(item.clone(), x.is_active()));
+                    } @selecthandler,
+                },
                 adw::Bin {
                     set_valign: gtk::Align::Center,
                     #[wrap(Some)]
@@ -539,19 +1289,78 @@ impl FactoryComponent for UpdateItemModel {
                         set_max_width_chars: 0,
                     },
                 },
-                // gtk::Button {
-                //     set_visible: self.item.pkgtype == InstallType::User,
-                //     set_valign: gtk::Align::Center,
-                //     set_halign: gtk::Align::End,
-                //     set_label: "Update",
-                //     set_can_focus: false,
-                // }
+                gtk::Button {
+                    add_css_class: "flat",
+                    set_valign: gtk::Align::Center,
+                    set_can_focus: false,
+                    set_label: &gettext("What changed?"),
+                    set_visible: self.item.pkg.is_some() && self.item.verto.is_some(),
+                    connect_clicked[sender, pkg = self.item.pkg.clone(), verto = self.item.verto.clone()] => move |_| {
+                        if let (Some(pkg), Some(verto)) = (&pkg, &verto) {
+                            let _ = sender.output(UpdateItemMsg::OpenChangelog(pkg.to_string(), verto.to_string()));
+                        }
+                    }
+                },
+                gtk::Button {
+                    add_css_class: "flat",
+                    set_valign: gtk::Align::Center,
+                    set_can_focus: false,
+                    set_label: &gettextf("Skip {}", &[self.item.verto.as_deref().unwrap_or_default()]),
+                    set_tooltip_text: Some(&gettext("Hide this update until a newer version is available")),
+                    set_visible: self.item.pkgtype == InstallType::User && self.item.verto.is_some(),
+                    connect_clicked[sender, item = self.item.clone()] => move |_| {
+                        let _ = sender.output(UpdateItemMsg::SkipVersion(item.clone()));
+                    }
+                },
+                gtk::Label {
+                    set_valign: gtk::Align::Center,
+                    add_css_class: "heading",
+                    add_css_class: match self.item.severity.as_deref() {
+                        Some("CRITICAL") | Some("HIGH") => "error",
+                        _ => "warning",
+                    },
+                    set_visible: self.item.severity.is_some(),
+                    set_label: &self.item.severity.as_deref().map(|x| {
+                        let mut s = x.to_lowercase();
+                        if let Some(c) = s.get_mut(0..1) {
+                            c.make_ascii_uppercase();
+                        }
+                        s
+                    }).unwrap_or_default(),
+                },
+                gtk::Spinner {
+                    set_valign: gtk::Align::Center,
+                    #[watch]
+                    set_spinning: self.progress == ItemProgress::Working,
+                    #[watch]
+                    set_visible: self.progress == ItemProgress::Working,
+                },
+                gtk::Image {
+                    add_css_class: "success",
+                    set_valign: gtk::Align::Center,
+                    set_icon_name: Some("emblem-ok-symbolic"),
+                    set_tooltip_text: Some(&gettext("Updated")),
+                    #[watch]
+                    set_visible: self.progress == ItemProgress::Done,
+                },
+                gtk::Button {
+                    set_visible: self.item.pkgtype == InstallType::User && !self.readonly,
+                    set_valign: gtk::Align::Center,
+                    set_halign: gtk::Align::End,
+                    set_label: &gettext("Update"),
+                    set_can_focus: false,
+                    #[watch]
+                    set_sensitive: self.progress == ItemProgress::Idle,
+                    connect_clicked[sender, item = self.item.clone()] => move |_| {
+                        let _ = sender.output(UpdateItemMsg::Update(item.clone()));
+                    }
+                }
             }
         }
     }
 
     fn init_model(
-        parent: Self::Init,
+        (parent, readonly): Self::Init,
         _index: &DynamicIndex,
         _sender: FactorySender<Self>,
     ) -> Self {
@@ -577,8 +1386,124 @@ impl FactoryComponent for UpdateItemModel {
             pkgtype: parent.pkgtype,
             verfrom: parent.verfrom,
             verto: parent.verto,
+            severity: parent.severity,
+            category: parent.category,
         };
 
-        Self { item }
+        Self {
+            item,
+            progress: ItemProgress::Idle,
+            selecting: false,
+            selected: false,
+            readonly,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct UpdateGroupInit {
+    category: String,
+    pkgtype: InstallType,
+    items: Vec<UpdateItem>,
+    targets: Vec<String>,
+    readonly: bool,
+}
+
+#[derive(Debug)]
+pub struct UpdateGroupModel {
+    category: String,
+    pkgtype: InstallType,
+    items: FactoryVecDeque<UpdateItemModel>,
+    targets: Vec<String>,
+    readonly: bool,
+}
+
+#[derive(Debug)]
+pub enum UpdateGroupMsg {
+    OpenRow(usize),
+    OpenChangelog(String, String),
+    SkipVersion(UpdateItem),
+    Update(UpdateItem),
+    Select(UpdateItem, bool),
+}
+
+#[relm4::factory(pub)]
+impl FactoryComponent for UpdateGroupModel {
+    type CommandOutput = ();
+    type Init = UpdateGroupInit;
+    type Input = UpdateGroupMsg;
+    type Output = UpdatePageMsg;
+    type ParentWidget = adw::gtk::ListBox;
+
+    view! {
+        adw::ExpanderRow {
+            set_title: &categories::sectionname(&self.category),
+            set_subtitle: &ngettextf("{} update", "{} updates", self.items.len() as u32, &[&self.items.len().to_string()]),
+            set_expanded: true,
+            add_action = &gtk::Button {
+                add_css_class: "suggested-action",
+                set_valign: gtk::Align::Center,
+                set_label: &gettext("Update"),
+                // The system is always rebuilt as a whole
+                set_visible: self.pkgtype == InstallType::User && !self.targets.is_empty() && !self.readonly,
+                connect_clicked[sender, targets = self.targets.clone()] => move |_| {
+                    let _ = sender.output(UpdatePageMsg::UpdateGroup(targets.clone()));
+                }
+            },
+            add_row: self.items.widget(),
+        }
+    }
+
+    fn init_model(
+        init: Self::Init,
+        _index: &DynamicIndex,
+        sender: FactorySender<Self>,
+    ) -> Self {
+        let mut items = FactoryVecDeque::builder().launch(gtk::ListBox::new()).forward(sender.input_sender(), |output| match output {
+            UpdateItemMsg::OpenChangelog(pkg, version) => UpdateGroupMsg::OpenChangelog(pkg, version),
+            UpdateItemMsg::SkipVersion(item) => UpdateGroupMsg::SkipVersion(item),
+            UpdateItemMsg::Update(item) => UpdateGroupMsg::Update(item),
+            UpdateItemMsg::Select(item, selected) => UpdateGroupMsg::Select(item, selected),
+        });
+        items.widget().set_selection_mode(gtk::SelectionMode::None);
+        items.widget().connect_row_activated(move |listbox, row| {
+            if let Some(i) = listbox.index_of_child(row) {
+                sender.input(UpdateGroupMsg::OpenRow(i as usize));
+            }
+        });
+        let mut items_guard = items.guard();
+        for item in init.items {
+            items_guard.push_back((item, init.readonly));
+        }
+        items_guard.drop();
+        Self {
+            category: init.category,
+            pkgtype: init.pkgtype,
+            items,
+            targets: init.targets,
+            readonly: init.readonly,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: FactorySender<Self>) {
+        match msg {
+            UpdateGroupMsg::OpenRow(row) => {
+                if let Some(pkg) = self.items.get(row).and_then(|x| x.item.pkg.clone()) {
+                    let _ = sender.output(UpdatePageMsg::OpenPkg(pkg));
+                }
+            }
+            UpdateGroupMsg::OpenChangelog(pkg, version) => {
+                let _ = sender.output(UpdatePageMsg::OpenChangelog(pkg, version));
+            }
+            UpdateGroupMsg::SkipVersion(item) => {
+                let _ = sender.output(UpdatePageMsg::SkipVersion(item));
+            }
+            UpdateGroupMsg::Update(item) => {
+                let _ = sender.output(UpdatePageMsg::UpdateUser(item));
+            }
+            UpdateGroupMsg::Select(item, selected) => {
+                let _ = sender.output(UpdatePageMsg::Select(item, selected));
+            }
+        }
     }
 }
@@ -2,13 +2,24 @@ use anyhow::{anyhow, Result};
 use log::*;
 use nix_data::config::configfile::NixDataConfig;
 use relm4::*;
-use std::{fs, path::Path, process::Stdio};
+use std::{collections::HashMap, fs, process::Stdio, sync::Mutex, time::Instant};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
 
-use crate::ui::{rebuild::RebuildMsg, window::REBUILD_BROKER};
+use crate::{
+    i18n::{gettext, gettextf},
+    parse::{
+        cmd::{helperpath, impureargs, nixargs, nixcmd, purityhint, rebuildargs},
+        config::NscConfig,
+        demo,
+        narinfo::{self, ClosureSize},
+        nixlog::{self, NixEvent, Substitutions},
+        nixpkgsconfig, nixversion, profile, remote, state, util,
+    },
+    ui::{rebuild::RebuildMsg, window::REBUILD_BROKER},
+};
 
 use super::{
-    updatepage::UpdatePageMsg,
+    updatepage::{ItemProgress, UpdatePageMsg},
     window::{SystemPkgs, UserPkgs},
 };
 
@@ -18,26 +29,62 @@ pub struct UpdateAsyncHandler {
     #[tracker::no_eq]
     process: Option<JoinHandle<()>>,
     config: NixDataConfig,
+    nscconfig: NscConfig,
+    extraargs: Vec<String>,
+    impure: Option<bool>,
+    /// User packages whose pending update was skipped
+    skipped: Vec<String>,
     syspkgs: SystemPkgs,
     userpkgs: UserPkgs,
+    /// SSH host whose user profile is updated instead of the local one
+    host: Option<String>,
 }
 
 #[derive(Debug)]
 pub enum UpdateAsyncHandlerMsg {
     UpdateConfig(NixDataConfig),
+    UpdateNscConfig(NscConfig),
+    SetExtraArgs(Vec<String>),
+    SetImpure(bool),
+    SetSkipped(Vec<String>),
     UpdatePkgTypes(SystemPkgs, UserPkgs),
+    SetHost(Option<String>),
 
     // UpdateChannels,
     // UpdateChannelsAndSystem,
     UpdateSystem,
-    UpdateSystemRemove(Vec<String>),
+    UpdateSystemRemove(PkgChanges),
 
     RebuildSystem,
     UpdateUserPkgs,
-    UpdateUserPkgsRemove(Vec<String>),
+    UpdateUserPkgsRemove(PkgChanges),
+    /// Only upgrade the given user packages, by attribute for nix profile and by name for nix-env
+    UpdateUserPkgsOnly(Vec<String>),
+    /// Upgrades one user package, `nix profile upgrade <name>` instead of `.*`
+    UpdateSinglePkg(String),
 
     UpdateAll,
-    UpdateAllRemove(Vec<String>, Vec<String>),
+    UpdateAllRemove(PkgChanges, PkgChanges),
+    /// Works out what updating everything would fetch and build without doing it
+    Preview,
+    Cancel,
+}
+
+/// What updating everything would fetch and build.
+#[derive(Debug, Clone, Default)]
+pub struct UpdatePreview {
+    /// Unset when the system isn't managed
+    pub system: Option<ClosureSize>,
+    pub user: ClosureSize,
+}
+
+/// Unavailable packages to remove before updating, and replacements to install in their place.
+#[derive(Debug, Default, Clone)]
+pub struct PkgChanges {
+    pub remove: Vec<String>,
+    pub install: Vec<String>,
+    /// User packages to leave at their current version
+    pub skip: Vec<String>,
 }
 
 enum NscCmd {
@@ -65,26 +112,99 @@ impl Worker for UpdateAsyncHandler {
                 flakearg: None,
                 generations: None
             },
+            nscconfig: NscConfig::default(),
+            extraargs: vec![],
+            impure: None,
+            skipped: vec![],
             syspkgs: params.syspkgs,
             userpkgs: params.userpkgs,
+            host: None,
             tracker: 0,
         }
     }
 
     fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
+        // The sample catalog stands in for a profile that must not be touched, and without nix
+        // there is nothing to update with
+        if (demo::enabled() || nixversion::detect().is_none())
+            && matches!(
+                msg,
+                UpdateAsyncHandlerMsg::UpdateSystem
+                    | UpdateAsyncHandlerMsg::UpdateSystemRemove(_)
+                    | UpdateAsyncHandlerMsg::RebuildSystem
+                    | UpdateAsyncHandlerMsg::UpdateUserPkgs
+                    | UpdateAsyncHandlerMsg::UpdateUserPkgsRemove(_)
+                    | UpdateAsyncHandlerMsg::UpdateUserPkgsOnly(_)
+                    | UpdateAsyncHandlerMsg::UpdateSinglePkg(_)
+                    | UpdateAsyncHandlerMsg::UpdateAll
+                    | UpdateAsyncHandlerMsg::UpdateAllRemove(..)
+            )
+        {
+            warn!("Not updating, packages can't be changed");
+            sender.output(UpdatePageMsg::FailedWorking);
+            return;
+        }
         match msg {
             UpdateAsyncHandlerMsg::UpdateConfig(config) => {
                 self.config = config;
             }
+            UpdateAsyncHandlerMsg::UpdateNscConfig(nscconfig) => {
+                self.nscconfig = nscconfig;
+                self.impure = None;
+            }
+            UpdateAsyncHandlerMsg::SetExtraArgs(extraargs) => {
+                self.extraargs = extraargs;
+            }
+            UpdateAsyncHandlerMsg::SetImpure(impure) => {
+                self.impure = Some(impure);
+            }
+            UpdateAsyncHandlerMsg::SetSkipped(skipped) => {
+                self.skipped = skipped;
+            }
             UpdateAsyncHandlerMsg::UpdatePkgTypes(syspkgs, userpkgs) => {
                 self.syspkgs = syspkgs;
                 self.userpkgs = userpkgs;
             }
+            UpdateAsyncHandlerMsg::SetHost(host) => {
+                self.host = host;
+            }
+            UpdateAsyncHandlerMsg::UpdateSinglePkg(pkg) => {
+                self.update(UpdateAsyncHandlerMsg::UpdateUserPkgsOnly(vec![pkg]), sender);
+            }
+            // Remote hosts only have a user profile, and skipped or removed packages are not tracked for them
+            UpdateAsyncHandlerMsg::UpdateUserPkgs
+            | UpdateAsyncHandlerMsg::UpdateUserPkgsRemove(_)
+            | UpdateAsyncHandlerMsg::UpdateUserPkgsOnly(_)
+            | UpdateAsyncHandlerMsg::UpdateAll
+            | UpdateAsyncHandlerMsg::UpdateAllRemove(..)
+                if self.host.is_some() =>
+            {
+                let host = self.host.clone().unwrap_or_default();
+                let targets = match msg {
+                    UpdateAsyncHandlerMsg::UpdateUserPkgsOnly(pkgs) => pkgs,
+                    _ => vec![],
+                };
+                let nscconfig = self.opconfig();
+                let extraargs = self.extraargs.clone();
+                self.process = Some(relm4::spawn(async move {
+                    match updateremote(&host, &targets, &nscconfig, &extraargs, &sender).await {
+                        Ok(true) => {
+                            sender.output(UpdatePageMsg::DoneWorking);
+                        }
+                        _ => {
+                            warn!("UPDATE {} FAILED", host);
+                            sender.output(UpdatePageMsg::FailedWorking);
+                        }
+                    }
+                }));
+            }
             UpdateAsyncHandlerMsg::UpdateSystem => {
                 let config = self.config.clone();
+                let nscconfig = self.opconfig();
+                let extraargs = self.extraargs.clone();
                 let syspkgs = self.syspkgs.clone();
-                relm4::spawn(async move {
-                    let result = runcmd(NscCmd::All, config, syspkgs, None).await;
+                self.process = Some(relm4::spawn(async move {
+                    let result = runcmd(NscCmd::All, config, &nscconfig, &extraargs, syspkgs, None).await;
                     match result {
                         Ok(true) => {
                             sender.output(UpdatePageMsg::DoneWorking);
@@ -94,14 +214,16 @@ impl Worker for UpdateAsyncHandler {
                             sender.output(UpdatePageMsg::FailedWorking);
                         }
                     }
-                });
+                }));
             }
             UpdateAsyncHandlerMsg::UpdateSystemRemove(pkgs) => {
                 let config = self.config.clone();
+                let nscconfig = self.opconfig();
+                let extraargs = self.extraargs.clone();
                 let syspkgs = self.syspkgs.clone();
-                relm4::spawn(async move {
+                self.process = Some(relm4::spawn(async move {
                     let result =
-                        runcmd(NscCmd::All, config, syspkgs, Some(pkgs)).await;
+                        runcmd(NscCmd::All, config, &nscconfig, &extraargs, syspkgs, Some(pkgs)).await;
                     match result {
                         Ok(true) => {
                             sender.output(UpdatePageMsg::DoneWorking);
@@ -111,18 +233,20 @@ impl Worker for UpdateAsyncHandler {
                             sender.output(UpdatePageMsg::FailedWorking);
                         }
                     }
-                });
+                }));
             }
             UpdateAsyncHandlerMsg::RebuildSystem => {
                 let config = self.config.clone();
+                let nscconfig = self.opconfig();
+                let extraargs = self.extraargs.clone();
                 let syspkgs = self.syspkgs.clone();
-                relm4::spawn(async move {
+                self.process = Some(relm4::spawn(async move {
                     let result = match syspkgs {
                         SystemPkgs::Legacy => {
-                            runcmd(NscCmd::Rebuild, config, syspkgs, None).await
+                            runcmd(NscCmd::Rebuild, config, &nscconfig, &extraargs, syspkgs, None).await
                         }
                         SystemPkgs::Flake => {
-                            runcmd(NscCmd::All, config, syspkgs, None).await
+                            runcmd(NscCmd::All, config, &nscconfig, &extraargs, syspkgs, None).await
                         }
                         SystemPkgs::None => Ok(true),
                     };
@@ -135,14 +259,17 @@ impl Worker for UpdateAsyncHandler {
                             sender.output(UpdatePageMsg::FailedWorking);
                         }
                     }
-                });
+                }));
             }
             UpdateAsyncHandlerMsg::UpdateUserPkgs => {
+                let nscconfig = self.opconfig();
+                let extraargs = self.extraargs.clone();
                 let userpkgs = self.userpkgs.clone();
-                relm4::spawn(async move {
+                let skipped = self.skipchanges();
+                self.process = Some(relm4::spawn(async move {
                     let result = match userpkgs {
-                        UserPkgs::Env => updateenv().await,
-                        UserPkgs::Profile => updateprofile(None).await,
+                        UserPkgs::Env => updateenv(&nscconfig, &extraargs, &[], &skipped.skip, &sender).await,
+                        UserPkgs::Profile => updateprofile(Some(skipped), &[], &nscconfig, &extraargs, &sender).await,
                     };
                     match result {
                         Ok(true) => {
@@ -153,14 +280,17 @@ impl Worker for UpdateAsyncHandler {
                             sender.output(UpdatePageMsg::FailedWorking);
                         }
                     }
-                });
+                }));
             }
-            UpdateAsyncHandlerMsg::UpdateUserPkgsRemove(pkgs) => {
+            UpdateAsyncHandlerMsg::UpdateUserPkgsRemove(mut pkgs) => {
+                let nscconfig = self.opconfig();
+                let extraargs = self.extraargs.clone();
                 let userpkgs = self.userpkgs.clone();
-                relm4::spawn(async move {
+                pkgs.skip.extend(self.skipped.iter().cloned());
+                self.process = Some(relm4::spawn(async move {
                     let result = match userpkgs {
-                        UserPkgs::Env => updateenv().await,
-                        UserPkgs::Profile => updateprofile(Some(pkgs)).await,
+                        UserPkgs::Env => updateenv(&nscconfig, &extraargs, &[], &pkgs.skip, &sender).await,
+                        UserPkgs::Profile => updateprofile(Some(pkgs), &[], &nscconfig, &extraargs, &sender).await,
                     };
                     match result {
                         Ok(true) => {
@@ -171,19 +301,42 @@ impl Worker for UpdateAsyncHandler {
                             sender.output(UpdatePageMsg::FailedWorking);
                         }
                     }
-                });
+                }));
+            }
+            UpdateAsyncHandlerMsg::UpdateUserPkgsOnly(pkgs) => {
+                let nscconfig = self.opconfig();
+                let extraargs = self.extraargs.clone();
+                let userpkgs = self.userpkgs.clone();
+                self.process = Some(relm4::spawn(async move {
+                    let result = match userpkgs {
+                        UserPkgs::Env => updateenv(&nscconfig, &extraargs, &pkgs, &[], &sender).await,
+                        UserPkgs::Profile => updateprofile(None, &pkgs, &nscconfig, &extraargs, &sender).await,
+                    };
+                    match result {
+                        Ok(true) => {
+                            sender.output(UpdatePageMsg::DoneWorking);
+                        }
+                        _ => {
+                            warn!("UPDATE USER FAILED");
+                            sender.output(UpdatePageMsg::FailedWorking);
+                        }
+                    }
+                }));
             }
             UpdateAsyncHandlerMsg::UpdateAll => {
                 let config = self.config.clone();
+                let nscconfig = self.opconfig();
+                let extraargs = self.extraargs.clone();
                 let syspkgs = self.syspkgs.clone();
                 let userpkgs = self.userpkgs.clone();
-                relm4::spawn(async move {
-                    let result = runcmd(NscCmd::All, config, syspkgs, None).await;
+                let skipped = self.skipchanges();
+                self.process = Some(relm4::spawn(async move {
+                    let result = runcmd(NscCmd::All, config, &nscconfig, &extraargs, syspkgs, None).await;
                     match result {
                         Ok(true) => {
                             match match userpkgs {
-                                UserPkgs::Env => updateenv().await,
-                                UserPkgs::Profile => updateprofile(None).await,
+                                UserPkgs::Env => updateenv(&nscconfig, &extraargs, &[], &skipped.skip, &sender).await,
+                                UserPkgs::Profile => updateprofile(Some(skipped), &[], &nscconfig, &extraargs, &sender).await,
                             } {
                                 Ok(true) => {
                                     sender.output(UpdatePageMsg::DoneWorking);
@@ -199,16 +352,47 @@ impl Worker for UpdateAsyncHandler {
                             sender.output(UpdatePageMsg::FailedWorking);
                         }
                     }
-                });
+                }));
             }
-            UpdateAsyncHandlerMsg::UpdateAllRemove(userrmpkgs, sysrmpkgs) => {
+            UpdateAsyncHandlerMsg::Preview => {
+                if self.host.is_some() {
+                    sender.output(UpdatePageMsg::SetPreview(Err(gettext(
+                        "Previews aren't available for profiles on other machines",
+                    ))));
+                    return;
+                }
                 let config = self.config.clone();
+                let nscconfig = self.opconfig();
+                let extraargs = self.extraargs.clone();
                 let syspkgs = self.syspkgs.clone();
                 let userpkgs = self.userpkgs.clone();
+                let skipped = self.skipped.clone();
                 relm4::spawn(async move {
+                    let preview = async {
+                        Ok::<_, anyhow::Error>(UpdatePreview {
+                            system: previewsystem(&syspkgs, &config, &nscconfig, &extraargs).await?,
+                            user: previewuser(&userpkgs, &nscconfig, &extraargs, &skipped).await?,
+                        })
+                    };
+                    sender.output(UpdatePageMsg::SetPreview(preview.await.map_err(|e| {
+                        warn!("Failed to preview updates: {}", e);
+                        e.to_string()
+                    })));
+                });
+            }
+            UpdateAsyncHandlerMsg::UpdateAllRemove(mut userrmpkgs, sysrmpkgs) => {
+                userrmpkgs.skip.extend(self.skipped.iter().cloned());
+                let config = self.config.clone();
+                let nscconfig = self.opconfig();
+                let extraargs = self.extraargs.clone();
+                let syspkgs = self.syspkgs.clone();
+                let userpkgs = self.userpkgs.clone();
+                self.process = Some(relm4::spawn(async move {
                     let result = runcmd(
                         NscCmd::All,
                         config,
+                        &nscconfig,
+                        &extraargs,
                         syspkgs,
                         Some(sysrmpkgs),
                     )
@@ -216,8 +400,8 @@ impl Worker for UpdateAsyncHandler {
                     match result {
                         Ok(true) => {
                             match match userpkgs {
-                                UserPkgs::Env => updateenv().await,
-                                UserPkgs::Profile => updateprofile(Some(userrmpkgs)).await,
+                                UserPkgs::Env => updateenv(&nscconfig, &extraargs, &[], &userrmpkgs.skip, &sender).await,
+                                UserPkgs::Profile => updateprofile(Some(userrmpkgs), &[], &nscconfig, &extraargs, &sender).await,
                             } {
                                 Ok(true) => {
                                     sender.output(UpdatePageMsg::DoneWorking);
@@ -233,45 +417,116 @@ impl Worker for UpdateAsyncHandler {
                             sender.output(UpdatePageMsg::FailedWorking);
                         }
                     }
-                });
+                }));
+            }
+            UpdateAsyncHandlerMsg::Cancel => {
+                info!("CANCELLING UPDATE");
+                if let Some(pid) = USERPROCESS.lock().unwrap().take() {
+                    // A negative pid signals the whole process group, including the builders nix started
+                    if let Err(e) = std::process::Command::new("kill")
+                        .arg("-TERM")
+                        .arg("--")
+                        .arg(format!("-{}", pid))
+                        .status()
+                    {
+                        warn!("Failed to stop process group {}: {}", pid, e);
+                    }
+                }
+                if let Some(p) = &mut self.process {
+                    p.abort()
+                }
+                self.process = None;
+                sender.output(UpdatePageMsg::CancelFinished);
             }
         }
     }
 }
 
+/// Removes and adds packages in the system configuration.
+fn editsysconfig(f: &str, changes: PkgChanges) -> Result<String> {
+    let newconfig = match nix_editor::write::rmarr(f, "environment.systemPackages", changes.remove) {
+        Ok(x) => x,
+        Err(_) => {
+            return Err(anyhow!("Failed to write configuration.nix"));
+        }
+    };
+    if changes.install.is_empty() {
+        return Ok(newconfig);
+    }
+    let prefix = match nix_editor::read::getwithvalue(&newconfig, "environment.systemPackages") {
+        Ok(s) if s.contains(&"pkgs".to_string()) => "",
+        _ => "pkgs.",
+    };
+    let install = changes
+        .install
+        .iter()
+        .map(|x| format!("{}{}", prefix, x))
+        .collect::<Vec<String>>();
+    match nix_editor::write::addtoarr(&newconfig, "environment.systemPackages", install) {
+        Ok(x) => Ok(x),
+        Err(_) => Err(anyhow!("Failed to write configuration.nix")),
+    }
+}
+
+impl UpdateAsyncHandler {
+    /// Config for the next operation, with the per-operation overrides applied.
+    fn opconfig(&self) -> NscConfig {
+        let mut nscconfig = self.nscconfig.clone();
+        if let Some(impure) = self.impure {
+            nscconfig.impure = impure;
+        }
+        nscconfig
+    }
+
+    fn skipchanges(&self) -> PkgChanges {
+        PkgChanges {
+            skip: self.skipped.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Process group of the running user package command. The system helper runs as root, so only user commands can be cancelled.
+static USERPROCESS: Mutex<Option<u32>> = Mutex::new(None);
+
+fn trackuser(child: &tokio::process::Child) {
+    *USERPROCESS.lock().unwrap() = child.id();
+    REBUILD_BROKER.send(RebuildMsg::SetCancellable(true));
+}
+
+/// Waits for a command started with [`trackuser`], and forgets it once it is reaped so a later cancel
+/// can't signal whatever reuses its pid.
+async fn waituser(child: &mut tokio::process::Child) -> std::io::Result<std::process::ExitStatus> {
+    let pid = child.id();
+    let status = child.wait().await;
+    let mut process = USERPROCESS.lock().unwrap();
+    if *process == pid {
+        *process = None;
+        REBUILD_BROKER.send(RebuildMsg::SetCancellable(false));
+    }
+    status
+}
+
 async fn runcmd(
     cmd: NscCmd,
     config: NixDataConfig,
+    nscconfig: &NscConfig,
+    extraargs: &[String],
     syspkgs: SystemPkgs,
-    rmpkgs: Option<Vec<String>>,
+    rmpkgs: Option<PkgChanges>,
 ) -> Result<bool> {
-    let systemconfig = config.systemconfig.unwrap_or_default();
-    let flakeargs = if let Some(flake) = config.flake {
-        if let Some(flakearg) = config.flakearg {
+    let systemconfig = config.systemconfig.clone().unwrap_or_default();
+    let flakeargs = if let Some(flake) = &config.flake {
+        if let Some(flakearg) = &config.flakearg {
             Some(format!("{}#{}", flake, flakearg))
         } else {
-            Some(flake)
+            Some(flake.to_string())
         }
     } else {
         None
     };
     let f = fs::read_to_string(&systemconfig)?;
-    let exe = match std::env::current_exe() {
-        Ok(mut e) => {
-            e.pop(); // root/bin
-            e.pop(); // root/
-            e.push("libexec"); // root/libexec
-            e.push("nsc-helper");
-            let x = e.to_string_lossy().to_string();
-            info!("nsc-helper path: {}", x);
-            if Path::new(&x).is_file() {
-                x
-            } else {
-                String::from("nsc-helper")
-            }
-        }
-        Err(_) => String::from("nsc-helper"),
-    };
+    let exe = helperpath();
 
     let flakepathsplit = flakeargs.clone().unwrap_or_default().to_string();
     let flakepath = flakepathsplit
@@ -281,20 +536,10 @@ async fn runcmd(
         .cloned()
         .unwrap_or_default();
 
-    let rebuildargs = if let Some(x) = flakeargs {
-        let mut v = vec![String::from("--flake")];
-        for arg in x.split(' ') {
-            if !arg.is_empty() {
-                v.push(String::from(arg));
-            }
-        }
-        v
-    } else {
-        vec![]
-    };
+    let rebuildargs = rebuildargs(&config);
 
     let mut cmd = match cmd {
-        NscCmd::Rebuild => tokio::process::Command::new("pkexec")
+        NscCmd::Rebuild => nixcmd("pkexec", nscconfig)
             .arg(&exe)
             .arg("rebuild")
             .arg("--generations")
@@ -302,26 +547,22 @@ async fn runcmd(
             .arg("--")
             .arg("switch")
             .args(&rebuildargs)
+            .args(nixargs(nscconfig))
+            .args(extraargs)
             .stderr(Stdio::piped())
             .spawn()?,
-        NscCmd::Channel => tokio::process::Command::new("pkexec")
+        NscCmd::Channel => nixcmd("pkexec", nscconfig)
             .arg(&exe)
             .arg("channel")
             .arg("--output")
             .arg(&systemconfig)
             .stderr(Stdio::piped())
             .spawn()?,
-        NscCmd::All => match syspkgs {
+        NscCmd::All => match &syspkgs {
             SystemPkgs::Legacy => {
                 if let Some(rmpkgs) = rmpkgs {
-                    let newconfig =
-                        match nix_editor::write::rmarr(&f, "environment.systemPackages", rmpkgs) {
-                            Ok(x) => x,
-                            Err(_) => {
-                                return Err(anyhow!("Failed to write configuration.nix"));
-                            }
-                        };
-                    let mut cmd = tokio::process::Command::new("pkexec")
+                    let newconfig = editsysconfig(&f, rmpkgs)?;
+                    let mut cmd = nixcmd("pkexec", nscconfig)
                         .arg(&exe)
                         .arg("channel")
                         .arg("--rebuild")
@@ -331,8 +572,7 @@ async fn runcmd(
                         .arg("--output")
                         .arg(&systemconfig)
                         .arg("--")
-                        .arg("switch")
-                        .args(&rebuildargs)
+                        .args(sysupdateargs(&syspkgs, &config, nscconfig, extraargs))
                         .stderr(Stdio::piped())
                         .stdin(Stdio::piped())
                         .spawn()?;
@@ -343,29 +583,22 @@ async fn runcmd(
                         .await?;
                     cmd
                 } else {
-                    tokio::process::Command::new("pkexec")
+                    nixcmd("pkexec", nscconfig)
                         .arg(&exe)
                         .arg("channel")
                         .arg("--rebuild")
                         .arg("--generations")
                         .arg(config.generations.unwrap_or(0).to_string())
                         .arg("--")
-                        .arg("switch")
-                        .args(&rebuildargs)
+                        .args(sysupdateargs(&syspkgs, &config, nscconfig, extraargs))
                         .stderr(Stdio::piped())
                         .spawn()?
                 }
             }
             SystemPkgs::Flake => {
                 if let Some(rmpkgs) = rmpkgs {
-                    let newconfig =
-                        match nix_editor::write::rmarr(&f, "environment.systemPackages", rmpkgs) {
-                            Ok(x) => x,
-                            Err(_) => {
-                                return Err(anyhow!("Failed to write configuration.nix"));
-                            }
-                        };
-                    let mut cmd = tokio::process::Command::new("pkexec")
+                    let newconfig = editsysconfig(&f, rmpkgs)?;
+                    let mut cmd = nixcmd("pkexec", nscconfig)
                         .arg(&exe)
                         .arg("flake")
                         .arg("--rebuild")
@@ -377,9 +610,7 @@ async fn runcmd(
                         .arg("--output")
                         .arg(&systemconfig)
                         .arg("--")
-                        .arg("switch")
-                        .arg("--impure")
-                        .args(&rebuildargs)
+                        .args(sysupdateargs(&syspkgs, &config, nscconfig, extraargs))
                         .stderr(Stdio::piped())
                         .stdin(Stdio::piped())
                         .spawn()?;
@@ -390,7 +621,7 @@ async fn runcmd(
                         .await?;
                     cmd
                 } else {
-                    tokio::process::Command::new("pkexec")
+                    nixcmd("pkexec", nscconfig)
                         .arg(&exe)
                         .arg("flake")
                         .arg("--rebuild")
@@ -401,9 +632,7 @@ async fn runcmd(
                         .arg("--output")
                         .arg(&systemconfig)
                         .arg("--")
-                        .arg("switch")
-                        .arg("--impure")
-                        .args(&rebuildargs)
+                        .args(sysupdateargs(&syspkgs, &config, nscconfig, extraargs))
                         .stderr(Stdio::piped())
                         .spawn()?
                 }
@@ -416,54 +645,369 @@ async fn runcmd(
     let reader = tokio::io::BufReader::new(stderr);
 
     let mut lines = reader.lines();
+    let mut hint = None;
     while let Ok(Some(line)) = lines.next_line().await {
         REBUILD_BROKER.send(RebuildMsg::UpdateText(line.to_string()));
         trace!("CAUGHT REBUILD LINE: {}", line);
+        hint = hint.or(purityhint(&line, nscconfig));
     }
     if cmd.wait().await?.success() {
         Ok(true)
     } else {
+        if let Some(hint) = hint {
+            REBUILD_BROKER.send(RebuildMsg::UpdateText(hint));
+        }
         Ok(false)
     }
 }
 
-async fn updateenv() -> Result<bool> {
-    let mut cmd = tokio::process::Command::new("nix-env")
-        .arg("-u")
+/// Arguments passed through `nsc-helper` to `nixos-rebuild` when updating the system.
+pub fn sysupdateargs(
+    syspkgs: &SystemPkgs,
+    config: &NixDataConfig,
+    nscconfig: &NscConfig,
+    extraargs: &[String],
+) -> Vec<String> {
+    let mut args = vec![String::from("switch")];
+    if syspkgs == &SystemPkgs::Flake {
+        args.extend(impureargs(nscconfig));
+    }
+    args.extend(rebuildargs(config));
+    args.extend(nixargs(nscconfig));
+    args.extend(extraargs.iter().cloned());
+    args
+}
+
+/// Program and arguments used to upgrade user packages.
+/// `targets` limits `nix profile` to the given elements, otherwise everything is upgraded.
+pub fn upgradecmd(
+    userpkgs: &UserPkgs,
+    nscconfig: &NscConfig,
+    extraargs: &[String],
+    targets: &[String],
+) -> (&'static str, Vec<String>) {
+    let (program, mut args) = match userpkgs {
+        UserPkgs::Env => {
+            let mut args = vec![String::from("-u")];
+            args.extend(targets.iter().cloned());
+            ("nix-env", args)
+        }
+        UserPkgs::Profile => {
+            let mut args = vec![String::from("profile"), String::from("upgrade")];
+            if targets.is_empty() {
+                args.extend(profile::allelementargs());
+            } else {
+                args.extend(profile::elementargs(targets));
+            }
+            ("nix", args)
+        }
+    };
+    if userpkgs == &UserPkgs::Profile {
+        // Allow updating potential unfree packages
+        args.extend(nixpkgsconfig::impureargs(nscconfig));
+    }
+    args.extend(nixargs(nscconfig));
+    args.extend(profile::profileargs());
+    args.extend(extraargs.iter().cloned());
+    (program, args)
+}
+
+/// Dry run of the system rebuild against the newest inputs: a recreated lock file for flakes,
+/// and the release channel for channels, as updating them needs root.
+async fn previewsystem(
+    syspkgs: &SystemPkgs,
+    config: &NixDataConfig,
+    nscconfig: &NscConfig,
+    extraargs: &[String],
+) -> Result<Option<ClosureSize>> {
+    let mut args = vec![String::from("dry-build")];
+    match syspkgs {
+        SystemPkgs::None => return Ok(None),
+        SystemPkgs::Flake => {
+            args.extend(impureargs(nscconfig));
+            args.push(String::from("--recreate-lock-file"));
+            args.push(String::from("--no-write-lock-file"));
+        }
+        SystemPkgs::Legacy => {
+            if let Some(release) = util::nixosrelease() {
+                args.push(String::from("-I"));
+                args.push(format!("nixpkgs=channel:{}", release));
+            }
+        }
+    }
+    args.extend(rebuildargs(config));
+    args.extend(nixargs(nscconfig));
+    args.extend(extraargs.iter().cloned());
+    let output = nixcmd("nixos-rebuild", nscconfig).args(&args).kill_on_drop(true).output().await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "nixos-rebuild dry-build failed: {}",
+            String::from_utf8_lossy(&output.stderr).lines().last().unwrap_or_default()
+        ));
+    }
+    Ok(Some(narinfo::parsedryrun(&String::from_utf8_lossy(&output.stderr))))
+}
+
+/// Dry run of upgrading the user packages. `nix profile upgrade` has none, so the newest
+/// versions of the installed attributes are built with `--dry-run` instead.
+async fn previewuser(
+    userpkgs: &UserPkgs,
+    nscconfig: &NscConfig,
+    extraargs: &[String],
+    skipped: &[String],
+) -> Result<ClosureSize> {
+    let mut cmd = match userpkgs {
+        UserPkgs::Env => {
+            let (_, current) = profile::generations()?;
+            let targets = if skipped.is_empty() {
+                vec![]
+            } else {
+                profile::generationpkgs(current, &UserPkgs::Env)
+                    .await?
+                    .into_keys()
+                    .filter(|x| !skipped.contains(x))
+                    .collect()
+            };
+            let (program, args) = upgradecmd(&UserPkgs::Env, nscconfig, extraargs, &targets);
+            let mut cmd = nixcmd(program, nscconfig);
+            cmd.args(&args).arg("--dry-run");
+            cmd
+        }
+        UserPkgs::Profile => {
+            let (_, current) = profile::generations()?;
+            let targets = profile::generationpkgs(current, &UserPkgs::Profile)
+                .await?
+                .into_keys()
+                .filter(|x| !skipped.contains(x))
+                .map(|x| format!("nixpkgs#{}", x))
+                .collect::<Vec<_>>();
+            if targets.is_empty() {
+                return Ok(ClosureSize::default());
+            }
+            let mut cmd = nixcmd("nix", nscconfig);
+            cmd.arg("build")
+                .arg("--dry-run")
+                .arg("--no-link")
+                .args(&targets)
+                .args(nixpkgsconfig::impureargs(nscconfig))
+                .args(nixargs(nscconfig))
+                .args(extraargs);
+            cmd
+        }
+    };
+    let output = cmd
+        .envs(nixpkgsconfig::envvars(nscconfig, None))
+        .kill_on_drop(true)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(anyhow!(gettextf(
+            "Dry run of the user update failed: {}",
+            &[String::from_utf8_lossy(&output.stderr).lines().last().unwrap_or_default()]
+        )));
+    }
+    Ok(narinfo::parsedryrun(&String::from_utf8_lossy(&output.stderr)))
+}
+
+/// Saves the statistics of a user update that just finished, passing its success through.
+fn recorduser(started: Instant, substitutions: &Substitutions, success: bool) -> bool {
+    if let Err(e) = state::recordoperation(started, substitutions, success) {
+        warn!("Failed to save operation statistics: {}", e);
+    }
+    success
+}
+
+/// Follows which packages nix is fetching or building, so their rows can show progress.
+#[derive(Default)]
+struct RowProgress {
+    /// Running activities by id, for `nix profile`
+    active: HashMap<u64, String>,
+    /// Package nix-env is upgrading, it only reports one at a time
+    current: Option<String>,
+}
+
+impl RowProgress {
+    fn event(&mut self, event: &NixEvent, sender: &ComponentSender<UpdateAsyncHandler>) {
+        match event {
+            NixEvent::Start { id, text, .. } => {
+                if let Some(pname) = nixlog::pkgname(text) {
+                    sender.output(UpdatePageMsg::SetItemProgress(pname.to_string(), ItemProgress::Working));
+                    self.active.insert(*id, pname);
+                }
+            }
+            NixEvent::Stop { id } => {
+                if let Some(pname) = self.active.remove(id) {
+                    if !self.active.values().any(|x| *x == pname) {
+                        sender.output(UpdatePageMsg::SetItemProgress(pname, ItemProgress::Done));
+                    }
+                }
+            }
+            NixEvent::Msg { text, .. } if text.starts_with("upgrading ") => {
+                if let Some(pname) = self.current.take() {
+                    sender.output(UpdatePageMsg::SetItemProgress(pname, ItemProgress::Done));
+                }
+                if let Some(pname) = nixlog::pkgname(text) {
+                    sender.output(UpdatePageMsg::SetItemProgress(pname.to_string(), ItemProgress::Working));
+                    self.current = Some(pname);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+async fn updateenv(
+    nscconfig: &NscConfig,
+    extraargs: &[String],
+    targets: &[String],
+    skip: &[String],
+    sender: &ComponentSender<UpdateAsyncHandler>,
+) -> Result<bool> {
+    let started = Instant::now();
+    // Like nix profile, skipped packages are left out by naming every other package
+    let mut targets = targets.to_vec();
+    if targets.is_empty() && !skip.is_empty() {
+        let (_, current) = profile::generations()?;
+        targets = profile::generationpkgs(current, &UserPkgs::Env)
+            .await?
+            .into_keys()
+            .filter(|x| !skip.contains(x))
+            .collect();
+        if targets.is_empty() {
+            info!("All user packages skipped");
+            return Ok(true);
+        }
+    }
+    let (program, args) = upgradecmd(&UserPkgs::Env, nscconfig, extraargs, &targets);
+    let mut cmd = nixcmd(program, nscconfig)
+        .args(&args)
+        .envs(nixpkgsconfig::envvars(nscconfig, None))
         .stderr(Stdio::piped())
+        .process_group(0)
         .spawn()?;
+    trackuser(&cmd);
 
     let stderr = cmd.stderr.take().unwrap();
     let reader = tokio::io::BufReader::new(stderr);
 
     let mut lines = reader.lines();
+    let mut progress = RowProgress::default();
+    let mut substitutions = Substitutions::default();
     while let Ok(Some(line)) = lines.next_line().await {
         REBUILD_BROKER.send(RebuildMsg::UpdateText(line.to_string()));
         trace!("CAUGHT NIXENV LINE: {}", line);
+        if let Some(event) = nixlog::parse(&line) {
+            progress.event(&event, sender);
+            substitutions.event(&event);
+        }
     }
-    if cmd.wait().await?.success() {
-        Ok(true)
-    } else {
-        Ok(false)
+    Ok(recorduser(started, &substitutions, waituser(&mut cmd).await?.success()))
+}
+
+async fn updateremote(
+    host: &str,
+    targets: &[String],
+    nscconfig: &NscConfig,
+    extraargs: &[String],
+    sender: &ComponentSender<UpdateAsyncHandler>,
+) -> Result<bool> {
+    let started = Instant::now();
+    let mut args = remote::upgradeargs(host, targets, nscconfig).await?;
+    args.extend(nixpkgsconfig::impureargs(nscconfig));
+    args.extend(nixargs(nscconfig));
+    args.extend(extraargs.iter().cloned());
+    args.extend(nixlog::logargs());
+    let mut cmd = remote::remotecmd(host, "nix", &args, &nixpkgsconfig::envvars(nscconfig, None), nscconfig)
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()?;
+    trackuser(&cmd);
+
+    let stderr = cmd.stderr.take().unwrap();
+    let reader = tokio::io::BufReader::new(stderr);
+
+    let mut lines = reader.lines();
+    let mut progress = RowProgress::default();
+    let mut substitutions = Substitutions::default();
+    while let Ok(Some(line)) = lines.next_line().await {
+        trace!("CAUGHT REMOTE LINE: {}", line);
+        let Some(event) = nixlog::parse(&line) else {
+            continue;
+        };
+        progress.event(&event, sender);
+        substitutions.event(&event);
+        if let Some(text) = event.text() {
+            REBUILD_BROKER.send(RebuildMsg::UpdateText(text.to_string()));
+        }
     }
+    Ok(recorduser(started, &substitutions, waituser(&mut cmd).await?.success()))
 }
 
-async fn updateprofile(rmpkgs: Option<Vec<String>>) -> Result<bool> {
+async fn updateprofile(
+    rmpkgs: Option<PkgChanges>,
+    only: &[String],
+    nscconfig: &NscConfig,
+    extraargs: &[String],
+    sender: &ComponentSender<UpdateAsyncHandler>,
+) -> Result<bool> {
+    let started = Instant::now();
+    // Skipped packages are left out by upgrading every other element explicitly
+    let mut targets = only.to_vec();
+    if let Some(rmpkgs) = &rmpkgs {
+        if !rmpkgs.skip.is_empty() && targets.is_empty() {
+            let (_, current) = profile::generations()?;
+            targets = profile::generationpkgs(current, &UserPkgs::Profile)
+                .await?
+                .into_keys()
+                .filter(|x| !rmpkgs.skip.contains(x) && !rmpkgs.remove.contains(x))
+                .collect::<Vec<String>>();
+        }
+    }
+    let skipall = rmpkgs.as_ref().map(|x| !x.skip.is_empty()).unwrap_or(false) && targets.is_empty();
     if let Some(rmpkgs) = rmpkgs {
-        if !rmpkgs.is_empty() {
-            let mut cmd = tokio::process::Command::new("nix")
+        if !rmpkgs.remove.is_empty() {
+            let mut cmd = nixcmd("nix", nscconfig)
                 .arg("profile")
                 .arg("remove")
+                .args(profile::elementargs(&rmpkgs.remove))
+                // Allow updating potential unfree packages
+                .args(impureargs(nscconfig))
+                .args(profile::profileargs())
+                .stderr(Stdio::piped())
+                .process_group(0)
+                .spawn()?;
+            trackuser(&cmd);
+
+            let stderr = cmd.stderr.take().unwrap();
+            let reader = tokio::io::BufReader::new(stderr);
+
+            let mut lines = reader.lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                REBUILD_BROKER.send(RebuildMsg::UpdateText(line.to_string()));
+                trace!("CAUGHT NIX PROFILE LINE: {}", line);
+            }
+            waituser(&mut cmd).await?;
+        }
+        if !rmpkgs.install.is_empty() {
+            let mut cmd = nixcmd("nix", nscconfig)
+                .arg("profile")
+                .arg("install")
                 .args(
                     &rmpkgs
+                        .install
                         .iter()
-                        .map(|x| format!("legacyPackages.x86_64-linux.{}", x))
+                        .map(|x| format!("nixpkgs#{}", x))
                         .collect::<Vec<String>>(),
                 )
-                // Allow updating potential unfree packages
-                .arg("--impure")
+                .args(nixpkgsconfig::impureargs(nscconfig))
+                .args(nixargs(nscconfig))
+                .args(profile::profileargs())
+                .args(extraargs)
+                .envs(nixpkgsconfig::envvars(nscconfig, None))
                 .stderr(Stdio::piped())
+                .process_group(0)
                 .spawn()?;
+            trackuser(&cmd);
 
             let stderr = cmd.stderr.take().unwrap();
             let reader = tokio::io::BufReader::new(stderr);
@@ -473,30 +1017,52 @@ async fn updateprofile(rmpkgs: Option<Vec<String>>) -> Result<bool> {
                 REBUILD_BROKER.send(RebuildMsg::UpdateText(line.to_string()));
                 trace!("CAUGHT NIX PROFILE LINE: {}", line);
             }
-            cmd.wait().await?;
+            if !waituser(&mut cmd).await?.success() {
+                warn!("Failed to install replacement packages: {:?}", rmpkgs.install);
+            }
         }
     }
 
-    let mut cmd = tokio::process::Command::new("nix")
-        .arg("profile")
-        .arg("upgrade")
-        .arg(".*")
-        // Allow updating potential unfree packages
-        .arg("--impure")
+    if skipall {
+        info!("All user packages skipped");
+        return Ok(true);
+    }
+
+    let (program, args) = upgradecmd(&UserPkgs::Profile, nscconfig, extraargs, &targets);
+    let mut cmd = nixcmd(program, nscconfig)
+        .args(&args)
+        .args(nixlog::logargs())
+        .envs(nixpkgsconfig::envvars(nscconfig, None))
         .stderr(Stdio::piped())
+        .process_group(0)
         .spawn()?;
+    trackuser(&cmd);
 
     let stderr = cmd.stderr.take().unwrap();
     let reader = tokio::io::BufReader::new(stderr);
 
     let mut lines = reader.lines();
+    let mut hint = None;
+    let mut progress = RowProgress::default();
+    let mut substitutions = Substitutions::default();
     while let Ok(Some(line)) = lines.next_line().await {
-        REBUILD_BROKER.send(RebuildMsg::UpdateText(line.to_string()));
         trace!("CAUGHT NIX PROFILE LINE: {}", line);
+        let Some(event) = nixlog::parse(&line) else {
+            continue;
+        };
+        progress.event(&event, sender);
+        substitutions.event(&event);
+        if let Some(text) = event.text() {
+            REBUILD_BROKER.send(RebuildMsg::UpdateText(text.to_string()));
+            hint = hint.or(purityhint(text, nscconfig));
+        }
     }
-    if cmd.wait().await?.success() {
+    if recorduser(started, &substitutions, waituser(&mut cmd).await?.success()) {
         Ok(true)
     } else {
+        if let Some(hint) = hint {
+            REBUILD_BROKER.send(RebuildMsg::UpdateText(hint));
+        }
         Ok(false)
     }
 }
@@ -1,9 +1,16 @@
 use crate::{
     config,
+    i18n::{gettext, gettextf},
     parse::{
-        config::{editconfig, getconfig},
-        packages::{AppData, LicenseEnum, PkgMaintainer, Platform},
-        util,
+        categories, channel, crash, demo,
+        config::{editconfig, editnscconfig, getconfig, getnscconfig, getpolicy, syspkgtype, NscConfig, Policy, PolicyBlock},
+        packages::{self, AppData, LicenseEnum, PkgMaintainer, PkgUri, Platform, SearchMode, UnavailableReason},
+        expression, http, narinfo, nixpkgsconfig, nixversion,
+        popularity::{self, PopularityData},
+        power,
+        report::{self, ReportItem},
+        profile, programs, remote, screenshot, state, util,
+        workqueue::WorkLane,
     },
     ui::{
         installedpage::InstalledItem, pkgpage::PkgPageInit, rebuild::RebuildMsg,
@@ -15,6 +22,7 @@ use crate::{
 use adw::prelude::*;
 use log::*;
 use nix_data::config::configfile::NixDataConfig;
+use rand::{prelude::SliceRandom, rngs::StdRng, seq::IndexedRandom, thread_rng, SeedableRng};
 use relm4::{
     self,
     actions::{RelmAction, RelmActionGroup},
@@ -22,33 +30,44 @@ use relm4::{
     Component, ComponentController, ComponentParts, ComponentSender, Controller, MessageBroker,
     RelmWidgetExt, WorkerController,
 };
+use relm4_components::save_dialog::{SaveDialog, SaveDialogMsg, SaveDialogResponse, SaveDialogSettings};
 use spdx::Expression;
-use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+use sqlx::{sqlite::SqliteConnectOptions, QueryBuilder, Sqlite, SqlitePool};
 use std::{
     collections::{HashMap, HashSet},
     convert::identity,
     fs,
-    path::Path,
+    path::{Path, PathBuf},
+    str::FromStr,
 };
 
 use super::{
     about::{AboutPageModel, AboutPageMsg},
+    dbus::{self, DbusCall},
+    allpkgspage::{self, AllPkgsPageModel, AllPkgsPageMsg},
     categories::{PkgCategory, PkgCategoryMsg, PkgGroup},
-    categorypage::{CategoryPageModel, CategoryPageMsg},
+    categorypage::{self, CategoryPageModel, CategoryPageMsg},
+    categorystrip::{CategoryStrip, CategoryStripInit},
     categorytile::CategoryTile,
+    cleanup::{CleanupModel, CleanupMsg},
     installedpage::{InstalledPageModel, InstalledPageMsg},
-    pkgpage::{self, InstallType, PkgInitModel, PkgModel, PkgMsg, WorkPkg},
+    pkgpage::{self, InstallType, PkgAction, PkgInitModel, PkgModel, PkgMsg, PkgVersions, WorkPkg},
     pkgtile::{PkgTile, PkgTileMsg},
     preferencespage::{PreferencesPageModel, PreferencesPageMsg},
     rebuild::RebuildModel,
-    searchpage::{SearchItem, SearchPageModel, SearchPageMsg},
-    unavailabledialog::UnavailableItemModel,
+    searchpage::{LicenseFilter, SearchFilters, SearchItem, SearchPageModel, SearchPageMsg},
+    unavailabledialog::{UnavailableChoice, UnavailableItemModel},
     updatepage::{UpdateItem, UpdatePageInit, UpdatePageModel, UpdatePageMsg, UpdateType},
     welcome::WelcomeModel,
     windowloading::{LoadErrorModel, LoadErrorMsg, WindowAsyncHandler, WindowAsyncHandlerMsg},
+    workqueue::WorkQueue,
 };
 
 pub static REBUILD_BROKER: MessageBroker<RebuildMsg> = MessageBroker::new();
+/// Progress of the user package lane, which runs at the same time as system rebuilds
+pub static USER_REBUILD_BROKER: MessageBroker<RebuildMsg> = MessageBroker::new();
+/// Lets the application pass opened files to the window
+pub static WINDOW_BROKER: MessageBroker<AppMsg> = MessageBroker::new();
 
 #[derive(PartialEq)]
 enum Page {
@@ -60,30 +79,33 @@ enum Page {
 enum MainPage {
     FrontPage,
     CategoryPage,
+    AllPkgsPage,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub enum SystemPkgs {
-    Legacy,
-    Flake,
-    None,
-}
+pub use nsc_core::{config::SystemPkgs, profile::UserPkgs};
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub enum UserPkgs {
-    Env,
-    Profile,
-}
+/// How long the pointer has to stay on a tile before its package is prefetched
+const PREFETCHDELAY: std::time::Duration = std::time::Duration::from_millis(150);
 
 #[tracker::track]
 pub struct AppModel {
     mainwindow: adw::ApplicationWindow,
     config: NixDataConfig,
+    nscconfig: NscConfig,
+    /// Restrictions set by the administrator
+    policy: Policy,
     #[tracker::no_eq]
     windowloading: WorkerController<WindowAsyncHandler>,
     #[tracker::no_eq]
     loaderrordialog: Controller<LoadErrorModel>,
     busy: bool,
+    /// Metadata downloads finished and started while loading
+    loadprogress: (usize, usize),
+    /// Channel revision when the package data was loaded, and a newer one once released
+    channelrev: Option<String>,
+    newrev: Option<String>,
+    /// Packages can't be changed, in demo mode or without nix
+    readonly: bool,
     page: Page,
     mainpage: MainPage,
     // #[tracker::no_eq]
@@ -100,13 +122,25 @@ pub struct AppModel {
     appdata: HashMap<String, AppData>,
     installeduserpkgs: HashMap<String, String>,
     installedsystempkgs: HashSet<String>,
+    /// Installed packages with an update in the update page
+    updatablepkgs: HashSet<String>,
     syspkgtype: SystemPkgs,
     userpkgtype: UserPkgs,
+    /// SSH host whose user profile is managed, only `nix profile` is supported there
+    host: Option<String>,
     categoryrec: HashMap<PkgCategory, Vec<String>>,
     categoryall: HashMap<PkgCategory, Vec<String>>,
     #[tracker::no_eq]
     recommendedapps: FactoryVecDeque<PkgTile>,
     #[tracker::no_eq]
+    trendingapps: FactoryVecDeque<PkgTile>,
+    /// Every app that qualifies for the recommended section
+    recpool: Vec<String>,
+    #[tracker::no_eq]
+    categorystrips: FactoryVecDeque<CategoryStrip>,
+    #[tracker::no_eq]
+    popularity: PopularityData,
+    #[tracker::no_eq]
     categories: FactoryVecDeque<PkgGroup>,
     #[tracker::no_eq]
     pkgpage: Controller<PkgModel>,
@@ -114,8 +148,13 @@ pub struct AppModel {
     searchpage: Controller<SearchPageModel>,
     #[tracker::no_eq]
     categorypage: Controller<CategoryPageModel>,
+    #[tracker::no_eq]
+    allpkgspage: Controller<AllPkgsPageModel>,
     searching: bool,
     searchquery: String,
+    searchfilters: SearchFilters,
+    /// Text put into the search entry by the app rather than typed
+    searchfill: String,
     vschild: String,
     showvsbar: bool,
     #[tracker::no_eq]
@@ -127,35 +166,77 @@ pub struct AppModel {
     #[tracker::no_eq]
     updatepage: Controller<UpdatePageModel>,
     viewstack: adw::ViewStack,
-    installedpagebusy: Vec<(String, InstallType)>,
+    /// Package operations requested from any page, run by the package page one at a time per lane
+    #[tracker::no_eq]
+    workqueue: WorkQueue,
     #[tracker::no_eq]
     rebuild: Controller<RebuildModel>,
     #[tracker::no_eq]
+    userrebuild: Controller<RebuildModel>,
+    #[tracker::no_eq]
     welcomepage: Controller<WelcomeModel>,
+    #[tracker::no_eq]
+    cleanup: Controller<CleanupModel>,
+    #[tracker::no_eq]
+    reportdialog: Controller<SaveDialog>,
     online: bool,
+    #[tracker::no_eq]
+    prefetched: Option<(String, Option<PkgRow>)>,
+    /// Running prefetch, aborted when the pointer moves on to another tile
+    #[tracker::no_eq]
+    prefetching: Option<tokio::task::JoinHandle<()>>,
+    /// Files opened before the appstream data was loaded
+    pendingfiles: Vec<PathBuf>,
+    /// `appstream://` and `nix://` links opened before the appstream data was loaded
+    pendinguris: Vec<String>,
 }
 
+/// Attribute, pname, description, version, system, platforms and license of a package.
+type AllPkgsRow = (String, String, String, String, String, String, String);
+
+type PkgRow = (String, String, String, String, String, String, String, String, String, String);
+
 #[derive(Debug)]
 pub enum AppMsg {
     UpdateSysconfig(Option<String>),
     UpdateFlake(Option<String>, Option<String>),
     TryLoad,
     UpdateDB,
+    /// Reload the package data from the newest channel release
+    RefreshData,
+    DismissRefresh,
+    /// Load the previous metadata snapshot instead of the latest, or go back to the latest
+    PinMetadata(bool),
     LoadConfig(NixDataConfig),
+    UpdateNscConfig(NscConfig),
     Close,
+    CancelUpdate,
     LoadError(String, String),
+    LoadProgress(usize, usize),
     Initialize(
         String,
         Option<String>,
         Option<String>,
         HashMap<String, AppData>,
         Vec<String>,
+        Vec<String>,
         HashMap<PkgCategory, Vec<String>>,
         HashMap<PkgCategory, Vec<String>>,
+        PopularityData,
+        Vec<String>,
     ),
     OpenPkg(String),
+    /// A package's private note was changed or removed
+    SetNote(String, Option<String>),
+    /// Opens the page of the package owning a `.desktop` or appstream file
+    OpenFile(PathBuf),
+    /// Opens the page an `appstream://` or `nix://` link points to
+    OpenUri(String),
+    PrefetchPkg(String),
     FrontPage,
     FrontFrontPage,
+    ClosePkgPage,
+    CloseCategoryPage,
     // UpdatePkgs(Option<Vec<String>>),
     UpdateInstalledPkgs,
     UpdateInstalledPage,
@@ -165,14 +246,35 @@ pub enum AppMsg {
     SetVsBar(bool),
     SetVsChild(String),
     Search(String),
-    AddInstalledToWorkQueue(WorkPkg),
-    RemoveInstalledBusy(WorkPkg),
+    /// Runs the current search again with other filters
+    SetSearchFilters(SearchFilters),
+    /// Opens the search with `query` filled in
+    SearchFor(String),
+    QueueWork(WorkPkg),
+    WorkFinished(WorkPkg, bool),
+    /// Drops queued operations on a package after its running process was stopped
+    CancelWork(String),
     OpenCategoryPage(PkgCategory),
+    OpenAllPkgs,
+    /// Packages from an index entry of the all packages page, after the given attribute
+    LoadAllPkgs(String, Option<String>),
     LoadCategory(PkgCategory),
+    LoadMoreCategory(PkgCategory, Vec<String>),
     UpdateRecPkgs(Vec<String>),
+    UpdateCategoryStrips(Vec<PkgCategory>),
+    ShuffleRecPkgs,
+    /// Opens a random recommended app, favouring popular ones
+    SurpriseMe,
     SetDarkMode(bool),
     GetUnavailableItems(HashMap<String, String>, HashMap<String, String>, UpdateType),
     CheckNetwork,
+    ReportPopularity,
+    SendCrashReports,
+    ExportReport(PathBuf),
+    OpenChangelog(String, String),
+    SetHost(Option<String>),
+    /// A method call on the D-Bus interface, answered through [`dbus::reply`]
+    Dbus(u32, DbusCall),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -187,11 +289,20 @@ pub struct PkgItem {
 
 #[derive(Debug)]
 pub enum AppAsyncMsg {
-    Search(String, Vec<SearchItem>),
+    /// Query, results, and the command with the packages providing it
+    Search(String, Vec<SearchItem>, String, Vec<SearchItem>),
     UpdateRecPkgs(Vec<PkgTile>),
+    UpdateTrendingPkgs(Vec<PkgTile>),
+    UpdateCategoryStrips(Vec<(PkgCategory, Vec<PkgTile>)>),
     UpdateInstalledPkgs(HashSet<String>, HashMap<String, String>),
-    LoadCategory(PkgCategory, Vec<CategoryTile>, Vec<CategoryTile>),
+    LoadCategory(PkgCategory, Vec<CategoryTile>, Vec<CategoryTile>, Vec<String>),
+    AppendCategory(PkgCategory, Vec<CategoryTile>),
+    AppendAllPkgs(String, Vec<AllPkgsRow>, bool),
     SetNetwork(bool),
+    Prefetched(String, Option<PkgRow>),
+    ChannelRevision(String),
+    /// Revision of the channel the package databases were just refreshed from
+    PkgsRevision(String),
 }
 
 #[relm4::component(pub)]
@@ -206,6 +317,23 @@ impl Component for AppModel {
         adw::ApplicationWindow {
             set_default_width: 1150,
             set_default_height: 800,
+            add_controller = gtk::DropTarget {
+                set_actions: gtk::gdk::DragAction::COPY,
+                set_types: &[gtk::gio::File::static_type()],
+                connect_drop[sender] => move |_, value, _, _| {
+                    match value.get::<gtk::gio::File>().ok().and_then(|x| x.path()) {
+                        Some(path) => {
+                            sender.input(AppMsg::OpenFile(path));
+                            true
+                        }
+                        None => false,
+                    }
+                }
+            },
+            connect_close_request[viewstack = model.viewstack.clone()] => move |window| {
+                saveuistate(window, &viewstack);
+                gtk::glib::Propagation::Proceed
+            },
             #[name(main_stack)]
             if model.busy {
                 gtk::Box {
@@ -217,7 +345,7 @@ impl Component for AppModel {
                         add_css_class: "flat",
                         #[wrap(Some)]
                         set_title_widget = &gtk::Label {
-                            set_label: "Nix Software Center"
+                            set_label: &gettext("Nix Software Center")
                         }
                     },
                     gtk::Box {
@@ -233,7 +361,16 @@ impl Component for AppModel {
                         },
                         gtk::Label {
                             add_css_class: "title-1",
-                            set_label: "Loading...",
+                            set_label: &gettext("Loading..."),
+                        },
+                        gtk::ProgressBar {
+                            #[watch]
+                            set_visible: model.loadprogress.1 > 0,
+                            set_show_text: true,
+                            #[watch]
+                            set_fraction: model.loadprogress.0 as f64 / model.loadprogress.1.max(1) as f64,
+                            #[watch]
+                            set_text: Some(&gettextf("Loaded {} of {} metadata sources", &[&model.loadprogress.0.to_string(), &model.loadprogress.1.to_string()])),
                         },
                     }
                 }
@@ -244,12 +381,23 @@ impl Component for AppModel {
                     set_homogeneous: false,
                     set_transition_type: adw::LeafletTransitionType::Over,
                     set_can_navigate_back: true,
+                    // Keep the model in sync when navigating back with a swipe
+                    connect_visible_child_notify[sender] => move |x| {
+                        if x.visible_child() == x.first_child() {
+                            sender.input(AppMsg::ClosePkgPage);
+                        }
+                    },
                     #[name(front_leaf)]
                     append = &adw::Leaflet {
                         set_can_unfold: false,
                         set_homogeneous: false,
                         set_transition_type: adw::LeafletTransitionType::Over,
                         set_can_navigate_back: true,
+                        connect_visible_child_notify[sender] => move |x| {
+                            if x.visible_child() == x.first_child() {
+                                sender.input(AppMsg::CloseCategoryPage);
+                            }
+                        },
                         #[name(main_box)]
                         append = &gtk::Box {
                             set_orientation: gtk::Orientation::Vertical,
@@ -269,7 +417,7 @@ impl Component for AppModel {
                                 #[name(viewswitchertitle)]
                                 #[wrap(Some)]
                                 set_title_widget = &adw::ViewSwitcherTitle {
-                                    set_title: "Nix Software Center",
+                                    set_title: &gettext("Nix Software Center"),
                                     set_stack: Some(viewstack),
                                     connect_title_visible_notify[sender] => move |x| {
                                         sender.input(AppMsg::SetVsBar(x.is_title_visible()))
@@ -284,6 +432,34 @@ impl Component for AppModel {
                                     }
                                 }
                             },
+                            gtk::Revealer {
+                                #[watch]
+                                set_reveal_child: model.newrev.is_some(),
+                                gtk::Box {
+                                    add_css_class: "toolbar",
+                                    set_spacing: 10,
+                                    gtk::Label {
+                                        set_hexpand: true,
+                                        set_wrap: true,
+                                        set_label: &gettext("New package data available"),
+                                    },
+                                    gtk::Button {
+                                        add_css_class: "suggested-action",
+                                        set_label: &gettext("Refresh"),
+                                        connect_clicked[sender] => move |_| {
+                                            sender.input(AppMsg::RefreshData);
+                                        }
+                                    },
+                                    gtk::Button {
+                                        add_css_class: "flat",
+                                        set_icon_name: "window-close-symbolic",
+                                        set_tooltip_text: Some(&gettext("Dismiss")),
+                                        connect_clicked[sender] => move |_| {
+                                            sender.input(AppMsg::DismissRefresh);
+                                        }
+                                    }
+                                }
+                            },
                             gtk::SearchBar {
                                 #[watch]
                                 set_search_mode: model.searching,
@@ -295,6 +471,9 @@ impl Component for AppModel {
                                         grab_focus: (),
                                         #[track(model.changed(AppModel::searching()) && !model.searching)]
                                         set_text: "",
+                                        #[track(model.changed(AppModel::searchfill()))]
+                                        set_text: &model.searchfill,
+                                        set_tooltip_text: Some(&gettext("Start with = to match an attribute exactly, or put a regular expression between slashes, e.g. /^gcc1[34]$/")),
                                         connect_search_changed[sender] => move |x| {
                                             if x.text().len() > 1 {
                                                 sender.input(AppMsg::Search(x.text().to_string()))
@@ -323,13 +502,52 @@ impl Component for AppModel {
                                             set_valign: gtk::Align::Start,
                                             set_margin_all: 15,
                                             set_spacing: 15,
+                                            gtk::Box {
+                                                set_orientation: gtk::Orientation::Horizontal,
+                                                gtk::Label {
+                                                    set_halign: gtk::Align::Start,
+                                                    set_hexpand: true,
+                                                    add_css_class: "title-4",
+                                                    set_label: &gettext("Categories"),
+                                                },
+                                                gtk::Button {
+                                                    add_css_class: "flat",
+                                                    set_label: &gettext("All Packages"),
+                                                    set_tooltip_text: Some(&gettext("Browse every package in nixpkgs")),
+                                                    connect_clicked[sender] => move |_| {
+                                                        sender.input(AppMsg::OpenAllPkgs);
+                                                    }
+                                                }
+                                            },
+                                            #[local_ref]
+                                            categorybox -> gtk::FlowBox {
+                                                set_halign: gtk::Align::Fill,
+                                                set_hexpand: true,
+                                                set_valign: gtk::Align::Center,
+                                                set_orientation: gtk::Orientation::Horizontal,
+                                                set_selection_mode: gtk::SelectionMode::None,
+                                                set_homogeneous: true,
+                                                set_max_children_per_line: 3,
+                                                set_min_children_per_line: 1,
+                                                set_column_spacing: 14,
+                                                set_row_spacing: 14,
+                                            },
+                                            #[local_ref]
+                                            stripbox -> gtk::Box {
+                                                set_orientation: gtk::Orientation::Vertical,
+                                                set_spacing: 15,
+                                                #[watch]
+                                                set_visible: !model.categorystrips.is_empty(),
+                                            },
                                             gtk::Label {
                                                 set_halign: gtk::Align::Start,
                                                 add_css_class: "title-4",
-                                                set_label: "Categories",
+                                                set_label: &gettext("Trending this week"),
+                                                #[watch]
+                                                set_visible: !model.trendingapps.is_empty(),
                                             },
                                             #[local_ref]
-                                            categorybox -> gtk::FlowBox {
+                                            trendbox -> gtk::FlowBox {
                                                 set_halign: gtk::Align::Fill,
                                                 set_hexpand: true,
                                                 set_valign: gtk::Align::Center,
@@ -340,11 +558,13 @@ impl Component for AppModel {
                                                 set_min_children_per_line: 1,
                                                 set_column_spacing: 14,
                                                 set_row_spacing: 14,
+                                                #[watch]
+                                                set_visible: !model.trendingapps.is_empty(),
                                             },
                                             gtk::Label {
                                                 set_halign: gtk::Align::Start,
                                                 add_css_class: "title-4",
-                                                set_label: "Recommended",
+                                                set_label: &gettext("Recommended"),
                                             },
                                             #[local_ref]
                                             recbox -> gtk::FlowBox {
@@ -358,6 +578,30 @@ impl Component for AppModel {
                                                 set_min_children_per_line: 1,
                                                 set_column_spacing: 14,
                                                 set_row_spacing: 14,
+                                            },
+                                            gtk::Box {
+                                                set_orientation: gtk::Orientation::Horizontal,
+                                                set_halign: gtk::Align::Center,
+                                                set_spacing: 10,
+                                                gtk::Button {
+                                                    add_css_class: "pill",
+                                                    set_label: &gettext("Show More"),
+                                                    #[watch]
+                                                    set_visible: model.recpool.len() > model.recommendedapps.len(),
+                                                    connect_clicked[sender] => move |_| {
+                                                        sender.input(AppMsg::ShuffleRecPkgs);
+                                                    }
+                                                },
+                                                gtk::Button {
+                                                    add_css_class: "pill",
+                                                    set_label: &gettext("Surprise Me"),
+                                                    set_tooltip_text: Some(&gettext("Open a random app")),
+                                                    #[watch]
+                                                    set_visible: !model.recpool.is_empty(),
+                                                    connect_clicked[sender] => move |_| {
+                                                        sender.input(AppMsg::SurpriseMe);
+                                                    }
+                                                }
                                             }
                                         }
                                     }
@@ -373,6 +617,7 @@ impl Component for AppModel {
                             }
                         },
                         append: model.categorypage.widget(),
+                        append: model.allpkgspage.widget(),
                     },
                     append: model.pkgpage.widget()
                 }
@@ -382,8 +627,10 @@ impl Component for AppModel {
 
     menu! {
         mainmenu: {
-            "Preferences" => PreferencesAction,
-            "About" => AboutAction,
+            &gettext("Free Up Space") => CleanupAction,
+            &gettext("Export Report…") => ExportReportAction,
+            &gettext("Preferences") => PreferencesAction,
+            &gettext("About") => AboutAction,
         }
     }
 
@@ -403,6 +650,9 @@ impl Component for AppModel {
             MainPage::CategoryPage => {
                 front_leaf.set_visible_child(model.categorypage.widget());
             }
+            MainPage::AllPkgsPage => {
+                front_leaf.set_visible_child(model.allpkgspage.widget());
+            }
         }
     }
 
@@ -442,51 +692,26 @@ impl Component for AppModel {
             )
         };
 
-        let userpkgtype = if let Ok(h) = std::env::var("HOME") {
-            if Path::new(&format!("{}/.nix-profile/manifest.json", h)).exists()
-                || !Path::new("/nix/var/nix/profiles/per-user/root/channels/nixos").exists()
-                || !Path::new(&format!("{}/.nix-profile/manifest.nix", h)).exists()
-                || if let Ok(m) = fs::read_to_string(&format!("{}/.nix-profile/manifest.nix", h)) {
-                    m == "[ ]"
-                } else {
-                    false
-                }
-            {
-                UserPkgs::Profile
-            } else {
-                UserPkgs::Env
-            }
+        let demo = demo::enabled();
+        // Without nix the catalog can only be browsed
+        let readonly = demo || nixversion::detect().is_none();
+        let (userpkgtype, syspkgtype) = if demo {
+            (UserPkgs::Profile, SystemPkgs::None)
         } else {
-            UserPkgs::Env
+            (profile::userpkgtype(), syspkgtype(&config))
         };
         let nixos = Path::new("/etc/NIXOS").exists();
-        let syspkgtype = if config.systemconfig.is_none() || !nixos {
-            SystemPkgs::None
-        } else {
-            match fs::read_to_string("/run/current-system/nixos-version") {
-                Ok(s) => {
-                    if !Path::new("/nix/var/nix/profiles/per-user/root/channels/nixos").exists()
-                        || config.flake.is_some()
-                    {
-                        SystemPkgs::Flake
-                    } else if let Some(last) = s.split('.').last() {
-                        if last.len() == 7 || last == "dirty" || last == "git" {
-                            SystemPkgs::Flake
-                        } else {
-                            SystemPkgs::Legacy
-                        }
-                    } else {
-                        SystemPkgs::Legacy
-                    }
-                }
-                Err(_) => SystemPkgs::None,
-            }
-        };
 
         debug!("userpkgtype: {:?}", userpkgtype);
         debug!("syspkgtype: {:?}", syspkgtype);
 
         let online = util::checkonline();
+        let nscconfig = getnscconfig();
+        let policy = getpolicy();
+        if policy.restricted() {
+            info!("Restricted by policy: {:?}", policy);
+        }
+        http::setmaxdownloads(nscconfig.maxdownloads);
 
         let windowloading = WindowAsyncHandler::builder()
             .detach_worker(())
@@ -499,6 +724,9 @@ impl Component for AppModel {
                 userpkgs: userpkgtype.clone(),
                 syspkgs: syspkgtype.clone(),
                 config: config.clone(),
+                nscconfig: nscconfig.clone(),
+                policy: policy.clone(),
+                readonly,
                 online,
             })
             .forward(sender.input_sender(), identity);
@@ -508,8 +736,11 @@ impl Component for AppModel {
         let categorypage = CategoryPageModel::builder()
             .launch(())
             .forward(sender.input_sender(), identity);
+        let allpkgspage = AllPkgsPageModel::builder()
+            .launch(())
+            .forward(sender.input_sender(), identity);
         let installedpage = InstalledPageModel::builder()
-            .launch((syspkgtype.clone(), userpkgtype.clone()))
+            .launch((syspkgtype.clone(), userpkgtype.clone(), policy.noremove || readonly))
             .forward(sender.input_sender(), identity);
         let updatepage = UpdatePageModel::builder()
             .launch(UpdatePageInit {
@@ -517,12 +748,18 @@ impl Component for AppModel {
                 systype: syspkgtype.clone(),
                 usertype: userpkgtype.clone(),
                 config: config.clone(),
+                nscconfig: nscconfig.clone(),
                 online,
+                readonly,
+                noremove: policy.noremove,
             })
             .forward(sender.input_sender(), identity);
         let rebuild = RebuildModel::builder()
             .launch_with_broker(root.clone().upcast(), &REBUILD_BROKER)
             .forward(sender.input_sender(), identity);
+        let userrebuild = RebuildModel::builder()
+            .launch_with_broker(root.clone().upcast(), &USER_REBUILD_BROKER)
+            .forward(sender.input_sender(), identity);
         let viewstack = adw::ViewStack::new();
         let welcomepage = WelcomeModel::builder()
             .launch(root.clone().upcast())
@@ -533,13 +770,33 @@ impl Component for AppModel {
         let preferencespage = PreferencesPageModel::builder()
             .launch(root.clone().upcast())
             .forward(sender.input_sender(), identity);
+        let cleanup = CleanupModel::builder()
+            .launch(root.clone().upcast())
+            .forward(sender.input_sender(), identity);
+        let reportdialog = {
+            let sender = sender.clone();
+            SaveDialog::builder()
+                .transient_for_native(&root)
+                .launch(SaveDialogSettings {
+                    create_folders: true,
+                    ..Default::default()
+                })
+                .connect_receiver(move |_, response| {
+                    if let SaveDialogResponse::Accept(path) = response {
+                        sender.input(AppMsg::ExportReport(path));
+                    }
+                })
+        };
 
         let model = AppModel {
             mainwindow: root.clone(),
             config,
+            nscconfig,
+            policy,
             windowloading,
             loaderrordialog,
             busy: true,
+            loadprogress: (0, 0),
             page: Page::FrontPage,
             mainpage: MainPage::FrontPage,
             pkgdb: String::new(),
@@ -548,35 +805,82 @@ impl Component for AppModel {
             appdata: HashMap::new(),
             installeduserpkgs: HashMap::new(),
             installedsystempkgs: HashSet::new(),
+            updatablepkgs: HashSet::new(),
             syspkgtype,
             userpkgtype,
+            host: None,
             categoryrec: HashMap::new(),
             categoryall: HashMap::new(),
             recommendedapps: FactoryVecDeque::builder().launch(gtk::FlowBox::new()).forward(sender.input_sender(), |output| match output {
                 PkgTileMsg::Open(x) => AppMsg::OpenPkg(x),
+                PkgTileMsg::Prefetch(x) => AppMsg::PrefetchPkg(x),
+            }),
+            trendingapps: FactoryVecDeque::builder().launch(gtk::FlowBox::new()).forward(sender.input_sender(), |output| match output {
+                PkgTileMsg::Open(x) => AppMsg::OpenPkg(x),
+                PkgTileMsg::Prefetch(x) => AppMsg::PrefetchPkg(x),
             }),
+            popularity: PopularityData::default(),
+            recpool: vec![],
+            categorystrips: FactoryVecDeque::builder().launch(gtk::Box::default()).forward(sender.input_sender(), identity),
             categories: FactoryVecDeque::builder().launch(gtk::FlowBox::new()).forward(sender.input_sender(), |output| match output {
                 PkgCategoryMsg::Open(x) => AppMsg::OpenCategoryPage(x),
             }),
             pkgpage,
             searchpage,
             categorypage,
+            allpkgspage,
             searching: false,
             searchquery: String::default(),
+            searchfilters: state::getnscstate().ui.searchfilters,
+            searchfill: String::default(),
             vschild: String::default(),
             showvsbar: false,
             installedpage,
             updatepage,
             viewstack,
-            installedpagebusy: vec![],
+            workqueue: WorkQueue::default(),
             rebuild,
+            userrebuild,
             welcomepage,
+            cleanup,
+            reportdialog,
             aboutpage,
             preferencespage,
             online,
+            prefetched: None,
+            prefetching: None,
+            pendingfiles: vec![],
+            pendinguris: vec![],
+            channelrev: state::getnscstate().pkgsrevision,
+            newrev: None,
+            readonly,
             tracker: 0,
         };
 
+        sender.command(|out, shutdown| {
+            shutdown
+                .register(async move {
+                    loop {
+                        // Background refreshes wait while the system is saving power
+                        let defer = if getnscconfig().ignorepowersaving {
+                            None
+                        } else {
+                            tokio::task::spawn_blocking(power::deferreason).await.unwrap_or(None)
+                        };
+                        if let Some(reason) = defer {
+                            debug!("Skipping the channel check, {}", reason);
+                        } else {
+                            match channel::latestrevision().await {
+                                Ok(rev) => out.send(AppAsyncMsg::ChannelRevision(rev)),
+                                Err(e) => debug!("Failed to check for a new channel release: {}", e),
+                            }
+                        }
+                        tokio::time::sleep(channel::POLLINTERVAL).await;
+                    }
+                })
+                .drop_on_shutdown()
+        });
+
         {
             let sender = sender.clone();
             adw::StyleManager::default()
@@ -584,8 +888,10 @@ impl Component for AppModel {
         }
 
         sender.input(AppMsg::SetDarkMode(adw::StyleManager::default().is_dark()));
+        sender.input(AppMsg::SendCrashReports);
+        model.installedpage.emit(InstalledPageMsg::SetHosts(model.nscconfig.remotehosts.clone()));
 
-        if welcome && nixos {
+        if welcome && nixos && !demo {
             model.welcomepage.emit(WelcomeMsg::Show);
         } else {
             model.windowloading.emit(WindowAsyncHandlerMsg::CheckCache(
@@ -595,7 +901,9 @@ impl Component for AppModel {
             ));
         }
         let recbox = model.recommendedapps.widget();
+        let trendbox = model.trendingapps.widget();
         let categorybox = model.categories.widget();
+        let stripbox = model.categorystrips.widget();
         let viewstack = &model.viewstack;
 
         let widgets = view_output!();
@@ -613,13 +921,31 @@ impl Component for AppModel {
             let preferencespage = model.preferencespage.widget().clone();
             let config = model.config.clone();
             RelmAction::new_stateless(move |_| {
-                sender.send(PreferencesPageMsg::Show(config.clone())).unwrap();
+                sender.send(PreferencesPageMsg::Show(config.clone(), getnscconfig())).unwrap();
                 preferencespage.present();
             })
         };
 
+        let cleanup: RelmAction<CleanupAction> = {
+            let sender = model.cleanup.sender().clone();
+            let userpkgtype = model.userpkgtype.clone();
+            RelmAction::new_stateless(move |_| {
+                sender.send(CleanupMsg::Show(userpkgtype.clone())).unwrap();
+            })
+        };
+        cleanup.set_enabled(!model.readonly);
+
+        let exportreport: RelmAction<ExportReportAction> = {
+            let sender = model.reportdialog.sender().clone();
+            RelmAction::new_stateless(move |_| {
+                sender.send(SaveDialogMsg::SaveAs(String::from("installed-software.html"))).unwrap();
+            })
+        };
+
         group.add_action(aboutpage);
         group.add_action(prefernecespage);
+        group.add_action(cleanup);
+        group.add_action(exportreport);
         let actions = group.into_action_group();
         widgets
             .main_window
@@ -631,9 +957,9 @@ impl Component for AppModel {
         let installedvs = widgets.viewstack.page(model.installedpage.widget());
         let updatesvs = widgets.viewstack.page(model.updatepage.widget());
         let searchvs = widgets.viewstack.page(model.searchpage.widget());
-        frontvs.set_title(Some("Explore"));
-        installedvs.set_title(Some("Installed"));
-        updatesvs.set_title(Some("Updates"));
+        frontvs.set_title(Some(&gettext("Explore")));
+        installedvs.set_title(Some(&gettext("Installed")));
+        updatesvs.set_title(Some(&gettext("Updates")));
         frontvs.set_name(Some("explore"));
         installedvs.set_name(Some("installed"));
         searchvs.set_name(Some("search"));
@@ -642,6 +968,23 @@ impl Component for AppModel {
         installedvs.set_icon_name(Some("nsc-installed-symbolic"));
         updatesvs.set_icon_name(Some("nsc-update-symbolic"));
 
+        let ui = state::getnscstate().ui;
+        if let (Some(width), Some(height)) = (ui.width, ui.height) {
+            widgets.main_window.set_default_size(width, height);
+        }
+        if ui.maximized {
+            widgets.main_window.maximize();
+        }
+        // Search results aren't kept, so reopening the search page would show it empty
+        if let Some(page) = ui.page.as_deref().filter(|x| *x != "search") {
+            widgets.viewstack.set_visible_child_name(page);
+        }
+        for (page, position) in ui.scroll {
+            if let Some(scroll) = widgets.viewstack.child_by_name(&page).and_downcast::<gtk::ScrolledWindow>() {
+                restorescroll(&scroll, position as f64);
+            }
+        }
+
         ComponentParts { model, widgets }
     }
 
@@ -667,6 +1010,32 @@ impl Component for AppModel {
                     self.syspkgtype.clone(),
                     self.userpkgtype.clone(),
                 ));
+                sender.command(|out, shutdown| {
+                    shutdown
+                        .register(async move {
+                            match channel::latestrevision().await {
+                                Ok(rev) => out.send(AppAsyncMsg::PkgsRevision(rev)),
+                                Err(e) => debug!("Failed to check the channel revision: {}", e),
+                            }
+                        })
+                        .drop_on_shutdown()
+                });
+            }
+            AppMsg::RefreshData => {
+                // Loading checks the cache against the channel, so it picks up the new release
+                if let Some(rev) = self.newrev.take() {
+                    self.setpkgsrevision(rev);
+                }
+                sender.input(AppMsg::TryLoad);
+            }
+            AppMsg::DismissRefresh => {
+                self.channelrev = self.newrev.take();
+            }
+            AppMsg::PinMetadata(pinned) => {
+                if let Err(e) = state::editnscstate(|x| x.pinnedmetadata = pinned) {
+                    warn!("Failed to save metadata choice: {}", e);
+                }
+                sender.input(AppMsg::TryLoad);
             }
             AppMsg::LoadConfig(config) => {
                 info!("AppMsg::LoadConfig");
@@ -674,39 +1043,9 @@ impl Component for AppModel {
                 if let Err(e) = editconfig(self.config.clone()) {
                     warn!("Error editing config: {}", e);
                 }
-                let nixos = Path::new("/etc/NIXOS").exists();
-                self.syspkgtype = if self.config.systemconfig.is_none() || !nixos {
-                    SystemPkgs::None
-                } else {
-                    match fs::read_to_string("/run/current-system/nixos-version") {
-                        Ok(s) => {
-                            if !Path::new("/nix/var/nix/profiles/per-user/root/channels/nixos")
-                                .exists()
-                                || self.config.flake.is_some()
-                            {
-                                SystemPkgs::Flake
-                            } else if let Some(last) = s.split('.').last() {
-                                if last.len() == 7 || last == "dirty" || last == "git" {
-                                    SystemPkgs::Flake
-                                } else {
-                                    SystemPkgs::Legacy
-                                }
-                            } else {
-                                SystemPkgs::Legacy
-                            }
-                        }
-                        Err(_) => SystemPkgs::None,
-                    }
-                };
-                self.pkgpage.emit(PkgMsg::UpdatePkgTypes(
-                    self.syspkgtype.clone(),
-                    self.userpkgtype.clone(),
-                ));
+                self.syspkgtype = syspkgtype(&self.config);
+                self.emitpkgtypes();
                 self.pkgpage.emit(PkgMsg::UpdateConfig(self.config.clone()));
-                self.updatepage.emit(UpdatePageMsg::UpdatePkgTypes(
-                    self.syspkgtype.clone(),
-                    self.userpkgtype.clone(),
-                ));
                 self.updatepage
                     .emit(UpdatePageMsg::UpdateConfig(self.config.clone()));
                 self.windowloading.emit(WindowAsyncHandlerMsg::CheckCache(
@@ -715,11 +1054,39 @@ impl Component for AppModel {
                     self.config.clone(),
                 ));
             }
+            AppMsg::UpdateNscConfig(nscconfig) => {
+                if nscconfig.maxdownloads != self.nscconfig.maxdownloads {
+                    http::setmaxdownloads(nscconfig.maxdownloads);
+                }
+                if nscconfig.allowunfree != self.nscconfig.allowunfree
+                    || nscconfig.allowinsecure != self.nscconfig.allowinsecure
+                {
+                    if let Err(e) = nixpkgsconfig::writeconfig(&nscconfig) {
+                        warn!("Error writing nixpkgs config: {}", e);
+                    }
+                }
+                self.nscconfig = nscconfig;
+                if let Err(e) = editnscconfig(self.nscconfig.clone()) {
+                    warn!("Error editing config: {}", e);
+                }
+                self.pkgpage.emit(PkgMsg::UpdateNscConfig(self.nscconfig.clone()));
+                self.updatepage
+                    .emit(UpdatePageMsg::UpdateNscConfig(self.nscconfig.clone()));
+                self.installedpage
+                    .emit(InstalledPageMsg::SetHosts(self.nscconfig.remotehosts.clone()));
+            }
             AppMsg::Close => {
                 relm4::main_application().quit();
             }
+            AppMsg::CancelUpdate => {
+                self.updatepage.emit(UpdatePageMsg::Cancel);
+            }
+            AppMsg::LoadProgress(done, total) => {
+                self.loadprogress = (done, total);
+            }
             AppMsg::LoadError(msg, msg2) => {
                 self.busy = false;
+                crash::failure("load", &format!("{}: {}", msg, msg2));
                 self.loaderrordialog.emit(LoadErrorMsg::Show(msg, msg2));
             }
             AppMsg::UpdateSysconfig(systemconfig) => {
@@ -748,18 +1115,7 @@ impl Component for AppModel {
                 self.pkgpage.emit(PkgMsg::UpdateConfig(self.config.clone()));
                 self.updatepage
                     .emit(UpdatePageMsg::UpdateConfig(self.config.clone()));
-                self.pkgpage.emit(PkgMsg::UpdatePkgTypes(
-                    self.syspkgtype.clone(),
-                    self.userpkgtype.clone(),
-                ));
-                self.updatepage.emit(UpdatePageMsg::UpdatePkgTypes(
-                    self.syspkgtype.clone(),
-                    self.userpkgtype.clone(),
-                ));
-                self.installedpage.emit(InstalledPageMsg::UpdatePkgTypes(
-                    self.syspkgtype.clone(),
-                    self.userpkgtype.clone(),
-                ));
+                self.emitpkgtypes();
             }
             AppMsg::UpdateFlake(flake, flakearg) => {
                 self.config = NixDataConfig {
@@ -784,18 +1140,7 @@ impl Component for AppModel {
                 self.pkgpage.emit(PkgMsg::UpdateConfig(self.config.clone()));
                 self.updatepage
                     .emit(UpdatePageMsg::UpdateConfig(self.config.clone()));
-                self.pkgpage.emit(PkgMsg::UpdatePkgTypes(
-                    self.syspkgtype.clone(),
-                    self.userpkgtype.clone(),
-                ));
-                self.updatepage.emit(UpdatePageMsg::UpdatePkgTypes(
-                    self.syspkgtype.clone(),
-                    self.userpkgtype.clone(),
-                ));
-                self.installedpage.emit(InstalledPageMsg::UpdatePkgTypes(
-                    self.syspkgtype.clone(),
-                    self.userpkgtype.clone(),
-                ));
+                self.emitpkgtypes();
             }
             AppMsg::Initialize(
                 pkgdb,
@@ -803,123 +1148,185 @@ impl Component for AppModel {
                 systemdb,
                 appdata,
                 recommendedapps,
+                recpool,
                 categoryrec,
                 categoryall,
+                popularity,
+                trendingapps,
             ) => {
                 info!("AppMsg::Initialize");
                 self.pkgdb = pkgdb;
+                self.prefetched = None;
                 self.nixpkgsdb = nixpkgsdb;
                 self.systemdb = systemdb;
                 self.appdata = appdata;
+                for path in std::mem::take(&mut self.pendingfiles) {
+                    sender.input(AppMsg::OpenFile(path));
+                }
+                for uri in std::mem::take(&mut self.pendinguris) {
+                    sender.input(AppMsg::OpenUri(uri));
+                }
+                let mut categoryrec = categoryrec;
+                let mut categoryall = categoryall;
+                let mut recpool = recpool;
+                let mut recommendedapps = recommendedapps;
+                let mut trendingapps = trendingapps;
+                if self.policy.restricted() {
+                    for pkgs in categoryrec.values_mut().chain(categoryall.values_mut()) {
+                        pkgs.retain(|x| self.policy.allows(x));
+                    }
+                    for pkgs in [&mut recpool, &mut recommendedapps, &mut trendingapps] {
+                        pkgs.retain(|x| self.policy.allows(x));
+                    }
+                }
                 self.categoryrec = categoryrec;
                 self.categoryall = categoryall;
 
                 self.pkgpage.emit(PkgMsg::UpdateConfig(self.config.clone()));
                 self.updatepage
                     .emit(UpdatePageMsg::UpdateConfig(self.config.clone()));
+                self.popularity = popularity;
+                self.recpool = recpool;
                 sender.input(AppMsg::UpdateRecPkgs(recommendedapps));
+                sender.input(AppMsg::UpdateTrendingPkgs(trendingapps));
+                // A few categories a day, the same ones all day
+                let mut stripcats = categories::categories()
+                .iter()
+                .cloned()
+                .filter(|x| self.categoryrec.get(x).map(|x| !x.is_empty()).unwrap_or(false))
+                .collect::<Vec<_>>();
+                stripcats.shuffle(&mut StdRng::seed_from_u64(popularity::now() / (24 * 60 * 60)));
+                stripcats.truncate(3);
+                sender.input(AppMsg::UpdateCategoryStrips(stripcats));
                 let mut cat_guard = self.categories.guard();
                 cat_guard.clear();
-                for c in vec![
-                    PkgCategory::Audio,
-                    PkgCategory::Development,
-                    PkgCategory::Games,
-                    PkgCategory::Graphics,
-                    PkgCategory::Web,
-                    PkgCategory::Video,
-                ] {
-                    cat_guard.push_back(c);
+                for c in categories::categories().iter().cloned() {
+                    let count = self
+                        .categoryrec
+                        .get(&c)
+                        .into_iter()
+                        .chain(self.categoryall.get(&c))
+                        .flatten()
+                        .collect::<HashSet<_>>()
+                        .len();
+                    cat_guard.push_back((c, count));
                 }
                 cat_guard.drop();
                 self.busy = false;
             }
             AppMsg::UpdateRecPkgs(pkgs) => {
                 info!("AppMsg::UpdateRecPkgs");
-                let appdata: HashMap<String, AppData> = self
-                    .appdata
-                    .iter()
-                    .filter_map(|(k, v)| {
-                        if pkgs.contains(k) {
-                            Some((k.to_string(), v.clone()))
-                        } else {
-                            None
-                        }
+                let tiles = self.pkgtiles(pkgs);
+                sender.oneshot_command(async move { AppAsyncMsg::UpdateRecPkgs(tiles.await) });
+            }
+            AppMsg::UpdateCategoryStrips(categories) => {
+                info!("AppMsg::UpdateCategoryStrips");
+                let strips = categories
+                    .into_iter()
+                    .map(|category| {
+                        let pkgs = self
+                            .categoryrec
+                            .get(&category)
+                            .map(|x| x.iter().take(10).cloned().collect::<Vec<_>>())
+                            .unwrap_or_default();
+                        (category, self.pkgtiles(pkgs))
                     })
-                    .collect();
-                let installeduser = self.installeduserpkgs.clone();
-                let installedsystem = self.installedsystempkgs.clone();
-                let poolref = self.pkgdb.clone();
-                let userpkgtype = self.userpkgtype.clone();
+                    .collect::<Vec<_>>();
                 sender.oneshot_command(async move {
-                    let mut pkgtiles = vec![];
-                    if let Ok(pool) = &SqlitePool::connect(&format!("sqlite://{}", poolref)).await {
-                        for pkg in pkgs {
-                            if let Some(data) = appdata.get(&pkg) {
-                                let pname: (String,) =
-                                    sqlx::query_as("SELECT pname FROM pkgs WHERE attribute = $1")
-                                        .bind(&pkg)
-                                        .fetch_one(pool)
-                                        .await
-                                        .unwrap();
-                                pkgtiles.push(PkgTile {
-                                    pkg: pkg.to_string(),
-                                    name: if let Some(name) = &data.name {
-                                        name.get("C").unwrap_or(&pname.0).to_string()
-                                    } else {
-                                        pname.0.to_string()
-                                    },
-                                    pname: pname.0.to_string(),
-                                    icon: data
-                                        .icon
-                                        .as_ref()
-                                        .and_then(|x| x.cached.as_ref())
-                                        .map(|x| x[0].name.clone()),
-                                    summary: data
-                                        .summary
-                                        .as_ref()
-                                        .and_then(|x| x.get("C"))
-                                        .map(|x| x.to_string())
-                                        .unwrap_or_default(),
-                                    installeduser: installeduser.contains_key(&match userpkgtype {
-                                        UserPkgs::Env => pname.0,
-                                        UserPkgs::Profile => pkg.to_string(),
-                                    }),
-                                    installedsystem: installedsystem.contains(&pkg),
-                                })
-                            }
-                        }
+                    let mut out = vec![];
+                    for (category, tiles) in strips {
+                        out.push((category, tiles.await));
                     }
-                    AppAsyncMsg::UpdateRecPkgs(pkgtiles)
+                    AppAsyncMsg::UpdateCategoryStrips(out)
+                });
+            }
+            AppMsg::ShuffleRecPkgs => {
+                let current = self.recommendedapps.iter().map(|x| x.pkg.to_string()).collect::<HashSet<_>>();
+                let mut pool = self.recpool.clone();
+                pool.shuffle(&mut thread_rng());
+                let picks = pool
+                    .into_iter()
+                    .filter(|x| !current.contains(x))
+                    .take(12)
+                    .collect::<Vec<_>>();
+                if !picks.is_empty() {
+                    sender.input(AppMsg::UpdateRecPkgs(picks));
+                }
+            }
+            AppMsg::SurpriseMe => {
+                let candidates = self
+                    .recpool
+                    .iter()
+                    .filter(|x| {
+                        !self.installedsystempkgs.contains(*x) && !self.installeduserpkgs.contains_key(*x)
+                    })
+                    .collect::<Vec<_>>();
+                let pick = candidates.choose_weighted(&mut thread_rng(), |x| {
+                    let screenshots = self
+                        .appdata
+                        .get(*x)
+                        .and_then(|x| x.screenshots.as_ref())
+                        .map(|x| !x.is_empty())
+                        .unwrap_or(false);
+                    1 + self.popularity.weight(x) + if screenshots { 2 } else { 0 }
                 });
+                if let Ok(pkg) = pick {
+                    info!("Surprise: {}", pkg);
+                    sender.input(AppMsg::OpenPkg(pkg.to_string()));
+                }
+            }
+            AppMsg::UpdateTrendingPkgs(pkgs) => {
+                info!("AppMsg::UpdateTrendingPkgs");
+                let tiles = self.pkgtiles(pkgs);
+                sender.oneshot_command(async move { AppAsyncMsg::UpdateTrendingPkgs(tiles.await) });
+            }
+            AppMsg::PrefetchPkg(pkg) => {
+                if !matches!(&self.prefetched, Some((p, _)) if *p == pkg) {
+                    trace!("AppMsg::PrefetchPkg {}", pkg);
+                    self.prefetched = Some((pkg.to_string(), None));
+                    let pkgdb = self.pkgdb.clone();
+                    let scrnurl = self
+                        .appdata
+                        .get(&pkg)
+                        .and_then(|x| packages::screenshots(x, packages::locales()).into_iter().next())
+                        .map(|x| x.url);
+                    if let Some(p) = self.prefetching.take() {
+                        p.abort();
+                    }
+                    let out = sender.command_sender().clone();
+                    self.prefetching = Some(relm4::spawn(async move {
+                        // Only tiles the pointer rests on, not every one it passes over
+                        tokio::time::sleep(PREFETCHDELAY).await;
+                        let row = match SqlitePool::connect(&format!("sqlite://{}", pkgdb)).await {
+                            Ok(pool) => pkgrow(&pool, &pkg).await.ok(),
+                            Err(_) => None,
+                        };
+                        out.emit(AppAsyncMsg::Prefetched(pkg, row));
+                        if let Some(url) = scrnurl {
+                            if let Err(e) = screenshot::fetchscreenshot(&url).await {
+                                trace!("Failed to prefetch screenshot {}: {}", url, e);
+                            }
+                        }
+                    }));
+                }
             }
             AppMsg::OpenPkg(pkg) => {
                 info!("AppMsg::OpenPkg {}", pkg);
                 sender.input(AppMsg::CheckNetwork);
                 if let Ok(pool) = &SqlitePool::connect(&format!("sqlite://{}", self.pkgdb)).await {
-                    let pkgdata: Result<
-                        (
-                            String,
-                            String,
-                            String,
-                            String,
-                            String,
-                            String,
-                            String,
-                            String,
-                            String,
-                        ),
-                        _,
-                    > = sqlx::query_as(
-                        r#"
-SELECT pname, version, system, description, longdescription, homepage, license, platforms, maintainers
-FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute = $1
-                    "#,
-                    )
-                    .bind(&pkg)
-                    .fetch_one(pool)
-                    .await;
+                    let pkgdata = match self.prefetched.take() {
+                        Some((p, Some(row))) if p == pkg => Ok(row),
+                        _ => pkgrow(pool, &pkg).await,
+                    };
+                    let pkgdata = match pkgdata {
+                        Ok(row) => Some(row),
+                        Err(e) => {
+                            debug!("No metadata for {}: {}", pkg, e);
+                            minimalrow(pool, &pkg, &self.installeduserpkgs).await
+                        }
+                    };
 
-                    if let Ok((
+                    if let Some((
                         pname,
                         version,
                         system,
@@ -929,8 +1336,10 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                         licensejson,
                         platformsjson,
                         maintainersjson,
+                        position,
                     )) = pkgdata
                     {
+                        let supported = packages::supportsplatform(&system, &platformsjson, &util::currentsystem());
                         let mut name = pname.to_string();
                         let mut summary = if description.is_empty() {
                             None
@@ -951,17 +1360,17 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
 
                         if let Some(data) = self.appdata.get(&pkg) {
                             if let Some(n) = &data.name {
-                                if let Some(n) = n.get("C") {
+                                if let Some(n) = packages::translated(n) {
                                     name = n.to_string();
                                 }
                             }
                             if let Some(s) = &data.summary {
-                                if let Some(s) = s.get("C") {
+                                if let Some(s) = packages::translated(s) {
                                     summary = Some(s.to_string());
                                 }
                             }
                             if let Some(d) = &data.description {
-                                if let Some(d) = d.get("C") {
+                                if let Some(d) = packages::translated(d) {
                                     description = Some(d.to_string());
                                 }
                             }
@@ -976,26 +1385,7 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                     }
                                 }
                             }
-                            if let Some(s) = &data.screenshots {
-                                for s in s {
-                                    if let Some(u) = &s.sourceimage {
-                                        if !screenshots.contains(&u.url) {
-                                            if s.default == Some(true) {
-                                                screenshots.insert(0, u.url.clone());
-                                            } else {
-                                                screenshots.push(u.url.clone());
-                                            }
-                                        } else if s.default == Some(true) {
-                                            if let Some(index) =
-                                                screenshots.iter().position(|x| *x == u.url)
-                                            {
-                                                screenshots.remove(index);
-                                                screenshots.insert(0, u.url.clone());
-                                            }
-                                        }
-                                    }
-                                }
-                            }
+                            screenshots = packages::screenshots(data, packages::locales());
                             if let Some(l) = &data.launchable {
                                 if let Some(d) = l.desktopid.get(0) {
                                     launchable = Some(d.to_string());
@@ -1149,7 +1539,9 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                             }
                         }
                         platforms.sort();
-                        platforms.insert(0, system);
+                        if !system.is_empty() {
+                            platforms.insert(0, system);
+                        }
 
                         if let Ok(m) = serde_json::from_str::<Vec<PkgMaintainer>>(&maintainersjson)
                         {
@@ -1158,6 +1550,46 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                             }
                         }
 
+                        let nixos = Path::new("/etc/NIXOS").exists();
+                        let pkgdbversion = if version.is_empty() { None } else { Some(version.to_string()) };
+                        let unstable = match &self.nixpkgsdb {
+                            Some(db) => dbversion(db, &pkg).await,
+                            None if !nixos => pkgdbversion.clone(),
+                            None => None,
+                        };
+                        let stable = if nixos {
+                            util::nixoschannel(self.config.flake.as_deref())
+                                .or_else(|| {
+                                    // Not necessarily what the system follows, so not named like a channel
+                                    util::nixosrelease().map(|x| gettextf("NixOS {}", &[x.trim_start_matches("nixos-")]))
+                                })
+                                .zip(pkgdbversion)
+                        } else {
+                            None
+                        };
+                        let system = match &self.systemdb {
+                            Some(db) if self.installedsystempkgs.contains(&pkg) => dbversion(db, &pkg).await,
+                            _ => None,
+                        };
+                        let versions = PkgVersions {
+                            unstable,
+                            stable,
+                            user: self
+                                .installeduserpkgs
+                                .get(match self.userpkgtype {
+                                    UserPkgs::Env => &pname,
+                                    UserPkgs::Profile => &pkg,
+                                })
+                                .cloned(),
+                            system,
+                        };
+
+                        let mature = self.appdata.get(&pkg).map(packages::mature).unwrap_or(false);
+                        let bugtracker = self.appdata.get(&pkg).and_then(|x| x.url.as_ref()).and_then(|x| x.bugtracker.clone());
+                        let developer = self
+                            .appdata
+                            .get(&pkg)
+                            .and_then(|x| packages::developer(x, Some(homepage.as_str()).filter(|x| !x.is_empty())));
                         let out = PkgInitModel {
                             name,
                             version: if version.is_empty() {
@@ -1171,17 +1603,23 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                             icon,
                             pkg,
                             screenshots,
+                            mature,
                             homepage: if homepage.is_empty() {
                                 None
                             } else {
                                 Some(homepage)
                             },
+                            bugtracker,
                             platforms,
+                            supported,
                             licenses,
                             maintainers,
                             installeduserpkgs: self.installeduserpkgs.keys().cloned().collect(),
                             installedsystempkgs: self.installedsystempkgs.clone(),
                             launchable,
+                            developer,
+                            versions,
+                            position: expression::Position::parse(&position),
                         };
                         self.page = Page::PkgPage;
                         if self.viewstack.visible_child_name()
@@ -1203,21 +1641,183 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                 self.page = Page::FrontPage;
                 self.mainpage = MainPage::FrontPage;
             }
-            AppMsg::UpdateInstalledPkgs => {
-                info!("AppMsg::UpdateInstalledPkgs");
-                let systemconfig = self.config.systemconfig.clone();
-                let syspkgtype = self.syspkgtype.clone();
-                let userpkgtype = self.userpkgtype.clone();
-                sender.oneshot_command(async move {
-                    let installedsystempkgs = if let Some(config) = &systemconfig {
-                        match syspkgtype {
-                            SystemPkgs::Flake => {
-                                let pkgs = nix_data::cache::flakes::getflakepkgs(&[config]).await;
-                                if let Ok(pkgs) = pkgs {
-                                    pkgs.keys().cloned().collect::<HashSet<String>>()
-                                } else {
-                                    HashSet::new()
-                                }
+            AppMsg::ClosePkgPage => {
+                if self.page == Page::PkgPage {
+                    self.pkgpage.emit(PkgMsg::Close);
+                }
+            }
+            AppMsg::CloseCategoryPage => {
+                match self.mainpage {
+                    MainPage::CategoryPage => self.categorypage.emit(CategoryPageMsg::Close),
+                    MainPage::AllPkgsPage => self.allpkgspage.emit(AllPkgsPageMsg::Close),
+                    MainPage::FrontPage => {}
+                }
+            }
+            AppMsg::SetNote(pkg, note) => {
+                self.installedpage.emit(InstalledPageMsg::SetNote(pkg, note));
+            }
+            AppMsg::SetHost(host) => {
+                info!("AppMsg::SetHost({:?})", host);
+                self.host = host;
+                if self.host.is_none() {
+                    self.syspkgtype = syspkgtype(&self.config);
+                    self.userpkgtype = profile::userpkgtype();
+                }
+                self.emitpkgtypes();
+                self.pkgpage.emit(PkgMsg::SetHost(self.host.clone()));
+                self.updatepage.emit(UpdatePageMsg::SetHost(self.host.clone()));
+                sender.input(AppMsg::UpdateInstalledPkgs);
+            }
+            AppMsg::Dbus(id, call) => {
+                info!("AppMsg::Dbus({:?})", call);
+                let Ok(pool) = SqlitePool::connect(&format!("sqlite://{}", self.pkgdb)).await else {
+                    dbus::fail(id, "The package data hasn't loaded yet");
+                    return;
+                };
+                let install = matches!(call, DbusCall::InstallPackage(_));
+                match call {
+                    DbusCall::InstallPackage(pkg) | DbusCall::RemovePackage(pkg) => {
+                        if self.readonly {
+                            dbus::fail(id, "Nix is not available");
+                            return;
+                        }
+                        if !install && self.policy.noremove {
+                            dbus::fail(id, "Removing packages is not allowed on this computer");
+                            return;
+                        }
+                        let row: Option<(String, String)> =
+                            sqlx::query_as("SELECT pname, license FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute = $1")
+                                .bind(&pkg)
+                                .fetch_optional(&pool)
+                                .await
+                                .unwrap_or_default();
+                        let Some((pname, license)) = row else {
+                            dbus::fail(id, &format!("No package {}", pkg));
+                            return;
+                        };
+                        if install {
+                            if let Some(blocked) = self.policy.blocksinstall(&pkg, packages::isunfree(&license)) {
+                                dbus::fail(id, match blocked {
+                                    PolicyBlock::NotAllowed => "Not in the software approved for this computer",
+                                    PolicyBlock::Unfree => "Unfree software is not allowed on this computer",
+                                });
+                                return;
+                            }
+                        }
+                        let userkey = match self.userpkgtype {
+                            UserPkgs::Env => &pname,
+                            UserPkgs::Profile => &pkg,
+                        };
+                        let installeduser = self.installeduserpkgs.contains_key(userkey);
+                        let installedsystem = self.installedsystempkgs.contains(&pkg);
+                        let (pkgtype, action) = if install {
+                            if installeduser || installedsystem {
+                                dbus::fail(id, &format!("{} is already installed", pkg));
+                                return;
+                            }
+                            (InstallType::User, PkgAction::Install)
+                        } else if installeduser {
+                            (InstallType::User, PkgAction::Remove)
+                        } else if installedsystem {
+                            (InstallType::System, PkgAction::Remove)
+                        } else {
+                            dbus::fail(id, &format!("{} is not installed", pkg));
+                            return;
+                        };
+                        sender.input(AppMsg::QueueWork(WorkPkg {
+                            pkg,
+                            pname,
+                            pkgtype,
+                            action,
+                            block: false,
+                            extraargs: vec![],
+                            impure: None,
+                        }));
+                        dbus::reply(id, None);
+                    }
+                    DbusCall::ListInstalled => {
+                        let mut installed = self
+                            .installeduserpkgs
+                            .iter()
+                            .map(|(k, v)| (k.to_string(), v.to_string(), String::from("user")))
+                            .collect::<Vec<_>>();
+                        for pkg in &self.installedsystempkgs {
+                            let version: Option<(String,)> = sqlx::query_as("SELECT version FROM pkgs WHERE attribute = $1")
+                                .bind(pkg)
+                                .fetch_optional(&pool)
+                                .await
+                                .unwrap_or_default();
+                            installed.push((pkg.to_string(), version.map(|(x,)| x).unwrap_or_default(), String::from("system")));
+                        }
+                        installed.sort();
+                        dbus::reply(id, Some((installed,).to_variant()));
+                    }
+                    DbusCall::SearchPackages(query) => {
+                        let Ok(pool) = searchpool(&self.pkgdb).await else {
+                            dbus::fail(id, "The package data hasn't loaded yet");
+                            return;
+                        };
+                        let (rows, _) = searchrows(
+                            &pool,
+                            &packages::searchmode(&query),
+                            &SearchFilters::default(),
+                            &self.appdata,
+                            &self.installeduserpkgs,
+                            &self.installedsystempkgs,
+                            &self.userpkgtype,
+                        )
+                        .await;
+                        let results = rows
+                            .into_iter()
+                            .filter(|x| self.policy.allows(&x.0))
+                            .take(100)
+                            .map(|(attr, pname, description, version, ..)| (attr, pname, version, description))
+                            .collect::<Vec<_>>();
+                        dbus::reply(id, Some((results,).to_variant()));
+                    }
+                }
+            }
+            AppMsg::UpdateInstalledPkgs => {
+                info!("AppMsg::UpdateInstalledPkgs");
+                if let Some(host) = self.host.clone() {
+                    let nscconfig = self.nscconfig.clone();
+                    sender.oneshot_command(async move {
+                        let installeduserpkgs = match remote::installed(&host, &nscconfig).await {
+                            Ok(pkgs) => pkgs,
+                            Err(e) => {
+                                warn!("Failed to list packages on {}: {}", host, e);
+                                REBUILD_BROKER.send(RebuildMsg::Show);
+                                REBUILD_BROKER.send(RebuildMsg::FinishError(Some(e.to_string())));
+                                HashMap::new()
+                            }
+                        };
+                        AppAsyncMsg::UpdateInstalledPkgs(HashSet::new(), installeduserpkgs)
+                    });
+                    return;
+                }
+                if demo::enabled() {
+                    sender.oneshot_command(async move {
+                        AppAsyncMsg::UpdateInstalledPkgs(HashSet::new(), demo::installed())
+                    });
+                    return;
+                }
+                if self.readonly {
+                    debug!("Nix is not available, nothing is installed");
+                    return;
+                }
+                let systemconfig = self.config.systemconfig.clone();
+                let syspkgtype = self.syspkgtype.clone();
+                let userpkgtype = self.userpkgtype.clone();
+                sender.oneshot_command(async move {
+                    let installedsystempkgs = if let Some(config) = &systemconfig {
+                        match syspkgtype {
+                            SystemPkgs::Flake => {
+                                let pkgs = nix_data::cache::flakes::getflakepkgs(&[config]).await;
+                                if let Ok(pkgs) = pkgs {
+                                    pkgs.keys().cloned().collect::<HashSet<String>>()
+                                } else {
+                                    HashSet::new()
+                                }
                             }
                             SystemPkgs::Legacy => {
                                 let pkgs = nix_data::cache::channel::getlegacypkgs(&[config]).await;
@@ -1234,6 +1834,10 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                     };
 
                     let installeduserpkgs = match userpkgtype {
+                        _ if profile::customprofile() => profile::installedversions(&userpkgtype).await.unwrap_or_else(|e| {
+                            warn!("Failed to list the packages in {}: {}", profile::userprofile().unwrap_or_default(), e);
+                            HashMap::new()
+                        }),
                         UserPkgs::Profile => {
                             let pkgs = nix_data::cache::profile::getprofilepkgs_versioned().await;
                             if let Ok(pkgs) = pkgs {
@@ -1257,10 +1861,20 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
             AppMsg::UpdateInstalledPage => {
                 info!("AppMsg::UpdateInstalledPage");
                 let mut installeduseritems = vec![];
+                let mut installedotheritems = vec![];
                 let mut updateuseritems = vec![];
                 // let pool = SqlitePool::connect(&self.pkgdb).await.unwrap();
                 debug!("Installed user pkgs: {:?}", self.installeduserpkgs);
                 debug!("Installed system pkgs: {:?}", self.installedsystempkgs);
+                // Packages installed before tracking started count as ours
+                let mut nscstate = state::getnscstate();
+                if nscstate.managed.is_none() && self.host.is_none() && !self.installeduserpkgs.is_empty() {
+                    let managed = self.installeduserpkgs.keys().cloned().collect::<HashSet<_>>();
+                    nscstate.managed = Some(managed.clone());
+                    if let Err(e) = state::editnscstate(|x| x.managed = Some(managed)) {
+                        warn!("Failed to save managed packages: {}", e);
+                    }
+                }
                 if let Ok(pool) = &SqlitePool::connect(&format!("sqlite://{}", self.pkgdb)).await {
                     match self.userpkgtype {
                         UserPkgs::Env => {
@@ -1297,12 +1911,12 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                         let mut icon = None;
                                         if let Some(data) = self.appdata.get(pkg) {
                                             if let Some(n) = &data.name {
-                                                if let Some(n) = n.get("C") {
+                                                if let Some(n) = packages::translated(n) {
                                                     name = n.to_string();
                                                 }
                                             }
                                             if let Some(s) = &data.summary {
-                                                if let Some(s) = s.get("C") {
+                                                if let Some(s) = packages::translated(s) {
                                                     summary = Some(s.to_string());
                                                 }
                                             }
@@ -1319,10 +1933,9 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                             summary: summary.clone(),
                                             icon: icon.clone(),
                                             pkgtype: InstallType::User,
-                                            busy: self.installedpagebusy.contains(&(
-                                                installedpname.to_string(),
-                                                InstallType::User,
-                                            )),
+                                            busy: self.workqueue.isbusy(&installedpname, WorkLane::User),
+                                            external: !state::ismanaged(&nscstate, installedpname),
+                                            note: None,
                                         });
                                         if !installedver.eq(&newver) {
                                             updateuseritems.push(UpdateItem {
@@ -1334,6 +1947,12 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                                 pkgtype: InstallType::User,
                                                 verfrom: Some(installedver.to_string()),
                                                 verto: Some(newver),
+                                                severity: None,
+                                                category: self
+                                                    .appdata
+                                                    .get(pkg)
+                                                    .and_then(|x| x.categories.as_deref())
+                                                    .and_then(packages::appcategory),
                                             });
                                         }
                                     }
@@ -1345,10 +1964,9 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                             summary: None,
                                             icon: None,
                                             pkgtype: InstallType::User,
-                                            busy: self.installedpagebusy.contains(&(
-                                                installedpname.clone(),
-                                                InstallType::User,
-                                            )),
+                                            busy: self.workqueue.isbusy(&installedpname, WorkLane::User),
+                                            external: !state::ismanaged(&nscstate, installedpname),
+                                            note: None,
                                         });
                                         let possibleversions: Vec<(String,)> = sqlx::query_as(
                                             "SELECT version FROM pkgs WHERE pname =  $1",
@@ -1368,23 +1986,51 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                                 pkgtype: InstallType::User,
                                                 verfrom: Some(installedver.to_string()),
                                                 verto: None,
+                                                severity: None,
+                                                category: None,
                                             });
                                         }
                                     }
-                                    _ => {}
+                                    _ => {
+                                        installedotheritems.push(InstalledItem {
+                                            name: format!("{}-{}", installedpname, installedver),
+                                            pname: installedpname.clone(),
+                                            pkg: Some(installedpname.clone()),
+                                            summary: Some(gettext("Not in the package database")),
+                                            icon: None,
+                                            pkgtype: InstallType::User,
+                                            busy: self.workqueue.isbusy(&installedpname, WorkLane::User),
+                                            external: !state::ismanaged(&nscstate, installedpname),
+                                            note: None,
+                                        });
+                                    }
                                 }
                             }
                         }
                         UserPkgs::Profile => {
                             for installedpkg in self.installeduserpkgs.keys() {
                                 debug!("Checking package {}", installedpkg);
-                                let (pname, version): (String, String) = sqlx::query_as(
+                                let versionpname: sqlx::Result<(String, String)> = sqlx::query_as(
                                     "SELECT pname, version FROM pkgs WHERE attribute = $1",
                                 )
                                 .bind(installedpkg)
                                 .fetch_one(pool)
-                                .await
-                                .unwrap();
+                                .await;
+                                let Ok((pname, version)) = versionpname else {
+                                    // Removed or renamed in nixpkgs
+                                    installedotheritems.push(InstalledItem {
+                                        name: installedpkg.clone(),
+                                        pname: installedpkg.clone(),
+                                        pkg: Some(installedpkg.clone()),
+                                        summary: Some(gettext("Not in the package database")),
+                                        icon: None,
+                                        pkgtype: InstallType::User,
+                                        busy: self.workqueue.isbusy(&installedpkg, WorkLane::User),
+                                        external: !state::ismanaged(&nscstate, installedpkg),
+                                        note: None,
+                                    });
+                                    continue;
+                                };
                                 let (description,): (String,) = sqlx::query_as(
                                     "SELECT description FROM meta WHERE attribute = $1",
                                 )
@@ -1401,12 +2047,12 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                 let mut icon = None;
                                 if let Some(data) = self.appdata.get(installedpkg) {
                                     if let Some(n) = &data.name {
-                                        if let Some(n) = n.get("C") {
+                                        if let Some(n) = packages::translated(n) {
                                             name = n.to_string();
                                         }
                                     }
                                     if let Some(s) = &data.summary {
-                                        if let Some(s) = s.get("C") {
+                                        if let Some(s) = packages::translated(s) {
                                             summary = Some(s.to_string());
                                         }
                                     }
@@ -1423,9 +2069,9 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                     summary: summary.clone(),
                                     icon: icon.clone(),
                                     pkgtype: InstallType::User,
-                                    busy: self
-                                        .installedpagebusy
-                                        .contains(&(installedpkg.clone(), InstallType::User)),
+                                    busy: self.workqueue.isbusy(&installedpkg, WorkLane::User),
+                                    external: !state::ismanaged(&nscstate, installedpkg),
+                                    note: None,
                                 });
                                 if let Some(latest) = &self.nixpkgsdb {
                                     if let Ok(latestpool) =
@@ -1446,9 +2092,15 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                                 pkg: Some(installedpkg.clone()),
                                                 summary,
                                                 icon,
-                                                pkgtype: InstallType::System,
+                                                pkgtype: InstallType::User,
                                                 verfrom: Some(version.clone()),
                                                 verto: Some(newver.clone()),
+                                                severity: None,
+                                                category: self
+                                                    .appdata
+                                                    .get(installedpkg)
+                                                    .and_then(|x| x.categories.as_deref())
+                                                    .and_then(packages::appcategory),
                                             })
                                         }
                                     }
@@ -1457,8 +2109,27 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                         }
                     }
 
-                    installeduseritems
-                        .sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+                    if self.userpkgtype == UserPkgs::Profile && self.host.is_none() {
+                        // Elements from other flakes aren't in the package database at all
+                        for element in profile::elements() {
+                            if element.attr.as_ref().map(|x| self.installeduserpkgs.contains_key(x)).unwrap_or(false) {
+                                continue;
+                            }
+                            installedotheritems.push(InstalledItem {
+                                name: element.storename.clone().unwrap_or_else(|| element.name.clone()),
+                                pname: element.name.clone(),
+                                pkg: Some(element.name.clone()),
+                                summary: element.flakeref.clone(),
+                                icon: None,
+                                pkgtype: InstallType::User,
+                                busy: self.workqueue.isbusy(&element.name, WorkLane::User),
+                                external: !state::ismanaged(&nscstate, &element.name),
+                                note: None,
+                            });
+                        }
+                    }
+                    installedotheritems.sort_by_cached_key(|x| util::sortkey(&x.name));
+                    installeduseritems.sort_by_cached_key(|x| util::sortkey(&x.name));
                     let mut installedsystemitems = vec![];
                     let mut updatesystemitems = vec![];
                     for installedpkg in &self.installedsystempkgs {
@@ -1483,12 +2154,12 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                 let mut icon = None;
                                 if let Some(data) = self.appdata.get(installedpkg) {
                                     if let Some(n) = &data.name {
-                                        if let Some(n) = n.get("C") {
+                                        if let Some(n) = packages::translated(n) {
                                             name = n.to_string();
                                         }
                                     }
                                     if let Some(s) = &data.summary {
-                                        if let Some(s) = s.get("C") {
+                                        if let Some(s) = packages::translated(s) {
                                             summary = Some(s.to_string());
                                         }
                                     }
@@ -1505,9 +2176,9 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                     summary: summary.clone(),
                                     icon: icon.clone(),
                                     pkgtype: InstallType::System,
-                                    busy: self
-                                        .installedpagebusy
-                                        .contains(&(installedpkg.clone(), InstallType::System)),
+                                    busy: self.workqueue.isbusy(&installedpkg, WorkLane::System),
+                                    external: false,
+                                    note: None,
                                 });
                                 if let Some(current) = &self.systemdb {
                                     if let Ok(currentpool) =
@@ -1531,6 +2202,12 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                                 pkgtype: InstallType::System,
                                                 verfrom: Some(currver.clone()),
                                                 verto: Some(version.clone()),
+                                                severity: None,
+                                                category: self
+                                                    .appdata
+                                                    .get(installedpkg)
+                                                    .and_then(|x| x.categories.as_deref())
+                                                    .and_then(packages::appcategory),
                                             })
                                         }
                                     }
@@ -1546,16 +2223,16 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                 updatesystemitems.insert(
                                     0,
                                     UpdateItem {
-                                        name: String::from("NixOS System"),
+                                        name: gettext("NixOS System"),
                                         pname: String::new(),
                                         pkg: None,
-                                        summary: Some(String::from(
-                                            "NixOS internal packages and modules",
-                                        )),
+                                        summary: Some(gettext("NixOS internal packages and modules")),
                                         icon: None,
                                         pkgtype: InstallType::System,
                                         verfrom: Some(old),
                                         verto: Some(new),
+                                        severity: None,
+                                        category: None,
                                     },
                                 )
                             }
@@ -1565,16 +2242,16 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                 updatesystemitems.insert(
                                     0,
                                     UpdateItem {
-                                        name: String::from("NixOS System"),
+                                        name: gettext("NixOS System"),
                                         pname: String::new(),
                                         pkg: None,
-                                        summary: Some(String::from(
-                                            "NixOS internal packages and modules",
-                                        )),
+                                        summary: Some(gettext("NixOS internal packages and modules")),
                                         icon: None,
                                         pkgtype: InstallType::System,
                                         verfrom: Some(old),
                                         verto: Some(new),
+                                        severity: None,
+                                        category: None,
                                     },
                                 )
                             }
@@ -1582,12 +2259,29 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                         SystemPkgs::None => {}
                     }
 
-                    installedsystemitems
-                        .sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+                    installedsystemitems.sort_by_cached_key(|x| util::sortkey(&x.name));
                     self.installedpage.emit(InstalledPageMsg::Update(
                         installeduseritems,
                         installedsystemitems,
+                        installedotheritems,
                     ));
+                    let updatablepkgs = updateuseritems
+                        .iter()
+                        .chain(updatesystemitems.iter())
+                        .filter_map(|x| x.pkg.clone())
+                        .collect::<HashSet<String>>();
+                    if updatablepkgs != self.updatablepkgs {
+                        self.updatablepkgs = updatablepkgs;
+                        let mut strips_guard = self.categorystrips.guard();
+                        let stripapps = strips_guard.iter_mut().map(|x| &mut x.tiles);
+                        for apps in [&mut self.recommendedapps, &mut self.trendingapps].into_iter().chain(stripapps) {
+                            let mut apps_guard = apps.guard();
+                            for item in apps_guard.iter_mut() {
+                                item.update = self.updatablepkgs.contains(&item.pkg);
+                            }
+                        }
+                        sender.input(AppMsg::UpdateCategoryPkgs);
+                    }
                     self.updatepage
                         .emit(UpdatePageMsg::Update(updateuseritems, updatesystemitems));
                 } else {
@@ -1598,6 +2292,7 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                 self.categorypage.emit(CategoryPageMsg::UpdateInstalled(
                     self.installeduserpkgs.keys().cloned().collect::<Vec<_>>(),
                     self.installedsystempkgs.iter().cloned().collect::<Vec<_>>(),
+                    self.updatablepkgs.iter().cloned().collect::<Vec<_>>(),
                 ));
             }
             AppMsg::SetSearch(show) => {
@@ -1624,6 +2319,20 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
             AppMsg::SetVsBar(vsbar) => {
                 self.set_showvsbar(vsbar);
             }
+            AppMsg::SearchFor(query) => {
+                self.set_searching(true);
+                // Filling in the entry starts the search, also when it is the same query again
+                self.update_searchfill(|x| *x = query);
+            }
+            AppMsg::SetSearchFilters(filters) => {
+                if let Err(e) = state::editnscstate(|x| x.ui.searchfilters = filters.clone()) {
+                    warn!("Failed to save search filters: {}", e);
+                }
+                self.set_searchfilters(filters);
+                if !self.searchquery.is_empty() {
+                    sender.input(AppMsg::Search(self.searchquery.to_string()));
+                }
+            }
             AppMsg::Search(search) => {
                 info!("AppMsg::Search");
                 debug!("Searching for: {}", search);
@@ -1634,76 +2343,109 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                 let userpkgtype = self.userpkgtype.clone();
                 let pkgdb = self.pkgdb.clone();
                 let appdata = self.appdata.clone();
+                let popularity = self.popularity.clone();
+                let policy = self.policy.clone();
+                let filters = self.searchfilters.clone();
                 sender.command(move |out, shutdown| {
                     let search = search.clone();
                     let installeduserpkgs = installeduserpkgs.clone();
                     let installedsystempkgs = installedsystempkgs;
                     let userpkgtype = userpkgtype.clone();
                     shutdown.register(async move {
-                        let searchsplit: Vec<String> = search.split(' ').filter(|x| x.len() > 1).map(|x| x.to_string()).collect();
-                        warn!("Searchsplit: {:?}", searchsplit);
-                        if let Ok(pkgpool) = &SqlitePool::connect(&format!("sqlite://{}", pkgdb)).await {
-                            let mut queryb: QueryBuilder<Sqlite> = QueryBuilder::new(
-                                "SELECT pkgs.attribute, pkgs.pname, description, version FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE (",
-                            );
-                            for (i, q) in searchsplit.iter().enumerate() {
-                                if i == searchsplit.len() - 1 {
-                                    queryb
-                                        .push(r#"pkgs.attribute LIKE "#)
-                                        .push_bind(format!("%{}%", q))
-                                        .push(r#" OR description LIKE "#)
-                                        .push_bind(format!("%{}%", q))
-                                        .push(")");
-                                } else {
-                                    queryb
-                                        .push(r#"pkgs.attribute LIKE "#)
-                                        .push_bind(format!("%{}%", q))
-                                        .push(r#" OR description LIKE "#)
-                                        .push_bind(format!("%{}%", q))
-                                        .push(r#") AND ("#);
+                        let mode = packages::searchmode(&search);
+                        let searchsplit: Vec<String> = match &mode {
+                            SearchMode::Words(words) => words.clone(),
+                            SearchMode::Exact(_) | SearchMode::Regex(_) => vec![],
+                        };
+                        trace!("Search mode: {:?}", mode);
+                        let pkgpool = searchpool(&pkgdb).await;
+                        if let Ok(pkgpool) = &pkgpool {
+                            let (q, ranked) = searchrows(pkgpool, &mode, &filters, &appdata, &installeduserpkgs, &installedsystempkgs, &userpkgtype).await;
+                            let currentsystem = util::currentsystem();
+                            let sizes = narinfo::getsizes();
+                            let searchitem = |(attr, pname, desc, version, system, platforms, license): AllPkgsRow| {
+                                let supported = packages::supportsplatform(&system, &platforms, &currentsystem);
+                                let size = narinfo::downloadsize(&sizes, &attr, &version);
+                                let unfree = packages::isunfree(&license);
+                                let license = packages::licenseshort(&license);
+                                let data = appdata.get(&attr);
+                                SearchItem {
+                                    name: match data {
+                                        Some(data) => if let Some(name) = &data.name { packages::translated(name).unwrap_or(&attr).to_string() } else { attr.to_string() },
+                                        None => pname.to_string(),
+                                    },
+                                    summary: if desc.is_empty() { None } else { Some(desc) },
+                                    icon: data
+                                        .and_then(|x| x.icon.as_ref())
+                                        .and_then(|x| x.cached.as_ref())
+                                        .map(|x| x[0].name.clone()),
+                                    installeduser: match userpkgtype {
+                                      UserPkgs::Env => installeduserpkgs.contains_key(&pname),
+                                      UserPkgs::Profile => installeduserpkgs.contains_key(&attr)
+                                    },
+                                    installedsystem: installedsystempkgs.contains(&attr),
+                                    supported,
+                                    unfree,
+                                    license,
+                                    size,
+                                    version: version.to_string(),
+                                    variants: vec![],
+                                    pkg: attr,
+                                    pname,
+                                }
+                            };
+                            // A single word may be a command, e.g. `convert` from imagemagick
+                            let mut providers = vec![];
+                            if let [command] = searchsplit.as_slice() {
+                                let attrs = programs::providers(command).await;
+                                if !attrs.is_empty() {
+                                    let mut queryb: QueryBuilder<Sqlite> = QueryBuilder::new(
+                                        "SELECT pkgs.attribute, pkgs.pname, description, version, system, platforms, license FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute IN (",
+                                    );
+                                    let mut separated = queryb.separated(", ");
+                                    for attr in &attrs {
+                                        separated.push_bind(attr.to_string());
+                                    }
+                                    queryb.push(")");
+                                    pushfilters(&mut queryb, &filters, &appdata, &installeduserpkgs, &installedsystempkgs, &userpkgtype);
+                                    match queryb.build_query_as().fetch_all(pkgpool).await {
+                                        Ok(rows) => {
+                                            providers = rows.into_iter().filter(|x: &AllPkgsRow| policy.allows(&x.0)).map(&searchitem).collect();
+                                        }
+                                        Err(e) => warn!("Failed to look up providers of {}: {}", command, e),
+                                    }
                                 }
                             }
-                            queryb.push("ORDER BY LENGTH(pkgs.attribute) ASC");
-                            let q: Vec<(String, String, String, String)> =
-                                queryb.build_query_as().fetch_all(pkgpool).await.unwrap();
                             let mut outpkgs = Vec::new();
-                            for (i, (attr, pname, desc, _version)) in q.into_iter().enumerate() {
-                                if let Some(data) = appdata.get(&attr) {
-                                    outpkgs.push(SearchItem {
-                                        pkg: attr.to_string(),
-                                        pname: pname.to_string(),
-                                        name: if let Some(name) = &data.name { name.get("C").unwrap_or(&attr).to_string() } else { attr.to_string() },
-                                        summary: if desc.is_empty() { None } else { Some(desc) },
-                                        icon: data
-                                            .icon
-                                            .as_ref()
-                                            .and_then(|x| x.cached.as_ref())
-                                            .map(|x| x[0].name.clone()),
-                                        installeduser: match userpkgtype {
-                                          UserPkgs::Env => installeduserpkgs.contains_key(&pname),
-                                          UserPkgs::Profile => installeduserpkgs.contains_key(&attr)
-                                        },
-                                        installedsystem: installedsystempkgs.contains(&attr),
-                                    })
-                                } else {
-                                    outpkgs.push(SearchItem {
-                                        pkg: attr.to_string(),
-                                        pname: pname.to_string(),
-                                        name: pname.to_string(),
-                                        summary: if desc.is_empty() { None } else { Some(desc) },
-                                        icon: None,
-                                        installeduser: match userpkgtype {
-                                          UserPkgs::Env => installeduserpkgs.contains_key(&pname),
-                                          UserPkgs::Profile => installeduserpkgs.contains_key(&attr)
-                                        },
-                                        installedsystem: installedsystempkgs.contains(&attr),
-                                    });
-                                }
+                            let q = q
+                                .into_iter()
+                                .filter(|x| policy.allows(&x.0))
+                                .filter(|x| !providers.iter().any(|p: &SearchItem| p.pkg == x.0));
+                            for (i, row) in q.enumerate() {
+                                outpkgs.push(searchitem(row));
                                 if i >= 200 {
                                     break;
                                 }
                             }
                             outpkgs.sort_by(|a, b| {
+                                let points = |x: &SearchItem| ranked.get(&x.pkg).map(|i| i / 10);
+                                if let (Some(apoints), Some(bpoints)) = (points(a), points(b)) {
+                                    let apoints = (apoints + 5).saturating_sub(if appdata.contains_key(&a.pkg) { 5 } else { 0 });
+                                    let bpoints = (bpoints + 5).saturating_sub(if appdata.contains_key(&b.pkg) { 5 } else { 0 });
+                                    let apoints = apoints.saturating_sub(popularity.weight(&a.pkg));
+                                    let bpoints = bpoints.saturating_sub(popularity.weight(&b.pkg));
+                                    return b
+                                        .supported
+                                        .cmp(&a.supported)
+                                        .then(apoints.cmp(&bpoints))
+                                        .then_with(|| util::sortkey(&a.name).cmp(&util::sortkey(&b.name)));
+                                }
+                                // Full-text matches before the substring ones
+                                match (points(a), points(b)) {
+                                    (Some(_), None) => return std::cmp::Ordering::Less,
+                                    (None, Some(_)) => return std::cmp::Ordering::Greater,
+                                    _ => {}
+                                }
                                 let mut aleft = a.name.to_lowercase() + &a.pkg.to_lowercase();
                                 let mut bleft = b.name.to_lowercase() + &b.pkg.to_lowercase();
                                 for q in searchsplit.iter() {
@@ -1735,29 +2477,48 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                 if appdata.get(&b.pkg).is_some() {
                                     bpoints -= 5;
                                 }
-                                apoints.cmp(&bpoints)
+                                apoints = apoints.saturating_sub(popularity.weight(&a.pkg));
+                                bpoints = bpoints.saturating_sub(popularity.weight(&b.pkg));
+                                // Packages that can't be installed on this system go last
+                                b.supported
+                                    .cmp(&a.supported)
+                                    .then(apoints.cmp(&bpoints))
+                                    .then_with(|| util::sortkey(&a.name).cmp(&util::sortkey(&b.name)))
                             });
-                            out.send(AppAsyncMsg::Search(search.to_string(), outpkgs));
+                            let command = searchsplit.first().cloned().unwrap_or_default();
+                            out.send(AppAsyncMsg::Search(search.to_string(), outpkgs, command, providers));
                         }
                     }).drop_on_shutdown()
                 })
             }
-            AppMsg::AddInstalledToWorkQueue(work) => {
-                let p = match work.pkgtype {
-                    InstallType::User => work.pname.to_string(),
-                    InstallType::System => work.pkg.to_string(),
-                };
-                self.installedpagebusy.push((p, work.pkgtype.clone()));
-                self.pkgpage.emit(PkgMsg::AddToQueue(work));
+            AppMsg::QueueWork(work) => {
+                if self.readonly {
+                    warn!("Not running {:?} on {}, packages can't be changed", work.action, work.pkg);
+                    return;
+                }
+                if self.policy.noremove && matches!(work.action, PkgAction::Remove | PkgAction::Revert(_)) {
+                    warn!("Not running {:?} on {}, removing packages is not allowed", work.action, work.pkg);
+                    return;
+                }
+                if self.workqueue.push(work.clone()) {
+                    dbus::operationchanged(&work, "pending");
+                    self.runnext();
+                    self.emitqueue();
+                }
             }
-            AppMsg::RemoveInstalledBusy(work) => {
-                let p = match work.pkgtype {
-                    InstallType::User => work.pname.to_string(),
-                    InstallType::System => work.pkg.to_string(),
-                };
-                self.installedpagebusy
-                    .retain(|(x, y)| x != &p && y != &work.pkgtype);
-                self.installedpage.emit(InstalledPageMsg::UnsetBusy(work));
+            AppMsg::WorkFinished(work, success) => {
+                self.workqueue.finish(&work, success);
+                dbus::operationchanged(&work, if success { "done" } else { "failed" });
+                self.runnext();
+                self.emitqueue();
+            }
+            AppMsg::CancelWork(pkg) => {
+                for work in self.workqueue.active().filter(|x| x.pkg == pkg) {
+                    dbus::operationchanged(work, "cancelled");
+                }
+                self.workqueue.cancel(&pkg);
+                self.runnext();
+                self.emitqueue();
             }
             AppMsg::OpenCategoryPage(category) => {
                 info!("AppMsg::OpenCategoryPage({:?})", category);
@@ -1767,123 +2528,76 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                     .emit(CategoryPageMsg::Loading(category.clone()));
                 sender.input(AppMsg::LoadCategory(category));
             }
+            AppMsg::OpenAllPkgs => {
+                self.page = Page::FrontPage;
+                self.mainpage = MainPage::AllPkgsPage;
+                self.allpkgspage.emit(AllPkgsPageMsg::Open);
+            }
+            AppMsg::LoadAllPkgs(letter, last) => {
+                let pkgdb = self.pkgdb.clone();
+                let policy = self.policy.clone();
+                sender.oneshot_command(async move {
+                    let (rows, more) = match SqlitePool::connect(&format!("sqlite://{}", pkgdb)).await {
+                        Ok(pool) => allpkgsrows(&pool, &letter, last, &policy).await,
+                        Err(e) => {
+                            error!("Failed to connect to pkgdb: {}", e);
+                            (vec![], false)
+                        }
+                    };
+                    AppAsyncMsg::AppendAllPkgs(letter, rows, more)
+                });
+            }
             AppMsg::LoadCategory(category) => {
                 info!("AppMsg::LoadCategory({:?})", category);
                 let pkgdb = self.pkgdb.clone();
                 let categoryrec = self.categoryrec.get(&category).unwrap_or(&vec![]).to_vec();
-                let categoryall = self.categoryall.get(&category).unwrap_or(&vec![]).to_vec();
+                let mut categoryall = self.categoryall.get(&category).unwrap_or(&vec![]).to_vec();
+                // The rest is loaded as the page is scrolled
+                let remaining = categoryall.split_off(categorypage::PAGESIZE.min(categoryall.len()));
                 let appdata = self.appdata.clone();
                 let installeduser = self.installeduserpkgs.clone();
                 let installedsystem = self.installedsystempkgs.clone();
+                let updatable = self.updatablepkgs.clone();
                 let category = category;
                 sender.oneshot_command(async move {
-                    let mut catrec = vec![];
-                    let mut catall = vec![];
-                    if let Ok(pool) = &SqlitePool::connect(&format!("sqlite://{}", pkgdb)).await {
-                        for pkg in categoryrec {
-                            if let Some(data) = appdata.get(&pkg) {
-                                let pname: (String,) =
-                                sqlx::query_as("SELECT pname FROM pkgs WHERE attribute = $1")
-                                    .bind(&pkg)
-                                    .fetch_one(pool)
-                                    .await
-                                    .unwrap();
-                                catrec.push(CategoryTile {
-                                    pkg: pkg.to_string(),
-                                    name: if let Some(name) = &data.name {
-                                        name.get("C").unwrap_or(&pname.0).to_string()
-                                    } else {
-                                        pname.0.to_string()
-                                    },
-                                    pname: pname.0,
-                                    icon: data
-                                        .icon
-                                        .as_ref()
-                                        .and_then(|x| x.cached.as_ref())
-                                        .map(|x| x[0].name.clone()),
-                                    summary: data
-                                        .summary
-                                        .as_ref()
-                                        .and_then(|x| x.get("C"))
-                                        .map(|x| x.to_string()),
-                                    installeduser: installeduser.contains_key(&pkg),
-                                    installedsystem: installedsystem.contains(&pkg),
-                                })
-                            } else {
-                                let (pname, description): (String, String) =
-                                sqlx::query_as("SELECT pname, description FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute = $1")
-                                    .bind(&pkg)
-                                    .fetch_one(pool)
-                                    .await
-                                    .unwrap();
-                                catrec.push(CategoryTile {
-                                    pkg: pkg.to_string(),
-                                    name: pname.to_string(),
-                                    pname: pname.to_string(),
-                                    icon: None,
-                                    summary: if description.is_empty() { None } else { Some(description) },
-                                    installeduser: installeduser.contains_key(&pkg),
-                                    installedsystem: installedsystem.contains(&pkg),
-                                })
-                            }
-                        }
-                        for pkg in categoryall {
-                            if let Some(data) = appdata.get(&pkg) {
-                                let pname: (String,) =
-                                sqlx::query_as("SELECT pname FROM pkgs WHERE attribute = $1")
-                                    .bind(&pkg)
-                                    .fetch_one(pool)
-                                    .await
-                                    .unwrap();
-                                catall.push(CategoryTile {
-                                    pkg: pkg.to_string(),
-                                    name: if let Some(name) = &data.name {
-                                        name.get("C").unwrap_or(&pname.0).to_string()
-                                    } else {
-                                        pname.0.to_string()
-                                    },
-                                    pname: pname.0,
-                                    icon: data
-                                        .icon
-                                        .as_ref()
-                                        .and_then(|x| x.cached.as_ref())
-                                        .map(|x| x[0].name.clone()),
-                                    summary: data
-                                        .summary
-                                        .as_ref()
-                                        .and_then(|x| x.get("C"))
-                                        .map(|x| x.to_string()),
-                                    installeduser: installeduser.contains_key(&pkg),
-                                    installedsystem: installedsystem.contains(&pkg),
-                                })
-                            } else {
-                                let (pname, description): (String, String) =
-                                sqlx::query_as("SELECT pname, description FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute = $1")
-                                    .bind(&pkg)
-                                    .fetch_one(pool)
-                                    .await
-                                    .unwrap();
-                                catall.push(CategoryTile {
-                                    pkg: pkg.to_string(),
-                                    name: pname.to_string(),
-                                    pname: pname.to_string(),
-                                    icon: None,
-                                    summary: if description.is_empty() { None } else { Some(description) },
-                                    installeduser: installeduser.contains_key(&pkg),
-                                    installedsystem: installedsystem.contains(&pkg),
-                                })
-                            }
-                        }
+                    let (catrec, catall) = if let Ok(pool) = &SqlitePool::connect(&format!("sqlite://{}", pkgdb)).await {
+                        tokio::join!(
+                            categorytiles(pool, &categoryrec, &appdata, &installeduser, &installedsystem, &updatable),
+                            categorytiles(pool, &categoryall, &appdata, &installeduser, &installedsystem, &updatable),
+                        )
                     } else {
-                        error!("Failed to connect to pkgdb")
-                    }
-                    AppAsyncMsg::LoadCategory(category, catrec, catall)
+                        error!("Failed to connect to pkgdb");
+                        (vec![], vec![])
+                    };
+                    AppAsyncMsg::LoadCategory(category, catrec, catall, remaining)
+                });
+            }
+            AppMsg::LoadMoreCategory(category, pkgs) => {
+                let pkgdb = self.pkgdb.clone();
+                let appdata = self
+                    .appdata
+                    .iter()
+                    .filter(|(k, _)| pkgs.contains(k))
+                    .map(|(k, v)| (k.to_string(), v.clone()))
+                    .collect::<HashMap<String, AppData>>();
+                let installeduser = self.installeduserpkgs.clone();
+                let installedsystem = self.installedsystempkgs.clone();
+                let updatable = self.updatablepkgs.clone();
+                sender.oneshot_command(async move {
+                    let tiles = if let Ok(pool) = &SqlitePool::connect(&format!("sqlite://{}", pkgdb)).await {
+                        categorytiles(pool, &pkgs, &appdata, &installeduser, &installedsystem, &updatable).await
+                    } else {
+                        error!("Failed to connect to pkgdb");
+                        vec![]
+                    };
+                    AppAsyncMsg::AppendCategory(category, tiles)
                 });
             }
             AppMsg::SetDarkMode(dark) => {
                 info!("AppMsg::SetDarkMode({})", dark);
                 let scheme = if dark { "Adwaita-dark" } else { "Adwaita" };
                 self.rebuild.emit(RebuildMsg::SetScheme(scheme.to_string()));
+                self.userrebuild.emit(RebuildMsg::SetScheme(scheme.to_string()));
             }
             AppMsg::GetUnavailableItems(userpkgs, syspkgs, updatetype) => {
                 info!("AppMsg::GetUnavailableItems");
@@ -1899,6 +2613,18 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                     })
                     .collect();
                 let poolref = self.pkgdb.clone();
+                // Ask before removing packages installed with the nix CLI, keep them by default
+                let nscstate = state::getnscstate();
+                let noremove = self.policy.noremove;
+                let userchoice = move |pkg: &str| {
+                    if noremove {
+                        UnavailableChoice::Skip
+                    } else if state::ismanaged(&nscstate, pkg) {
+                        UnavailableChoice::Remove
+                    } else {
+                        UnavailableChoice::Skip
+                    }
+                };
                 relm4::spawn(async move {
                     let mut unavailableuser = vec![];
                     let mut unavailablesys = vec![];
@@ -1906,6 +2632,9 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                         let mut sortuserpkgs = userpkgs.into_iter().collect::<Vec<_>>();
                         sortuserpkgs.sort();
                         for (pkg, msg) in sortuserpkgs {
+                            let replacement = replacement(pool, &msg).await;
+                            let reason = unavailablereason(pool, &pkg, &msg, replacement.as_deref()).await;
+                            let reasonurl = reason.url(&pkg, &msg);
                             if let Some(data) = appdata.get(&pkg) {
                                 let pname: Result<(String,), sqlx::Error> =
                                     sqlx::query_as("SELECT pname FROM pkgs WHERE attribute = $1")
@@ -1916,7 +2645,7 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                     unavailableuser.push(UnavailableItemModel {
                                         pkg: pkg.to_string(),
                                         name: if let Some(name) = &data.name {
-                                            name.get("C").unwrap_or(&pname.0).to_string()
+                                            packages::translated(name).unwrap_or(&pname.0).to_string()
                                         } else {
                                             pname.0.to_string()
                                         },
@@ -1927,12 +2656,18 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                             .and_then(|x| x.cached.as_ref())
                                             .map(|x| x[0].name.clone()),
                                         message: msg,
+                                        replacement: replacement.clone(),
+                                        reason: reason.describe(),
+                                        reasonurl: reasonurl.clone(),
+                                        choice: userchoice(&pkg),
+                                        system: false,
+                                        noremove,
                                     })
                                 } else {
                                     unavailableuser.push(UnavailableItemModel {
                                         pkg: pkg.to_string(),
                                         name: if let Some(name) = &data.name {
-                                            name.get("C").unwrap_or(&pkg).to_string()
+                                            packages::translated(name).unwrap_or(&pkg).to_string()
                                         } else {
                                             pkg.to_string()
                                         },
@@ -1943,6 +2678,12 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                             .and_then(|x| x.cached.as_ref())
                                             .map(|x| x[0].name.clone()),
                                         message: msg,
+                                        replacement: replacement.clone(),
+                                        reason: reason.describe(),
+                                        reasonurl: reasonurl.clone(),
+                                        choice: userchoice(&pkg),
+                                        system: false,
+                                        noremove,
                                     })
                                 }
                             } else {
@@ -1952,12 +2693,21 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                     pname: String::new(),
                                     icon: None,
                                     message: msg,
+                                    replacement: replacement.clone(),
+                                    reason: reason.describe(),
+                                    reasonurl: reasonurl.clone(),
+                                    choice: userchoice(&pkg),
+                                    system: false,
+                                    noremove,
                                 })
                             }
                         }
                         let mut sortsyspkgs = syspkgs.into_iter().collect::<Vec<_>>();
                         sortsyspkgs.sort();
                         for (pkg, msg) in sortsyspkgs {
+                            let replacement = replacement(pool, &msg).await;
+                            let reason = unavailablereason(pool, &pkg, &msg, replacement.as_deref()).await;
+                            let reasonurl = reason.url(&pkg, &msg);
                             if let Some(data) = appdata.get(&pkg) {
                                 let pname: Result<(String,), sqlx::Error> =
                                     sqlx::query_as("SELECT pname FROM pkgs WHERE attribute = $1")
@@ -1968,7 +2718,7 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                     unavailablesys.push(UnavailableItemModel {
                                         pkg: pkg.to_string(),
                                         name: if let Some(name) = &data.name {
-                                            name.get("C").unwrap_or(&pname.0).to_string()
+                                            packages::translated(name).unwrap_or(&pname.0).to_string()
                                         } else {
                                             pname.0.to_string()
                                         },
@@ -1979,12 +2729,18 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                             .and_then(|x| x.cached.as_ref())
                                             .map(|x| x[0].name.clone()),
                                         message: msg,
+                                        replacement: replacement.clone(),
+                                        reason: reason.describe(),
+                                        reasonurl: reasonurl.clone(),
+                                        choice: if noremove { UnavailableChoice::Keep } else { UnavailableChoice::Remove },
+                                        system: true,
+                                        noremove,
                                     })
                                 } else {
                                     unavailablesys.push(UnavailableItemModel {
                                         pkg: pkg.to_string(),
                                         name: if let Some(name) = &data.name {
-                                            name.get("C").unwrap_or(&pkg).to_string()
+                                            packages::translated(name).unwrap_or(&pkg).to_string()
                                         } else {
                                             pkg.to_string()
                                         },
@@ -1995,6 +2751,12 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                             .and_then(|x| x.cached.as_ref())
                                             .map(|x| x[0].name.clone()),
                                         message: msg,
+                                        replacement: replacement.clone(),
+                                        reason: reason.describe(),
+                                        reasonurl: reasonurl.clone(),
+                                        choice: if noremove { UnavailableChoice::Keep } else { UnavailableChoice::Remove },
+                                        system: true,
+                                        noremove,
                                     })
                                 }
                             } else {
@@ -2004,6 +2766,12 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                                     pname: String::new(),
                                     icon: None,
                                     message: msg,
+                                    replacement: replacement.clone(),
+                                    reason: reason.describe(),
+                                    reasonurl: reasonurl.clone(),
+                                    choice: if noremove { UnavailableChoice::Keep } else { UnavailableChoice::Remove },
+                                    system: true,
+                                    noremove,
                                 })
                             }
                         }
@@ -2015,6 +2783,75 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                     ));
                 });
             }
+            AppMsg::OpenChangelog(pkg, version) => {
+                let homepage = if let Ok(pool) = &SqlitePool::connect(&format!("sqlite://{}", self.pkgdb)).await {
+                    let homepage: sqlx::Result<(String,)> =
+                        sqlx::query_as("SELECT homepage FROM meta WHERE attribute = $1")
+                            .bind(&pkg)
+                            .fetch_one(pool)
+                            .await;
+                    homepage.ok().map(|(x,)| x).filter(|x| !x.is_empty())
+                } else {
+                    None
+                };
+                if let Some(url) = packages::changelogurl(self.appdata.get(&pkg), homepage.as_deref(), &version) {
+                    if let Err(e) = gtk::gio::AppInfo::launch_default_for_uri(&url, gtk::gio::AppLaunchContext::NONE) {
+                        warn!("Failed to open changelog: {}", e);
+                    }
+                } else {
+                    warn!("No changelog found for {}", pkg);
+                }
+            }
+            AppMsg::OpenFile(path) => {
+                info!("AppMsg::OpenFile {}", path.display());
+                if self.busy {
+                    self.pendingfiles.push(path);
+                    return;
+                }
+                let owner = packages::fileids(&path).map(|ids| packages::ownerof(&self.appdata, &ids));
+                match owner {
+                    Ok(Some(pkg)) => sender.input(AppMsg::OpenPkg(pkg)),
+                    Ok(None) | Err(_) => {
+                        if let Err(e) = &owner {
+                            warn!("{}", e);
+                        }
+                        let name = path.file_name().map(|x| x.to_string_lossy().to_string()).unwrap_or_default();
+                        self.nopkgdialog(&match owner {
+                            Ok(_) => gettextf("No package with app data provides {}.", &[&name]),
+                            Err(_) => gettextf("{} is not a launcher or app data file.", &[&name]),
+                        });
+                    }
+                }
+            }
+            AppMsg::OpenUri(uri) => {
+                info!("AppMsg::OpenUri {}", uri);
+                if self.busy {
+                    self.pendinguris.push(uri);
+                    return;
+                }
+                let pkg = match packages::parseuri(&uri) {
+                    Some(PkgUri::Appstream(id)) => packages::ownerof(&self.appdata, &[id]),
+                    Some(PkgUri::Attribute(attr)) => {
+                        let found: Option<(String,)> = match SqlitePool::connect(&format!("sqlite://{}", self.pkgdb)).await {
+                            Ok(pool) => sqlx::query_as("SELECT attribute FROM pkgs WHERE attribute = $1")
+                                .bind(&attr)
+                                .fetch_optional(&pool)
+                                .await
+                                .unwrap_or_default(),
+                            Err(_) => None,
+                        };
+                        found.map(|(x,)| x)
+                    }
+                    None => {
+                        self.nopkgdialog(&gettextf("{} is not a package link.", &[&uri]));
+                        return;
+                    }
+                };
+                match pkg.filter(|x| self.policy.allows(x)) {
+                    Some(pkg) => sender.input(AppMsg::OpenPkg(pkg)),
+                    None => self.nopkgdialog(&gettextf("No package matches {}.", &[&uri])),
+                }
+            }
             AppMsg::CheckNetwork => {
                 let selfonline = self.online;
                 let senderclone = sender.clone();
@@ -2027,6 +2864,62 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                     AppAsyncMsg::SetNetwork(online)
                 });
             }
+            AppMsg::ExportReport(path) => {
+                info!("AppMsg::ExportReport {}", path.display());
+                let pkgdb = self.pkgdb.clone();
+                let userpkgtype = self.userpkgtype.clone();
+                let installeduser = self.installeduserpkgs.clone();
+                let installedsystem = self.installedsystempkgs.clone();
+                relm4::spawn(async move {
+                    let items = reportitems(&pkgdb, &userpkgtype, installeduser, installedsystem).await;
+                    let system = util::currentsystem();
+                    let ext = path.extension().and_then(|x| x.to_str()).unwrap_or_default().to_lowercase();
+                    let out = if ext == "md" || ext == "markdown" {
+                        report::markdown(&items, &system)
+                    } else {
+                        report::html(&items, &system)
+                    };
+                    if let Err(e) = fs::write(&path, out) {
+                        warn!("Failed to write report to {}: {}", path.display(), e);
+                    }
+                });
+            }
+            AppMsg::SendCrashReports => {
+                let Some(url) = self.nscconfig.crashreportsurl.clone().filter(|_| self.nscconfig.crashreports) else {
+                    return;
+                };
+                if !self.online {
+                    return;
+                }
+                relm4::spawn(async move {
+                    crash::sendpending(&url).await;
+                });
+            }
+            AppMsg::ReportPopularity => {
+                let Some(url) = self.nscconfig.popularityurl.clone().filter(|_| self.nscconfig.popularity) else {
+                    return;
+                };
+                if !self.online || !popularity::due(&state::getnscstate()) {
+                    return;
+                }
+                let pkgs = self
+                    .installedsystempkgs
+                    .iter()
+                    .chain(self.installeduserpkgs.keys())
+                    .cloned()
+                    .collect::<Vec<_>>();
+                relm4::spawn(async move {
+                    match popularity::report(&url, pkgs).await {
+                        Ok(_) => {
+                            info!("Sent popularity report");
+                            if let Err(e) = state::editnscstate(|x| x.lastpopularity = Some(popularity::now())) {
+                                warn!("Failed to save popularity report time: {}", e);
+                            }
+                        }
+                        Err(e) => warn!("Failed to send popularity report: {}", e),
+                    }
+                });
+            }
         }
     }
 
@@ -2037,9 +2930,9 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
         _root: &Self::Root,
     ) {
         match msg {
-            AppAsyncMsg::Search(search, pkgitems) => {
+            AppAsyncMsg::Search(search, pkgitems, command, providers) => {
                 if search == self.searchquery {
-                    self.searchpage.emit(SearchPageMsg::Search(pkgitems))
+                    self.searchpage.emit(SearchPageMsg::Search(pkgitems, command, providers))
                 }
             }
             AppAsyncMsg::UpdateRecPkgs(pkgtiles) => {
@@ -2053,6 +2946,22 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                 sender.input(AppMsg::UpdateInstalledPkgs);
                 info!("DONE AppAsyncMsg::UpdateRecPkgs");
             }
+            AppAsyncMsg::UpdateTrendingPkgs(pkgtiles) => {
+                let mut trendapps_guard = self.trendingapps.guard();
+                trendapps_guard.clear();
+                for tile in pkgtiles {
+                    trendapps_guard.push_back(tile);
+                }
+            }
+            AppAsyncMsg::UpdateCategoryStrips(strips) => {
+                let mut strips_guard = self.categorystrips.guard();
+                strips_guard.clear();
+                for (category, tiles) in strips {
+                    if !tiles.is_empty() {
+                        strips_guard.push_back(CategoryStripInit { category, tiles });
+                    }
+                }
+            }
             AppAsyncMsg::UpdateInstalledPkgs(installedsystempkgs, installeduserpkgs) => {
                 info!("AppAsyncMsg::UpdateInstalledPkgs");
                 if installedsystempkgs != self.installedsystempkgs
@@ -2063,16 +2972,20 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                     self.installeduserpkgs = installeduserpkgs;
                     sender.input(AppMsg::UpdateInstalledPage);
                     debug!("Getting recommended apps guard");
-                    let mut recommendedapps_guard = self.recommendedapps.guard();
-                    debug!("Got recommended apps guard");
-                    for item in recommendedapps_guard.iter_mut() {
-                        debug!("Got item {}", item.pkg);
-                        item.installeduser =
-                            self.installeduserpkgs.contains_key(match self.userpkgtype {
-                                UserPkgs::Env => &item.pname,
-                                UserPkgs::Profile => &item.pkg,
-                            });
-                        item.installedsystem = self.installedsystempkgs.contains(&item.pkg);
+                    let mut strips_guard = self.categorystrips.guard();
+                    let stripapps = strips_guard.iter_mut().map(|x| &mut x.tiles);
+                    for apps in [&mut self.recommendedapps, &mut self.trendingapps].into_iter().chain(stripapps) {
+                        let mut apps_guard = apps.guard();
+                        debug!("Got recommended apps guard");
+                        for item in apps_guard.iter_mut() {
+                            debug!("Got item {}", item.pkg);
+                            item.installeduser =
+                                self.installeduserpkgs.contains_key(match self.userpkgtype {
+                                    UserPkgs::Env => &item.pname,
+                                    UserPkgs::Profile => &item.pkg,
+                                });
+                            item.installedsystem = self.installedsystempkgs.contains(&item.pkg);
+                        }
                     }
                     if self.searching {
                         self.searchpage.emit(SearchPageMsg::UpdateInstalled(
@@ -2080,22 +2993,661 @@ FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute =
                             self.installedsystempkgs.clone(),
                         ));
                     }
+                    self.allpkgspage.emit(AllPkgsPageMsg::UpdateInstalled(
+                        self.installeduserpkgs.keys().cloned().collect(),
+                        self.installedsystempkgs.clone(),
+                    ));
                 }
+                sender.input(AppMsg::ReportPopularity);
                 info!("DONE AppAsyncMsg::UpdateInstalledPkgs");
             }
-            AppAsyncMsg::LoadCategory(category, catrec, catall) => {
+            AppAsyncMsg::LoadCategory(category, catrec, catall, remaining) => {
                 self.categorypage
-                    .emit(CategoryPageMsg::Open(category, catrec, catall));
+                    .emit(CategoryPageMsg::Open(category, catrec, catall, remaining));
+            }
+            AppAsyncMsg::AppendCategory(category, tiles) => {
+                self.categorypage
+                    .emit(CategoryPageMsg::Append(category, tiles));
+            }
+            AppAsyncMsg::AppendAllPkgs(letter, rows, more) => {
+                let currentsystem = util::currentsystem();
+                let sizes = narinfo::getsizes();
+                let items = rows
+                    .into_iter()
+                    .map(|(attr, pname, desc, version, system, platforms, license)| {
+                        let data = self.appdata.get(&attr);
+                        SearchItem {
+                            name: data
+                                .and_then(|x| x.name.as_ref())
+                                .and_then(packages::translated)
+                                .cloned()
+                                .unwrap_or_else(|| pname.to_string()),
+                            icon: data
+                                .and_then(|x| x.icon.as_ref())
+                                .and_then(|x| x.cached.as_ref())
+                                .map(|x| x[0].name.clone()),
+                            summary: if desc.is_empty() { None } else { Some(desc) },
+                            installeduser: self.installeduserpkgs.contains_key(match self.userpkgtype {
+                                UserPkgs::Env => &pname,
+                                UserPkgs::Profile => &attr,
+                            }),
+                            installedsystem: self.installedsystempkgs.contains(&attr),
+                            supported: packages::supportsplatform(&system, &platforms, &currentsystem),
+                            unfree: packages::isunfree(&license),
+                            license: packages::licenseshort(&license),
+                            size: narinfo::downloadsize(&sizes, &attr, &version),
+                            version,
+                            variants: vec![],
+                            pkg: attr,
+                            pname,
+                        }
+                    })
+                    .collect();
+                self.allpkgspage.emit(AllPkgsPageMsg::Append(letter, items, more));
+            }
+            AppAsyncMsg::PkgsRevision(rev) => {
+                self.newrev = None;
+                self.setpkgsrevision(rev);
             }
+            AppAsyncMsg::ChannelRevision(rev) => match &self.channelrev {
+                None => self.channelrev = Some(rev),
+                Some(current) if *current != rev => {
+                    info!("New {} release: {}", channel::channel(), rev);
+                    self.newrev = Some(rev);
+                }
+                _ => {}
+            },
             AppAsyncMsg::SetNetwork(online) => {
                 self.online = online;
                 self.updatepage.emit(UpdatePageMsg::UpdateOnline(online));
                 self.pkgpage.emit(PkgMsg::UpdateOnline(online));
             }
+            AppAsyncMsg::Prefetched(pkg, row) => {
+                if matches!(&self.prefetched, Some((p, _)) if *p == pkg) {
+                    self.prefetched = Some((pkg, row));
+                }
+            }
+        }
+    }
+}
+
+/// Keeps the window size, visible page and scroll positions for the next start.
+fn saveuistate(window: &adw::ApplicationWindow, viewstack: &adw::ViewStack) {
+    let (width, height) = window.default_size();
+    let maximized = window.is_maximized();
+    let page = viewstack.visible_child_name().map(|x| x.to_string());
+    let mut scroll = HashMap::new();
+    let pages = viewstack.pages();
+    for i in 0..pages.n_items() {
+        let Some(vspage) = pages.item(i).and_downcast::<adw::ViewStackPage>() else {
+            continue;
+        };
+        if let (Some(name), Some(child)) = (vspage.name(), vspage.child().downcast::<gtk::ScrolledWindow>().ok()) {
+            scroll.insert(name.to_string(), child.vadjustment().value() as u32);
         }
     }
+    if let Err(e) = state::editnscstate(|x| {
+        // The default size is the size before maximizing. Filters are saved by the pages as they change.
+        x.ui.width = Some(width);
+        x.ui.height = Some(height);
+        x.ui.maximized = maximized;
+        x.ui.page = page;
+        x.ui.scroll = scroll;
+    }) {
+        warn!("Failed to save window state: {}", e);
+    }
+}
+
+/// Scrolls to `position` once the page has loaded enough content to reach it.
+fn restorescroll(scroll: &gtk::ScrolledWindow, position: f64) {
+    if position <= 0.0 {
+        return;
+    }
+    let done = std::cell::Cell::new(false);
+    scroll.vadjustment().connect_changed(move |adjustment| {
+        if !done.get() && adjustment.upper() - adjustment.page_size() >= position {
+            done.set(true);
+            adjustment.set_value(position);
+        }
+    });
+}
+
+/// A connection to the package database for searching. REGEXP is a function sqlx registers on the
+/// connection, SQLite itself has none.
+async fn searchpool(pkgdb: &str) -> Result<SqlitePool, sqlx::Error> {
+    let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", pkgdb))?;
+    SqlitePool::connect_with(options.with_regexp()).await
+}
+
+/// Packages matching `mode` with `filters` applied, and the rank of the full-text matches among them.
+async fn searchrows(
+    pkgpool: &SqlitePool,
+    mode: &SearchMode,
+    filters: &SearchFilters,
+    appdata: &HashMap<String, AppData>,
+    installeduserpkgs: &HashMap<String, String>,
+    installedsystempkgs: &HashSet<String>,
+    userpkgtype: &UserPkgs,
+) -> (Vec<AllPkgsRow>, HashMap<String, usize>) {
+    let searchsplit: Vec<String> = match mode {
+        SearchMode::Words(words) => words.clone(),
+        SearchMode::Exact(_) | SearchMode::Regex(_) => vec![],
+    };
+    // Ranked full-text matches first, then substring matches the index misses because the
+    // words only appear inside others, e.g. "fox" in "firefox"
+    let mut ranked: HashMap<String, usize> = HashMap::new();
+    let mut q: Vec<(String, String, String, String, String, String, String)> = vec![];
+    if !searchsplit.is_empty() {
+        let mut queryb: QueryBuilder<Sqlite> = QueryBuilder::new(
+            "SELECT pkgs.attribute, pkgs.pname, meta.description, version, system, platforms, license FROM pkgsearch JOIN pkgs ON (pkgs.attribute = pkgsearch.attribute) JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgsearch MATCH ",
+        );
+        queryb.push_bind(packages::searchmatch(&searchsplit));
+        pushfilters(&mut queryb, filters, appdata, installeduserpkgs, installedsystempkgs, userpkgtype);
+        queryb.push(" ORDER BY bm25(pkgsearch, 10.0, 10.0, 1.0) LIMIT 250");
+        match queryb.build_query_as().fetch_all(pkgpool).await {
+            Ok(rows) => q = rows,
+            Err(e) => debug!("No full-text search: {}", e),
+        }
+        ranked = q.iter().enumerate().map(|(i, x)| (x.0.to_string(), i)).collect();
+    }
+    let mut queryb: QueryBuilder<Sqlite> = QueryBuilder::new(
+        "SELECT pkgs.attribute, pkgs.pname, description, version, system, platforms, license FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE (",
+    );
+    match mode {
+        SearchMode::Exact(attr) => {
+            queryb
+                .push("pkgs.attribute = ")
+                .push_bind(attr.to_string())
+                .push(" OR pkgs.pname = ")
+                .push_bind(attr.to_string())
+                .push(") ");
+        }
+        SearchMode::Regex(re) => {
+            queryb
+                .push("pkgs.attribute REGEXP ")
+                .push_bind(re.to_string())
+                .push(" OR pkgs.pname REGEXP ")
+                .push_bind(re.to_string())
+                .push(") ");
+        }
+        SearchMode::Words(_) => {}
+    }
+    for (i, q) in searchsplit.iter().enumerate() {
+        if i == searchsplit.len() - 1 {
+            queryb
+                .push(r#"pkgs.attribute LIKE "#)
+                .push_bind(format!("%{}%", q))
+                .push(r#" OR description LIKE "#)
+                .push_bind(format!("%{}%", q))
+                .push(")");
+        } else {
+            queryb
+                .push(r#"pkgs.attribute LIKE "#)
+                .push_bind(format!("%{}%", q))
+                .push(r#" OR description LIKE "#)
+                .push_bind(format!("%{}%", q))
+                .push(r#") AND ("#);
+        }
+    }
+    pushfilters(&mut queryb, filters, appdata, installeduserpkgs, installedsystempkgs, userpkgtype);
+    queryb.push(" ORDER BY LENGTH(pkgs.attribute) ASC");
+    let substrings: Vec<(String, String, String, String, String, String, String)> = match queryb.build_query_as().fetch_all(pkgpool).await {
+        Ok(q) => q,
+        Err(e) => {
+            // Most likely an invalid regex
+            warn!("Search failed: {}", e);
+            vec![]
+        }
+    };
+    q.extend(substrings.into_iter().filter(|x| !ranked.contains_key(&x.0)));
+    (q, ranked)
+}
+
+/// Adds the search filters to the WHERE clause of a package query joining `pkgs` and `meta`.
+fn pushfilters(
+    queryb: &mut QueryBuilder<Sqlite>,
+    filters: &SearchFilters,
+    appdata: &HashMap<String, AppData>,
+    installeduserpkgs: &HashMap<String, String>,
+    installedsystempkgs: &HashSet<String>,
+    userpkgtype: &UserPkgs,
+) {
+    // A license is unfree when any of the licenses it is made of is
+    let unfree =
+        "(json_valid(meta.license) AND EXISTS (SELECT 1 FROM json_tree(meta.license) WHERE key = 'free' AND atom = 0))";
+    match filters.license {
+        LicenseFilter::Any => {}
+        LicenseFilter::Free => {
+            queryb.push(format!(" AND NOT {}", unfree));
+        }
+        LicenseFilter::Unfree => {
+            queryb.push(format!(" AND {}", unfree));
+        }
+    }
+    if filters.gui || filters.category.is_some() {
+        let attrs = appdata.iter().filter(|(_, data)| match &filters.category {
+            Some(category) => data
+                .categories
+                .as_ref()
+                .and_then(|x| packages::appcategory(x))
+                .is_some_and(|x| &x == category),
+            None => true,
+        });
+        queryb.push(" AND pkgs.attribute IN (");
+        let mut separated = queryb.separated(", ");
+        for (attr, _) in attrs {
+            separated.push_bind(attr.to_string());
+        }
+        queryb.push(")");
+    }
+    if filters.installed {
+        queryb.push(" AND (pkgs.attribute IN (");
+        let mut separated = queryb.separated(", ");
+        for attr in installedsystempkgs {
+            separated.push_bind(attr.to_string());
+        }
+        queryb.push(match userpkgtype {
+            UserPkgs::Env => ") OR pkgs.pname IN (",
+            UserPkgs::Profile => ") OR pkgs.attribute IN (",
+        });
+        let mut separated = queryb.separated(", ");
+        for pkg in installeduserpkgs.keys() {
+            separated.push_bind(pkg.to_string());
+        }
+        queryb.push("))");
+    }
+}
+
+/// Looks up the tiles for a category in as few queries as possible, keeping the order of `pkgs`.
+async fn categorytiles(
+    pool: &SqlitePool,
+    pkgs: &[String],
+    appdata: &HashMap<String, AppData>,
+    installeduser: &HashMap<String, String>,
+    installedsystem: &HashSet<String>,
+    updatable: &HashSet<String>,
+) -> Vec<CategoryTile> {
+    let currentsystem = util::currentsystem();
+    let mut rows: HashMap<String, (String, String, String, String, String, String)> = HashMap::new();
+    // Stay well below SQLite's limit on bound parameters
+    for chunk in pkgs.chunks(500) {
+        let sql = format!(
+            "SELECT pkgs.attribute, pname, description, system, platforms, license, version FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute IN ({})",
+            vec!["?"; chunk.len()].join(", ")
+        );
+        let mut query = sqlx::query_as::<_, (String, String, String, String, String, String, String)>(&sql);
+        for pkg in chunk {
+            query = query.bind(pkg);
+        }
+        match query.fetch_all(pool).await {
+            Ok(result) => rows.extend(result.into_iter().map(|(attribute, pname, description, system, platforms, license, version)| {
+                (attribute, (pname, description, system, platforms, license, version))
+            })),
+            Err(e) => error!("Failed to load category packages: {}", e),
+        }
+    }
+
+    let sizes = narinfo::getsizes();
+    let mut tiles = vec![];
+    for pkg in pkgs {
+        let Some((pname, description, system, platforms, license, version)) = rows.remove(pkg) else {
+            continue;
+        };
+        if !packages::supportsplatform(&system, &platforms, &currentsystem) {
+            continue;
+        }
+        let tile = if let Some(data) = appdata.get(pkg) {
+            CategoryTile {
+                pkg: pkg.to_string(),
+                name: if let Some(name) = &data.name {
+                    packages::translated(name).unwrap_or(&pname).to_string()
+                } else {
+                    pname.to_string()
+                },
+                pname,
+                icon: data
+                    .icon
+                    .as_ref()
+                    .and_then(|x| x.cached.as_ref())
+                    .map(|x| x[0].name.clone()),
+                summary: data
+                    .summary
+                    .as_ref()
+                    .and_then(packages::translated)
+                    .map(|x| x.to_string()),
+                installeduser: installeduser.contains_key(pkg),
+                installedsystem: installedsystem.contains(pkg),
+                update: updatable.contains(pkg),
+                unfree: packages::isunfree(&license),
+                license: packages::licenseshort(&license),
+                size: narinfo::downloadsize(&sizes, pkg, &version),
+                developer: packages::developer(data, None),
+            }
+        } else {
+            CategoryTile {
+                pkg: pkg.to_string(),
+                name: pname.to_string(),
+                pname,
+                icon: None,
+                summary: if description.is_empty() { None } else { Some(description) },
+                installeduser: installeduser.contains_key(pkg),
+                installedsystem: installedsystem.contains(pkg),
+                update: updatable.contains(pkg),
+                unfree: packages::isunfree(&license),
+                license: packages::licenseshort(&license),
+                size: narinfo::downloadsize(&sizes, pkg, &version),
+                developer: None,
+            }
+        };
+        tiles.push(tile);
+    }
+    tiles
+}
+
+impl AppModel {
+    /// Makes `rev` the baseline for new channel releases, here and at the next start.
+    fn setpkgsrevision(&mut self, rev: String) {
+        if let Err(e) = state::editnscstate(|x| x.pkgsrevision = Some(rev.clone())) {
+            warn!("Failed to save the channel revision: {}", e);
+        }
+        self.channelrev = Some(rev);
+    }
+
+    /// Sends the package types to the pages. Remote hosts only manage a `nix profile`, so system
+    /// packages are hidden while one is selected.
+    fn emitpkgtypes(&mut self) {
+        if self.host.is_some() {
+            self.syspkgtype = SystemPkgs::None;
+            self.userpkgtype = UserPkgs::Profile;
+        }
+        self.pkgpage.emit(PkgMsg::UpdatePkgTypes(
+            self.syspkgtype.clone(),
+            self.userpkgtype.clone(),
+        ));
+        self.updatepage.emit(UpdatePageMsg::UpdatePkgTypes(
+            self.syspkgtype.clone(),
+            self.userpkgtype.clone(),
+        ));
+        self.installedpage.emit(InstalledPageMsg::UpdatePkgTypes(
+            self.syspkgtype.clone(),
+            self.userpkgtype.clone(),
+        ));
+    }
+
+    /// Tells the user that an opened file or link doesn't match any package.
+    fn nopkgdialog(&self, body: &str) {
+        let dialog = adw::MessageDialog::new(Some(&self.mainwindow), Some(&gettext("No Package Found")), Some(body));
+        dialog.add_response("close", &gettext("Close"));
+        dialog.present();
+    }
+
+    /// Starts the queued operations that no longer wait on a running one.
+    fn runnext(&mut self) {
+        for work in self.workqueue.startready() {
+            dbus::operationchanged(&work, "running");
+            self.pkgpage.emit(PkgMsg::Process(work));
+        }
+    }
+
+    fn emitqueue(&self) {
+        self.pkgpage.emit(PkgMsg::SetQueue(self.workqueue.clone()));
+        self.installedpage.emit(InstalledPageMsg::SetQueue(self.workqueue.clone()));
+    }
+
+    /// Builds the tiles for `pkgs` in the background, using the state at the time of the call.
+    fn pkgtiles(&self, pkgs: Vec<String>) -> impl std::future::Future<Output = Vec<PkgTile>> + Send + 'static {
+        let appdata: HashMap<String, AppData> = self
+            .appdata
+            .iter()
+            .filter_map(|(k, v)| {
+                if pkgs.contains(k) {
+                    Some((k.to_string(), v.clone()))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let installeduser = self.installeduserpkgs.clone();
+        let installedsystem = self.installedsystempkgs.clone();
+        let updatable = self.updatablepkgs.clone();
+        let poolref = self.pkgdb.clone();
+        let userpkgtype = self.userpkgtype.clone();
+        async move {
+            let sizes = narinfo::getsizes();
+            let mut pkgtiles = vec![];
+            if let Ok(pool) = &SqlitePool::connect(&format!("sqlite://{}", poolref)).await {
+                for pkg in pkgs {
+                    if let Some(data) = appdata.get(&pkg) {
+                        let (pname, license, version): (String, String, String) = sqlx::query_as(
+                            "SELECT pname, license, version FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute = $1",
+                        )
+                        .bind(&pkg)
+                        .fetch_one(pool)
+                        .await
+                        .unwrap();
+                        pkgtiles.push(PkgTile {
+                            pkg: pkg.to_string(),
+                            name: if let Some(name) = &data.name {
+                                packages::translated(name).unwrap_or(&pname).to_string()
+                            } else {
+                                pname.to_string()
+                            },
+                            pname: pname.to_string(),
+                            icon: data
+                                .icon
+                                .as_ref()
+                                .and_then(|x| x.cached.as_ref())
+                                .map(|x| x[0].name.clone()),
+                            summary: data
+                                .summary
+                                .as_ref()
+                                .and_then(packages::translated)
+                                .map(|x| x.to_string())
+                                .unwrap_or_default(),
+                            installeduser: installeduser.contains_key(&match userpkgtype {
+                                UserPkgs::Env => pname,
+                                UserPkgs::Profile => pkg.to_string(),
+                            }),
+                            installedsystem: installedsystem.contains(&pkg),
+                            update: updatable.contains(&pkg),
+                            unfree: packages::isunfree(&license),
+                            license: packages::licenseshort(&license),
+                            size: narinfo::downloadsize(&sizes, &pkg, &version),
+                            developer: packages::developer(data, None),
+                        })
+                    }
+                }
+            }
+            pkgtiles
+        }
+    }
+}
+
+/// Collects version, license and homepage for everything installed, user packages first.
+async fn reportitems(
+    pkgdb: &str,
+    userpkgtype: &UserPkgs,
+    installeduser: HashMap<String, String>,
+    installedsystem: HashSet<String>,
+) -> Vec<ReportItem> {
+    let pool = match SqlitePool::connect(&format!("sqlite://{}", pkgdb)).await {
+        Ok(x) => Some(x),
+        Err(e) => {
+            warn!("Error connecting to pkgdb: {}", e);
+            None
+        }
+    };
+    let mut items = vec![];
+    let mut user = installeduser.into_iter().collect::<Vec<_>>();
+    user.sort();
+    for (pkg, version) in user {
+        let query = match userpkgtype {
+            UserPkgs::Env => "SELECT pkgs.attribute, pname, version, license, homepage FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pname = $1 LIMIT 1",
+            UserPkgs::Profile => "SELECT pkgs.attribute, pname, version, license, homepage FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute = $1",
+        };
+        items.push(reportitem(pool.as_ref(), query, &pkg, Some(version), "User").await);
+    }
+    let mut system = installedsystem.into_iter().collect::<Vec<_>>();
+    system.sort();
+    for pkg in system {
+        let query = "SELECT pkgs.attribute, pname, version, license, homepage FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute = $1";
+        items.push(reportitem(pool.as_ref(), query, &pkg, None, "System").await);
+    }
+    items
+}
+
+async fn reportitem(pool: Option<&SqlitePool>, query: &str, pkg: &str, version: Option<String>, source: &str) -> ReportItem {
+    let row: Option<(String, String, String, String, String)> = match pool {
+        Some(pool) => sqlx::query_as(query).bind(pkg).fetch_one(pool).await.ok(),
+        None => None,
+    };
+    match row {
+        Some((attribute, pname, dbversion, license, homepage)) => ReportItem {
+            name: pname,
+            attribute: Some(attribute),
+            version: version.unwrap_or(dbversion),
+            licenses: report::licensenames(&license),
+            homepage: if homepage.is_empty() { None } else { Some(homepage) },
+            source: source.to_string(),
+        },
+        None => ReportItem {
+            name: pkg.to_string(),
+            attribute: None,
+            version: version.unwrap_or_default(),
+            licenses: vec![],
+            homepage: None,
+            source: source.to_string(),
+        },
+    }
+}
+
+async fn pkgrow(pool: &SqlitePool, pkg: &str) -> Result<PkgRow, sqlx::Error> {
+    sqlx::query_as(
+        r#"
+SELECT pname, version, system, description, longdescription, homepage, license, platforms, maintainers, position
+FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE pkgs.attribute = $1
+        "#,
+    )
+    .bind(pkg)
+    .fetch_one(pool)
+    .await
+}
+
+async fn dbversion(db: &str, pkg: &str) -> Option<String> {
+    let pool = SqlitePool::connect(&format!("sqlite://{}", db)).await.ok()?;
+    let version: sqlx::Result<(String,)> = sqlx::query_as("SELECT version FROM pkgs WHERE attribute = $1")
+        .bind(pkg)
+        .fetch_one(&pool)
+        .await;
+    version.ok().map(|x| x.0)
+}
+
+/// A row with only the fields available for a package without metadata, taken from the
+/// `pkgs` table alone or from what is installed in the user profile.
+async fn minimalrow(pool: &SqlitePool, pkg: &str, installeduserpkgs: &HashMap<String, String>) -> Option<PkgRow> {
+    let row: sqlx::Result<(String, String, String)> =
+        sqlx::query_as("SELECT pname, version, system FROM pkgs WHERE attribute = $1")
+            .bind(pkg)
+            .fetch_one(pool)
+            .await;
+    let (pname, version, system, description) = if let Ok((pname, version, system)) = row {
+        (pname, version, system, String::new())
+    } else if let Some(element) = profile::elements()
+        .into_iter()
+        .find(|x| x.name == pkg || x.attr.as_deref() == Some(pkg))
+    {
+        let (pname, version) = match &element.storename {
+            Some(storename) => {
+                let pname = profile::pnamefromname(storename);
+                let version = storename
+                    .get(pname.len() + 1..)
+                    .unwrap_or_default()
+                    .to_string();
+                (pname, version)
+            }
+            None => (element.name.to_string(), String::new()),
+        };
+        (pname, version, String::new(), element.flakeref.unwrap_or_default())
+    } else if let Some(version) = installeduserpkgs.get(pkg) {
+        (pkg.to_string(), version.to_string(), String::new(), String::new())
+    } else {
+        return None;
+    };
+    Some((
+        pname,
+        version,
+        system,
+        description,
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+    ))
+}
+
+/// The attribute an unavailable package was renamed to, if it exists in the current package set.
+async fn replacement(pool: &SqlitePool, message: &str) -> Option<String> {
+    let target = packages::aliastarget(message)?;
+    let exists: Result<(String,), sqlx::Error> =
+        sqlx::query_as("SELECT attribute FROM pkgs WHERE attribute = $1")
+            .bind(&target)
+            .fetch_one(pool)
+            .await;
+    exists.ok().map(|x| x.0)
+}
+
+async fn unavailablereason(pool: &SqlitePool, pkg: &str, message: &str, replacement: Option<&str>) -> UnavailableReason {
+    let license: Result<(String,), sqlx::Error> =
+        sqlx::query_as("SELECT license FROM meta WHERE attribute = $1")
+            .bind(pkg)
+            .fetch_one(pool)
+            .await;
+    let license = license.ok().map(|x| x.0);
+    packages::unavailablereason(message, replacement, license.is_some(), license.as_deref())
 }
 
 relm4::new_action_group!(MenuActionGroup, "menu");
 relm4::new_stateless_action!(AboutAction, MenuActionGroup, "about");
 relm4::new_stateless_action!(PreferencesAction, MenuActionGroup, "preferences");
+relm4::new_stateless_action!(CleanupAction, MenuActionGroup, "cleanup");
+relm4::new_stateless_action!(ExportReportAction, MenuActionGroup, "exportreport");
+
+/// A page of the all packages list in case-insensitive order, starting at `letter` or after `last`.
+/// Also returns whether there are more.
+async fn allpkgsrows(pool: &SqlitePool, letter: &str, last: Option<String>, policy: &Policy) -> (Vec<AllPkgsRow>, bool) {
+    let mut out = vec![];
+    let mut last = last;
+    loop {
+        let mut queryb: QueryBuilder<Sqlite> = QueryBuilder::new(
+            "SELECT pkgs.attribute, pkgs.pname, description, version, system, platforms, license FROM pkgs JOIN meta ON (pkgs.attribute = meta.attribute) WHERE lower(pkgs.attribute) >= ",
+        );
+        queryb.push_bind(letter.to_string());
+        if let Some(last) = &last {
+            queryb
+                .push(" AND (lower(pkgs.attribute), pkgs.attribute) > (lower(")
+                .push_bind(last.to_string())
+                .push("), ")
+                .push_bind(last.to_string())
+                .push(")");
+        }
+        queryb
+            .push(" ORDER BY lower(pkgs.attribute), pkgs.attribute LIMIT ")
+            .push_bind(allpkgspage::PAGESIZE as i64);
+        let rows: Vec<AllPkgsRow> = match queryb.build_query_as().fetch_all(pool).await {
+            Ok(x) => x,
+            Err(e) => {
+                error!("Failed to list packages: {}", e);
+                return (out, false);
+            }
+        };
+        let more = rows.len() == allpkgspage::PAGESIZE;
+        last = rows.last().map(|x| x.0.to_string());
+        // Keep going when the policy hid most of the page, scrolling wouldn't load more otherwise
+        out.extend(rows.into_iter().filter(|x| policy.allows(&x.0)));
+        if !more || out.len() >= allpkgspage::PAGESIZE / 2 {
+            return (out, more);
+        }
+    }
+}
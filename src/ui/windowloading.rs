@@ -1,18 +1,32 @@
 use super::window::AppMsg;
 use super::window::SystemPkgs;
+use crate::parse::appstream;
+use crate::parse::bundle::findbundle;
+use crate::i18n::{gettext, gettextf};
+use crate::parse::categories;
+use crate::parse::demo;
+use crate::parse::config::getnscconfig;
+use crate::parse::metadata;
+use crate::parse::state;
+use crate::parse::util;
 use crate::parse::packages::appsteamdata;
-use crate::parse::packages::AppData;
+use crate::parse::packages::appsteamdatafrom;
+use crate::parse::nixversion;
+use crate::parse::packages::{self, AppData};
+use crate::parse::popularity::{self, PopularityData};
 use crate::ui::categories::PkgCategory;
 use crate::ui::window::UserPkgs;
 use log::*;
 use nix_data::config::configfile::NixDataConfig;
 use rand::prelude::SliceRandom;
-use rand::thread_rng;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use relm4::adw::prelude::*;
 use relm4::*;
 use sqlx::SqlitePool;
 use std::path::Path;
-use std::{collections::HashMap, env};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{collections::{HashMap, HashSet}, env};
 
 pub struct WindowAsyncHandler;
 
@@ -41,112 +55,196 @@ impl Worker for WindowAsyncHandler {
                     let mut catpkgs: HashMap<PkgCategory, Vec<String>> = HashMap::new();
 
                     let nixos = Path::new("/etc/NIXOS").exists();
-
-                    let pkgdb = if nixos {
-                        match nix_data::cache::nixos::nixospkgs().await {
-                            Ok(p) => p,
-                            Err(e) => {
-                                error!("Error getting NixOS pkgs: {}", e);
-                                let _ = sender.output(AppMsg::LoadError(
-                                    String::from("Error retrieving NixOS package database"),
-                                    e.to_string(),
-                                ));
-                                return;
-                            }
+                    let nscconfig = getnscconfig();
+                    let bundle = findbundle(&nscconfig);
+                    let demo = demo::enabled();
+                    let pinned = if !demo && state::getnscstate().pinnedmetadata {
+                        let previous = metadata::previous();
+                        if previous.is_none() {
+                            warn!("No previous metadata to load, downloading the latest");
                         }
+                        previous
                     } else {
-                        match nix_data::cache::nonnixos::nixpkgs().await {
-                            Ok(p) => p,
-                            Err(e) => {
-                                error!("Error getting nixpkgs: {}", e);
+                        None
+                    };
+                    // Workers pick their nix profile arguments from this
+                    let nix = tokio::task::spawn_blocking(nixversion::detect).await.ok().flatten();
+
+                    // The databases and appstream data don't depend on each other, so fetch them all at once
+                    let total = 2
+                        + usize::from(userpkgs == UserPkgs::Profile)
+                        + usize::from(syspkgs != SystemPkgs::None);
+                    let done = AtomicUsize::new(0);
+                    let step = || {
+                        let n = done.fetch_add(1, Ordering::SeqCst) + 1;
+                        let _ = sender.output(AppMsg::LoadProgress(n, total));
+                    };
+                    let _ = sender.output(AppMsg::LoadProgress(0, total));
+                    let (pkgdb, nixpkgsdb, systemdb, appdata) = tokio::join!(
+                        async {
+                            if demo {
+                                step();
+                                return demo::pkgdb().await;
+                            }
+                            if let Some(pinned) = &pinned {
+                                step();
+                                return Ok(pinned.pkgdb.clone());
+                            }
+                            let pkgdb = if nixos {
+                                nix_data::cache::nixos::nixospkgs().await
+                            } else {
+                                nix_data::cache::nonnixos::nixpkgs().await
+                            };
+                            step();
+                            pkgdb
+                        },
+                        async {
+                            if userpkgs != UserPkgs::Profile {
+                                return None;
+                            }
+                            if demo {
+                                step();
+                                return None;
+                            }
+                            if let Some(pinned) = &pinned {
+                                step();
+                                return pinned.nixpkgsdb.clone();
+                            }
+                            // Finding the nixpkgs of the profile needs nix
+                            if nix.is_none() {
+                                step();
+                                return None;
+                            }
+                            let nixpkgsdb = nix_data::cache::profile::nixpkgslatest().await.ok();
+                            step();
+                            nixpkgsdb
+                        },
+                        async {
+                            if syspkgs == SystemPkgs::None {
+                                return None;
+                            }
+                            if let Some(pinned) = &pinned {
+                                step();
+                                return pinned.systemdb.clone();
+                            }
+                            if nix.is_none() {
+                                step();
+                                return None;
+                            }
+                            let systemdb = match syspkgs {
+                                SystemPkgs::None => None,
+                                SystemPkgs::Legacy => nix_data::cache::channel::legacypkgs().await.ok(),
+                                SystemPkgs::Flake => nix_data::cache::flakes::flakespkgs().await.ok(),
+                            };
+                            step();
+                            systemdb
+                        },
+                        async {
+                            if demo {
+                                step();
+                                return demo::appstream()
+                                    .ok_or_else(|| anyhow::anyhow!("No demo appstream data"))
+                                    .and_then(|x| appsteamdatafrom(&x));
+                            }
+                            // The last catalog downloaded in the background, or the installed one
+                            let appdata = tokio::task::spawn_blocking(|| match appstream::downloaded() {
+                                Some(path) => appsteamdatafrom(&path).or_else(|e| {
+                                    warn!("Error reading downloaded appstream data, using the installed copy: {}", e);
+                                    appsteamdata()
+                                }),
+                                None => appsteamdata(),
+                            })
+                                .await
+                                .map_err(anyhow::Error::from)
+                                .and_then(|x| x);
+                            step();
+                            appdata
+                        },
+                    );
+
+                    // Only downloads that load get kept, not the bundle or an earlier copy
+                    let mut fresh = pinned.is_none() && !demo;
+                    let pkgdb = match pkgdb {
+                        Ok(p) => p,
+                        Err(e) => {
+                            fresh = false;
+                            let name = if nixos { "NixOS" } else { "nixpkgs" };
+                            if let Some(good) = metadata::current() {
+                                warn!("Error getting {} pkgs, using the last metadata that loaded: {}", name, e);
+                                good.pkgdb
+                            } else if let Some(bundle) = &bundle {
+                                warn!("Error getting {} pkgs, using preseeded metadata: {}", name, e);
+                                bundle.pkgdb()
+                            } else {
+                                error!("Error getting {} pkgs: {}", name, e);
                                 let _ = sender.output(AppMsg::LoadError(
-                                    String::from("Error retrieving nixpkgs package database"),
+                                    gettextf("Error retrieving {} package database", &[name]),
                                     e.to_string(),
                                 ));
                                 return;
                             }
                         }
                     };
-
-                    let pool = match SqlitePool::connect(&format!("sqlite://{}", pkgdb)).await {
-                        Ok(p) => p,
-                        Err(e) => {
-                            error!("Error connecting to pkgdb: {}", e);
-                            let _ = sender.output(AppMsg::LoadError(
-                                String::from("Error connecting to package database"),
-                                e.to_string(),
-                            ));
-                            return;
-                        }
-                    };
-
-                    let nixpkgsdb = match userpkgs {
-                        UserPkgs::Profile => {
-                            if let Ok(x) = nix_data::cache::profile::nixpkgslatest().await {
-                                Some(x)
-                            } else {
-                                None
-                            }
+                    let nixpkgsdb = nixpkgsdb.or_else(|| {
+                        if userpkgs == UserPkgs::Profile {
+                            bundle.as_ref().and_then(|x| x.nixpkgsdb())
+                        } else {
+                            None
                         }
-                        UserPkgs::Env => None,
-                    };
-
-                    let systemdb = match syspkgs {
-                        SystemPkgs::None => None,
-                        SystemPkgs::Legacy => {
-                            if let Ok(x) = nix_data::cache::channel::legacypkgs().await {
-                                Some(x)
-                            } else {
-                                None
-                            }
+                    });
+                    let systemdb = systemdb.or_else(|| {
+                        if syspkgs != SystemPkgs::None {
+                            bundle.as_ref().and_then(|x| x.systemdb())
+                        } else {
+                            None
                         }
-                        SystemPkgs::Flake => {
-                            if let Ok(x) = nix_data::cache::flakes::flakespkgs().await {
-                                Some(x)
-                            } else {
-                                None
+                    });
+
+                    let (pkgdb, nixpkgsdb, systemdb, pkglist, posvec) = match readpkgdb(&pkgdb).await {
+                        Ok((pkglist, posvec)) => (pkgdb, nixpkgsdb, systemdb, pkglist, posvec),
+                        Err((title, e)) => {
+                            error!("{}: {}", title, e);
+                            fresh = false;
+                            let good = metadata::current().filter(|x| x.pkgdb != pkgdb);
+                            let loaded = match &good {
+                                Some(good) => readpkgdb(&good.pkgdb).await.ok(),
+                                None => None,
+                            };
+                            match (good, loaded) {
+                                (Some(good), Some((pkglist, posvec))) => {
+                                    warn!("Using the last metadata that loaded");
+                                    (good.pkgdb, good.nixpkgsdb.or(nixpkgsdb), good.systemdb.or(systemdb), pkglist, posvec)
+                                }
+                                _ => {
+                                    let _ = sender.output(AppMsg::LoadError(title, e));
+                                    return;
+                                }
                             }
                         }
                     };
-
-                    let pkglist: Vec<(String,)> = match sqlx::query_as("SELECT attribute FROM pkgs")
-                        .fetch_all(&pool)
-                        .await
                     {
-                        Ok(x) => x,
-                        Err(e) => {
-                            error!("Error getting pkglist: {}", e);
-                            let _ = sender.output(AppMsg::LoadError(
-                                String::from("Malformed package database"),
-                                e.to_string(),
-                            ));
-                            return;
-                        }
-                    };
-
-                    let pkglist = pkglist.iter().map(|x| x.0.clone()).collect::<Vec<String>>();
+                        let pkgdb = pkgdb.clone();
+                        relm4::spawn(async move {
+                            if let Err(e) = packages::buildsearchindex(&pkgdb).await {
+                                warn!("Failed to index {} for search: {}", pkgdb, e);
+                            }
+                        });
+                    }
 
-                    let posvec: Vec<(String, String)> =
-                        match sqlx::query_as("SELECT attribute, position FROM meta")
-                            .fetch_all(&pool)
-                            .await
-                        {
-                            Ok(x) => x,
-                            Err(e) => {
-                                error!("Error getting package metadata: {}", e);
-                                let _ = sender.output(AppMsg::LoadError(
-                                    String::from("Malformed package database"),
-                                    e.to_string(),
-                                ));
-                                return;
+                    let appdata = match appdata.or_else(|e| {
+                        match bundle.as_ref().and_then(|x| x.appstream()) {
+                            Some(path) => {
+                                warn!("Error getting appdata, using preseeded metadata: {}", e);
+                                appsteamdatafrom(&path)
                             }
-                        };
-                    let appdata = match appsteamdata() {
+                            None => Err(e),
+                        }
+                    }) {
                         Ok(x) => x,
                         Err(e) => {
                             error!("Error getting appdata: {}", e);
                             let _ = sender.output(AppMsg::LoadError(
-                                String::from("Error retrieving appstream data"),
+                                gettext("Error retrieving appstream data"),
                                 e.to_string(),
                             ));
                             return;
@@ -178,8 +276,10 @@ impl Worker for WindowAsyncHandler {
                         })
                         .collect::<Vec<_>>();
 
-                    let mut rng = thread_rng();
+                    // Same picks for the whole day, different ones the next
+                    let mut rng = StdRng::seed_from_u64(popularity::now() / (24 * 60 * 60));
                     recpkgs.shuffle(&mut rng);
+                    let recpool = recpkgs.iter().map(|x| x.to_string()).collect::<Vec<_>>();
 
                     let mut desktoppicks = recpkgs
                         .iter()
@@ -211,14 +311,7 @@ impl Worker for WindowAsyncHandler {
                         .map(|(x, y)| (x, if y.is_empty() { None } else { Some(y) }))
                         .collect::<HashMap<String, Option<String>>>();
 
-                    for category in vec![
-                        PkgCategory::Audio,
-                        PkgCategory::Development,
-                        PkgCategory::Games,
-                        PkgCategory::Graphics,
-                        PkgCategory::Web,
-                        PkgCategory::Video,
-                    ] {
+                    for category in categories::categories().iter().cloned() {
                         desktoppicks.shuffle(&mut rng);
                         let mut cvec = vec![];
                         let mut allvec = vec![];
@@ -229,130 +322,15 @@ impl Worker for WindowAsyncHandler {
                             appdata: &HashMap<String, AppData>,
                             category: PkgCategory,
                         ) -> bool {
-                            match category {
-                                PkgCategory::Audio => {
-                                    // Audio:
-                                    // - pkgs/applications/audio
-                                    if let Some(Some(pos)) = pospkgs.get(&pkg) {
-                                        if pos.starts_with("pkgs/applications/audio") {
-                                            return true;
-                                        }
-                                        if let Some(data) = appdata.get(&pkg) {
-                                            if let Some(categories) = &data.categories {
-                                                if categories.contains(&String::from("Audio")) {
-                                                    return true;
-                                                }
-                                            }
-                                        }
-                                    }
-                                    false
-                                }
-                                PkgCategory::Development => {
-                                    // Development:
-                                    // - pkgs/development
-                                    // - pkgs/applications/terminal-emulators
-                                    // - xdg: Development
-                                    if let Some(Some(pos)) = pospkgs.get(&pkg) {
-                                        if pos.starts_with("pkgs/development")
-                                            || pos
-                                                .starts_with("pkgs/applications/terminal-emulators")
-                                        {
-                                            return true;
-                                        }
-                                        if let Some(data) = appdata.get(&pkg) {
-                                            if let Some(categories) = &data.categories {
-                                                if categories.contains(&String::from("Development"))
-                                                {
-                                                    return true;
-                                                }
-                                            }
-                                        }
-                                    }
-                                    false
-                                }
-                                PkgCategory::Games => {
-                                    // Games:
-                                    // - pkgs/games
-                                    // - pkgs/applications/emulators
-                                    // - pkgs/tools/games
-                                    // - xdg::Games
-                                    if let Some(Some(pos)) = pospkgs.get(&pkg) {
-                                        if pos.starts_with("pkgs/games")
-                                            || pos.starts_with("pkgs/applications/emulators")
-                                            || pos.starts_with("pkgs/tools/games")
-                                        {
-                                            return true;
-                                        }
-                                        if let Some(data) = &appdata.get(&pkg) {
-                                            if let Some(categories) = &data.categories {
-                                                if categories.contains(&String::from("Games")) {
-                                                    return true;
-                                                }
-                                            }
-                                        }
-                                    }
-                                    false
-                                }
-                                PkgCategory::Graphics => {
-                                    // Graphics:
-                                    // - pkgs/applications/graphics
-                                    // - xdg: Graphics
-                                    if let Some(Some(pos)) = pospkgs.get(&pkg) {
-                                        if pos.starts_with("pkgs/applications/graphics")
-                                            || pos.starts_with("xdg:Graphics")
-                                        {
-                                            return true;
-                                        }
-                                        if let Some(data) = &appdata.get(&pkg) {
-                                            if let Some(categories) = &data.categories {
-                                                if categories.contains(&String::from("Graphics")) {
-                                                    return true;
-                                                }
-                                            }
-                                        }
-                                    }
-                                    false
-                                }
-                                PkgCategory::Web => {
-                                    // Web:
-                                    // - pkgs/applications/networking
-                                    // - xdg: Network
-                                    if let Some(Some(pos)) = pospkgs.get(&pkg) {
-                                        if pos.starts_with("pkgs/applications/networking")
-                                            || pos.starts_with("xdg:Network")
-                                        {
-                                            return true;
-                                        }
-                                        if let Some(data) = &appdata.get(&pkg) {
-                                            if let Some(categories) = &data.categories {
-                                                if categories.contains(&String::from("Network")) {
-                                                    return true;
-                                                }
-                                            }
-                                        }
-                                    }
-                                    false
-                                }
-                                PkgCategory::Video => {
-                                    // Video:
-                                    // - pkgs/applications/video
-                                    // - xdg: Video
-                                    if let Some(Some(pos)) = pospkgs.get(&pkg) {
-                                        if pos.starts_with("pkgs/applications/video")
-                                            || pos.starts_with("xdg:Video")
-                                        {
-                                            return true;
-                                        }
-                                        if let Some(data) = &appdata.get(&pkg) {
-                                            if let Some(categories) = &data.categories {
-                                                if categories.contains(&String::from("Video")) {
-                                                    return true;
-                                                }
-                                            }
-                                        }
-                                    }
-                                    false
-                                }
+                            // Packages in the category's nixpkgs directories, or apps with its freedesktop categories
+                            if let Some(Some(pos)) = pospkgs.get(&pkg) {
+                                let xdg = appdata
+                                    .get(&pkg)
+                                    .and_then(|x| x.categories.as_deref())
+                                    .unwrap_or_default();
+                                category.matches(pos, xdg)
+                            } else {
+                                false
                             }
                         }
 
@@ -384,27 +362,7 @@ impl Worker for WindowAsyncHandler {
                             .filter(|x| {
                                 if appdata.get(*x).is_some() {
                                     if let Some(Some(position)) = &pospkgs.get(*x) {
-                                        (position.starts_with("pkgs/applications/audio")
-                                            && category == PkgCategory::Audio)
-                                            || (position.starts_with(
-                                                "pkgs/applications/terminal-emulators",
-                                            ) && category == PkgCategory::Development)
-                                            || (position.starts_with("pkgs/applications/emulators")
-                                                && category == PkgCategory::Games)
-                                            || (position.starts_with("pkgs/applications/graphics")
-                                                && category == PkgCategory::Graphics)
-                                            || (position
-                                                .starts_with("pkgs/applications/networking")
-                                                && category == PkgCategory::Web)
-                                            || (position.starts_with("pkgs/applications/video")
-                                                && category == PkgCategory::Video)
-                                            || (position.starts_with("pkgs/tools/games")
-                                                && category == PkgCategory::Games)
-                                            || (position.starts_with("pkgs/games")
-                                                && category == PkgCategory::Games)
-                                            || (position.starts_with("pkgs/development")
-                                                && category == PkgCategory::Development)
-                                            || recpkgs.contains(x)
+                                        category.inpath(position) || recpkgs.contains(x)
                                     } else {
                                         false
                                     }
@@ -421,7 +379,10 @@ impl Worker for WindowAsyncHandler {
                         }
 
                         cvec.shuffle(&mut rng);
-                        allvec.sort_by_key(|x| x.to_lowercase());
+                        allvec.sort_by_cached_key(|x| {
+                            let name = appdata.get(x).and_then(|x| x.name.as_ref()).and_then(packages::translated);
+                            util::sortkey(name.unwrap_or(x))
+                        });
                         catpicks.insert(category.clone(), cvec);
                         catpkgs.insert(category.clone(), allvec);
                     }
@@ -437,14 +398,58 @@ impl Worker for WindowAsyncHandler {
                     }
                     recpicks.shuffle(&mut rng);
 
+                    let popularity = match &nscconfig.popularitydata {
+                        Some(url) => popularity::popularitydata(url).await.unwrap_or_else(|e| {
+                            warn!("Error getting popularity data: {}", e);
+                            PopularityData::default()
+                        }),
+                        None => PopularityData::default(),
+                    };
+                    let pkgset = pkglist.iter().collect::<HashSet<_>>();
+                    let trendpicks = popularity
+                        .trending
+                        .iter()
+                        .filter(|x| pkgset.contains(x) && appdata.contains_key(*x))
+                        .take(6)
+                        .cloned()
+                        .collect::<Vec<_>>();
+
+                    if fresh {
+                        let (pkgdb, nixpkgsdb, systemdb) = (pkgdb.clone(), nixpkgsdb.clone(), systemdb.clone());
+                        relm4::spawn_blocking(move || {
+                            if let Err(e) = metadata::keep(&pkgdb, nixpkgsdb.as_deref(), systemdb.as_deref()) {
+                                warn!("Failed to keep a copy of the metadata: {}", e);
+                            }
+                        });
+                    }
+
                     sender.output(AppMsg::Initialize(
-                        pkgdb, nixpkgsdb, systemdb, appdata, recpicks, catpicks, catpkgs,
+                        pkgdb, nixpkgsdb, systemdb, appdata, recpicks, recpool, catpicks, catpkgs, popularity, trendpicks,
                     ));
+
+                    // The catalog is downloaded on its own schedule and used from the next load,
+                    // a failure only delays the next try
+                    if let Some(url) = nscconfig.appstreamurl.filter(|_| !demo && appstream::due(&state::getnscstate())) {
+                        relm4::spawn(async move {
+                            let result = match appstream::download(&url).await {
+                                Ok(tmp) => tokio::task::spawn_blocking(move || appstream::install(&tmp))
+                                    .await
+                                    .map_err(anyhow::Error::from)
+                                    .and_then(|x| x),
+                                Err(e) => Err(e),
+                            };
+                            if let Err(e) = &result {
+                                warn!("Failed to refresh appstream data: {}", e);
+                            }
+                            appstream::refreshed(result.is_ok());
+                        });
+                    }
                 });
             }
             WindowAsyncHandlerMsg::UpdateDB(syspkgs, userpkgs) => {
                 relm4::spawn(async move {
                     let nixos = Path::new("/etc/NIXOS").exists();
+                    let nix = tokio::task::spawn_blocking(nixversion::detect).await.ok().flatten();
 
                     let _pkgdb = if nixos {
                         match nix_data::cache::nixos::nixospkgs().await {
@@ -452,7 +457,7 @@ impl Worker for WindowAsyncHandler {
                             Err(e) => {
                                 error!("Error getting NixOS pkgs: {}", e);
                                 sender.output(AppMsg::LoadError(
-                                    String::from("Error retrieving NixOS package database"),
+                                    gettext("Error retrieving NixOS package database"),
                                     e.to_string(),
                                 ));
                                 return;
@@ -464,7 +469,7 @@ impl Worker for WindowAsyncHandler {
                             Err(e) => {
                                 error!("Error getting nixpkgs: {}", e);
                                 sender.output(AppMsg::LoadError(
-                                    String::from("Error retrieving nixpkgs package database"),
+                                    gettext("Error retrieving nixpkgs package database"),
                                     e.to_string(),
                                 ));
                                 return;
@@ -473,17 +478,18 @@ impl Worker for WindowAsyncHandler {
                     };
 
                     let _nixpkgsdb = match userpkgs {
-                        UserPkgs::Profile => {
+                        UserPkgs::Profile if nix.is_some() => {
                             if let Ok(x) = nix_data::cache::profile::nixpkgslatest().await {
                                 Some(x)
                             } else {
                                 None
                             }
                         }
-                        UserPkgs::Env => None,
+                        _ => None,
                     };
 
                     let _systemdb = match syspkgs {
+                        _ if nix.is_none() => None,
                         SystemPkgs::None => None,
                         SystemPkgs::Legacy => {
                             if let Ok(x) = nix_data::cache::channel::legacypkgs().await {
@@ -506,6 +512,22 @@ impl Worker for WindowAsyncHandler {
     }
 }
 
+/// Attributes and positions from the package database, or a title and message for the error dialog.
+async fn readpkgdb(pkgdb: &str) -> Result<(Vec<String>, Vec<(String, String)>), (String, String)> {
+    let pool = SqlitePool::connect(&format!("sqlite://{}", pkgdb))
+        .await
+        .map_err(|e| (gettext("Error connecting to package database"), e.to_string()))?;
+    let pkglist: Vec<(String,)> = sqlx::query_as("SELECT attribute FROM pkgs")
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (gettext("Malformed package database"), e.to_string()))?;
+    let posvec: Vec<(String, String)> = sqlx::query_as("SELECT attribute, position FROM meta")
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| (gettext("Malformed package database"), e.to_string()))?;
+    Ok((pkglist.into_iter().map(|x| x.0).collect(), posvec))
+}
+
 pub struct LoadErrorModel {
     hidden: bool,
     msg: String,
@@ -537,9 +559,9 @@ impl SimpleComponent for LoadErrorModel {
             set_secondary_text: Some(&model.msg2),
             set_use_markup: true,
             set_secondary_use_markup: true,
-            add_button: ("Retry", gtk::ResponseType::Accept),
+            add_button: (&gettext("Retry"), gtk::ResponseType::Accept),
             // add_button: ("Preferences", gtk::ResponseType::Help),
-            add_button: ("Quit", gtk::ResponseType::Close),
+            add_button: (&gettext("Quit"), gtk::ResponseType::Close),
             connect_response[sender] => move |_, resp| {
                 sender.input(match resp {
                     gtk::ResponseType::Accept => LoadErrorMsg::Retry,
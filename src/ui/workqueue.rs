@@ -0,0 +1,28 @@
+use super::pkgpage::{InstallType, WorkPkg};
+use crate::parse::workqueue::{self, Work, WorkLane};
+
+/// Package operations requested in the interface. Owned by the main window, which starts them
+/// with [`workqueue::WorkQueue::startready`], and copied to the pages that show their progress.
+pub type WorkQueue = workqueue::WorkQueue<WorkPkg>;
+
+pub fn lane(pkgtype: &InstallType) -> WorkLane {
+    match pkgtype {
+        InstallType::User => WorkLane::User,
+        InstallType::System => WorkLane::System,
+    }
+}
+
+impl Work for WorkPkg {
+    fn lane(&self) -> WorkLane {
+        lane(&self.pkgtype)
+    }
+
+    fn pkg(&self) -> &str {
+        &self.pkg
+    }
+
+    /// User packages match by attribute or name, since nix-env tracks them by name.
+    fn matches(&self, key: &str, lane: WorkLane) -> bool {
+        self.lane() == lane && (self.pkg == key || (lane == WorkLane::User && self.pname == key))
+    }
+}